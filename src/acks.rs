@@ -0,0 +1,104 @@
+use crate::game::VerifyFailure;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+
+// failures acknowledged as known issues, by what they're verified in,
+// like "mame", a software list or a collection, so they're reported
+// apart from new failures and don't count against a run
+#[derive(Default, Serialize, Deserialize)]
+pub struct AckDb {
+    scopes: BTreeMap<String, Acks>,
+}
+
+// acknowledged parts by game, where games without any parts
+// have all their failures acknowledged
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Acks {
+    games: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl AckDb {
+    #[inline]
+    pub fn scope(&self, scope: &str) -> Acks {
+        self.scopes.get(scope).cloned().unwrap_or_default()
+    }
+
+    // acknowledging a whole game covers any parts already acknowledged
+    pub fn ack(&mut self, scope: &str, game: &str, part: Option<String>) {
+        let games = &mut self.scopes.entry(scope.to_owned()).or_default().games;
+
+        match (games.entry(game.to_owned()), part) {
+            (Entry::Vacant(entry), part) => {
+                entry.insert(part.into_iter().collect());
+            }
+            (Entry::Occupied(mut entry), None) => entry.get_mut().clear(),
+            (Entry::Occupied(entry), Some(_)) if entry.get().is_empty() => {}
+            (Entry::Occupied(mut entry), Some(part)) => {
+                entry.get_mut().insert(part);
+            }
+        }
+    }
+
+    pub fn unack(&mut self, scope: &str, game: &str, part: Option<&str>) {
+        if let Some(acks) = self.scopes.get_mut(scope) {
+            match part {
+                None => {
+                    acks.games.remove(game);
+                }
+                Some(part) => {
+                    // games left without parts would be acknowledged whole
+                    if let Some(parts) = acks.games.get_mut(game) {
+                        if parts.remove(part) && parts.is_empty() {
+                            acks.games.remove(game);
+                        }
+                    }
+                }
+            }
+
+            if acks.games.is_empty() {
+                self.scopes.remove(scope);
+            }
+        }
+    }
+}
+
+impl Acks {
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &BTreeSet<String>)> {
+        self.games.iter()
+    }
+
+    pub fn covers(&self, game: &str, failure: &VerifyFailure) -> bool {
+        match self.games.get(game) {
+            None => false,
+            Some(parts) if parts.is_empty() => true,
+            Some(parts) => parts.iter().any(|part| failure.path().ends_with(part)),
+        }
+    }
+
+    // moves acknowledged failures out of results and into their own
+    pub fn separate<'r, 'f>(
+        &self,
+        results: &mut BTreeMap<&'r str, Vec<VerifyFailure<'f>>>,
+    ) -> BTreeMap<&'r str, Vec<VerifyFailure<'f>>> {
+        let mut acknowledged = BTreeMap::new();
+
+        if self.games.is_empty() {
+            return acknowledged;
+        }
+
+        for (game, failures) in results.iter_mut() {
+            let (acked, unacked) = std::mem::take(failures)
+                .into_iter()
+                .partition::<Vec<_>, _>(|failure| self.covers(game, failure));
+
+            *failures = unacked;
+            if !acked.is_empty() {
+                acknowledged.insert(*game, acked);
+            }
+        }
+
+        acknowledged
+    }
+}
@@ -0,0 +1,137 @@
+use crate::collection::Collection;
+use crate::game::{Part, VerifyFailure};
+use crate::progress::BarSink;
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+// a collection's verified state at one point in time,
+// recording whatever's on disk whether it's right or not,
+// so later audits can tell what's changed since
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub collection: String,
+    pub taken: u64,
+    // game -> part name -> digest of the file there
+    pub games: BTreeMap<String, BTreeMap<String, Part>>,
+}
+
+impl Snapshot {
+    pub fn take(collection: &Collection, deep: bool) -> Result<Self, Error> {
+        let mut games = BTreeMap::new();
+
+        for root in collection.roots.iter() {
+            let present = collection.games_in(root)?;
+            let results = collection.db.verify(
+                root,
+                &present,
+                deep,
+                collection.is_zip(),
+                &BarSink::default(),
+            );
+
+            for (name, failures) in results.iter() {
+                let game = match collection.db.game(name) {
+                    Some(game) => game,
+                    None => continue,
+                };
+
+                let mut parts: BTreeMap<String, Part> = game
+                    .parts
+                    .iter()
+                    .map(|(name, part)| (name.clone(), part.clone()))
+                    .collect();
+
+                for failure in failures {
+                    match failure {
+                        VerifyFailure::Missing { name, .. } => {
+                            parts.remove(*name);
+                        }
+                        VerifyFailure::Bad { name, actual, .. } => {
+                            parts.insert(name.to_string(), actual.clone());
+                        }
+                        VerifyFailure::Extra {
+                            path,
+                            part: Ok(part),
+                        } => {
+                            if let Some(file) = path.file_name() {
+                                parts.insert(file.to_string_lossy().into_owned(), part.clone());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                // games in more than one root count where they're found first
+                games.entry(name.to_string()).or_insert(parts);
+            }
+        }
+
+        Ok(Self {
+            collection: collection.name.clone(),
+            taken: crate::collection::unix_now(),
+            games,
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        ciborium::de::from_reader(BufReader::new(File::open(path)?))
+            .map_err(|_| Error::InvalidSnapshot(path.to_path_buf()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        ciborium::ser::into_writer(self, BufWriter::new(File::create(path)?))
+            .map_err(Error::CborWrite)
+    }
+}
+
+// displays what's appeared, vanished or changed from old to new,
+// returning the number of differences
+pub fn diff(old: &Snapshot, new: &Snapshot) -> usize {
+    use std::collections::BTreeSet;
+
+    let mut differences = Vec::new();
+
+    let names = old
+        .games
+        .keys()
+        .chain(new.games.keys())
+        .collect::<BTreeSet<_>>();
+
+    for game in names {
+        match (old.games.get(game), new.games.get(game)) {
+            (Some(_), None) => differences.push(format!("VANISHED : {}", game)),
+            (None, Some(_)) => differences.push(format!("APPEARED : {}", game)),
+            (Some(old), Some(new)) => {
+                for part in old.keys().chain(new.keys()).collect::<BTreeSet<_>>() {
+                    match (old.get(part), new.get(part)) {
+                        (Some(_), None) => {
+                            differences.push(format!("VANISHED : {}/{}", game, part))
+                        }
+                        (None, Some(_)) => {
+                            differences.push(format!("APPEARED : {}/{}", game, part))
+                        }
+                        (Some(old), Some(new)) if old != new => differences.push(format!(
+                            "CHANGED : {}/{} : {} \u{2192} {}",
+                            game,
+                            part,
+                            old.digest(),
+                            new.digest()
+                        )),
+                        _ => {}
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    for difference in differences.iter() {
+        println!("{}", difference);
+    }
+
+    differences.len()
+}
@@ -0,0 +1,75 @@
+use crate::game::{parse_sha1, Part};
+use crate::{Error, FileError};
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::path::Path;
+
+// blocked SHA1s and the reason each was blocked
+pub type Blocklist = BTreeMap<[u8; 20], String>;
+
+// the blocklist database, loaded once,
+// where having never initialized one blocks nothing
+#[cfg(feature = "native")]
+pub fn load() -> Result<&'static Blocklist, Error> {
+    use once_cell::sync::OnceCell;
+
+    static LOADED: OnceCell<Blocklist> = OnceCell::new();
+
+    LOADED.get_or_try_init(
+        || match crate::read_game_db(crate::BLOCKLIST, crate::DB_BLOCKLIST) {
+            Err(Error::MissingCache(_)) => Ok(Blocklist::default()),
+            result => result,
+        },
+    )
+}
+
+// blocked parts, such as known-bad dumps or unwanted content,
+// are never used as sources and are flagged when found on disk
+#[cfg(feature = "native")]
+pub fn is_blocked(part: &Part) -> Result<bool, Error> {
+    let (Part::Rom { sha1 } | Part::Disk { sha1 }) = part;
+
+    load().map(|blocklist| blocklist.contains_key(sha1))
+}
+
+// without a local database there's no blocklist to load
+#[cfg(not(feature = "native"))]
+#[inline]
+pub fn is_blocked(_part: &Part) -> Result<bool, Error> {
+    Ok(false)
+}
+
+// blocklists use the same format as download lists, like:
+//
+// 6d9ed13b5a4a4ea50d1b03e3a1ba6bf8ddc97dbd overdumped
+//
+// where the text after the digest is the reason it's blocked
+pub fn read_blocklist(path: &Path) -> Result<Blocklist, Error> {
+    let mut blocklist = Blocklist::new();
+
+    for line in std::io::BufReader::new(std::fs::File::open(path)?).lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (digest, reason) = match line.split_once(char::is_whitespace) {
+            Some((digest, reason)) => (digest, reason.trim()),
+            None => (line, ""),
+        };
+
+        blocklist.insert(
+            parse_sha1(digest).map_err(|error| {
+                Error::InvalidSha1(FileError {
+                    file: path.to_owned(),
+                    error,
+                })
+            })?,
+            reason.to_owned(),
+        );
+    }
+
+    Ok(blocklist)
+}
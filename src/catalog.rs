@@ -0,0 +1,219 @@
+use crate::game::{FileId, Part, RomSource};
+use crate::Error;
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// bumped whenever what's cataloged changes,
+// so catalogs from older versions are simply rebuilt
+const VERSION: u32 = 1;
+
+// the parts found in source files on earlier runs,
+// so files which haven't changed since needn't be reread
+#[derive(Default, Serialize, Deserialize)]
+struct Catalog {
+    version: u32,
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    dev: u64,
+    ino: u64,
+    path: PathBuf,
+    stamp: Stamp,
+    parts: Vec<(Part, Location)>,
+}
+
+// files are considered unchanged while their size
+// and modification time remain the same
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    secs: u64,
+    nanos: u32,
+}
+
+impl Stamp {
+    fn new(path: &Path) -> Option<Self> {
+        let metadata = path.metadata().ok()?;
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?;
+
+        Some(Self {
+            size: metadata.len(),
+            secs: modified.as_secs(),
+            nanos: modified.subsec_nanos(),
+        })
+    }
+}
+
+// where within the file a part was found
+#[derive(Clone, Serialize, Deserialize)]
+enum Location {
+    File {
+        has_xattr: bool,
+        zip_parts: Vec<usize>,
+    },
+    Rar {
+        index: usize,
+    },
+    Tar {
+        index: usize,
+    },
+}
+
+struct Loaded {
+    entries: DashMap<(u64, u64), Entry>,
+    changed: AtomicBool,
+}
+
+fn loaded() -> &'static Loaded {
+    static LOADED: OnceCell<Loaded> = OnceCell::new();
+
+    LOADED.get_or_init(|| {
+        let catalog: Catalog =
+            crate::read_game_db(crate::CATALOG, crate::DB_CATALOG).unwrap_or_default();
+
+        Loaded {
+            entries: if catalog.version == VERSION {
+                catalog
+                    .entries
+                    .into_iter()
+                    .map(|entry| ((entry.dev, entry.ino), entry))
+                    .collect()
+            } else {
+                DashMap::default()
+            },
+            changed: AtomicBool::new(false),
+        }
+    })
+}
+
+// the parts in the file at path, as cataloged if it's unchanged
+// or as read from the file itself otherwise
+pub fn rom_sources<'u>(path: PathBuf) -> Result<Vec<(Part, RomSource<'u>)>, Error> {
+    let (id, stamp) = match (FileId::new(&path), Stamp::new(&path)) {
+        (Ok(id), Some(stamp)) => (id, stamp),
+        _ => return RomSource::from_path(path),
+    };
+
+    let loaded = loaded();
+
+    if let Some(entry) = loaded.entries.get(&(id.dev, id.ino)) {
+        if entry.stamp == stamp {
+            let file = Arc::new(path);
+
+            return Ok(entry
+                .parts
+                .iter()
+                .map(|(part, location)| {
+                    (
+                        part.clone(),
+                        match location {
+                            Location::File {
+                                has_xattr,
+                                zip_parts,
+                            } => RomSource::File {
+                                file: file.clone(),
+                                has_xattr: *has_xattr,
+                                zip_parts: zip_parts.clone(),
+                            },
+                            Location::Rar { index } => RomSource::Rar {
+                                file: file.clone(),
+                                index: *index,
+                            },
+                            Location::Tar { index } => RomSource::Tar {
+                                file: file.clone(),
+                                index: *index,
+                            },
+                        },
+                    )
+                })
+                .collect());
+        }
+    }
+
+    let sources = RomSource::from_path(path.clone())?;
+
+    let parts = sources
+        .iter()
+        .map(|(part, source)| {
+            Some((
+                part.clone(),
+                match source {
+                    RomSource::File {
+                        has_xattr,
+                        zip_parts,
+                        ..
+                    } => Location::File {
+                        has_xattr: *has_xattr,
+                        zip_parts: zip_parts.clone(),
+                    },
+                    RomSource::Rar { index, .. } => Location::Rar { index: *index },
+                    RomSource::Tar { index, .. } => Location::Tar { index: *index },
+                    RomSource::Url { .. } | RomSource::Remote { .. } => return None,
+                },
+            ))
+        })
+        .collect::<Option<Vec<_>>>();
+
+    if let Some(parts) = parts {
+        loaded.entries.insert(
+            (id.dev, id.ino),
+            Entry {
+                dev: id.dev,
+                ino: id.ino,
+                path,
+                stamp,
+                parts,
+            },
+        );
+        loaded.changed.store(true, Ordering::Relaxed);
+    }
+
+    Ok(sources)
+}
+
+// writes the catalog if anything new was found,
+// leaving out files which have since changed or gone away
+pub fn save() -> Result<(), Error> {
+    use rayon::prelude::*;
+
+    let loaded = loaded();
+
+    if crate::game::is_read_only() || !loaded.changed.swap(false, Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    let entries = loaded
+        .entries
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter(|entry| {
+            matches!(FileId::new(&entry.path), Ok(id) if id.dev == entry.dev && id.ino == entry.ino)
+                && Stamp::new(&entry.path) == Some(entry.stamp)
+        })
+        .collect();
+
+    crate::write_game_db(
+        crate::DB_CATALOG,
+        Catalog {
+            version: VERSION,
+            entries,
+        },
+    )
+}
+
+// forgets everything cataloged
+pub fn clear() -> Result<(), Error> {
+    crate::write_game_db(crate::DB_CATALOG, Catalog::default())
+}
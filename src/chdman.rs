@@ -0,0 +1,87 @@
+use crate::game::Part;
+use crate::Error;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// disc images chdman can turn into CHDs
+const IMAGE_EXTENSIONS: &[&str] = &["cue", "gdi", "toc", "iso"];
+
+// ISO images larger than any CD are taken to be DVDs
+const MAX_CD_SIZE: u64 = 900 * 1024 * 1024;
+
+// MAME's chdman, which checks CHDs' data against their own headers
+// and builds CHDs from raw disc images
+pub struct Chdman {
+    program: PathBuf,
+}
+
+impl Chdman {
+    #[inline]
+    pub fn new(program: PathBuf) -> Self {
+        Self { program }
+    }
+
+    // runs chdman, returning its exit status along with
+    // whatever it printed to stderr, which says why it failed
+    fn run(&self, command: &mut Command) -> Result<(std::process::ExitStatus, String), Error> {
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .map(|output| {
+                (
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+                )
+            })
+            .map_err(|err| {
+                Error::IO(std::io::Error::new(
+                    err.kind(),
+                    format!("{}: {}", self.program.display(), err),
+                ))
+            })
+    }
+
+    // whether the CHD's data still matches the digest in its header,
+    // which tells a damaged CHD apart from an intact one of some other disk
+    pub fn verify(&self, chd: &Path) -> Result<bool, Error> {
+        self.run(Command::new(&self.program).arg("verify").arg("-i").arg(chd))
+            .map(|(status, _)| status.success())
+    }
+
+    // builds a CHD from the given disc image,
+    // returning the CHD's part
+    pub fn create(&self, image: &Path, chd: &Path) -> Result<Part, Error> {
+        let is_dvd = image
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("iso"))
+            && image.metadata()?.len() > MAX_CD_SIZE;
+
+        let (status, stderr) = self.run(
+            Command::new(&self.program)
+                .arg(if is_dvd { "createdvd" } else { "createcd" })
+                .arg("-i")
+                .arg(image)
+                .arg("-o")
+                .arg(chd)
+                .arg("--force"),
+        )?;
+
+        if status.success() {
+            Part::from_path(chd).map_err(Error::IO)
+        } else {
+            let _ = std::fs::remove_file(chd);
+            Err(Error::ChdmanExit(status, stderr))
+        }
+    }
+}
+
+#[inline]
+pub fn is_image(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        IMAGE_EXTENSIONS
+            .iter()
+            .any(|image| extension.eq_ignore_ascii_case(image))
+    })
+}
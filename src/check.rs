@@ -0,0 +1,64 @@
+// checks files already in memory against a DAT also in memory,
+// using nothing which needs a filesystem, so that a tool can check
+// dumps before they're uploaded anywhere
+use crate::dat::parse_datafile;
+use crate::game::{GameDb, Part};
+use crate::Error;
+use fxhash::FxHashSet;
+use std::path::Path;
+
+// a game with at least one part among the checked files
+#[derive(Debug)]
+pub struct Check {
+    pub game: String,
+    pub found: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl Check {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+// parses DAT data in either Logiqx XML or clrmamepro's text format
+pub fn read_dat(data: &[u8]) -> Result<GameDb, Error> {
+    parse_datafile(Path::new("<buffer>"), data)?
+        .into_game_db()
+        .map(|(db, _)| db)
+        .map_err(Error::InvalidDat)
+}
+
+// returns every game with a part among the given files,
+// along with which of its parts are found or missing
+pub fn check_files<'b, I>(db: &GameDb, files: I) -> Result<Vec<Check>, Error>
+where
+    I: IntoIterator<Item = &'b [u8]>,
+{
+    let parts = files
+        .into_iter()
+        .map(Part::from_slice)
+        .collect::<Result<FxHashSet<_>, _>>()?;
+
+    let mut checks = db
+        .games_iter()
+        .filter(|game| game.parts.values().any(|part| parts.contains(part)))
+        .map(|game| {
+            let (found, missing) = game
+                .parts
+                .iter()
+                .partition::<Vec<_>, _>(|(_, part)| parts.contains(part));
+
+            Check {
+                game: game.name.clone(),
+                found: found.into_iter().map(|(name, _)| name.clone()).collect(),
+                missing: missing.into_iter().map(|(name, _)| name.clone()).collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    checks.sort_unstable_by(|x, y| x.game.cmp(&y.game));
+
+    Ok(checks)
+}
@@ -0,0 +1,355 @@
+use crate::game::{GameDb, VerifyResultsSummary};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+const COLLECTIONS_CONFIG_FILE: &str = "collections.toml";
+const COLLECTIONS_STATE_DIR: &str = "collections";
+const VERIFY_STATE_FILE: &str = "last-verify.toml";
+const MAINTAIN_STATE_FILE: &str = "last-maintain.toml";
+const QUARANTINE_DIR: &str = "quarantine";
+
+// collections bundle a database with where its games are stored
+// and how they're stored there, like:
+//
+// [arcade]
+// db = "mame"
+// roots = ["/roms/mame", "/mnt/archive/mame"]
+// mode = "zip"
+// ignore = ["neogeo", "pgm*"]
+//
+// [nes]
+// db = "sl:nes"
+// roots = ["/roms/nes"]
+// sources = ["/incoming/nes", "http://nas.local/roms/nes/"]
+// quarantine = "/roms/quarantine/nes"
+//
+// [fbneo]
+// db = "dat:FinalBurn Neo - Arcade Games"
+// roots = ["/roms/fbneo"]
+//
+// where "dat:" databases are Logiqx DATs
+// imported with "emuman collection init",
+// and "sources" and "quarantine" are where "emuman maintain"
+// finds missing parts and moves extra files, respectively
+//
+// with mode = "hashed", parts are stored once apiece by digest
+// for frontends to link to, while games are still verified by name
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CollectionConfig {
+    pub db: String,
+    pub roots: Vec<PathBuf>,
+    #[serde(default)]
+    pub mode: SetMode,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    pub state: Option<PathBuf>,
+    #[serde(default)]
+    pub sources: Vec<String>,
+    pub quarantine: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SetMode {
+    // each game is a directory of files
+    #[default]
+    Directory,
+    // each game is a Zip archive of ROMs
+    Zip,
+    // each part is a file named by its digest, shared by every game using it
+    Hashed,
+}
+
+pub fn configs() -> Result<BTreeMap<String, CollectionConfig>, Error> {
+    match std::fs::read(location()) {
+        Ok(toml) => toml::from_slice(&toml).map_err(|error| {
+            Error::TomlRead(crate::FileError {
+                file: location(),
+                error,
+            })
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::default()),
+        Err(err) => Err(Error::IO(err)),
+    }
+}
+
+// reads a database given as "mame", "sl:<software list>" or "dat:<name>"
+pub fn read_db(db: String) -> Result<GameDb, Error> {
+    match db.split_once(':') {
+        None if db == crate::MAME => crate::read_game_db(crate::MAME, crate::DB_MAME),
+        Some(("sl", software_list)) => {
+            crate::read_named_db(crate::MESS, crate::DIR_SL, software_list)
+        }
+        Some(("dat", dat)) => crate::read_named_db(crate::COLLECTION, crate::DIR_DAT, dat),
+        _ => Err(Error::InvalidCollectionDb(db)),
+    }
+}
+
+fn location() -> PathBuf {
+    crate::data_dir().join(COLLECTIONS_CONFIG_FILE)
+}
+
+pub struct Collection {
+    pub name: String,
+    pub db: GameDb,
+    pub roots: Vec<PathBuf>,
+    pub mode: SetMode,
+    pub sources: Vec<String>,
+    ignore: Vec<String>,
+    state_dir: PathBuf,
+    quarantine: Option<PathBuf>,
+}
+
+impl Collection {
+    pub fn open(name: &str) -> Result<Self, Error> {
+        let CollectionConfig {
+            db,
+            roots,
+            mode,
+            ignore,
+            state,
+            sources,
+            quarantine,
+        } = configs()?
+            .remove(name)
+            .ok_or_else(|| Error::NoSuchCollection(name.to_owned()))?;
+
+        let db = read_db(db)?;
+
+        let state_dir = state.unwrap_or_else(|| default_state_dir(name));
+
+        let collection = Self {
+            name: name.to_owned(),
+            db,
+            roots,
+            mode,
+            sources,
+            ignore,
+            state_dir,
+            quarantine,
+        };
+
+        collection.use_storage();
+
+        Ok(collection)
+    }
+
+    // collections are usually opened one at a time, each before being used,
+    // but any opened alongside others must be switched back to
+    #[inline]
+    pub fn use_storage(&self) {
+        crate::game::set_hashed_storage(self.is_hashed());
+    }
+
+    #[inline]
+    pub fn is_hashed(&self) -> bool {
+        matches!(self.mode, SetMode::Hashed)
+    }
+
+    #[inline]
+    pub fn is_zip(&self) -> bool {
+        matches!(self.mode, SetMode::Zip)
+    }
+
+    // new games are added to the first root
+    #[inline]
+    pub fn primary_root(&self) -> Option<&Path> {
+        self.roots.first().map(|root| root.as_path())
+    }
+
+    #[inline]
+    pub fn is_ignored(&self, game: &str) -> bool {
+        self.ignore
+            .iter()
+            .any(|pattern| matches_pattern(pattern, game))
+    }
+
+    // all the database's games which aren't ignored
+    pub fn all_games(&self) -> HashSet<String> {
+        let mut games: HashSet<String> = self.db.all_games();
+        games.retain(|game| !self.is_ignored(game));
+        games
+    }
+
+    // games in the given root which aren't ignored,
+    // where games in hashed storage have any of their parts there
+    pub fn games_in(&self, root: &Path) -> Result<HashSet<String>, Error> {
+        if self.is_hashed() {
+            use rayon::prelude::*;
+
+            return Ok(self
+                .all_games()
+                .into_par_iter()
+                .filter(|game| {
+                    self.db.game(game).is_some_and(|game| {
+                        game.parts
+                            .iter()
+                            .any(|(_, part)| part.hashed_path(root).is_file())
+                    })
+                })
+                .collect());
+        }
+
+        Ok(root
+            .read_dir()?
+            .filter_map(|e| {
+                e.ok()
+                    .and_then(|e| crate::entry_game(e, self.is_zip()))
+                    .filter(|s| self.db.is_game(s) && !self.is_ignored(s))
+            })
+            .collect())
+    }
+
+    // remembers the outcome of the most recent verification
+    pub fn record_verify(&self, summary: &VerifyResultsSummary) -> Result<(), Error> {
+        let state = VerifyState {
+            tested: summary.total,
+            ok: summary.successes,
+            timestamp: unix_now(),
+        };
+
+        std::fs::create_dir_all(&self.state_dir)?;
+
+        std::fs::write(
+            self.state_dir.join(VERIFY_STATE_FILE),
+            toml::to_string_pretty(&state)?,
+        )
+        .map_err(Error::IO)
+    }
+
+    pub fn last_verify(&self) -> Option<VerifyState> {
+        toml::from_str(&std::fs::read_to_string(self.state_dir.join(VERIFY_STATE_FILE)).ok()?).ok()
+    }
+}
+
+// the outcome of the most recent verification
+#[derive(Serialize, Deserialize)]
+pub struct VerifyState {
+    pub tested: usize,
+    pub ok: usize,
+    pub timestamp: u64,
+}
+
+// what "emuman maintain" found last time,
+// so each run can report what's changed since
+#[derive(Default, Serialize, Deserialize)]
+pub struct MaintainState {
+    pub timestamp: u64,
+    pub tested: usize,
+    pub ok: usize,
+    pub failing: BTreeSet<String>,
+}
+
+impl Collection {
+    pub fn last_maintain(&self) -> Option<MaintainState> {
+        toml::from_str(&std::fs::read_to_string(self.state_dir.join(MAINTAIN_STATE_FILE)).ok()?)
+            .ok()
+    }
+
+    pub fn record_maintain(&self, state: &MaintainState) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.state_dir)?;
+
+        std::fs::write(
+            self.state_dir.join(MAINTAIN_STATE_FILE),
+            toml::to_string_pretty(state)?,
+        )
+        .map_err(Error::IO)
+    }
+
+    // moves extra files out of root and into a quarantine directory
+    // of their own for this run, keeping their paths relative to root,
+    // so anything removed by mistake can be put back;
+    // pinned files and those which would overwrite others are left alone
+    pub fn quarantine(&self, root: &Path, files: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+        let dir =
+            quarantine_dir(self.quarantine.clone(), &self.state_dir).join(unix_now().to_string());
+
+        let mut moved = Vec::new();
+
+        for file in files {
+            let target = match file.strip_prefix(root) {
+                Ok(relative) if !crate::pin::is_pinned(file) => dir.join(relative),
+                _ => continue,
+            };
+
+            if target.exists() {
+                continue;
+            }
+
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            match std::fs::rename(file, &target) {
+                Ok(()) => {}
+                // quarantine may well be on another filesystem
+                Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+                    std::fs::copy(file, &target)?;
+                    std::fs::remove_file(file)?;
+                }
+                Err(err) => return Err(Error::IO(err)),
+            }
+
+            crate::journal::record(crate::journal::Entry::Renamed {
+                from: crate::trash::absolute(file),
+                to: crate::trash::absolute(&target),
+            })?;
+
+            moved.push(target);
+        }
+
+        Ok(moved)
+    }
+}
+
+#[inline]
+fn default_state_dir(name: &str) -> PathBuf {
+    crate::data_dir().join(COLLECTIONS_STATE_DIR).join(name)
+}
+
+#[inline]
+fn quarantine_dir(quarantine: Option<PathBuf>, state_dir: &Path) -> PathBuf {
+    quarantine.unwrap_or_else(|| state_dir.join(QUARANTINE_DIR))
+}
+
+// where every collection moves its extra files,
+// which hold ROMs rather than any of our own state
+pub fn quarantine_dirs() -> Result<Vec<PathBuf>, Error> {
+    Ok(configs()?
+        .into_iter()
+        .map(|(name, config)| {
+            let state_dir = config.state.unwrap_or_else(|| default_state_dir(&name));
+            quarantine_dir(config.quarantine, &state_dir)
+        })
+        .collect())
+}
+
+#[inline]
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// patterns are matched against whole game names,
+// where "*" matches any run of characters
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => match name.strip_prefix(prefix) {
+            Some(name) => {
+                rest.is_empty()
+                    || name
+                        .char_indices()
+                        .map(|(i, _)| &name[i..])
+                        .chain(std::iter::once(""))
+                        .any(|tail| matches_pattern(rest, tail))
+            }
+            None => false,
+        },
+    }
+}
@@ -0,0 +1,106 @@
+use crate::sink::ProgressSink;
+use crate::Error;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static CONSUMING: AtomicBool = AtomicBool::new(false);
+
+// sources which have had their parts placed into sets,
+// as plain files and as Zip archives with the indexes of their used files
+struct Consumed {
+    files: BTreeSet<PathBuf>,
+    archives: BTreeMap<PathBuf, BTreeSet<usize>>,
+}
+
+static CONSUMED: Mutex<Consumed> = Mutex::new(Consumed {
+    files: BTreeSet::new(),
+    archives: BTreeMap::new(),
+});
+
+// once set, sources are removed after adding finishes,
+// turning a download directory into an organized collection in one pass
+#[inline]
+pub fn set_consuming() {
+    CONSUMING.store(true, Ordering::Relaxed)
+}
+
+#[inline]
+pub fn is_consuming() -> bool {
+    CONSUMING.load(Ordering::Relaxed)
+}
+
+// notes a plain file, or file within a Zip archive, as placed into a set,
+// where files within Zips nested in others are never consumed
+pub fn consumed(file: &Path, zip_parts: &[usize]) {
+    let mut consumed = CONSUMED.lock().unwrap();
+
+    match zip_parts {
+        [] => {
+            consumed.files.insert(file.to_path_buf());
+        }
+        [index] => {
+            consumed
+                .archives
+                .entry(file.to_path_buf())
+                .or_default()
+                .insert(*index);
+        }
+        _ => {}
+    }
+}
+
+// removes consumed plain files along with any Zip archives
+// whose every file was consumed, returning how many were removed,
+// where pinned sources are always kept
+// and ones which can't be removed are reported to sink
+pub fn remove_consumed(sink: &dyn ProgressSink) -> Result<usize, Error> {
+    let consumed = std::mem::replace(
+        &mut *CONSUMED.lock().unwrap(),
+        Consumed {
+            files: BTreeSet::new(),
+            archives: BTreeMap::new(),
+        },
+    );
+
+    let archives = consumed
+        .archives
+        .into_iter()
+        .filter(|(archive, used)| is_fully_consumed(archive, used))
+        .map(|(archive, _)| archive);
+
+    let sources: Vec<PathBuf> = consumed
+        .files
+        .into_iter()
+        .chain(archives)
+        .filter(|source| !crate::pin::is_pinned(source))
+        .collect();
+
+    crate::safety::before_deleting_all(sources.iter().map(|source| source.as_path()))?;
+
+    let mut removed = 0;
+
+    for source in sources {
+        match crate::trash::remove_file(&source) {
+            Ok(()) => removed += 1,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => sink.warning(&format!("{} : {}", source.display(), err)),
+        }
+    }
+
+    Ok(removed)
+}
+
+// split archives and ones which can't be read are kept
+fn is_fully_consumed(archive: &Path, used: &BTreeSet<usize>) -> bool {
+    let mut zip = match std::fs::File::open(archive).map(zip::ZipArchive::new) {
+        Ok(Ok(zip)) => zip,
+        _ => return false,
+    };
+
+    (0..zip.len()).all(|index| match zip.by_index(index) {
+        Ok(file) => file.is_dir() || used.contains(&index),
+        Err(_) => false,
+    })
+}
@@ -1,8 +1,14 @@
 use super::{Error, FileError};
-use crate::game::{GameParts, Part, RomSources, VerifyFailure};
+#[cfg(feature = "native")]
+use crate::game::RomSources;
+use crate::game::{qualify_name, GameDb, GameParts, Part, Status, VerifyFailure};
+use crate::locale::{Locale, LocaleFilter};
+use crate::select::{Candidate, Choice};
+#[cfg(feature = "native")]
+use crate::sink::{ProgressSink, Task, Total};
 use fxhash::FxHashSet;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Deserialize)]
@@ -13,23 +19,228 @@ pub struct Datafile {
 }
 
 impl Datafile {
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.header.name.as_str()
+    }
+
     #[inline]
     pub fn games(&self) -> impl Iterator<Item = &Game> {
         self.game.iter().flatten()
     }
+
+    // the DAT header's directives, with any user-provided overrides applied
+    fn directives(&self, overrides: &DirectiveOverrides) -> Directives {
+        let header = self.header.clrmamepro.as_ref();
+
+        Directives {
+            merging: overrides
+                .merging
+                .or_else(|| header.and_then(|h| h.forcemerging))
+                .unwrap_or_default(),
+            nodump: overrides
+                .nodump
+                .or_else(|| header.and_then(|h| h.forcenodump))
+                .unwrap_or_default(),
+            packing: overrides
+                .packing
+                .or_else(|| header.and_then(|h| h.forcepacking))
+                .unwrap_or_default(),
+        }
+    }
+
+    // splits the datafile into its header and games,
+    // with the games rearranged according to the merging mode
+    fn into_merged_games(self, merging: Merging) -> (Header, Vec<Game>) {
+        let mut games: Vec<Game> = self
+            .game
+            .into_iter()
+            .flatten()
+            .chain(self.machine.into_iter().flatten())
+            .collect();
+
+        match merging {
+            Merging::None => {}
+            Merging::Split => {
+                let names: FxHashSet<String> = games.iter().map(|g| g.name.clone()).collect();
+
+                // parent ROMs are expected in the parent's set
+                for game in games.iter_mut() {
+                    if matches!(&game.cloneof, Some(parent) if names.contains(parent)) {
+                        game.remove_merged();
+                    }
+                }
+            }
+            Merging::Full => {
+                let (clones, parents): (Vec<Game>, Vec<Game>) =
+                    games.into_iter().partition(|game| game.cloneof.is_some());
+
+                let mut parents: BTreeMap<String, Game> = parents
+                    .into_iter()
+                    .map(|game| (game.name.clone(), game))
+                    .collect();
+
+                // clone ROMs are folded into the parent's set
+                for mut clone in clones {
+                    match clone
+                        .cloneof
+                        .as_ref()
+                        .and_then(|parent| parents.get_mut(parent))
+                    {
+                        Some(parent) => {
+                            clone.remove_merged();
+                            parent.absorb(clone);
+                        }
+                        None => {
+                            parents.insert(clone.name.clone(), clone);
+                        }
+                    }
+                }
+
+                games = parents.into_values().collect();
+            }
+        }
+
+        (self.header, games)
+    }
+
+    // converts the datafile into a full game database,
+    // returning it along with the number of ROMs skipped
+    // for having no SHA1 to verify them by
+    pub fn into_game_db(self) -> Result<(GameDb, usize), hex::FromHexError> {
+        let description = self
+            .header
+            .description
+            .clone()
+            .unwrap_or_else(|| self.header.name.clone());
+
+        let mut skipped = 0;
+
+        let games = self
+            .game
+            .into_iter()
+            .flatten()
+            .chain(self.machine.into_iter().flatten())
+            .map(|game| {
+                skipped += game.roms().filter(|rom| rom.is_unverifiable()).count();
+                game.into_game().map(|game| (game.name.clone(), game))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok((GameDb::new(description, games), skipped))
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Header {
     name: String,
+    description: Option<String>,
     version: String,
+    clrmamepro: Option<ClrMamePro>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClrMamePro {
+    forcemerging: Option<Merging>,
+    forcenodump: Option<NoDump>,
+    forcepacking: Option<Packing>,
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Merging {
+    #[default]
+    None,
+    Split,
+    Full,
+}
+
+impl std::str::FromStr for Merging {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "none" | "non-merged" => Ok(Merging::None),
+            "split" => Ok(Merging::Split),
+            "full" | "merged" => Ok(Merging::Full),
+            _ => Err("invalid merging value".to_string()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoDump {
+    #[default]
+    Obsolete,
+    Required,
+    Ignore,
+}
+
+impl std::str::FromStr for NoDump {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "obsolete" => Ok(NoDump::Obsolete),
+            "required" => Ok(NoDump::Required),
+            "ignore" => Ok(NoDump::Ignore),
+            _ => Err("invalid nodump value".to_string()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Packing {
+    Zip,
+    #[default]
+    Unzip,
+}
+
+impl std::str::FromStr for Packing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "zip" => Ok(Packing::Zip),
+            "unzip" => Ok(Packing::Unzip),
+            _ => Err("invalid packing value".to_string()),
+        }
+    }
+}
+
+// DAT header directives, as honored by the imported database
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Directives {
+    pub merging: Merging,
+    pub nodump: NoDump,
+    pub packing: Packing,
+}
+
+// user-provided directives which take precedence over the DAT header's
+#[derive(Default)]
+pub struct DirectiveOverrides {
+    pub merging: Option<Merging>,
+    pub nodump: Option<NoDump>,
+    pub packing: Option<Packing>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Game {
     name: String,
+    cloneof: Option<String>,
+    romof: Option<String>,
+    isbios: Option<String>,
+    isdevice: Option<String>,
+    ismechanical: Option<String>,
+    category: Option<String>,
+    description: Option<String>,
+    year: Option<String>,
+    manufacturer: Option<String>,
     rom: Option<Vec<Rom>>,
     disk: Option<Vec<Disk>>,
+    release: Option<Vec<Release>>,
 }
 
 impl Game {
@@ -38,11 +249,195 @@ impl Game {
         self.name.as_str()
     }
 
+    // the locale given by the DAT's release entries, if any
+    fn release_locale(&self) -> Option<Locale> {
+        let releases = self.release.as_deref().filter(|r| !r.is_empty())?;
+
+        let mut regions = Vec::new();
+        let mut languages = Vec::new();
+        for release in releases {
+            if let Some(region) = &release.region {
+                if !regions.contains(region) {
+                    regions.push(region.clone());
+                }
+            }
+            for language in release.language.iter().flat_map(|l| l.split(',')) {
+                let language = language.trim().to_owned();
+                if !language.is_empty() && !languages.contains(&language) {
+                    languages.push(language);
+                }
+            }
+        }
+
+        Some(Locale::from_metadata(&self.name, regions, languages))
+    }
+
+    #[inline]
+    fn locale(&self) -> Locale {
+        self.release_locale()
+            .unwrap_or_else(|| Locale::from_name(&self.name))
+    }
+
     #[inline]
     pub fn roms(&self) -> impl Iterator<Item = &Rom> {
         self.rom.iter().flatten()
     }
 
+    // Logiqx DATs mark machine classes with attributes,
+    // while others give them as categories
+    fn class(&self) -> crate::game::Class {
+        use crate::game::Class;
+
+        let yes = |attr: &Option<String>| matches!(attr.as_deref(), Some("yes"));
+
+        match self.category.as_deref() {
+            _ if yes(&self.isdevice) => Class::Device,
+            _ if yes(&self.isbios) => Class::Bios,
+            _ if yes(&self.ismechanical) => Class::Mechanical,
+            Some(category) if category.eq_ignore_ascii_case("device") => Class::Device,
+            Some(category) if category.eq_ignore_ascii_case("bios") => Class::Bios,
+            Some(category) if category.eq_ignore_ascii_case("mechanical") => Class::Mechanical,
+            _ => Class::Arcade,
+        }
+    }
+
+    // the names of parts whose only dumps are known to be bad
+    fn bad_dumps(&self) -> BTreeSet<String> {
+        self.roms()
+            .filter(|rom| matches!(rom.status.as_deref(), Some("baddump")))
+            .map(|rom| rom.name.clone())
+            .chain(
+                self.disk
+                    .iter()
+                    .flatten()
+                    .filter(|disk| matches!(disk.status.as_deref(), Some("baddump")))
+                    .map(|disk| disk.name.clone() + ".chd"),
+            )
+            .collect()
+    }
+
+    // the names of parts which were never dumped
+    fn undumped(&self) -> BTreeSet<String> {
+        self.roms()
+            .filter(|rom| matches!(rom.status.as_deref(), Some("nodump")))
+            .map(|rom| rom.name.clone())
+            .chain(
+                self.disk
+                    .iter()
+                    .flatten()
+                    .filter(|disk| matches!(disk.status.as_deref(), Some("nodump")))
+                    .map(|disk| disk.name.clone() + ".chd"),
+            )
+            .collect()
+    }
+
+    #[inline]
+    fn family(&self) -> Option<Family> {
+        match (&self.cloneof, &self.romof) {
+            (None, None) => None,
+            (cloneof, romof) => Some(Family {
+                cloneof: cloneof.clone(),
+                romof: romof.clone(),
+            }),
+        }
+    }
+
+    // removes ROMs and disks which are merged from the parent's set
+    fn remove_merged(&mut self) {
+        if let Some(roms) = self.rom.as_mut() {
+            roms.retain(|rom| rom.merge.is_none());
+        }
+        if let Some(disks) = self.disk.as_mut() {
+            disks.retain(|disk| disk.merge.is_none());
+        }
+    }
+
+    // adds a clone's ROMs and disks to our own,
+    // skipping any already present under the same name,
+    // while any whose name is taken by different contents
+    // are kept in a subdirectory named for the clone
+    fn absorb(&mut self, clone: Game) {
+        let roms = self.rom.get_or_insert_with(Vec::new);
+        for mut rom in clone.rom.into_iter().flatten() {
+            match roms.iter().find(|r| r.name == rom.name) {
+                None => roms.push(rom),
+                Some(r) if r.sha1 == rom.sha1 => {}
+                Some(_) => {
+                    rom.name = qualify_name(&rom.name, &clone.name);
+                    roms.push(rom);
+                }
+            }
+        }
+
+        let disks = self.disk.get_or_insert_with(Vec::new);
+        for mut disk in clone.disk.into_iter().flatten() {
+            match disks.iter().find(|d| d.name == disk.name) {
+                None => disks.push(disk),
+                Some(d) if d.sha1 == disk.sha1 => {}
+                Some(_) => {
+                    disk.name = qualify_name(&disk.name, &clone.name);
+                    disks.push(disk);
+                }
+            }
+        }
+    }
+
+    fn into_game(self) -> Result<crate::game::Game, hex::FromHexError> {
+        let merged = self
+            .roms()
+            .filter(|rom| rom.merge.is_some())
+            .map(|rom| rom.name.clone())
+            .chain(
+                self.disk
+                    .iter()
+                    .flatten()
+                    .filter(|disk| disk.merge.is_some())
+                    .map(|disk| disk.name.clone() + ".chd"),
+            )
+            .collect();
+
+        let bad_dumps = self.bad_dumps();
+
+        let sizes = self
+            .roms()
+            .filter_map(|rom| Some((rom.name.clone(), rom.size?)))
+            .collect();
+
+        let description = self
+            .description
+            .clone()
+            .unwrap_or_else(|| self.name.clone());
+        let creator = self.manufacturer.clone().unwrap_or_default();
+        let year = self.year.clone().unwrap_or_default();
+        let cloneof = self.cloneof.clone();
+        let romof = self.romof.clone();
+        let class = self.class();
+        let locale = self.locale();
+        let (name, parts) = self.into_parts()?;
+
+        Ok(crate::game::Game {
+            name,
+            description,
+            creator,
+            year,
+            status: Status::Working,
+            driver: None,
+            is_device: false,
+            class,
+            parts,
+            devices: Vec::default(),
+            bad_dumps,
+            cloneof,
+            romof,
+            merged,
+            sizes,
+            requires: Vec::default(),
+            category: None,
+            players: None,
+            locale,
+        })
+    }
+
     #[inline]
     fn into_parts(self) -> Result<(String, GameParts), hex::FromHexError> {
         Ok((
@@ -66,7 +461,7 @@ impl Game {
     // flatten it into a single (rom_name, part) tuple,
     // otherwise return a (game_name, GameParts) tuple
     // of all the game parts it contains
-    fn try_flatten(self) -> Result<Result<(String, Part), (String, GameParts)>, hex::FromHexError> {
+    fn try_flatten(self) -> Result<FlattenedGame, hex::FromHexError> {
         match &self {
             Game {
                 rom: Some(roms),
@@ -97,13 +492,36 @@ impl Game {
     }
 }
 
+type FlattenedGame = Result<(String, Part), (String, GameParts)>;
+
+// a release of the game, as Logiqx DATs may list
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    region: Option<String>,
+    language: Option<String>,
+}
+
+// a game's parent and the set it shares ROMs with
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Family {
+    pub cloneof: Option<String>,
+    pub romof: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Rom {
     name: String,
     size: Option<u64>,
+    crc: Option<String>,
+    md5: Option<String>,
     sha1: Option<String>,
+    merge: Option<String>,
+    status: Option<String>,
 }
 
+// the SHA1 of every empty file
+const EMPTY_SHA1: &str = "da39a3ee5e6b4b0d3255bfef95601890afd80709";
+
 impl Rom {
     #[inline]
     pub fn name(&self) -> &str {
@@ -120,13 +538,32 @@ impl Rom {
         self.sha1.as_deref()
     }
 
+    // ROMs which are dumped but only identified by CRC32 or MD5,
+    // which parts can't be verified against
+    #[inline]
+    fn is_unverifiable(&self) -> bool {
+        self.sha1.is_none()
+            && self.size != Some(0)
+            && (self.crc.is_some() || self.md5.is_some())
+            && !matches!(self.status.as_deref(), Some("nodump"))
+    }
+
     #[inline]
     fn into_part(self) -> Option<Result<(String, Part), hex::FromHexError>> {
-        match self.sha1 {
-            Some(sha1) => match Part::new_rom(&sha1) {
+        // undumped parts can't be verified, so skip them entirely
+        if matches!(self.status.as_deref(), Some("nodump")) {
+            return None;
+        }
+
+        match self.sha1.as_deref() {
+            Some(sha1) => match Part::new_rom(sha1) {
                 Ok(part) => Some(Ok((self.name, part))),
                 Err(err) => Some(Err(err)),
             },
+            // some DATs leave empty files' digests out
+            None if self.size == Some(0) => Part::new_rom(EMPTY_SHA1)
+                .ok()
+                .map(|part| Ok((self.name, part))),
             None => None,
         }
     }
@@ -136,11 +573,18 @@ impl Rom {
 pub struct Disk {
     name: String,
     sha1: Option<String>,
+    merge: Option<String>,
+    status: Option<String>,
 }
 
 impl Disk {
     #[inline]
     fn into_part(self) -> Option<Result<(String, Part), hex::FromHexError>> {
+        // undumped parts can't be verified, so skip them entirely
+        if matches!(self.status.as_deref(), Some("nodump")) {
+            return None;
+        }
+
         match self.sha1 {
             Some(sha1) => match Part::new_disk(&sha1) {
                 Ok(part) => Some(Ok((self.name + ".chd", part))),
@@ -155,59 +599,144 @@ impl Disk {
 pub struct DatFile {
     name: String,
     version: String,
+    #[serde(default)]
+    directives: Directives,
     // games with a single ROM
     flat: GameParts,
     // games with multiple ROMs
     tree: BTreeMap<String, GameParts>,
+    // games which are clones or share another's ROMs
+    #[serde(default)]
+    families: BTreeMap<String, Family>,
+    // locales given by the DAT's metadata,
+    // where games without any have them parsed from their names
+    #[serde(default)]
+    locales: BTreeMap<String, Locale>,
+    // with nodump = "required", each game's parts which were never dumped
+    // but must be present all the same, found by name alone
+    #[serde(default)]
+    undumped: BTreeMap<String, BTreeSet<String>>,
+    // games, as keyed in flat or tree, with parts whose only dumps are bad
+    #[serde(default)]
+    bad_dumps: BTreeSet<String>,
 }
 
 impl DatFile {
-    pub fn new_flattened(datafile: Datafile) -> Result<Self, hex::FromHexError> {
+    pub fn new_flattened(
+        datafile: Datafile,
+        overrides: &DirectiveOverrides,
+    ) -> Result<Self, hex::FromHexError> {
+        let directives = datafile.directives(overrides);
+
+        // zipped games are each an archive of their own, even with one part
+        if matches!(directives.packing, Packing::Zip) {
+            return Self::new_unflattened(datafile, overrides);
+        }
+
+        let (header, games) = datafile.into_merged_games(directives.merging);
+
         let mut flat = GameParts::default();
         let mut tree = BTreeMap::default();
+        let mut families = BTreeMap::default();
+        let mut locales = BTreeMap::default();
+        let mut undumped = BTreeMap::default();
+        let mut bad_dumps = BTreeSet::default();
 
-        for game in datafile
-            .game
-            .into_iter()
-            .flatten()
-            .chain(datafile.machine.into_iter().flatten())
-        {
-            match game.try_flatten()? {
+        for game in games {
+            if let Some(family) = game.family() {
+                families.insert(game.name.clone(), family);
+            }
+
+            if matches!(directives.nodump, NoDump::Required) {
+                let names = game.undumped();
+                if !names.is_empty() {
+                    undumped.insert(game.name.clone(), names);
+                }
+            }
+
+            let locale = game.release_locale();
+            let has_bad_dumps = !game.bad_dumps().is_empty();
+
+            let name = match game.try_flatten()? {
                 Ok((name, part)) => {
-                    flat.insert(name, part);
+                    flat.insert(name.clone(), part);
+                    name
                 }
                 Err((name, parts)) => {
-                    tree.insert(name, parts);
+                    tree.insert(name.clone(), parts);
+                    name
                 }
+            };
+
+            if has_bad_dumps {
+                bad_dumps.insert(name.clone());
+            }
+
+            if let Some(locale) = locale {
+                locales.insert(name, locale);
             }
         }
 
         Ok(Self {
-            name: datafile.header.name,
-            version: datafile.header.version,
+            name: header.name,
+            version: header.version,
+            directives,
             flat,
             tree,
+            families,
+            locales,
+            undumped,
+            bad_dumps,
         })
     }
 
-    pub fn new_unflattened(datafile: Datafile) -> Result<Self, hex::FromHexError> {
+    pub fn new_unflattened(
+        datafile: Datafile,
+        overrides: &DirectiveOverrides,
+    ) -> Result<Self, hex::FromHexError> {
+        let directives = datafile.directives(overrides);
+        let (header, games) = datafile.into_merged_games(directives.merging);
+
         let mut tree = BTreeMap::default();
+        let mut families = BTreeMap::default();
+        let mut locales = BTreeMap::default();
+        let mut undumped = BTreeMap::default();
+        let mut bad_dumps = BTreeSet::default();
+
+        for game in games {
+            if let Some(family) = game.family() {
+                families.insert(game.name.clone(), family);
+            }
+
+            if matches!(directives.nodump, NoDump::Required) {
+                let names = game.undumped();
+                if !names.is_empty() {
+                    undumped.insert(game.name.clone(), names);
+                }
+            }
+
+            if let Some(locale) = game.release_locale() {
+                locales.insert(game.name.clone(), locale);
+            }
+
+            if !game.bad_dumps().is_empty() {
+                bad_dumps.insert(game.name.clone());
+            }
 
-        for game in datafile
-            .game
-            .into_iter()
-            .flatten()
-            .chain(datafile.machine.into_iter().flatten())
-        {
             let (name, parts) = game.into_parts()?;
             tree.insert(name, parts);
         }
 
         Ok(Self {
-            name: datafile.header.name,
-            version: datafile.header.version,
+            name: header.name,
+            version: header.version,
+            directives,
             flat: GameParts::default(),
             tree,
+            families,
+            locales,
+            undumped,
+            bad_dumps,
         })
     }
 
@@ -219,59 +748,100 @@ impl DatFile {
         self.version.as_str()
     }
 
-    pub fn games(&self) -> impl Iterator<Item = &str> {
-        self.flat.keys().chain(self.tree.keys()).map(|s| s.as_str())
+    pub fn directives(&self) -> Directives {
+        self.directives
     }
 
-    pub fn game_parts(&self) -> impl Iterator<Item = (&str, &GameParts)> {
-        std::iter::once(("", &self.flat))
-            .chain(self.tree.iter().map(|(game, parts)| (game.as_str(), parts)))
+    pub fn games(&self) -> impl Iterator<Item = &str> {
+        self.flat.keys().chain(self.tree.keys()).map(|s| s.as_str())
     }
 
-    pub fn list_all<I, T>(iter: I)
-    where
-        I: IntoIterator<Item = (T, Self)>,
-    {
-        use prettytable::{cell, format, row, Table};
-        let mut table = Table::new();
-
-        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-
-        for (_, datfile) in iter {
-            table.add_row(row![datfile.name(), datfile.version()]);
+    // keeps only games in the wanted regions and languages
+    pub fn retain_locale(&mut self, filter: &LocaleFilter) {
+        if filter.is_empty() {
+            return;
         }
 
-        table.printstd();
+        let locales = &self.locales;
+        let allowed = |name: &String| match locales.get(name) {
+            Some(locale) => filter.allows(locale),
+            None => filter.allows(&Locale::from_name(name)),
+        };
+
+        self.flat.retain(|name, _| allowed(name));
+        self.tree.retain(|name, _| allowed(name));
     }
 
-    pub fn list(&self) {
-        use prettytable::{cell, format, row, Table};
+    // keeps only the one preferred game of each title,
+    // returning the choices made
+    pub fn retain_one_game_one_rom(&mut self, priority: &[String]) -> Vec<Choice> {
+        let choices = crate::select::one_game_one_rom(
+            self.games().map(|key| {
+                // single-part games are keyed by their part's name
+                let name = if self.flat.contains_key(key) {
+                    Path::new(key)
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .unwrap_or(key)
+                } else {
+                    key
+                };
+
+                Candidate {
+                    key,
+                    name,
+                    parent: self.parent(name),
+                    locale: self
+                        .locales
+                        .get(key)
+                        .cloned()
+                        .unwrap_or_else(|| Locale::from_name(name)),
+                }
+            }),
+            priority,
+        );
 
-        let mut games = self.games().collect::<Vec<_>>();
-        games.sort_unstable();
+        let chosen: FxHashSet<&str> = choices.iter().map(|choice| choice.key.as_str()).collect();
+        self.flat.retain(|name, _| chosen.contains(name.as_str()));
+        self.tree.retain(|name, _| chosen.contains(name.as_str()));
 
-        let mut table = Table::new();
+        choices
+    }
 
-        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    pub fn game_parts(&self) -> impl Iterator<Item = (&str, &GameParts)> {
+        std::iter::once(("", &self.flat))
+            .chain(self.tree.iter().map(|(game, parts)| (game.as_str(), parts)))
+    }
 
-        for game in games {
-            table.add_row(row![game]);
-        }
+    // whether the game, as keyed in results, can be no better than bad dumps
+    #[inline]
+    pub fn has_bad_dumps(&self, game: &str) -> bool {
+        self.bad_dumps.contains(game)
+    }
 
-        table.printstd();
+    #[inline]
+    pub fn parent(&self, game: &str) -> Option<&str> {
+        self.families.get(game).and_then(|f| f.cloneof.as_deref())
     }
 
-    pub fn verify(&self, root: &Path, all: bool) -> BTreeMap<&str, Vec<VerifyFailure>> {
-        let progress_bar =
-            indicatif::ProgressBar::new(self.flat.len() as u64 + self.tree.len() as u64)
-                .with_style(crate::game::verify_style())
-                .with_message(format!("verifying : {} ({})", self.name, self.version));
+    #[cfg(feature = "native")]
+    pub fn verify(
+        &self,
+        root: &Path,
+        all: bool,
+        sink: &dyn ProgressSink,
+    ) -> BTreeMap<&str, Vec<VerifyFailure<'_>>> {
+        let task = Task::start(
+            sink,
+            &format!("verifying : {} ({})", self.name, self.version),
+            Total::Items(self.flat.len() as u64 + self.tree.len() as u64),
+        );
 
         let mut failures = BTreeMap::default();
 
-        let (flat_successes, flat_failures) = self
-            .flat
-            .verify_with_progress::<Vec<_>, Vec<_>, _>(root, || progress_bar.inc(1));
+        let (flat_successes, flat_failures) =
+            self.flat
+                .verify_with_progress::<Vec<_>, Vec<_>, _>(root, sink, || task.advance(1));
 
         failures.extend(
             flat_successes
@@ -290,11 +860,10 @@ impl DatFile {
                     }),
             );
 
-            failures.extend(
-                progress_bar
-                    .wrap_iter(self.tree.iter())
-                    .map(|(name, game)| (name.as_str(), game.verify_failures(&root.join(name)))),
-            );
+            failures.extend(self.tree.iter().map(|(name, game)| {
+                task.advance(1);
+                (name.as_str(), self.verify_game(root, name, game, sink))
+            }));
         } else {
             failures.extend(
                 flat_failures
@@ -305,41 +874,69 @@ impl DatFile {
                     }),
             );
 
-            for (name, game) in progress_bar.wrap_iter(self.tree.iter()) {
-                let game_root = root.join(name);
-                if game_root.is_dir() {
-                    failures.insert(name, game.verify_failures(&game_root));
+            for (name, game) in self.tree.iter() {
+                task.advance(1);
+
+                if self.game_path(root, name).exists() {
+                    failures.insert(name, self.verify_game(root, name, game, sink));
                 }
             }
         }
 
-        progress_bar.finish_and_clear();
+        drop(task);
+
+        for (game, game_failures) in failures.iter() {
+            crate::game::report_failures(game, game_failures, sink);
+        }
+
+        crate::hooks::fire_results(
+            &self.name,
+            &failures,
+            crate::hooks::Event::VerifyComplete {
+                name: &self.name,
+                root,
+                tested: failures.len(),
+                ok: failures.values().filter(|v| v.is_empty()).count(),
+            },
+            sink,
+        );
 
         failures
     }
 
+    #[cfg(feature = "native")]
     pub fn add_and_verify(
         &self,
         roms: &mut RomSources,
         root: &Path,
         all: bool,
-    ) -> Result<BTreeMap<&str, Vec<VerifyFailure>>, Error> {
-        let progress_bar =
-            indicatif::ProgressBar::new(self.flat.len() as u64 + self.tree.len() as u64)
-                .with_style(crate::game::verify_style())
-                .with_message(format!(
-                    "adding and verifying : {} ({})",
-                    self.name, self.version
-                ));
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+    ) -> Result<(BTreeMap<&str, Vec<VerifyFailure<'_>>>, crate::FailedGames), Error> {
+        if !dry_run {
+            crate::snapshot::before_modifying(root, sink)?;
+        }
+
+        let task = Task::start(
+            sink,
+            &format!("adding and verifying : {} ({})", self.name, self.version),
+            Total::Items(self.flat.len() as u64 + self.tree.len() as u64),
+        );
+
+        let stamp = crate::stamp::Stamp::new(&self.name);
 
         let mut failures: BTreeMap<&str, Vec<_>> = BTreeMap::default();
 
-        let (flat_successes, flat_failures): (Vec<_>, Vec<_>) =
-            self.flat.add_and_verify_with_progress(
+        // each flattened part is a game of its own,
+        // so one which can't be added doesn't stop the rest
+        let (flat_successes, flat_failures, mut failed): (Vec<_>, Vec<_>, _) =
+            self.flat.add_and_verify_each(
                 roms,
                 root,
-                || progress_bar.inc(1),
-                |r| progress_bar.println(r.to_string()),
+                dry_run,
+                sink,
+                || task.advance(1),
+                |part| sink.part_placed(&part),
             )?;
 
         failures.extend(
@@ -359,13 +956,23 @@ impl DatFile {
                     }),
             );
 
-            for (name, game) in progress_bar.wrap_iter(self.tree.iter()) {
-                failures.insert(
+            for (name, game) in self.tree.iter() {
+                task.advance(1);
+
+                match self.add_and_verify_game(
+                    roms,
+                    root,
                     name,
-                    game.add_and_verify_failures(roms, &root.join(name), |r| {
-                        progress_bar.println(r.to_string())
-                    })?,
-                );
+                    game,
+                    stamp.as_ref(),
+                    dry_run,
+                    sink,
+                ) {
+                    Ok((_, game_failures)) => {
+                        failures.insert(name, game_failures);
+                    }
+                    Err(err) => failed.push((name.clone(), err)),
+                }
             }
         } else {
             failures.extend(
@@ -377,30 +984,168 @@ impl DatFile {
                     }),
             );
 
-            for (name, game) in progress_bar.wrap_iter(self.tree.iter()) {
-                let (
-                    crate::game::ExtendExists {
-                        exists: has_successes,
-                        ..
-                    },
-                    game_failures,
-                ): (_, Vec<_>) = game.add_and_verify(roms, &root.join(name), |r| {
-                    progress_bar.println(r.to_string())
-                })?;
+            for (name, game) in self.tree.iter() {
+                task.advance(1);
+
+                let (has_successes, game_failures) = match self.add_and_verify_game(
+                    roms,
+                    root,
+                    name,
+                    game,
+                    stamp.as_ref(),
+                    dry_run,
+                    sink,
+                ) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        failed.push((name.clone(), err));
+                        continue;
+                    }
+                };
 
                 if has_successes
-                    || !game_failures
-                        .iter()
-                        .all(|f| matches!(f, VerifyFailure::Missing { .. }))
+                    || !game_failures.iter().all(|f| {
+                        matches!(
+                            f,
+                            VerifyFailure::Missing { .. } | VerifyFailure::MissingUndumped { .. }
+                        )
+                    })
                 {
                     failures.insert(name, game_failures);
                 }
             }
         }
 
-        progress_bar.finish_and_clear();
+        drop(task);
+
+        for (game, game_failures) in failures.iter() {
+            crate::game::report_failures(game, game_failures, sink);
+        }
+
+        if !dry_run {
+            crate::hooks::fire_results(
+                &self.name,
+                &failures,
+                crate::hooks::Event::AddFinished {
+                    name: &self.name,
+                    root,
+                    added: failures.len(),
+                    ok: failures.values().filter(|v| v.is_empty()).count(),
+                },
+                sink,
+            );
+        }
+
+        Ok((failures, failed))
+    }
+
+    // where a game of several parts is stored under root
+    #[cfg(feature = "native")]
+    #[inline]
+    fn game_path(&self, root: &Path, game: &str) -> PathBuf {
+        match self.directives.packing {
+            Packing::Zip => root.join(format!("{}.zip", game)),
+            Packing::Unzip => root.join(game),
+        }
+    }
+
+    // verifies a game of several parts as the DAT packs it,
+    // where zipped games keep their disks in a directory alongside
+    #[cfg(feature = "native")]
+    fn verify_game<'s>(
+        &'s self,
+        root: &Path,
+        name: &str,
+        game: &'s GameParts,
+        sink: &dyn ProgressSink,
+    ) -> Vec<VerifyFailure<'s>> {
+        let game_path = self.game_path(root, name);
+
+        let mut failures = match self.directives.packing {
+            Packing::Zip => {
+                let (_, failures): (crate::game::ExtendExists<_>, _) =
+                    game.verify_zip(&game_path, &root.join(name), sink);
+                failures
+            }
+            Packing::Unzip => game.verify_failures(&game_path, sink),
+        };
+
+        self.check_undumped(name, &game_path, &root.join(name), &mut failures);
+
+        failures
+    }
+
+    // adds and verifies a game of several parts as the DAT packs it,
+    // along with whether any of the game was there afterward
+    #[cfg(feature = "native")]
+    #[allow(clippy::too_many_arguments)]
+    fn add_and_verify_game<'s>(
+        &'s self,
+        roms: &RomSources,
+        root: &Path,
+        name: &str,
+        game: &'s GameParts,
+        stamp: Option<&crate::stamp::Stamp>,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+    ) -> Result<(bool, Vec<VerifyFailure<'s>>), Error> {
+        let game_path = self.game_path(root, name);
+
+        let (exists, mut failures) = match self.directives.packing {
+            Packing::Zip => {
+                let existed = game_path.is_file();
+                let placed = std::cell::Cell::new(false);
+                let failures = game.add_and_verify_zip(
+                    roms,
+                    &game_path,
+                    &root.join(name),
+                    stamp,
+                    dry_run,
+                    sink,
+                    |part| {
+                        placed.set(true);
+                        sink.part_placed(&part)
+                    },
+                )?;
+                (existed || placed.get(), failures)
+            }
+            Packing::Unzip => {
+                let (crate::game::ExtendExists { exists, .. }, failures) =
+                    game.add_and_verify(roms, &game_path, dry_run, sink, |part| {
+                        sink.part_placed(&part)
+                    })?;
+                (exists, failures)
+            }
+        };
+
+        self.check_undumped(name, &game_path, &root.join(name), &mut failures);
 
-        Ok(failures)
+        Ok((exists, failures))
+    }
+
+    // with nodump = "required", a game's undumped parts must be present,
+    // so any found aren't extras and any not found are missing
+    #[cfg(feature = "native")]
+    fn check_undumped<'s>(
+        &'s self,
+        game: &str,
+        game_path: &Path,
+        disk_root: &Path,
+        failures: &mut Vec<VerifyFailure<'s>>,
+    ) {
+        for name in self.undumped.get(game).into_iter().flatten() {
+            let path = game_path.join(name);
+
+            match failures.iter().position(
+                |failure| matches!(failure, VerifyFailure::Extra { path: extra, .. } if extra == &path),
+            ) {
+                Some(found) => {
+                    failures.remove(found);
+                }
+                None if disk_root.join(name).is_file() => {}
+                None => failures.push(VerifyFailure::MissingUndumped { path, name }),
+            }
+        }
     }
 
     pub fn required_parts(&self) -> FxHashSet<Part> {
@@ -412,22 +1157,49 @@ impl DatFile {
     }
 }
 
+#[cfg(feature = "native")]
 #[inline]
-fn parse_dat(file: PathBuf, data: Box<[u8]>, flatten: bool) -> Result<DatFile, Error> {
-    let datafile: Datafile = match quick_xml::de::from_reader(std::io::Cursor::new(data)) {
-        Ok(dat) => dat,
-        Err(error) => return Err(Error::XmlFile(FileError { file, error })),
-    };
+fn parse_dat(
+    file: PathBuf,
+    data: Box<[u8]>,
+    flatten: bool,
+    overrides: &DirectiveOverrides,
+) -> Result<DatFile, Error> {
+    let datafile = parse_datafile(&file, &data)?;
 
     (if flatten {
-        DatFile::new_flattened(datafile)
+        DatFile::new_flattened(datafile, overrides)
     } else {
-        DatFile::new_unflattened(datafile)
+        DatFile::new_unflattened(datafile, overrides)
     })
     .map_err(|error| Error::InvalidSha1(FileError { file, error }))
 }
 
-pub fn read_dats_from_file(file: PathBuf) -> Result<Vec<(PathBuf, Box<[u8]>)>, Error> {
+// parses DAT data in either Logiqx XML or clrmamepro's text format
+pub fn parse_datafile(file: &Path, data: &[u8]) -> Result<Datafile, Error> {
+    let text = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+
+    match text.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'<') => quick_xml::de::from_reader(text).map_err(|error| {
+            Error::XmlFile(FileError {
+                file: file.to_owned(),
+                error,
+            })
+        }),
+        _ => Datafile::from_clrmamepro(&String::from_utf8_lossy(text)).map_err(|error| {
+            Error::ClrMamePro(FileError {
+                file: file.to_owned(),
+                error,
+            })
+        }),
+    }
+}
+
+#[cfg(feature = "native")]
+type DatData = (PathBuf, Box<[u8]>);
+
+#[cfg(feature = "native")]
+pub fn read_dats_from_file(file: PathBuf) -> Result<Vec<DatData>, Error> {
     use super::is_zip;
     use std::io::Read;
 
@@ -460,20 +1232,623 @@ pub fn read_dats_from_file(file: PathBuf) -> Result<Vec<(PathBuf, Box<[u8]>)>, E
     }
 }
 
+#[cfg(feature = "native")]
 #[inline]
-pub fn read_dats(file: PathBuf) -> Result<Vec<DatFile>, Error> {
+pub fn read_dats(file: PathBuf, overrides: &DirectiveOverrides) -> Result<Vec<DatFile>, Error> {
     read_dats_from_file(file).and_then(|v| {
         v.into_iter()
-            .map(|(file, data)| parse_dat(file, data, true))
+            .map(|(file, data)| parse_dat(file, data, true, overrides))
             .collect()
     })
 }
 
+#[cfg(feature = "native")]
 #[inline]
-pub fn read_unflattened_dats(file: PathBuf) -> Result<Vec<DatFile>, Error> {
+pub fn read_unflattened_dats(
+    file: PathBuf,
+    overrides: &DirectiveOverrides,
+) -> Result<Vec<DatFile>, Error> {
     read_dats_from_file(file).and_then(|v| {
         v.into_iter()
-            .map(|(file, data)| parse_dat(file, data, false))
+            .map(|(file, data)| parse_dat(file, data, false, overrides))
             .collect()
     })
 }
+
+// escapes text for use in XML or HTML
+pub fn escape(s: &str) -> std::borrow::Cow<'_, str> {
+    if s.contains(['&', '<', '>', '"', '\'']) {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+            .into()
+    } else {
+        s.into()
+    }
+}
+
+// writes games as a Logiqx DAT, with merged parts marked
+// so other tools can rebuild them in any layout
+//
+// since our databases only store SHA1 digests,
+// ROMs are written without sizes or CRCs
+pub fn write_logiqx<'g, W, I>(
+    mut w: W,
+    name: &str,
+    description: &str,
+    version: &str,
+    games: I,
+) -> Result<(), std::io::Error>
+where
+    W: std::io::Write,
+    I: IntoIterator<Item = &'g crate::game::Game>,
+{
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/Dats/datafile.dtd">"#
+    )?;
+    writeln!(w, "<datafile>")?;
+    writeln!(w, "\t<header>")?;
+    writeln!(w, "\t\t<name>{}</name>", escape(name))?;
+    writeln!(w, "\t\t<description>{}</description>", escape(description))?;
+    writeln!(w, "\t\t<version>{}</version>", escape(version))?;
+    writeln!(w, "\t</header>")?;
+
+    for game in games {
+        write!(w, "\t<game name=\"{}\"", escape(&game.name))?;
+        if game.is_device {
+            write!(w, " isdevice=\"yes\"")?;
+        }
+        if let Some(cloneof) = &game.cloneof {
+            write!(w, " cloneof=\"{}\"", escape(cloneof))?;
+        }
+        if let Some(romof) = &game.romof {
+            write!(w, " romof=\"{}\"", escape(romof))?;
+        }
+        writeln!(w, ">")?;
+
+        writeln!(
+            w,
+            "\t\t<description>{}</description>",
+            escape(&game.description)
+        )?;
+        if !game.year.is_empty() {
+            writeln!(w, "\t\t<year>{}</year>", escape(&game.year))?;
+        }
+        if !game.creator.is_empty() {
+            writeln!(
+                w,
+                "\t\t<manufacturer>{}</manufacturer>",
+                escape(&game.creator)
+            )?;
+        }
+
+        let parts: BTreeMap<&str, &Part> = game
+            .parts
+            .iter()
+            .map(|(name, part)| (name.as_str(), part))
+            .collect();
+
+        for (part_name, part) in parts {
+            let (element, name, digest) = match part {
+                Part::Rom { sha1 } => ("rom", part_name, hex::encode(sha1)),
+                Part::Disk { sha1 } => (
+                    "disk",
+                    part_name.strip_suffix(".chd").unwrap_or(part_name),
+                    hex::encode(sha1),
+                ),
+            };
+
+            write!(w, "\t\t<{} name=\"{}\"", element, escape(name))?;
+            if game.merged.contains(part_name) {
+                write!(w, " merge=\"{}\"", escape(name))?;
+            }
+            write!(w, " sha1=\"{}\"", digest)?;
+            if game.bad_dumps.contains(part_name) {
+                write!(w, " status=\"baddump\"")?;
+            }
+            writeln!(w, "/>")?;
+        }
+
+        for device in game.devices.iter() {
+            writeln!(w, "\t\t<device_ref name=\"{}\"/>", escape(device))?;
+        }
+
+        writeln!(w, "\t</game>")?;
+    }
+
+    writeln!(w, "</datafile>")?;
+    w.flush()
+}
+
+// the games found with missing or bad parts while verifying,
+// limited to just those parts, to be sourced elsewhere
+pub struct Fixdat {
+    path: PathBuf,
+    games: BTreeMap<String, crate::game::Game>,
+}
+
+impl Fixdat {
+    #[inline]
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            games: BTreeMap::default(),
+        }
+    }
+
+    pub fn add_failures(&mut self, game: &crate::game::Game, failures: &[VerifyFailure]) {
+        let wanted: Vec<(&str, &Part)> = failures
+            .iter()
+            .filter_map(|failure| match failure {
+                VerifyFailure::Missing { name, part, .. }
+                | VerifyFailure::Bad {
+                    name,
+                    expected: part,
+                    ..
+                } => Some((*name, *part)),
+                _ => None,
+            })
+            .collect();
+
+        if wanted.is_empty() {
+            return;
+        }
+
+        let fix = self
+            .games
+            .entry(game.name.clone())
+            .or_insert_with(|| crate::game::Game {
+                name: game.name.clone(),
+                description: game.description.clone(),
+                creator: game.creator.clone(),
+                year: game.year.clone(),
+                cloneof: game.cloneof.clone(),
+                romof: game.romof.clone(),
+                ..crate::game::Game::default()
+            });
+
+        for (name, part) in wanted {
+            if game.merged.contains(name) {
+                fix.merged.insert(name.to_owned());
+            }
+            fix.parts
+                .extend(std::iter::once((name.to_owned(), part.clone())));
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    pub fn write(self, name: &str, description: &str) -> Result<(), Error> {
+        write_logiqx(
+            std::io::BufWriter::new(std::fs::File::create(&self.path)?),
+            &format!("fix_{}", name),
+            description,
+            "1",
+            self.games.values(),
+        )
+        .map_err(Error::IO)
+    }
+}
+
+// clrmamepro's older text DAT format, like:
+//
+// clrmamepro (
+// 	name "Nintendo - Game Boy"
+// 	version 20220101
+// 	forcemerging split
+// )
+//
+// game (
+// 	name "Tetris (World)"
+// 	description "Tetris (World)"
+// 	rom ( name "Tetris (World).gb" size 32768 crc 46df91ad sha1 74591cc9... )
+// )
+impl Datafile {
+    fn from_clrmamepro(text: &str) -> Result<Self, ClrMameProError> {
+        let mut tokens = Tokens::new(text);
+        let mut header = None;
+        let mut games = Vec::new();
+
+        while let Some((line, token)) = tokens.next().transpose()? {
+            let key = match token {
+                Token::Word(key) => key,
+                _ => return Err(ClrMameProError::new(line, "expected block name")),
+            };
+
+            let block = match tokens.next().transpose()? {
+                Some((_, Token::Open)) => Block::parse(&mut tokens, line)?,
+                _ => return Err(ClrMameProError::new(line, "expected \"(\"")),
+            };
+
+            match key.as_str() {
+                "clrmamepro" | "emulator" => header = Some(block.into_header()),
+                "game" | "machine" | "resource" => games.push(block.into_game(line)?),
+                // anything else, like "sample" blocks, isn't needed
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            header: header.ok_or_else(|| ClrMameProError::new(1, "missing header"))?,
+            game: Some(games),
+            machine: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ClrMameProError {
+    line: usize,
+    message: &'static str,
+}
+
+impl ClrMameProError {
+    #[inline]
+    fn new(line: usize, message: &'static str) -> Self {
+        Self { line, message }
+    }
+}
+
+impl std::error::Error for ClrMameProError {}
+
+impl std::fmt::Display for ClrMameProError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+struct Tokens<'t> {
+    chars: std::iter::Peekable<std::str::Chars<'t>>,
+    line: usize,
+}
+
+impl<'t> Tokens<'t> {
+    #[inline]
+    fn new(text: &'t str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+}
+
+impl<'t> Iterator for Tokens<'t> {
+    type Item = Result<(usize, Token), ClrMameProError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(c) = self.chars.next_if(|c| c.is_whitespace()) {
+            if c == '\n' {
+                self.line += 1;
+            }
+        }
+
+        let line = self.line;
+
+        Some(Ok((
+            line,
+            match self.chars.next()? {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                // there are no escapes, so backslashes in
+                // paths like "Disc\track01.bin" are kept as-is
+                '"' => {
+                    let mut word = String::new();
+                    loop {
+                        match self.chars.next() {
+                            Some('"') => break,
+                            Some(c) => {
+                                if c == '\n' {
+                                    self.line += 1;
+                                }
+                                word.push(c)
+                            }
+                            None => {
+                                return Some(Err(ClrMameProError::new(line, "unterminated string")))
+                            }
+                        }
+                    }
+                    Token::Word(word)
+                }
+                c => {
+                    let mut word = c.to_string();
+                    while let Some(c) = self
+                        .chars
+                        .next_if(|c| !c.is_whitespace() && *c != '(' && *c != ')')
+                    {
+                        word.push(c);
+                    }
+                    Token::Word(word)
+                }
+            },
+        )))
+    }
+}
+
+enum Value {
+    Word(String),
+    Block(Block),
+}
+
+// a block's fields in the order given
+struct Block(Vec<(String, Value)>);
+
+impl Block {
+    // parses fields up to the block's closing parenthesis
+    fn parse(tokens: &mut Tokens, start: usize) -> Result<Self, ClrMameProError> {
+        let mut fields = Vec::new();
+
+        loop {
+            let key = match tokens.next().transpose()? {
+                Some((_, Token::Close)) => break Ok(Block(fields)),
+                Some((_, Token::Word(key))) => key,
+                Some((line, Token::Open)) => {
+                    break Err(ClrMameProError::new(line, "unexpected \"(\""))
+                }
+                None => break Err(ClrMameProError::new(start, "unclosed block")),
+            };
+
+            fields.push((
+                key,
+                match tokens.next().transpose()? {
+                    Some((line, Token::Open)) => Value::Block(Block::parse(tokens, line)?),
+                    Some((_, Token::Word(value))) => Value::Word(value),
+                    Some((line, Token::Close)) => {
+                        return Err(ClrMameProError::new(line, "missing value"))
+                    }
+                    None => return Err(ClrMameProError::new(start, "unclosed block")),
+                },
+            ));
+        }
+    }
+
+    fn word(&self, key: &str) -> Option<String> {
+        self.0.iter().find_map(|(k, value)| match value {
+            Value::Word(word) if k == key => Some(word.clone()),
+            _ => None,
+        })
+    }
+
+    fn blocks<'b>(&'b self, key: &'b str) -> impl Iterator<Item = &'b Block> {
+        self.0.iter().filter_map(move |(k, value)| match value {
+            Value::Block(block) if k == key => Some(block),
+            _ => None,
+        })
+    }
+
+    fn into_header(self) -> Header {
+        Header {
+            name: self.word("name").unwrap_or_default(),
+            description: self.word("description"),
+            version: self.word("version").unwrap_or_default(),
+            clrmamepro: Some(ClrMamePro {
+                forcemerging: self.word("forcemerging").and_then(|s| s.parse().ok()),
+                forcenodump: self.word("forcenodump").and_then(|s| s.parse().ok()),
+                forcepacking: self.word("forcepacking").and_then(|s| s.parse().ok()),
+            }),
+        }
+    }
+
+    fn into_game(self, line: usize) -> Result<Game, ClrMameProError> {
+        // older DATs flag bad dumps as "flags baddump"
+        let status = |block: &Block| block.word("status").or_else(|| block.word("flags"));
+
+        let rom = self
+            .blocks("rom")
+            .map(|rom| {
+                Ok(Rom {
+                    name: rom
+                        .word("name")
+                        .ok_or_else(|| ClrMameProError::new(line, "ROM without name"))?,
+                    size: rom.word("size").and_then(|s| s.parse().ok()),
+                    crc: rom.word("crc"),
+                    md5: rom.word("md5"),
+                    sha1: rom.word("sha1"),
+                    merge: rom.word("merge"),
+                    status: status(rom),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let disk = self
+            .blocks("disk")
+            .map(|disk| {
+                Ok(Disk {
+                    name: disk
+                        .word("name")
+                        .ok_or_else(|| ClrMameProError::new(line, "disk without name"))?,
+                    sha1: disk.word("sha1"),
+                    merge: disk.word("merge"),
+                    status: status(disk),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Game {
+            name: self
+                .word("name")
+                .ok_or_else(|| ClrMameProError::new(line, "game without name"))?,
+            cloneof: self.word("cloneof"),
+            romof: self.word("romof"),
+            isbios: None,
+            isdevice: None,
+            ismechanical: None,
+            category: self.word("category"),
+            description: self.word("description"),
+            year: self.word("year"),
+            manufacturer: self.word("manufacturer"),
+            rom: (!rom.is_empty()).then_some(rom),
+            disk: (!disk.is_empty()).then_some(disk),
+            release: Some(
+                self.blocks("release")
+                    .map(|release| Release {
+                        region: release.word("region"),
+                        language: release.word("language"),
+                    })
+                    .collect(),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        Tokens::new(text)
+            .filter_map(|token| match token.unwrap().1 {
+                Token::Word(word) => Some(word),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn error_line(text: &str) -> usize {
+        Datafile::from_clrmamepro(text).err().unwrap().line
+    }
+
+    #[test]
+    fn clrmamepro_words() {
+        assert_eq!(
+            words("name \"Tetris (World)\" size 32768 crc 46df91ad"),
+            ["name", "Tetris (World)", "size", "32768", "crc", "46df91ad"]
+        );
+        assert_eq!(words("name(foo)"), ["name", "foo"]);
+        assert_eq!(
+            words(r#"name "Disc\track01.bin""#),
+            ["name", r"Disc\track01.bin"]
+        );
+        assert_eq!(words("name \"\""), ["name", ""]);
+    }
+
+    #[test]
+    fn clrmamepro_blocks() {
+        let datafile = Datafile::from_clrmamepro(
+            r#"clrmamepro (
+	name "Sony - PlayStation"
+	version 20220101
+	forcemerging split
+)
+
+game (
+	name "Game (USA)"
+	description "Game (USA)"
+	rom ( name "Game (USA)\track01.bin" size 1024 crc 46df91ad sha1 74591cc9d4e8a4f3e8a0ad6e6f1d3a9a4d5ed7a1 )
+	rom ( name "Game (USA).cue" size 100 flags baddump )
+	release ( region USA )
+)
+
+game (
+	name "Game (USA) (Rev 1)"
+	cloneof "Game (USA)"
+	disk ( name game status nodump )
+)
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(datafile.name(), "Sony - PlayStation");
+        assert_eq!(datafile.header.version, "20220101");
+
+        let games = datafile.game.unwrap();
+        assert_eq!(games.len(), 2);
+
+        let roms = games[0].rom.as_ref().unwrap();
+        assert_eq!(roms[0].name, r"Game (USA)\track01.bin");
+        assert_eq!(roms[0].size, Some(1024));
+        assert_eq!(roms[0].status, None);
+        assert_eq!(roms[1].status.as_deref(), Some("baddump"));
+        assert_eq!(
+            games[0].release.as_ref().unwrap()[0].region.as_deref(),
+            Some("USA")
+        );
+
+        assert_eq!(games[1].cloneof.as_deref(), Some("Game (USA)"));
+        assert_eq!(
+            games[1].disk.as_ref().unwrap()[0].status.as_deref(),
+            Some("nodump")
+        );
+    }
+
+    #[test]
+    fn clrmamepro_directives() {
+        let dat = |directives: &str| {
+            let datafile = Datafile::from_clrmamepro(&format!(
+                r#"clrmamepro (
+	name "Nintendo - Game Boy"
+	{}
+)
+
+game (
+	name "Game (USA)"
+	rom ( name "Game (USA).gb" size 32768 sha1 74591cc9d4e8a4f3e8a0ad6e6f1d3a9a4d5ed7a1 flags baddump )
+)
+
+game (
+	name "Other (USA)"
+	rom ( name "Other (USA).gb" size 32768 sha1 da39a3ee5e6b4b0d3255bfef95601890afd80709 )
+	rom ( name "Other (USA).sav" size 8192 status nodump )
+)
+"#,
+                directives
+            ))
+            .unwrap();
+            DatFile::new_flattened(datafile, &DirectiveOverrides::default()).unwrap()
+        };
+
+        let unzipped = dat("");
+        assert!(unzipped.flat.contains_key("Game (USA).gb"));
+        assert!(unzipped.undumped.is_empty());
+        assert!(unzipped.has_bad_dumps("Game (USA).gb"));
+        assert!(!unzipped.has_bad_dumps("Other (USA)"));
+
+        let zipped = dat("forcepacking zip");
+        assert!(zipped.flat.is_empty());
+        assert!(zipped.tree.contains_key("Game (USA)"));
+        assert!(zipped.has_bad_dumps("Game (USA)"));
+        assert_eq!(
+            zipped.game_path(Path::new("roms"), "Game (USA)"),
+            Path::new("roms/Game (USA).zip")
+        );
+
+        let required = dat("forcenodump required");
+        assert_eq!(
+            required
+                .undumped
+                .get("Other (USA)")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            ["Other (USA).sav"]
+        );
+    }
+
+    #[test]
+    fn clrmamepro_errors() {
+        assert_eq!(error_line("game ( name foo )"), 1);
+        assert_eq!(error_line("clrmamepro ( name foo )\n\ngame name"), 3);
+        assert_eq!(
+            error_line("clrmamepro ( name foo )\ngame (\n name foo\n"),
+            2
+        );
+        assert_eq!(error_line("clrmamepro (\n name )"), 2);
+        assert_eq!(error_line("clrmamepro ( name \"foo )\n"), 1);
+        assert_eq!(
+            error_line("clrmamepro ( name foo )\ngame (\n rom ( size 1 )\n)"),
+            2
+        );
+    }
+}
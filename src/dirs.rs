@@ -1,4 +1,5 @@
 use super::Error;
+use crate::sink::ProgressSink;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
@@ -8,6 +9,7 @@ const DIR_CONFIG_FILE: &str = "dirs.toml";
 #[derive(Default, Serialize, Deserialize)]
 struct DirectoryConfig {
     mame: Option<String>,
+    mame_disks: Option<String>,
     mess: Option<String>,
     extra: BTreeMap<String, String>,
     redump: BTreeMap<String, String>,
@@ -44,10 +46,7 @@ impl DirectoryConfig {
     }
 
     fn location() -> PathBuf {
-        directories::ProjectDirs::from("", "", "EmuMan")
-            .expect("no valid home directory")
-            .data_local_dir()
-            .join(DIR_CONFIG_FILE)
+        crate::data_dir().join(DIR_CONFIG_FILE)
     }
 
     #[inline]
@@ -62,6 +61,12 @@ impl DirectoryConfig {
     where
         F: FnOnce(&mut DirectoryConfig, String) -> Set,
     {
+        // directories given while only reading, like snapshots,
+        // shouldn't become the defaults
+        if crate::game::is_read_only() {
+            return Ok(Set::Unchanged);
+        }
+
         let value = value
             .into_os_string()
             .into_string()
@@ -80,6 +85,24 @@ pub fn default() -> PathBuf {
     PathBuf::from(".")
 }
 
+// every directory remembered as a default
+pub fn configured_dirs() -> Vec<PathBuf> {
+    DirectoryConfig::new()
+        .map(|config| {
+            config
+                .mame
+                .into_iter()
+                .chain(config.mame_disks)
+                .chain(config.mess)
+                .chain(config.extra.into_values())
+                .chain(config.redump.into_values())
+                .chain(config.nointro.into_values())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 enum RomSource {
     UserProvided(PathBuf),
     FromConfig(PathBuf),
@@ -112,25 +135,31 @@ impl AsRef<Path> for RomSource {
     }
 }
 
-pub struct MameRoms(RomSource);
+pub struct MameRoms<'s> {
+    roms: RomSource,
+    sink: &'s dyn ProgressSink,
+}
 
-impl MameRoms {
+impl<'s> MameRoms<'s> {
     #[inline]
-    fn new(roms: Option<PathBuf>) -> Self {
-        Self(RomSource::new(roms, || DirectoryConfig::get(|d| d.mame)))
+    fn new(roms: Option<PathBuf>, sink: &'s dyn ProgressSink) -> Self {
+        Self {
+            roms: RomSource::new(roms, || DirectoryConfig::get(|d| d.mame)),
+            sink,
+        }
     }
 }
 
-impl AsRef<Path> for MameRoms {
+impl AsRef<Path> for MameRoms<'_> {
     #[inline]
     fn as_ref(&self) -> &Path {
-        self.0.as_ref()
+        self.roms.as_ref()
     }
 }
 
-impl Drop for MameRoms {
+impl Drop for MameRoms<'_> {
     fn drop(&mut self) {
-        if let RomSource::UserProvided(roms) = &self.0 {
+        if let RomSource::UserProvided(roms) = &self.roms {
             match roms.canonicalize().map_err(Error::IO).and_then(|pb| {
                 DirectoryConfig::set(
                     |d, s| {
@@ -144,35 +173,100 @@ impl Drop for MameRoms {
                     pb,
                 )
             }) {
-                Ok(Set::Changed) => eprintln!(
-                    "* default MAME ROMs directory updated to : \"{}\"",
+                Ok(Set::Changed) => self.sink.notice(&format!(
+                    "default MAME ROMs directory updated to : \"{}\"",
                     roms.display()
-                ),
+                )),
+                Ok(Set::Unchanged) => {}
+                Err(err) => self.sink.warning(&err.to_string()),
+            }
+        }
+    }
+}
+
+// any directory given is remembered as the default once dropped,
+// which is reported to sink
+#[inline]
+pub fn mame_roms(roms: Option<PathBuf>, sink: &dyn ProgressSink) -> MameRoms<'_> {
+    MameRoms::new(roms, sink)
+}
+
+// CHDs may be kept apart from ROMs, like on slower storage,
+// or are otherwise stored among them
+pub struct MameDisks<'s> {
+    disks: Option<RomSource>,
+    sink: &'s dyn ProgressSink,
+}
+
+impl<'s> MameDisks<'s> {
+    #[inline]
+    fn new(disks: Option<PathBuf>, sink: &'s dyn ProgressSink) -> Self {
+        Self {
+            disks: match disks {
+                Some(disks) => Some(RomSource::UserProvided(disks)),
+                None => DirectoryConfig::get(|d| d.mame_disks).map(RomSource::FromConfig),
+            },
+            sink,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self) -> Option<&Path> {
+        self.disks.as_ref().map(|disks| disks.as_ref())
+    }
+}
+
+impl Drop for MameDisks<'_> {
+    fn drop(&mut self) {
+        if let Some(RomSource::UserProvided(disks)) = &self.disks {
+            match disks.canonicalize().map_err(Error::IO).and_then(|pb| {
+                DirectoryConfig::set(
+                    |d, s| {
+                        if d.mame_disks.as_ref() != Some(&s) {
+                            d.mame_disks = Some(s);
+                            Set::Changed
+                        } else {
+                            Set::Unchanged
+                        }
+                    },
+                    pb,
+                )
+            }) {
+                Ok(Set::Changed) => self.sink.notice(&format!(
+                    "default MAME disks directory updated to : \"{}\"",
+                    disks.display()
+                )),
                 Ok(Set::Unchanged) => {}
-                Err(err) => eprintln!("* {}", err),
+                Err(err) => self.sink.warning(&err.to_string()),
             }
         }
     }
 }
 
 #[inline]
-pub fn mame_roms(roms: Option<PathBuf>) -> MameRoms {
-    MameRoms::new(roms)
+pub fn mame_disks(disks: Option<PathBuf>, sink: &dyn ProgressSink) -> MameDisks<'_> {
+    MameDisks::new(disks, sink)
 }
 
 pub struct MessRoms<'s> {
     roms: RomSource,
     software_list: Option<&'s str>,
+    sink: &'s dyn ProgressSink,
 }
 
 impl<'s> MessRoms<'s> {
-    fn new(roms: Option<PathBuf>, software_list: Option<&'s str>) -> Self {
+    fn new(
+        roms: Option<PathBuf>,
+        software_list: Option<&'s str>,
+        sink: &'s dyn ProgressSink,
+    ) -> Self {
         Self {
             roms: RomSource::new(roms, || match software_list {
                 None => DirectoryConfig::get(|d| d.mess),
                 Some(list) => DirectoryConfig::get(|d| d.mess).map(|d| d.join(list)),
             }),
             software_list,
+            sink,
         }
     }
 }
@@ -205,12 +299,12 @@ impl<'s> Drop for MessRoms<'s> {
                         pb,
                     )
                 }) {
-                    Ok(Set::Changed) => eprintln!(
-                        "* default software list ROMs directory updated to : \"{}\"",
+                    Ok(Set::Changed) => self.sink.notice(&format!(
+                        "default software list ROMs directory updated to : \"{}\"",
                         roms.display()
-                    ),
+                    )),
                     Ok(Set::Unchanged) => {}
-                    Err(err) => eprintln!("* {}", err),
+                    Err(err) => self.sink.warning(&err.to_string()),
                 }
             }
         }
@@ -218,27 +312,33 @@ impl<'s> Drop for MessRoms<'s> {
 }
 
 #[inline]
-pub fn mess_roms_all(root: Option<PathBuf>) -> MessRoms<'static> {
-    MessRoms::new(root, None)
+pub fn mess_roms_all(root: Option<PathBuf>, sink: &dyn ProgressSink) -> MessRoms<'_> {
+    MessRoms::new(root, None, sink)
 }
 
 #[inline]
-pub fn mess_roms(roms: Option<PathBuf>, software_list: &str) -> MessRoms<'_> {
-    MessRoms::new(roms, Some(software_list))
+pub fn mess_roms<'s>(
+    roms: Option<PathBuf>,
+    software_list: &'s str,
+    sink: &'s dyn ProgressSink,
+) -> MessRoms<'s> {
+    MessRoms::new(roms, Some(software_list), sink)
 }
 
 pub struct ExtraParts<'e> {
     extras: RomSource,
     extra: &'e str,
+    sink: &'e dyn ProgressSink,
 }
 
 impl<'e> ExtraParts<'e> {
-    fn new(extras: Option<PathBuf>, extra: &'e str) -> Self {
+    fn new(extras: Option<PathBuf>, extra: &'e str, sink: &'e dyn ProgressSink) -> Self {
         Self {
             extras: RomSource::new(extras, || {
                 DirectoryConfig::get(|mut d| d.extra.remove(extra))
             }),
             extra,
+            sink,
         }
     }
 }
@@ -262,13 +362,13 @@ impl<'e> Drop for ExtraParts<'e> {
                     pb,
                 )
             }) {
-                Ok(Set::Changed) => eprintln!(
-                    "* default \"{}\" directory updated to : \"{}\"",
+                Ok(Set::Changed) => self.sink.notice(&format!(
+                    "default \"{}\" directory updated to : \"{}\"",
                     self.extra,
                     extras.display()
-                ),
+                )),
                 Ok(Set::Unchanged) => {}
-                Err(err) => eprintln!("* {}", err),
+                Err(err) => self.sink.warning(&err.to_string()),
             }
         }
     }
@@ -285,38 +385,34 @@ pub fn extra_dirs() -> Box<dyn Iterator<Item = (String, PathBuf)>> {
 }
 
 #[inline]
-pub fn extra_dir(dir: Option<PathBuf>, extra: &str) -> ExtraParts<'_> {
-    ExtraParts::new(dir, extra)
+pub fn extra_dir<'e>(
+    dir: Option<PathBuf>,
+    extra: &'e str,
+    sink: &'e dyn ProgressSink,
+) -> ExtraParts<'e> {
+    ExtraParts::new(dir, extra, sink)
 }
 
 pub fn extra_dir_names() -> Option<Vec<String>> {
     DirectoryConfig::new()
-        .map(|DirectoryConfig { extra, .. }| extra.into_iter().map(|(k, _)| k).collect::<Vec<_>>())
+        .map(|DirectoryConfig { extra, .. }| extra.into_keys().collect::<Vec<_>>())
         .filter(|v| !v.is_empty())
 }
 
-pub fn select_extra_name() -> Result<String, Error> {
-    extra_dir_names()
-        .ok_or(Error::NoDatFiles)
-        .and_then(|names| {
-            inquire::Select::new("select extras category", names)
-                .prompt()
-                .map_err(Error::Inquire)
-        })
-}
-
 pub struct NointroRoms<'s> {
     roms: RomSource,
     name: &'s str,
+    sink: &'s dyn ProgressSink,
 }
 
 impl<'s> NointroRoms<'s> {
-    fn new(roms: Option<PathBuf>, name: &'s str) -> Self {
+    fn new(roms: Option<PathBuf>, name: &'s str, sink: &'s dyn ProgressSink) -> Self {
         Self {
             roms: RomSource::new(roms, || {
                 DirectoryConfig::get(|mut d| d.nointro.remove(name))
             }),
             name,
+            sink,
         }
     }
 }
@@ -340,21 +436,25 @@ impl<'s> Drop for NointroRoms<'s> {
                     pb,
                 )
             }) {
-                Ok(Set::Changed) => eprintln!(
-                    "* default \"{}\" directory updated to : \"{}\"",
+                Ok(Set::Changed) => self.sink.notice(&format!(
+                    "default \"{}\" directory updated to : \"{}\"",
                     self.name,
                     roms.display()
-                ),
+                )),
                 Ok(Set::Unchanged) => {}
-                Err(err) => eprintln!("* {}", err),
+                Err(err) => self.sink.warning(&err.to_string()),
             }
         }
     }
 }
 
 #[inline]
-pub fn nointro_roms(roms: Option<PathBuf>, name: &str) -> NointroRoms<'_> {
-    NointroRoms::new(roms, name)
+pub fn nointro_roms<'s>(
+    roms: Option<PathBuf>,
+    name: &'s str,
+    sink: &'s dyn ProgressSink,
+) -> NointroRoms<'s> {
+    NointroRoms::new(roms, name, sink)
 }
 
 pub fn nointro_dirs() -> Box<dyn Iterator<Item = (String, PathBuf)>> {
@@ -368,32 +468,22 @@ pub fn nointro_dirs() -> Box<dyn Iterator<Item = (String, PathBuf)>> {
 
 pub fn nointro_dir_names() -> Option<Vec<String>> {
     DirectoryConfig::new()
-        .map(|DirectoryConfig { nointro, .. }| {
-            nointro.into_iter().map(|(k, _)| k).collect::<Vec<_>>()
-        })
+        .map(|DirectoryConfig { nointro, .. }| nointro.into_keys().collect::<Vec<_>>())
         .filter(|v| !v.is_empty())
 }
 
-pub fn select_nointro_name() -> Result<String, Error> {
-    nointro_dir_names()
-        .ok_or(Error::NoDatFiles)
-        .and_then(|names| {
-            inquire::Select::new("select DAT", names)
-                .prompt()
-                .map_err(Error::Inquire)
-        })
-}
-
 pub struct RedumpRoms<'r> {
     roms: RomSource,
     name: &'r str,
+    sink: &'r dyn ProgressSink,
 }
 
 impl<'r> RedumpRoms<'r> {
-    fn new(roms: Option<PathBuf>, name: &'r str) -> Self {
+    fn new(roms: Option<PathBuf>, name: &'r str, sink: &'r dyn ProgressSink) -> Self {
         Self {
             roms: RomSource::new(roms, || DirectoryConfig::get(|mut d| d.redump.remove(name))),
             name,
+            sink,
         }
     }
 }
@@ -417,21 +507,25 @@ impl<'r> Drop for RedumpRoms<'r> {
                     pb,
                 )
             }) {
-                Ok(Set::Changed) => eprintln!(
-                    "* default \"{}\" directory updated to : \"{}\"",
+                Ok(Set::Changed) => self.sink.notice(&format!(
+                    "default \"{}\" directory updated to : \"{}\"",
                     self.name,
                     roms.display()
-                ),
+                )),
                 Ok(Set::Unchanged) => {}
-                Err(err) => eprintln!("* {}", err),
+                Err(err) => self.sink.warning(&err.to_string()),
             }
         }
     }
 }
 
 #[inline]
-pub fn redump_roms(roms: Option<PathBuf>, name: &str) -> RedumpRoms<'_> {
-    RedumpRoms::new(roms, name)
+pub fn redump_roms<'r>(
+    roms: Option<PathBuf>,
+    name: &'r str,
+    sink: &'r dyn ProgressSink,
+) -> RedumpRoms<'r> {
+    RedumpRoms::new(roms, name, sink)
 }
 
 pub fn redump_dirs() -> Box<dyn Iterator<Item = (String, PathBuf)>> {
@@ -445,18 +539,6 @@ pub fn redump_dirs() -> Box<dyn Iterator<Item = (String, PathBuf)>> {
 
 pub fn redump_dir_names() -> Option<Vec<String>> {
     DirectoryConfig::new()
-        .map(|DirectoryConfig { redump, .. }| {
-            redump.into_iter().map(|(k, _)| k).collect::<Vec<_>>()
-        })
+        .map(|DirectoryConfig { redump, .. }| redump.into_keys().collect::<Vec<_>>())
         .filter(|v| !v.is_empty())
 }
-
-pub fn select_redump_name() -> Result<String, Error> {
-    redump_dir_names()
-        .ok_or(Error::NoDatFiles)
-        .and_then(|names| {
-            inquire::Select::new("select DAT", names)
-                .prompt()
-                .map_err(Error::Inquire)
-        })
-}
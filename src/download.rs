@@ -0,0 +1,146 @@
+use crate::game::{parse_sha1, Part};
+use crate::http::RateLimiter;
+use crate::{Error, FileError};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+// a part to be fetched, saved under the given name
+pub struct Missing {
+    pub sha1: [u8; 20],
+    pub name: String,
+}
+
+// missing lists have one part per line, like:
+//
+// 6d9ed13b5a4a4ea50d1b03e3a1ba6bf8ddc97dbd pacman.6e
+// sha1:0c944964cb4e1ea6ff6cc2ef4f3ee8ed4ddd0c2f
+//
+// where the name defaults to the digest if not given
+// and lines starting with "#" are ignored
+pub fn read_missing_list(path: &Path) -> Result<Vec<Missing>, Error> {
+    let mut missing = Vec::new();
+
+    for line in std::io::BufReader::new(std::fs::File::open(path)?).lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (digest, name) = match line.split_once(char::is_whitespace) {
+            Some((digest, name)) => (digest, name.trim()),
+            None => (line, ""),
+        };
+
+        let sha1 = parse_sha1(digest).map_err(|error| {
+            Error::InvalidSha1(FileError {
+                file: path.to_owned(),
+                error,
+            })
+        })?;
+
+        missing.push(Missing {
+            sha1,
+            name: if name.is_empty() {
+                hex::encode(sha1)
+            } else {
+                name.to_owned()
+            },
+        });
+    }
+
+    Ok(missing)
+}
+
+pub struct Downloader {
+    mirrors: Vec<String>,
+    retries: u32,
+    limiter: Option<RateLimiter>,
+}
+
+impl Downloader {
+    pub fn new(mirrors: Vec<String>, retries: u32, bytes_per_sec: Option<u64>) -> Self {
+        Self {
+            mirrors,
+            retries,
+            limiter: bytes_per_sec.map(RateLimiter::new),
+        }
+    }
+
+    // fetches the part into target_dir from the first mirror which has it,
+    // resuming any earlier partial download of it
+    //
+    // returns the path of the downloaded file
+    pub fn download(&self, missing: &Missing, target_dir: &Path) -> Result<PathBuf, Error> {
+        use std::path::Component;
+
+        // names come from the missing list, so they may only
+        // lead somewhere under target_dir
+        if !Path::new(&missing.name)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(Error::InvalidName(missing.name.clone()));
+        }
+
+        let target = target_dir.join(&missing.name);
+
+        if matches!(Part::from_path(&target), Ok(part) if part_sha1(&part) == &missing.sha1) {
+            return Ok(target);
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let partial = target_dir.join(format!("{}.part", missing.name));
+        let mut last_error = Error::NoMirrors;
+
+        for mirror in self.mirrors.iter() {
+            let url = mirror_url(mirror, missing);
+
+            if let Err(err) =
+                crate::http::fetch_url_file(&url, &partial, self.limiter.as_ref(), self.retries)
+            {
+                // a partial download from one mirror
+                // can't be resumed from another
+                let _ = std::fs::remove_file(&partial);
+                last_error = err;
+                continue;
+            }
+
+            if part_sha1(&Part::from_path(&partial)?) == &missing.sha1 {
+                std::fs::rename(&partial, &target)?;
+                return Ok(target);
+            }
+
+            std::fs::remove_file(&partial)?;
+            last_error = Error::ChecksumMismatch(url);
+        }
+
+        Err(last_error)
+    }
+}
+
+// mirrors may place "{sha1}" and "{name}" anywhere in their URLs,
+// otherwise the part's SHA1 is appended
+fn mirror_url(mirror: &str, missing: &Missing) -> String {
+    let sha1 = hex::encode(missing.sha1);
+
+    if mirror.contains("{sha1}") || mirror.contains("{name}") {
+        mirror
+            .replace("{sha1}", &sha1)
+            .replace("{name}", &missing.name)
+    } else {
+        format!("{}/{}", mirror.trim_end_matches('/'), sha1)
+    }
+}
+
+// disks are listed by their CHD's internal SHA1
+#[inline]
+fn part_sha1(part: &Part) -> &[u8; 20] {
+    match part {
+        Part::Rom { sha1 } | Part::Disk { sha1 } => sha1,
+    }
+}
@@ -74,3 +74,48 @@ impl Duplicates for DuplicateParts {
         }
     }
 }
+
+// bytes freed by removing path, which is nothing
+// if other links to it remain
+pub fn reclaimable(path: &Path) -> Result<u64, std::io::Error> {
+    path.metadata().map(|metadata| {
+        if link_count(&metadata) == 1 {
+            metadata.len()
+        } else {
+            0
+        }
+    })
+}
+
+// replaces duplicate with a hard link to original,
+// linked under a temporary name first so duplicate is never missing,
+// returning the bytes reclaimed
+pub fn link(original: &Path, duplicate: &Path) -> Result<u64, std::io::Error> {
+    let reclaimed = reclaimable(duplicate)?;
+
+    let mut temporary = duplicate.as_os_str().to_owned();
+    temporary.push(".emuman-link");
+    let temporary = PathBuf::from(temporary);
+
+    std::fs::hard_link(original, &temporary)?;
+    if let Err(err) = std::fs::rename(&temporary, duplicate) {
+        let _ = std::fs::remove_file(&temporary);
+        return Err(err);
+    }
+
+    Ok(reclaimed)
+}
+
+#[cfg(unix)]
+#[inline]
+fn link_count(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+#[inline]
+fn link_count(_metadata: &std::fs::Metadata) -> u64 {
+    1
+}
@@ -0,0 +1,211 @@
+use crate::game::Part;
+use std::collections::HashSet;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+
+// a C interface to the verification engine, like:
+//
+// char sha1[EMUMAN_SHA1_SIZE];
+// emuman_hash_file("/roms/pacman/pacman.6e", sha1, sizeof(sha1));
+//
+// EmumanResults *results = emuman_verify_path("mame", "/roms/mame");
+// const EmumanFailure *failure;
+// while ((failure = emuman_results_next(results)) != NULL) {
+//     printf("%s : %s : %s\n", failure->kind, failure->path, failure->game);
+// }
+// emuman_results_free(results);
+
+#[repr(C)]
+pub struct EmumanFailure {
+    pub game: *const c_char,
+    pub kind: *const c_char,
+    pub path: *const c_char,
+}
+
+pub struct EmumanResults {
+    // owns the strings each failure points to
+    _strings: Vec<CString>,
+    failures: Vec<EmumanFailure>,
+    tested: usize,
+    next: usize,
+}
+
+// the room a SHA1 digest needs as 40 hex digits and a terminating NUL,
+// which C callers may define as EMUMAN_SHA1_SIZE
+pub const SHA1_SIZE: usize = 41;
+
+unsafe fn to_str<'s>(s: *const c_char) -> Option<&'s str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+#[inline]
+unsafe fn to_path<'p>(s: *const c_char) -> Option<&'p Path> {
+    to_str(s).map(Path::new)
+}
+
+#[inline]
+fn to_c_string(s: &str) -> CString {
+    CString::new(s).unwrap_or_default()
+}
+
+// a panic mustn't unwind into C, so each function's body
+// runs behind this and a panic returns its error value instead
+#[inline]
+fn guarded<T, F: FnOnce() -> T>(on_panic: T, f: F) -> T {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+/// Writes the SHA1 digest of the ROM or CHD at `path` to `sha1`
+/// as 40 hex digits and a terminating NUL, which takes 41 bytes.
+///
+/// Returns 0 on success, -1 if `path` can't be read,
+/// -2 if `sha1` is NULL or `len` is under 41 bytes,
+/// in which case nothing is written and the digest isn't truncated,
+/// or -3 if hashing fails unexpectedly.
+///
+/// # Safety
+///
+/// `path` must be a NUL-terminated string
+/// and `sha1` must point to at least `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn emuman_hash_file(
+    path: *const c_char,
+    sha1: *mut c_char,
+    len: usize,
+) -> c_int {
+    guarded(-3, || {
+        if sha1.is_null() || len < SHA1_SIZE {
+            return -2;
+        }
+
+        let part = match to_path(path).map(Part::from_path) {
+            Some(Ok(part)) => part,
+            _ => return -1,
+        };
+
+        let digest = to_c_string(&part.digest().to_string());
+        let digest = digest.as_bytes_with_nul();
+        std::ptr::copy_nonoverlapping(digest.as_ptr().cast(), sha1, digest.len());
+
+        0
+    })
+}
+
+/// Verifies the games in `root` against the database `db`,
+/// given as "mame", "sl:<software list>" or "dat:<name>",
+/// returning NULL if the database, blocklist or directory can't be read
+/// or verifying fails unexpectedly.
+///
+/// # Safety
+///
+/// `db` and `root` must be NUL-terminated strings.
+/// The results must be released with `emuman_results_free`.
+#[no_mangle]
+pub unsafe extern "C" fn emuman_verify_path(
+    db: *const c_char,
+    root: *const c_char,
+) -> *mut EmumanResults {
+    guarded(std::ptr::null_mut(), || {
+        let (db, root) = match (to_str(db), to_path(root)) {
+            (Some(db), Some(root)) => (db, root),
+            _ => return std::ptr::null_mut(),
+        };
+
+        let db = match crate::collection::read_db(db.to_owned()) {
+            Ok(db) => db,
+            Err(_) => return std::ptr::null_mut(),
+        };
+
+        if crate::blocklist::load().is_err() {
+            return std::ptr::null_mut();
+        }
+
+        let games: HashSet<String> = match root.read_dir() {
+            Ok(dir) => dir
+                .filter_map(|e| {
+                    e.ok()
+                        .and_then(|e| crate::entry_game(e, false))
+                        .filter(|s| db.is_game(s))
+                })
+                .collect(),
+            Err(_) => return std::ptr::null_mut(),
+        };
+
+        let mut strings = Vec::new();
+        let mut failures = Vec::new();
+
+        for (game, game_failures) in db.verify(root, &games, false, false, &crate::sink::Quiet) {
+            for failure in game_failures {
+                let game = to_c_string(game);
+                let kind = to_c_string(failure.kind());
+                let path = to_c_string(&failure.path().display().to_string());
+
+                // a CString's data stays put when the CString itself is moved
+                failures.push(EmumanFailure {
+                    game: game.as_ptr(),
+                    kind: kind.as_ptr(),
+                    path: path.as_ptr(),
+                });
+
+                strings.extend([game, kind, path]);
+            }
+        }
+
+        Box::into_raw(Box::new(EmumanResults {
+            _strings: strings,
+            failures,
+            tested: games.len(),
+            next: 0,
+        }))
+    })
+}
+
+/// Returns the number of games verified.
+///
+/// # Safety
+///
+/// `results` must come from `emuman_verify_path`.
+#[no_mangle]
+pub unsafe extern "C" fn emuman_results_tested(results: *const EmumanResults) -> usize {
+    guarded(0, || results.as_ref().map(|r| r.tested).unwrap_or_default())
+}
+
+/// Returns the next failure, or NULL once there are no more.
+///
+/// # Safety
+///
+/// `results` must come from `emuman_verify_path`
+/// and the failure is only valid until the results are freed.
+#[no_mangle]
+pub unsafe extern "C" fn emuman_results_next(results: *mut EmumanResults) -> *const EmumanFailure {
+    guarded(std::ptr::null(), || match results.as_mut() {
+        Some(results) => match results.failures.get(results.next) {
+            Some(failure) => {
+                results.next += 1;
+                failure
+            }
+            None => std::ptr::null(),
+        },
+        None => std::ptr::null(),
+    })
+}
+
+/// Releases results and all their failures.
+///
+/// # Safety
+///
+/// `results` must come from `emuman_verify_path`
+/// and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn emuman_results_free(results: *mut EmumanResults) {
+    guarded((), || {
+        if !results.is_null() {
+            drop(Box::from_raw(results));
+        }
+    })
+}
@@ -1,21 +1,35 @@
-use super::{is_zip, Error};
+use super::Error;
+#[cfg(feature = "native")]
+use super::{is_rar, is_zip};
+use crate::dat::Merging;
+use crate::locale::Locale;
+use crate::sink::ProgressSink;
+#[cfg(feature = "native")]
+use crate::sink::{Task, Total};
 use core::num::ParseIntError;
+#[cfg(feature = "native")]
 use dashmap::mapref::entry::OccupiedEntry;
 use dashmap::DashMap;
+#[cfg(feature = "native")]
+use dashmap::DashSet;
+#[cfg(feature = "native")]
+use fxhash::FxHashMap;
 use fxhash::FxHashSet;
-use indicatif::{ProgressBar, ProgressStyle};
-use prettytable::Table;
 use serde_derive::{Deserialize, Serialize};
 use sha1_smol::Sha1;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
-use std::io::{Read, Seek};
+use std::io::Read;
+#[cfg(feature = "native")]
+use std::io::Seek;
 use std::iter::FromIterator;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+#[cfg(feature = "native")]
 use std::sync::Arc;
 
+#[cfg(feature = "native")]
 const CACHE_XATTR: &str = "user.emupart";
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -70,6 +84,184 @@ impl GameDb {
         self.games.retain(|_, game| game.is_working())
     }
 
+    // replaces every game's category with the given ones,
+    // returning how many of our games were categorized
+    #[inline]
+    pub fn categorize(&mut self, categories: HashMap<String, String>) -> usize {
+        self.annotate(categories, |game| &mut game.category)
+    }
+
+    // replaces every game's player counts with the given ones,
+    // returning how many of our games have them
+    #[inline]
+    pub fn set_players(&mut self, players: HashMap<String, String>) -> usize {
+        self.annotate(players, |game| &mut game.players)
+    }
+
+    fn annotate(
+        &mut self,
+        mut values: HashMap<String, String>,
+        field: fn(&mut Game) -> &mut Option<String>,
+    ) -> usize {
+        let mut annotated = 0;
+        for (name, game) in self.games.iter_mut() {
+            *field(game) = values.remove(name);
+            if field(game).is_some() {
+                annotated += 1;
+            }
+        }
+        annotated
+    }
+
+    // our games are non-merged by default,
+    // with each set containing all of its parts
+    pub fn apply_layout(&mut self, layout: Merging) {
+        match layout {
+            Merging::None => {}
+            Merging::Split => {
+                let names: HashSet<String> = self.games.keys().cloned().collect();
+
+                // shared parts are expected in the parent or BIOS set
+                for game in self.games.values_mut() {
+                    if matches!(&game.romof, Some(romof) if names.contains(romof)) {
+                        game.remove_merged();
+                    }
+                }
+            }
+            Merging::Full => {
+                self.apply_layout(Merging::Split);
+
+                let clones: Vec<String> = self
+                    .games
+                    .values()
+                    .filter(|game| {
+                        matches!(&game.cloneof, Some(parent) if self.games.contains_key(parent))
+                    })
+                    .map(|game| game.name.clone())
+                    .collect();
+
+                // clone parts are folded into the parent's set
+                for clone in clones {
+                    if let Some(clone) = self.games.remove(&clone) {
+                        if let Some(parent) = clone
+                            .cloneof
+                            .as_ref()
+                            .and_then(|parent| self.games.get_mut(parent))
+                        {
+                            parent.absorb(clone);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // what changed going from this database to the new one,
+    // where removed games whose parts all turn up in an added game
+    // are considered renamed
+    pub fn diff<'s>(&'s self, new: &'s GameDb) -> DbDiff<'s> {
+        fn parts_key(game: &Game) -> Vec<String> {
+            let mut key: Vec<String> = game
+                .parts
+                .iter()
+                .map(|(_, part)| part.digest().to_string())
+                .collect();
+            key.sort_unstable();
+            key
+        }
+
+        let mut added: BTreeSet<&str> = new
+            .games
+            .keys()
+            .filter(|name| !self.games.contains_key(*name))
+            .map(|name| name.as_str())
+            .collect();
+
+        let mut removed: BTreeSet<&str> = self
+            .games
+            .keys()
+            .filter(|name| !new.games.contains_key(*name))
+            .map(|name| name.as_str())
+            .collect();
+
+        let mut added_by_parts: HashMap<Vec<String>, &str> = added
+            .iter()
+            .map(|name| (parts_key(&new.games[*name]), *name))
+            .filter(|(key, _)| !key.is_empty())
+            .collect();
+
+        let renamed: BTreeMap<&str, &str> = removed
+            .iter()
+            .filter_map(|old| {
+                added_by_parts
+                    .remove(&parts_key(&self.games[*old]))
+                    .map(|new| (*old, new))
+            })
+            .collect();
+
+        for (old, new) in renamed.iter() {
+            removed.remove(old);
+            added.remove(new);
+        }
+
+        let mut parts = Vec::new();
+        let mut changed = 0;
+
+        let mut common: Vec<&str> = self
+            .games
+            .keys()
+            .filter(|name| new.games.contains_key(*name))
+            .map(|name| name.as_str())
+            .collect();
+        common.sort_unstable();
+
+        for name in common {
+            let old_parts: BTreeMap<&str, &Part> = self.games[name]
+                .parts
+                .iter()
+                .map(|(part_name, part)| (part_name.as_str(), part))
+                .collect();
+
+            let new_parts: BTreeMap<&str, &Part> = new.games[name]
+                .parts
+                .iter()
+                .map(|(part_name, part)| (part_name.as_str(), part))
+                .collect();
+
+            let changes = parts.len();
+
+            for (&part_name, old_part) in old_parts.iter() {
+                match new_parts.get(part_name) {
+                    Some(new_part) if new_part == old_part => {}
+                    Some(new_part) => parts.push(PartChange::Changed {
+                        old: PartId::new(name, part_name, old_part),
+                        new: PartId::new(name, part_name, new_part),
+                    }),
+                    None => parts.push(PartChange::Removed(PartId::new(name, part_name, old_part))),
+                }
+            }
+
+            for (&part_name, new_part) in new_parts
+                .iter()
+                .filter(|(p, _)| !old_parts.contains_key(*p))
+            {
+                parts.push(PartChange::Added(PartId::new(name, part_name, new_part)));
+            }
+
+            if parts.len() > changes {
+                changed += 1;
+            }
+        }
+
+        DbDiff {
+            added,
+            removed,
+            renamed,
+            parts,
+            changed,
+        }
+    }
+
     pub fn validate_games<I>(&self, games: I) -> Result<(), Error>
     where
         I: IntoIterator,
@@ -84,6 +276,50 @@ impl GameDb {
         })
     }
 
+    // the BIOS set a game's shared parts come from, if any,
+    // which may be by way of its parent
+    pub fn bios(&self, game: &Game) -> Option<&Game> {
+        let mut romof = game.romof.as_deref();
+
+        // chains are short, but malformed data may loop
+        for _ in 0..8 {
+            let set = self.game(romof?)?;
+            if matches!(set.class, Class::Bios) {
+                return Some(set);
+            }
+            romof = set.romof.as_deref();
+        }
+
+        None
+    }
+
+    // the BIOS set a game needs stored alongside it,
+    // when the layout leaves the BIOS parts out of the game's own set
+    pub fn separate_bios(&self, game: &Game) -> Option<&Game> {
+        self.bios(game).filter(|bios| {
+            bios.parts
+                .iter()
+                .any(|(name, _)| !game.parts.contains_key(name))
+        })
+    }
+
+    // the given games along with any separate BIOS sets they need
+    pub fn with_bios<'g, I>(&'g self, games: I) -> impl Iterator<Item = &'g Game>
+    where
+        I: Iterator<Item = &'g Game>,
+    {
+        let mut sets: BTreeMap<&str, &Game> = BTreeMap::new();
+
+        for game in games {
+            sets.insert(game.name.as_str(), game);
+            if let Some(bios) = self.separate_bios(game) {
+                sets.insert(bios.name.as_str(), bios);
+            }
+        }
+
+        sets.into_values()
+    }
+
     pub fn required_parts<I>(&self, games: I) -> Result<FxHashSet<Part>, Error>
     where
         I: IntoIterator,
@@ -95,6 +331,9 @@ impl GameDb {
             .try_for_each(|game| {
                 if let Some(game) = self.game(game.as_ref()) {
                     parts.extend(game.parts.values().cloned());
+                    if let Some(bios) = self.bios(game) {
+                        parts.extend(bios.parts.values().cloned());
+                    }
                     Ok(())
                 } else {
                     Err(Error::NoSuchSoftware(game.as_ref().to_string()))
@@ -103,92 +342,546 @@ impl GameDb {
             .map(|()| parts)
     }
 
+    #[cfg(feature = "native")]
+    #[inline]
     pub fn verify<'a>(
         &self,
         root: &Path,
         games: &'a HashSet<String>,
-    ) -> BTreeMap<&'a str, Vec<VerifyFailure>> {
-        use indicatif::ParallelProgressIterator;
+        deep: bool,
+        zip: bool,
+        sink: &dyn ProgressSink,
+    ) -> BTreeMap<&'a str, Vec<VerifyFailure<'_>>> {
+        self.verify_with_disks(root, root, games, deep, zip, false, sink)
+    }
+
+    // like verify, but with disks laid out as "<game>/<disk>.chd"
+    // under disk_root rather than among each game's ROMs
+    //
+    // if shared_devices is set, device sets are verified once
+    // in their own directories, as MAME loads them,
+    // rather than being required within every game using them
+    #[cfg(feature = "native")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_with_disks<'a>(
+        &self,
+        root: &Path,
+        disk_root: &Path,
+        games: &'a HashSet<String>,
+        deep: bool,
+        zip: bool,
+        shared_devices: bool,
+        sink: &dyn ProgressSink,
+    ) -> BTreeMap<&'a str, Vec<VerifyFailure<'_>>> {
         use rayon::prelude::*;
 
-        let pbar = ProgressBar::new(games.len() as u64).with_style(verify_style());
-        pbar.set_message("verifying games");
+        let disks = self.disk_locations();
 
-        games
+        let verify_one = |game: &str, deep: bool| {
+            if shared_devices {
+                self.verify_own_parts(root, disk_root, game, &disks, deep, zip, sink)
+            } else {
+                self.verify_game(root, disk_root, game, &disks, deep, zip, sink)
+            }
+        };
+
+        // BIOS sets which games rely on are checked once up front
+        // rather than again for every game using them,
+        // with the results kept for any BIOS being verified itself
+        let bios_results: DashMap<&str, Vec<VerifyFailure<'_>>> = games
+            .iter()
+            .filter_map(|game| self.game(game))
+            .filter_map(|game| self.separate_bios(game))
+            .map(|bios| bios.name.as_str())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|bios| (bios, verify_one(bios, false)))
+            .collect();
+
+        let bios_complete: HashMap<&str, bool> = bios_results
+            .iter()
+            .map(|entry| {
+                (
+                    *entry.key(),
+                    entry.value().iter().all(|failure| !failure.is_incomplete()),
+                )
+            })
+            .collect();
+
+        // likewise for devices, when they're shared
+        let device_results: DashMap<&str, Vec<VerifyFailure<'_>>> = if shared_devices {
+            games
+                .iter()
+                .filter_map(|game| self.game(game))
+                .flat_map(|game| self.all_devices(game))
+                .collect::<BTreeSet<_>>()
+                .into_par_iter()
+                .map(|device| (device, verify_one(device, false)))
+                .collect()
+        } else {
+            DashMap::default()
+        };
+
+        let device_complete: HashMap<&str, bool> = device_results
+            .iter()
+            .map(|entry| {
+                (
+                    *entry.key(),
+                    entry.value().iter().all(|failure| !failure.is_incomplete()),
+                )
+            })
+            .collect();
+
+        let verify_game = |game: &'a String| {
+            let mut failures = match bios_results
+                .remove(game.as_str())
+                .or_else(|| device_results.remove(game.as_str()))
+            {
+                Some((_, failures)) if !deep => failures,
+                _ => verify_one(game, deep),
+            };
+
+            if let Some(bios) = self.game(game).and_then(|game| self.separate_bios(game)) {
+                if !bios_complete
+                    .get(bios.name.as_str())
+                    .copied()
+                    .unwrap_or(true)
+                {
+                    failures.push(VerifyFailure::MissingBios {
+                        path: if zip {
+                            root.join(format!("{}.zip", bios.name))
+                        } else {
+                            root.join(&bios.name)
+                        },
+                        bios: &bios.name,
+                    });
+                }
+            }
+
+            if shared_devices {
+                if let Some(game) = self.game(game) {
+                    failures.extend(
+                        self.all_devices(game)
+                            .into_iter()
+                            .filter(|device| !device_complete.get(device).copied().unwrap_or(true))
+                            .map(|device| VerifyFailure::MissingDevice {
+                                path: if zip {
+                                    root.join(format!("{}.zip", device))
+                                } else {
+                                    root.join(device)
+                                },
+                                device,
+                            }),
+                    );
+                }
+            }
+
+            report_failures(game, &failures, sink);
+            (game.as_str(), failures)
+        };
+
+        if zip {
+            let task = Task::start(sink, "verifying games", Total::Items(games.len() as u64));
+
+            return games
+                .par_iter()
+                .map(|game| {
+                    let result = verify_game(game);
+                    task.advance(1);
+                    result
+                })
+                .collect();
+        }
+
+        // games whose files all have cached digests verify quickly,
+        // so they're done first and the rest are weighted by
+        // the bytes left to hash for a more useful ETA
+        let (cached, uncached): (Vec<_>, Vec<_>) = games
             .par_iter()
-            .progress_with(pbar)
-            .map(|game| (game.as_str(), self.verify_game(root, game)))
-            .collect()
+            .map(|game| {
+                (
+                    game,
+                    self.uncached_bytes(root, disk_root, game, !shared_devices),
+                )
+            })
+            .partition(|(_, bytes)| *bytes == 0);
+
+        let task = Task::start(
+            sink,
+            "verifying cached games",
+            Total::Items(cached.len() as u64),
+        );
+
+        let mut results: BTreeMap<_, _> = cached
+            .par_iter()
+            .map(|(game, _)| {
+                let result = verify_game(game);
+                task.advance(1);
+                result
+            })
+            .collect();
+
+        drop(task);
+
+        if !uncached.is_empty() {
+            let task = Task::start(
+                sink,
+                &format!("hashing {} games", uncached.len()),
+                Total::Bytes(uncached.iter().map(|(_, bytes)| bytes).sum()),
+            );
+
+            results.par_extend(uncached.par_iter().map(|(game, bytes)| {
+                let result = verify_game(game);
+                task.advance(*bytes);
+                result
+            }));
+        }
+
+        results
+    }
+
+    // adds a failure for each game whose Zip archive verified OK
+    // but isn't in canonical form
+    #[cfg(feature = "native")]
+    pub fn verify_canonical(
+        &self,
+        root: &Path,
+        results: &mut BTreeMap<&str, Vec<VerifyFailure<'_>>>,
+    ) {
+        use rayon::prelude::*;
+
+        results
+            .par_iter_mut()
+            .filter(|(_, failures)| failures.is_empty())
+            .for_each(|(game, failures)| {
+                let path = root.join(format!("{}.zip", game));
+
+                match crate::torrentzip::is_canonical(&path) {
+                    Ok(true) => {}
+                    Ok(false) => failures.push(VerifyFailure::NotCanonical { path }),
+                    // games without parts have no archive
+                    Err(Error::IO(err)) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(Error::IO(err)) => failures.push(VerifyFailure::Error { path, err }),
+                    Err(err) => failures.push(VerifyFailure::Error {
+                        path,
+                        err: std::io::Error::other(err.to_string()),
+                    }),
+                }
+            });
+    }
+
+    // the bytes of a game's files, and its devices' files if with_devices,
+    // which have no cached digest and will need hashing
+    #[cfg(feature = "native")]
+    fn uncached_bytes(
+        &self,
+        root: &Path,
+        disk_root: &Path,
+        game_name: &str,
+        with_devices: bool,
+    ) -> u64 {
+        match self.game(game_name) {
+            Some(game) => {
+                let uncached = |dir: PathBuf| -> u64 {
+                    std::fs::read_dir(dir)
+                        .map(|dir| {
+                            dir.filter_map(|e| e.ok())
+                                .filter(|e| matches!(Part::has_xattr(&e.path()), Ok(false)))
+                                .filter_map(|e| e.metadata().ok())
+                                .filter(|m| m.is_file())
+                                .map(|m| m.len())
+                                .sum()
+                        })
+                        .unwrap_or(0)
+                };
+
+                let own = if is_hashed_storage() {
+                    game.parts
+                        .iter()
+                        .map(|(_, part)| part.hashed_path(root))
+                        .filter(|path| matches!(Part::has_xattr(path), Ok(false)))
+                        .filter_map(|path| path.metadata().ok())
+                        .map(|m| m.len())
+                        .sum()
+                } else {
+                    uncached(root.join(game_name))
+                };
+
+                own + if disk_root != root {
+                    uncached(disk_root.join(game_name))
+                } else {
+                    0
+                } + if with_devices {
+                    game.devices
+                        .iter()
+                        .map(|device| self.uncached_bytes(root, disk_root, device, true))
+                        .sum::<u64>()
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        }
     }
 
-    fn verify_game(&self, root: &Path, game_name: &str) -> Vec<VerifyFailure> {
+    // verifies a game's own parts along with those of its devices
+    #[cfg(feature = "native")]
+    #[allow(clippy::too_many_arguments)]
+    fn verify_game(
+        &self,
+        root: &Path,
+        disk_root: &Path,
+        game_name: &str,
+        disks: &DiskLocations<'_>,
+        deep: bool,
+        zip: bool,
+        sink: &dyn ProgressSink,
+    ) -> Vec<VerifyFailure<'_>> {
+        let mut results = self.verify_own_parts(root, disk_root, game_name, disks, deep, zip, sink);
         if let Some(game) = self.game(game_name) {
-            let mut results = game.parts.verify_failures(&root.join(game_name));
-            results.extend(
-                game.devices
-                    .iter()
-                    .flat_map(|device| self.verify_game(root, device)),
-            );
+            results.extend(game.devices.iter().flat_map(|device| {
+                self.verify_game(root, disk_root, device, disks, deep, zip, sink)
+            }));
+        }
+        results
+    }
+
+    // verifies only a game's own parts, leaving out its devices
+    #[cfg(feature = "native")]
+    #[allow(clippy::too_many_arguments)]
+    fn verify_own_parts(
+        &self,
+        root: &Path,
+        disk_root: &Path,
+        game_name: &str,
+        disks: &DiskLocations<'_>,
+        deep: bool,
+        zip: bool,
+        sink: &dyn ProgressSink,
+    ) -> Vec<VerifyFailure<'_>> {
+        if let Some(game) = self.game(game_name) {
+            // stored disks have no parents in directories of their own to check
+            if is_hashed_storage() {
+                let (_, results): (ExtendSink<_>, _) = game.parts.verify_hashed(root, sink);
+                return results;
+            }
+
+            let game_disk_root = disk_root.join(game_name);
+            let (successes, mut results): (Vec<_>, Vec<_>) = if zip {
+                game.parts.verify_zip(
+                    &root.join(format!("{}.zip", game_name)),
+                    &game_disk_root,
+                    sink,
+                )
+            } else {
+                game.parts
+                    .verify_with_disks(&root.join(game_name), &game_disk_root, sink)
+            };
+            results.extend(successes.into_iter().filter_map(|success| {
+                Self::verify_disk(disk_root, &game_disk_root, success, disks, deep)
+            }));
             results
         } else {
             Vec::new()
         }
     }
 
-    pub fn list_results(&self, search: Option<&str>, simple: bool) -> Vec<GameRow> {
-        if let Some(search) = search {
-            self.games_iter()
-                .filter(|g| !g.is_device)
-                .map(|g| g.report(simple))
-                .filter(|g| g.matches(search))
-                .collect()
+    // all the devices a game uses, including devices of devices,
+    // which have any parts of their own to verify
+    fn all_devices<'s>(&'s self, game: &'s Game) -> BTreeSet<&'s str> {
+        let mut devices = BTreeSet::new();
+        let mut pending: Vec<&str> = game.devices.iter().map(|d| d.as_str()).collect();
+
+        while let Some(device) = pending.pop() {
+            if let Some(game) = self.game(device) {
+                if devices.insert(game.name.as_str()) {
+                    pending.extend(game.devices.iter().map(|d| d.as_str()));
+                }
+            }
+        }
+
+        devices.retain(|device| self.game(device).is_some_and(|game| !game.parts.is_empty()));
+        devices
+    }
+
+    // maps each disk part to the games and names it may be stored under
+    #[cfg(feature = "native")]
+    fn disk_locations(&self) -> DiskLocations<'_> {
+        let mut disks = DiskLocations::default();
+
+        for game in self.games_iter() {
+            for (name, part) in game.parts.iter() {
+                if matches!(part, Part::Disk { .. }) {
+                    disks
+                        .entry(part)
+                        .or_default()
+                        .push((game.name.as_str(), name.as_str()));
+                }
+            }
+        }
+
+        disks
+    }
+
+    // performs additional checks on successfully verified CHDs
+    #[cfg(feature = "native")]
+    fn verify_disk<'s>(
+        root: &Path,
+        game_root: &Path,
+        VerifySuccess { name, part }: VerifySuccess<'s>,
+        disks: &DiskLocations<'_>,
+        deep: bool,
+    ) -> Option<VerifyFailure<'s>> {
+        if !matches!(part, Part::Disk { .. }) {
+            return None;
+        }
+
+        let path = game_root.join(name);
+
+        let parent_path = match Part::disk_parent(&path).ok().flatten() {
+            Some(parent) => match Self::locate_disk_parent(root, &parent, disks) {
+                Ok(parent_path) => Some(parent_path),
+                Err(Some(parent_path)) => {
+                    return Some(VerifyFailure::BadParent { path, parent_path })
+                }
+                Err(None) => return Some(VerifyFailure::MissingParent { path, parent }),
+            },
+            None => None,
+        };
+
+        if deep {
+            match verify_chd_data(&path, parent_path.as_deref()) {
+                Ok(true) => None,
+                Ok(false) => Some(VerifyFailure::Corrupt { path }),
+                Err(err) => Some(VerifyFailure::Error { path, err }),
+            }
         } else {
-            self.games_iter()
-                .filter(|g| !g.is_device)
-                .map(|g| g.report(simple))
-                .collect()
+            None
+        }
+    }
+
+    // if a CHD is delta-compressed against a parent CHD,
+    // returns the parent's path if it is present and correct
+    // or the path of a mismatched parent, if any
+    #[cfg(feature = "native")]
+    fn locate_disk_parent(
+        root: &Path,
+        parent: &Part,
+        disks: &DiskLocations<'_>,
+    ) -> Result<PathBuf, Option<PathBuf>> {
+        let mut bad_parent = None;
+
+        for (parent_game, parent_name) in disks.get(parent).into_iter().flatten() {
+            let parent_path = root.join(parent_game).join(parent_name);
+            if parent_path.is_file() {
+                match Part::from_cached_path(&parent_path) {
+                    Ok(ref actual) if actual == parent => return Ok(parent_path),
+                    Ok(_) => bad_parent = Some(parent_path),
+                    Err(_) => { /* treat unreadable parents as missing */ }
+                }
+            }
+        }
+
+        Err(bad_parent)
+    }
+
+    pub fn list_results(
+        &self,
+        search: Option<&str>,
+        family: Option<&str>,
+        filter: &Filter,
+        simple: bool,
+    ) -> Vec<GameRow<'_>> {
+        let mut results: Vec<GameRow> = self
+            .games_iter()
+            .filter(|g| filter.allows(g))
+            .map(|g| g.report(simple))
+            .collect();
+
+        if let Some(search) = search {
+            results.retain(|g| g.matches(search));
+        }
+
+        if let Some(family) = family {
+            results.retain(|g| g.in_family(family));
+        }
+
+        results
+    }
+
+    // the games matching the search and family,
+    // along with any parents and devices they depend on
+    pub fn export_games(
+        &self,
+        search: Option<&str>,
+        family: Option<&str>,
+    ) -> BTreeMap<&str, &Game> {
+        let mut games = BTreeMap::new();
+        let mut pending: Vec<&str> = self
+            .list_results(search, family, &Filter::default(), true)
+            .into_iter()
+            .map(|row| row.name)
+            .collect();
+
+        while let Some(name) = pending.pop() {
+            if let Some(game) = self.game(name) {
+                if games.insert(game.name.as_str(), game).is_none() {
+                    pending.extend(
+                        game.cloneof
+                            .iter()
+                            .chain(game.romof.iter())
+                            .chain(game.devices.iter())
+                            .map(|name| name.as_str()),
+                    );
+                }
+            }
         }
+
+        games
     }
 
-    pub fn list(&self, search: Option<&str>, sort: GameColumn, simple: bool) {
-        let mut results = self.list_results(search, simple);
+    pub fn list(
+        &self,
+        search: Option<&str>,
+        family: Option<&str>,
+        filter: &Filter,
+        sort: GameColumn,
+        simple: bool,
+    ) -> Vec<GameRow<'_>> {
+        let mut results = self.list_results(search, family, filter, simple);
         results.sort_by(|a, b| a.compare(b, sort));
-        GameDb::display_report(&results)
+        results
     }
 
-    pub fn games<I>(&self, games: I, simple: bool)
+    pub fn game_rows<I>(&self, games: I, simple: bool) -> Vec<GameRow<'_>>
     where
         I: IntoIterator,
         I::Item: AsRef<str>,
     {
-        GameDb::display_report(
-            &games
-                .into_iter()
-                .filter_map(|g| self.game(g.as_ref()).map(|g| g.report(simple)))
-                .collect::<Vec<GameRow>>(),
-        )
+        games
+            .into_iter()
+            .filter_map(|g| self.game(g.as_ref()).map(|g| g.report(simple)))
+            .collect()
     }
 
-    pub fn display_all_games(self, simple: bool) {
-        GameDb::display_report(
-            &self
-                .games_iter()
-                .map(|g| g.report(simple))
-                .collect::<Vec<GameRow>>(),
-        )
+    #[inline]
+    pub fn all_game_rows(&self, simple: bool) -> Vec<GameRow<'_>> {
+        self.games_iter().map(|g| g.report(simple)).collect()
     }
 
     pub fn report_results(
         &self,
         games: &HashSet<String>,
         search: Option<&str>,
+        family: Option<&str>,
+        filter: &Filter,
         simple: bool,
-    ) -> Vec<GameRow> {
+    ) -> Vec<GameRow<'_>> {
         let mut results: Vec<GameRow> = games
             .iter()
             .filter_map(|g| {
                 self.game(g)
-                    .filter(|g| !g.is_device)
+                    .filter(|g| filter.allows(g))
                     .map(|g| g.report(simple))
             })
             .collect();
@@ -197,6 +890,10 @@ impl GameDb {
             results.retain(|g| g.matches(search));
         }
 
+        if let Some(family) = family {
+            results.retain(|g| g.in_family(family));
+        }
+
         results
     }
 
@@ -204,82 +901,290 @@ impl GameDb {
         &self,
         games: &HashSet<String>,
         search: Option<&str>,
+        family: Option<&str>,
+        filter: &Filter,
         sort: GameColumn,
         simple: bool,
-    ) {
-        let mut results = self.report_results(games, search, simple);
+    ) -> Vec<GameRow<'_>> {
+        let mut results = self.report_results(games, search, family, filter, simple);
         results.sort_by(|a, b| a.compare(b, sort));
-        GameDb::display_report(&results)
-    }
-
-    fn display_report(games: &[GameRow]) {
-        use prettytable::{cell, format, row};
-
-        let mut table = Table::new();
-        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-        table.get_format().column_separator('\u{2502}');
-
-        for game in games {
-            let description = game.description;
-            let creator = game.creator;
-            let year = game.year;
-            let name = game.name;
-
-            table.add_row(match game.status {
-                Status::Working => row![description, creator, year, name],
-                Status::Partial => row![FY => description, creator, year, name],
-                Status::NotWorking => row![FR => description, creator, year, name],
-            });
-        }
-
-        table.printstd();
+        results
     }
 
-    pub fn display_parts(&self, name: &str) -> Result<(), Error> {
-        use prettytable::{cell, format, row};
-
+    // where a game's part comes from and which other games share it
+    pub fn why<'s>(&'s self, game_name: &str, part_name: &str) -> Result<PartOrigin<'s>, Error> {
         let game = self
-            .game(name)
-            .ok_or_else(|| Error::NoSuchSoftware(name.to_string()))?;
+            .game(game_name)
+            .ok_or_else(|| Error::NoSuchSoftware(game_name.to_string()))?;
 
-        let mut table = Table::new();
-        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-        table.get_format().column_separator('\u{2502}');
+        let find = |game: &'s Game| {
+            game.parts
+                .get_key_value(part_name)
+                .or_else(|| game.parts.get_key_value(&format!("{}.chd", part_name)))
+        };
 
-        let devices: BTreeMap<&str, &Game> = game
-            .devices
-            .iter()
-            .map(|dev| self.game(dev).expect("unknown device in game"))
-            .filter(|game| !game.parts.is_empty())
-            .map(|game| (game.name.as_str(), game))
-            .collect();
+        let (name, part, source) = match find(game) {
+            Some((name, part)) if game.merged.contains(name) => {
+                (name, part, self.merged_source(game, part))
+            }
+            Some((name, part)) => (name, part, PartSource::Own),
+            None => self
+                .all_devices(game)
+                .into_iter()
+                .filter_map(|device| self.game(device))
+                .find_map(|device| {
+                    find(device).map(|(name, part)| (name, part, PartSource::Device(device)))
+                })
+                .ok_or_else(|| Error::NoSuchPart(game_name.to_string(), part_name.to_string()))?,
+        };
 
-        if devices.is_empty() {
-            game.display_parts(&mut table);
-        } else {
-            table.add_row(row![H3cu->name]);
-            game.display_parts(&mut table);
-            for (dev_name, dev) in devices.into_iter() {
-                table.add_row(row![H3cu->dev_name]);
-                dev.display_parts(&mut table);
+        // devices are their own sets, so it's those using them which share them
+        let shared = match source {
+            PartSource::Device(device) => self
+                .games_iter()
+                .filter(|other| other.name != game.name)
+                .filter(|other| other.devices.contains(&device.name))
+                .map(|other| (other.name.as_str(), name.as_str()))
+                .collect(),
+            _ => self
+                .games_iter()
+                .filter(|other| other.name != game.name)
+                .flat_map(|other| {
+                    other
+                        .parts
+                        .iter()
+                        .filter(|(_, other_part)| *other_part == part)
+                        .map(move |(other_name, _)| (other.name.as_str(), other_name.as_str()))
+                })
+                .collect(),
+        };
+
+        Ok(PartOrigin {
+            game,
+            name,
+            part,
+            source,
+            shared,
+        })
+    }
+
+    // follows a game's romof chain to the set
+    // whose own part the merged part is
+    fn merged_source<'s>(&'s self, game: &'s Game, part: &Part) -> PartSource<'s> {
+        let mut source = PartSource::Own;
+        let mut romof = game.romof.as_deref();
+
+        // chains are short, but malformed data may loop
+        for _ in 0..8 {
+            let set = match romof.and_then(|romof| self.game(romof)) {
+                Some(set) => set,
+                None => break,
+            };
+
+            match set.parts.iter().find(|(_, set_part)| *set_part == part) {
+                Some((name, _)) => {
+                    source = match set.class {
+                        Class::Bios => PartSource::Bios(set),
+                        _ => PartSource::Parent(set),
+                    };
+                    if !set.merged.contains(name) {
+                        break;
+                    }
+                }
+                None => break,
             }
+
+            romof = set.romof.as_deref();
+        }
+
+        source
+    }
+}
+
+// which set a game's part is expected to come from
+pub enum PartSource<'s> {
+    // the game's own set
+    Own,
+    // a parent set, by way of the game's romof
+    Parent(&'s Game),
+    // a BIOS set, by way of the game's romof
+    Bios(&'s Game),
+    // a device the game uses
+    Device(&'s Game),
+}
+
+impl fmt::Display for PartSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PartSource::Own => write!(f, "belongs to the machine itself"),
+            PartSource::Parent(set) => write!(f, "comes from parent \"{}\"", set.name),
+            PartSource::Bios(set) => write!(f, "comes from BIOS \"{}\"", set.name),
+            PartSource::Device(set) => write!(f, "comes from device \"{}\"", set.name),
+        }
+    }
+}
+
+pub struct PartOrigin<'s> {
+    pub game: &'s Game,
+    pub name: &'s str,
+    pub part: &'s Part,
+    pub source: PartSource<'s>,
+    // other games needing the same data, and the names they give it
+    pub shared: Vec<(&'s str, &'s str)>,
+}
+
+// how one database differs from another
+pub struct DbDiff<'s> {
+    pub added: BTreeSet<&'s str>,
+    pub removed: BTreeSet<&'s str>,
+    // old name -> new name
+    pub renamed: BTreeMap<&'s str, &'s str>,
+    // parts of games in both, in game order
+    pub parts: Vec<PartChange<'s>>,
+    // the number of games in both with any parts changed
+    pub changed: usize,
+}
+
+pub enum PartChange<'s> {
+    Changed { old: PartId<'s>, new: PartId<'s> },
+    Removed(PartId<'s>),
+    Added(PartId<'s>),
+}
+
+// which games are listed, by class, by user tag, by category and by players,
+// where empty lists allow any
+#[derive(Copy, Clone, Default)]
+pub struct Filter<'f> {
+    pub classes: &'f [Class],
+    // tags and notes to display alongside games, if any
+    pub curation: Option<&'f crate::tags::TagDb>,
+    pub tags: &'f [String],
+    // category patterns, like "Shooter/*"
+    pub categories: &'f [String],
+    // player count patterns, like "2P sim"
+    pub players: &'f [String],
+    // driver statuses, like "imperfect" emulation or "supported" save states
+    pub driver_status: Option<&'f str>,
+    pub emulation: Option<&'f str>,
+    pub savestate: Option<&'f str>,
+}
+
+impl Filter<'_> {
+    // devices are only listed when asked for by class
+    fn allows(&self, game: &Game) -> bool {
+        (if self.classes.is_empty() {
+            !game.is_device
+        } else {
+            game.in_classes(self.classes)
+        }) && (self.tags.is_empty()
+            || self
+                .curation
+                .map(|curation| curation.has_any(&game.name, self.tags))
+                .unwrap_or(false))
+            && (self.categories.is_empty()
+                || game.category.as_deref().is_some_and(|category| {
+                    self.categories
+                        .iter()
+                        .any(|pattern| pattern_matches(pattern, category))
+                }))
+            && game.has_players(self.players)
+            && game.has_driver_status(self.driver_status, |driver| &driver.status)
+            && game.has_driver_status(self.emulation, |driver| &driver.emulation)
+            && game.has_driver_status(self.savestate, |driver| &driver.savestate)
+    }
+}
+
+// patterns match case-insensitively,
+// where "*" matches any run of characters
+// and spacing around "/" separators is ignored
+fn pattern_matches(pattern: &str, value: &str) -> bool {
+    fn normalize(s: &str) -> Vec<char> {
+        s.split('/')
+            .map(|s| s.trim())
+            .collect::<Vec<_>>()
+            .join("/")
+            .to_lowercase()
+            .chars()
+            .collect()
+    }
+
+    fn glob(pattern: &[char], s: &[char]) -> bool {
+        match pattern.split_first() {
+            None => s.is_empty(),
+            Some(('*', rest)) => (0..=s.len()).any(|i| glob(rest, &s[i..])),
+            Some((c, rest)) => s
+                .split_first()
+                .is_some_and(|(d, s)| c == d && glob(rest, s)),
         }
+    }
+
+    glob(&normalize(pattern), &normalize(value))
+}
+
+#[cfg(feature = "native")]
+type DiskLocations<'d> = HashMap<&'d Part, Vec<(&'d str, &'d str)>>;
+
+// what sort of machine or software a game is
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Class {
+    #[default]
+    Arcade,
+    Mechanical,
+    Bios,
+    Device,
+    Console,
+}
+
+impl FromStr for Class {
+    type Err = String;
 
-        table.printstd();
-        Ok(())
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "arcade" => Ok(Class::Arcade),
+            "mechanical" => Ok(Class::Mechanical),
+            "bios" => Ok(Class::Bios),
+            "device" => Ok(Class::Device),
+            "console" => Ok(Class::Console),
+            _ => Err("invalid class".to_string()),
+        }
     }
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub enum Status {
+    #[default]
     Working,
     Partial,
     NotWorking,
 }
 
-impl Default for Status {
-    fn default() -> Self {
-        Status::Working
+// the finer-grained statuses MAME gives a machine's driver,
+// which its overall status follows from, like "good", "imperfect"
+// or "preliminary" for the driver and its emulation,
+// and "supported" or "unsupported" for save states
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DriverStatus {
+    pub status: String,
+    pub emulation: String,
+    pub savestate: String,
+}
+
+impl DriverStatus {
+    // only what falls short is worth mentioning
+    pub fn shortcomings(&self) -> String {
+        let mut shortcomings = Vec::new();
+
+        if !matches!(self.status.as_str(), "good" | "") {
+            shortcomings.push(format!("driver {}", self.status));
+        }
+        if !matches!(self.emulation.as_str(), "good" | "") {
+            shortcomings.push(format!("emulation {}", self.emulation));
+        }
+        if self.savestate == "unsupported" {
+            shortcomings.push("no save states".to_owned());
+        }
+
+        shortcomings.join(", ")
     }
 }
 
@@ -290,12 +1195,122 @@ pub struct Game {
     pub creator: String,
     pub year: String,
     pub status: Status,
+    // MAME machines' driver statuses
+    #[serde(default)]
+    pub driver: Option<DriverStatus>,
     pub is_device: bool,
+    #[serde(default)]
+    pub class: Class,
     pub parts: GameParts,
     pub devices: Vec<String>,
+    // parts whose only available dumps are known to be bad
+    #[serde(default)]
+    pub bad_dumps: BTreeSet<String>,
+    #[serde(default)]
+    pub cloneof: Option<String>,
+    #[serde(default)]
+    pub romof: Option<String>,
+    // parts which are shared with the romof set
+    #[serde(default)]
+    pub merged: BTreeSet<String>,
+    // the sizes of ROMs, in bytes, where known
+    #[serde(default)]
+    pub sizes: BTreeMap<String, u64>,
+    // other software needed to run this one,
+    // as "software" or "list:software"
+    #[serde(default)]
+    pub requires: Vec<String>,
+    // genre from catver.ini, like "Shooter / Flying Vertical"
+    #[serde(default)]
+    pub category: Option<String>,
+    // player counts from nplayers.ini, like "4P alt / 2P sim"
+    #[serde(default)]
+    pub players: Option<String>,
+    // regions and languages, for DATs which give them
+    #[serde(default)]
+    pub locale: Locale,
 }
 
 impl Game {
+    // games in any of the given classes,
+    // where no classes at all means any game
+    #[inline]
+    pub fn in_classes(&self, classes: &[Class]) -> bool {
+        classes.is_empty() || classes.contains(&self.class)
+    }
+
+    // games whose driver has the given status, where no status means any game
+    // and games without driver statuses never have one
+    pub fn has_driver_status(
+        &self,
+        wanted: Option<&str>,
+        field: fn(&DriverStatus) -> &str,
+    ) -> bool {
+        wanted.is_none_or(|wanted| {
+            self.driver
+                .as_ref()
+                .is_some_and(|driver| field(driver).eq_ignore_ascii_case(wanted))
+        })
+    }
+
+    // games playable in any of the given ways, like "2P sim",
+    // where no patterns at all means any game
+    pub fn has_players(&self, patterns: &[String]) -> bool {
+        patterns.is_empty()
+            || self.players.as_deref().is_some_and(|players| {
+                players.split('/').any(|players| {
+                    patterns
+                        .iter()
+                        .any(|pattern| pattern_matches(pattern, players))
+                })
+            })
+    }
+
+    #[inline]
+    pub fn has_bad_dumps(&self) -> bool {
+        !self.bad_dumps.is_empty()
+    }
+
+    fn remove_merged(&mut self) {
+        let merged = &self.merged;
+        self.parts.retain(|name, _| !merged.contains(name));
+        self.bad_dumps.retain(|name| !merged.contains(name));
+    }
+
+    // adds a clone's parts and devices to our own,
+    // skipping any already present under the same name,
+    // while any whose name is taken by different contents
+    // are kept in a subdirectory named for the clone
+    fn absorb(&mut self, clone: Game) {
+        for (name, part) in clone.parts.into_iter() {
+            let target = match self.parts.get_key_value(&name) {
+                None => name.clone(),
+                Some((_, existing)) if *existing == part => continue,
+                Some(_) => qualify_name(&name, &clone.name),
+            };
+
+            if clone.bad_dumps.contains(&name) {
+                self.bad_dumps.insert(target.clone());
+            }
+            if target != name {
+                if let Some(size) = clone.sizes.get(&name) {
+                    self.sizes.insert(target.clone(), *size);
+                }
+            }
+            self.parts.insert(target, part);
+        }
+
+        for (name, size) in clone.sizes {
+            self.sizes.entry(name).or_insert(size);
+        }
+
+        for device in clone.devices {
+            if !self.devices.contains(&device) {
+                self.devices.push(device);
+            }
+        }
+    }
+
     #[inline]
     pub fn is_working(&self) -> bool {
         match self.status {
@@ -304,7 +1319,7 @@ impl Game {
         }
     }
 
-    pub fn report(&self, simple: bool) -> GameRow {
+    pub fn report(&self, simple: bool) -> GameRow<'_> {
         #[inline]
         fn no_parens(s: &str) -> &str {
             if let Some(index) = s.find('(') {
@@ -337,44 +1352,72 @@ impl Game {
             },
             year: &self.year,
             status: self.status,
+            driver: self.driver.as_ref(),
+            cloneof: self.cloneof.as_deref(),
+            category: self.category.as_deref(),
+            players: self.players.as_deref(),
         }
     }
 
-    // appends game's name to root automatically
+    // appends game's name to root automatically,
+    // with any disks stored under disk_dir instead
+    #[cfg(feature = "native")]
     #[inline]
-    pub fn add_and_verify<H>(
+    pub fn add_and_verify(
         &self,
         rom_sources: &RomSources,
         target_dir: &Path,
-        handle_failure: H,
-    ) -> Result<Vec<VerifyFailure>, Error>
-    where
-        H: Fn(ExtractedPart<'_>) + Send + Sync + Copy,
-    {
-        self.parts.add_and_verify_failures(
+        disk_dir: &Path,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+    ) -> Result<Vec<VerifyFailure<'_>>, Error> {
+        if is_hashed_storage() {
+            return self.parts.add_and_verify_hashed(
+                rom_sources,
+                target_dir,
+                dry_run,
+                sink,
+                |part| sink.part_placed(&part),
+            );
+        }
+
+        self.parts.add_and_verify_with_disks(
             rom_sources,
             &target_dir.join(&self.name),
-            handle_failure,
+            &disk_dir.join(&self.name),
+            dry_run,
+            sink,
+            |part| sink.part_placed(&part),
         )
     }
 
-    pub fn display_parts(&self, table: &mut Table) {
-        use prettytable::{cell, row};
-
-        let parts: BTreeMap<&str, &Part> = self
-            .parts
-            .iter()
-            .map(|(name, part)| (name.as_str(), part))
-            .collect();
-
-        if !parts.is_empty() {
-            for (name, part) in parts {
-                table.add_row(row![name, part.digest()]);
-            }
-        }
+    // stores game's ROMs in a "<name>.zip" archive in target_dir
+    // with any disks in a "<name>" directory in disk_dir,
+    // stamping the archive if it's rebuilt
+    #[cfg(feature = "native")]
+    #[inline]
+    pub fn add_and_verify_zip(
+        &self,
+        rom_sources: &RomSources,
+        target_dir: &Path,
+        disk_dir: &Path,
+        stamp: Option<&crate::stamp::Stamp>,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+    ) -> Result<Vec<VerifyFailure<'_>>, Error> {
+        self.parts.add_and_verify_zip(
+            rom_sources,
+            &target_dir.join(format!("{}.zip", self.name)),
+            &disk_dir.join(&self.name),
+            stamp,
+            dry_run,
+            sink,
+            |part| sink.part_placed(&part),
+        )
     }
 }
 
+#[cfg(feature = "native")]
 fn read_game_dir<'s, I, S, F>(dir: I) -> (S, F)
 where
     I: Iterator<Item = std::io::Result<std::fs::DirEntry>>,
@@ -384,10 +1427,13 @@ where
     let mut files_on_disk = S::default();
     let mut failures = F::default();
 
-    for entry in dir
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
-    {
+    for entry in dir.filter_map(|e| e.ok()).filter(|e| match e.file_type() {
+        Ok(t) if t.is_file() => true,
+        // parts may be linked to rather than stored,
+        // where links to nowhere are reported when read
+        Ok(t) if t.is_symlink() => !e.path().is_dir(),
+        _ => false,
+    }) {
         match entry.file_name().into_string() {
             Ok(name) => files_on_disk.extend_item((name, entry.path())),
             Err(_) => failures.extend_item(VerifyFailure::extra(entry.path())),
@@ -397,12 +1443,43 @@ where
     (files_on_disk, failures)
 }
 
+// escapes a part's name by appending "~" and an index
+// ahead of its extension, like "foo.bin" to "foo~1.bin"
+pub fn escape_name(name: &str, index: usize) -> String {
+    let stem_start = name.rfind('/').map(|slash| slash + 1).unwrap_or(0);
+
+    match name[stem_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let (stem, extension) = name.split_at(stem_start + dot);
+            format!("{}~{}{}", stem, index, extension)
+        }
+        _ => format!("{}~{}", name, index),
+    }
+}
+
+// qualifies a part's name by placing it in a subdirectory
+// named by the qualifier, like "foo.bin" to "clone/foo.bin"
+#[inline]
+pub fn qualify_name(name: &str, qualifier: &str) -> String {
+    format!("{}/{}", qualifier, name)
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct GameParts {
     parts: HashMap<String, Part>,
 }
 
+impl IntoIterator for GameParts {
+    type Item = (String, Part);
+    type IntoIter = std::collections::hash_map::IntoIter<String, Part>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.parts.into_iter()
+    }
+}
+
 impl FromIterator<(String, Part)> for GameParts {
     #[inline]
     fn from_iter<T>(iter: T) -> Self
@@ -441,11 +1518,6 @@ impl GameParts {
         self.parts.iter()
     }
 
-    #[inline]
-    pub fn into_iter(self) -> impl Iterator<Item = (String, Part)> {
-        self.parts.into_iter()
-    }
-
     #[inline]
     pub fn keys(&self) -> impl Iterator<Item = &String> {
         self.parts.keys()
@@ -461,68 +1533,169 @@ impl GameParts {
         self.parts.insert(k, v)
     }
 
+    #[inline]
+    pub fn contains_key(&self, k: &str) -> bool {
+        self.parts.contains_key(k)
+    }
+
+    #[inline]
+    pub fn get_key_value(&self, k: &str) -> Option<(&String, &Part)> {
+        self.parts.get_key_value(k)
+    }
+
+    #[inline]
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&String, &mut Part) -> bool,
+    {
+        self.parts.retain(f)
+    }
+
+    // groups of part names which differ only by case, sorted
+    pub fn case_collisions(&self) -> Vec<Vec<&str>> {
+        let mut folded: HashMap<String, Vec<&str>> = HashMap::default();
+
+        for name in self.parts.keys() {
+            folded.entry(name.to_lowercase()).or_default().push(name);
+        }
+
+        let mut collisions = folded
+            .into_values()
+            .filter(|names| names.len() > 1)
+            .map(|mut names| {
+                names.sort_unstable();
+                names
+            })
+            .collect::<Vec<_>>();
+
+        collisions.sort_unstable();
+
+        collisions
+    }
+
+    // parts whose names differ only by case would overwrite
+    // one another on case-insensitive filesystems,
+    // so all but the first of each are stored under escaped names
+    pub fn escaped_names(&self) -> HashMap<&str, String> {
+        self.case_collisions()
+            .into_iter()
+            .flat_map(|names| {
+                names
+                    .into_iter()
+                    .enumerate()
+                    .skip(1)
+                    .map(|(index, name)| (name, escape_name(name, index)))
+            })
+            .collect()
+    }
+
     // game_root is the root directory to start looking for files
+    // disk_root is where disks are looked for, which is usually game_root
+    // sink is told of any files hashed along the way
     // increment_progress is called once per (name, part) pair
     // handle_failure is an attempt to recover from failures
-    fn process_parts<'s, S, F, I, H, E>(
+    #[cfg(feature = "native")]
+    #[allow(clippy::too_many_arguments)]
+    fn process_parts<'s, S, F, I, P, H, E>(
         &'s self,
         game_root: &Path,
+        disk_root: &Path,
+        sink: &dyn ProgressSink,
         increment_progress: I,
+        plan: P,
         handle_failure: H,
     ) -> Result<(S, F), E>
     where
         S: Default + ExtendOne<VerifySuccess<'s>> + Send,
         F: Default + ExtendOne<VerifyFailure<'s>> + Send,
         I: Fn() + Send + Sync,
-        H: Fn(VerifyFailure) -> Result<Result<(), VerifyFailure>, E> + Send + Sync,
+        P: FnOnce(&[&VerifyFailure<'s>]) -> Result<(), E>,
+        H: Fn(VerifyFailure<'s>) -> Result<Result<(), VerifyFailure<'s>>, E> + Send + Sync,
         E: Send,
     {
         use rayon::prelude::*;
         use std::sync::Mutex;
 
-        let (files_on_disk, failures): (DashMap<_, _>, F) = std::fs::read_dir(&game_root)
+        let (files_on_disk, mut failures): (DashMap<_, _>, F) = std::fs::read_dir(game_root)
             .map(read_game_dir)
             .unwrap_or_default();
 
-        let successes = Mutex::new(S::default());
-        let failures = Mutex::new(failures);
+        // disks kept apart from ROMs are looked for on their own
+        let disks_on_disk: Option<DashMap<_, _>> = if disk_root != game_root {
+            let (disks_on_disk, disk_failures): (_, Vec<_>) = std::fs::read_dir(disk_root)
+                .map(read_game_dir)
+                .unwrap_or_default();
+            failures.extend_many(disk_failures);
+            Some(disks_on_disk)
+        } else {
+            None
+        };
 
-        // verify all game parts
-        self.parts.par_iter().try_for_each(|(name, part)| {
-            match files_on_disk.remove(name) {
-                Some((_, pathbuf)) => match part.verify(name, pathbuf) {
-                    Ok(success) => successes.lock().unwrap().extend_item(success),
+        let escaped = self.escaped_names();
 
-                    Err(failure) => match handle_failure(failure)? {
-                        Ok(()) => successes
-                            .lock()
-                            .unwrap()
-                            .extend_item(VerifySuccess { name, part }),
+        let successes = Mutex::new(S::default());
+        let failures = Mutex::new(failures);
 
-                        Err(failure) => failures.lock().unwrap().extend_item(failure),
+        // verify all game parts, setting aside those which fail
+        let failed: Vec<(&String, &Part, VerifyFailure<'s>)> = self
+            .parts
+            .par_iter()
+            .filter_map(|(name, part)| {
+                let disk_name = escaped.get(name.as_str()).unwrap_or(name);
+
+                let (root, files) = match (part, disks_on_disk.as_ref()) {
+                    (Part::Disk { .. }, Some(disks_on_disk)) => (disk_root, disks_on_disk),
+                    _ => (game_root, &files_on_disk),
+                };
+
+                match files.remove(disk_name) {
+                    Some((_, pathbuf)) => match part.verify(name, pathbuf, sink) {
+                        Ok(success) => {
+                            successes.lock().unwrap().extend_item(success);
+                            increment_progress();
+                            None
+                        }
+                        Err(failure) => Some((name, part, failure)),
                     },
-                },
 
-                None => {
-                    match handle_failure(VerifyFailure::Missing {
-                        path: game_root.join(name),
-                        part,
+                    None => Some((
                         name,
-                    })? {
-                        Ok(()) => successes
-                            .lock()
-                            .unwrap()
-                            .extend_item(VerifySuccess { name, part }),
+                        part,
+                        VerifyFailure::Missing {
+                            path: root.join(disk_name),
+                            part,
+                            name,
+                        },
+                    )),
+                }
+            })
+            .collect();
 
-                        Err(failure) => failures.lock().unwrap().extend_item(failure),
-                    }
+        // so that everything handling them would do
+        // is known before any of it is done
+        plan(
+            &failed
+                .iter()
+                .map(|(_, _, failure)| failure)
+                .collect::<Vec<_>>(),
+        )?;
+
+        failed
+            .into_par_iter()
+            .try_for_each(|(name, part, failure)| {
+                match handle_failure(failure)? {
+                    Ok(()) => successes
+                        .lock()
+                        .unwrap()
+                        .extend_item(VerifySuccess { name, part }),
+
+                    Err(failure) => failures.lock().unwrap().extend_item(failure),
                 }
-            }
 
-            increment_progress();
+                increment_progress();
 
-            Ok(())
-        })?;
+                Ok(())
+            })?;
 
         let mut failures = failures.into_inner().unwrap();
 
@@ -530,16 +1703,112 @@ impl GameParts {
         failures.extend_many(
             files_on_disk
                 .into_iter()
+                .chain(disks_on_disk.into_iter().flatten())
                 .map(|(_, pb)| VerifyFailure::extra(pb)),
         );
 
         Ok((successes.into_inner().unwrap(), failures))
     }
 
+    // parts in hashed storage are looked for one at a time, since a game
+    // may list the same part under several names and all share one file
+    #[cfg(feature = "native")]
+    fn process_hashed_parts<'s, S, F, P, H, E>(
+        &'s self,
+        store: &Path,
+        sink: &dyn ProgressSink,
+        plan: P,
+        handle_failure: H,
+    ) -> Result<(S, F), E>
+    where
+        S: Default + ExtendOne<VerifySuccess<'s>>,
+        F: Default + ExtendOne<VerifyFailure<'s>>,
+        P: FnOnce(&[&VerifyFailure<'s>]) -> Result<(), E>,
+        H: Fn(VerifyFailure<'s>) -> Result<Result<(), VerifyFailure<'s>>, E>,
+    {
+        let mut successes = S::default();
+        let mut failures = F::default();
+        let mut failed = Vec::new();
+
+        for (name, part) in self.parts.iter() {
+            let path = part.hashed_path(store);
+
+            let result = if path.is_file() {
+                part.verify(name, path, sink)
+            } else {
+                Err(VerifyFailure::Missing { path, name, part })
+            };
+
+            match result {
+                Ok(success) => successes.extend_item(success),
+                Err(failure) => failed.push((name, part, failure)),
+            }
+        }
+
+        plan(
+            &failed
+                .iter()
+                .map(|(_, _, failure)| failure)
+                .collect::<Vec<_>>(),
+        )?;
+
+        for (name, part, failure) in failed {
+            match handle_failure(failure)? {
+                Ok(()) => successes.extend_item(VerifySuccess { name, part }),
+                Err(failure) => failures.extend_item(failure),
+            }
+        }
+
+        Ok((successes, failures))
+    }
+
+    #[cfg(feature = "native")]
+    #[inline]
+    pub fn verify_hashed<'s, S, F>(&'s self, store: &Path, sink: &dyn ProgressSink) -> (S, F)
+    where
+        S: Default + ExtendOne<VerifySuccess<'s>>,
+        F: Default + ExtendOne<VerifyFailure<'s>>,
+    {
+        self.process_hashed_parts(
+            store,
+            sink,
+            |_| Ok(()),
+            |failure| -> Result<Result<(), VerifyFailure>, Never> { Ok(Err(failure)) },
+        )
+        .unwrap()
+    }
+
+    #[cfg(feature = "native")]
+    pub fn add_and_verify_hashed<'s, H>(
+        &'s self,
+        rom_sources: &RomSources,
+        store: &Path,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+        handle_failure: H,
+    ) -> Result<Vec<VerifyFailure<'s>>, Error>
+    where
+        H: Fn(ExtractedPart<'_>) + Copy,
+    {
+        self.process_hashed_parts(
+            store,
+            sink,
+            |failures| plan_fixes(failures, rom_sources, dry_run),
+            |failure| {
+                failure
+                    .try_fix(rom_sources, dry_run)
+                    .map(|r| r.map(handle_failure))
+            },
+        )
+        .map(|(_, failures): (ExtendSink<_>, _)| failures)
+    }
+
+    #[cfg(feature = "native")]
     #[inline]
     pub fn verify_with_progress<'s, S, F, I>(
         &'s self,
         game_root: &Path,
+        sink: &dyn ProgressSink,
         increment_progress: I,
     ) -> (S, F)
     where
@@ -549,32 +1818,70 @@ impl GameParts {
     {
         self.process_parts(
             game_root,
+            game_root,
+            sink,
             increment_progress,
+            |_| Ok(()),
+            |failure| -> Result<Result<(), VerifyFailure>, Never> { Ok(Err(failure)) },
+        )
+        .unwrap()
+    }
+
+    // like verify, but with disks in disk_root rather than game_root
+    #[cfg(feature = "native")]
+    #[inline]
+    pub fn verify_with_disks<'s, S, F>(
+        &'s self,
+        game_root: &Path,
+        disk_root: &Path,
+        sink: &dyn ProgressSink,
+    ) -> (S, F)
+    where
+        S: Default + ExtendOne<VerifySuccess<'s>> + Send,
+        F: Default + ExtendOne<VerifyFailure<'s>> + Send,
+    {
+        self.process_parts(
+            game_root,
+            disk_root,
+            sink,
+            || {},
+            |_| Ok(()),
             |failure| -> Result<Result<(), VerifyFailure>, Never> { Ok(Err(failure)) },
         )
         .unwrap()
     }
 
+    #[cfg(feature = "native")]
     #[inline]
-    pub fn verify<'s, S, F>(&'s self, game_root: &Path) -> (S, F)
+    pub fn verify<'s, S, F>(&'s self, game_root: &Path, sink: &dyn ProgressSink) -> (S, F)
     where
         S: Default + ExtendOne<VerifySuccess<'s>> + Send,
         F: Default + ExtendOne<VerifyFailure<'s>> + Send,
     {
-        self.verify_with_progress(game_root, || {})
+        self.verify_with_progress(game_root, sink, || {})
     }
 
+    #[cfg(feature = "native")]
     #[inline]
-    pub fn verify_failures<'s>(&'s self, game_root: &Path) -> Vec<VerifyFailure<'s>> {
-        let (_, failures): (ExtendSink<_>, _) = self.verify(game_root);
+    pub fn verify_failures<'s>(
+        &'s self,
+        game_root: &Path,
+        sink: &dyn ProgressSink,
+    ) -> Vec<VerifyFailure<'s>> {
+        let (_, failures): (ExtendSink<_>, _) = self.verify(game_root, sink);
         failures
     }
 
+    // dry_run reports which source would populate each part
+    // without touching anything on disk
+    #[cfg(feature = "native")]
     #[inline]
     pub fn add_and_verify_with_progress<'s, S, F, I, H>(
         &'s self,
         rom_sources: &RomSources,
         game_root: &Path,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
         increment_progress: I,
         handle_failure: H,
     ) -> Result<(S, F), Error>
@@ -584,39 +1891,445 @@ impl GameParts {
         I: Fn() + Send + Sync,
         H: Fn(ExtractedPart<'_>) + Send + Sync + Copy,
     {
-        self.process_parts(game_root, increment_progress, |failure| {
-            failure.try_fix(rom_sources).map(|r| r.map(handle_failure))
-        })
+        self.process_parts(
+            game_root,
+            game_root,
+            sink,
+            increment_progress,
+            |failures| plan_fixes(failures, rom_sources, dry_run),
+            |failure| {
+                failure
+                    .try_fix(rom_sources, dry_run)
+                    .map(|r| r.map(handle_failure))
+            },
+        )
     }
 
-    #[inline]
-    pub fn add_and_verify<'s, S, F, H>(
+    // like add_and_verify_with_progress, but for parts which are each
+    // a game of their own, so one which can't be added is returned
+    // along with why rather than stopping all the others
+    #[cfg(feature = "native")]
+    #[allow(clippy::type_complexity)]
+    pub fn add_and_verify_each<'s, S, F, I, H>(
         &'s self,
         rom_sources: &RomSources,
         game_root: &Path,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+        increment_progress: I,
         handle_failure: H,
-    ) -> Result<(S, F), Error>
+    ) -> Result<(S, F, crate::FailedGames), Error>
     where
         S: Default + ExtendOne<VerifySuccess<'s>> + Send,
         F: Default + ExtendOne<VerifyFailure<'s>> + Send,
+        I: Fn() + Send + Sync,
         H: Fn(ExtractedPart<'_>) + Send + Sync + Copy,
     {
-        self.add_and_verify_with_progress(rom_sources, game_root, || {}, handle_failure)
+        use std::sync::Mutex;
+
+        let failed = Mutex::new(Vec::new());
+
+        let (successes, failures) = self.process_parts(
+            game_root,
+            game_root,
+            sink,
+            increment_progress,
+            |failures| plan_fixes(failures, rom_sources, dry_run),
+            |failure| -> Result<Result<(), VerifyFailure>, Error> {
+                // only missing and bad parts are ever fixed
+                let (name, path) = match &failure {
+                    VerifyFailure::Missing { name, path, .. }
+                    | VerifyFailure::Bad { name, path, .. } => (*name, path.clone()),
+                    _ => return Ok(Err(failure)),
+                };
+
+                Ok(match failure.try_fix(rom_sources, dry_run) {
+                    Ok(r) => r.map(handle_failure),
+                    Err(err) => {
+                        let failure = VerifyFailure::Error {
+                            path,
+                            err: std::io::Error::other(err.to_string()),
+                        };
+                        failed.lock().unwrap().push((name.to_owned(), err));
+                        Err(failure)
+                    }
+                })
+            },
+        )?;
+
+        Ok((successes, failures, failed.into_inner().unwrap()))
     }
 
-    #[inline]
-    pub fn add_and_verify_failures<'s, H>(
+    // like add_and_verify_failures, but with disks in disk_root rather than game_root
+    #[cfg(feature = "native")]
+    pub fn add_and_verify_with_disks<'s, H>(
         &'s self,
         rom_sources: &RomSources,
         game_root: &Path,
+        disk_root: &Path,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
         handle_failure: H,
-    ) -> Result<Vec<VerifyFailure>, Error>
+    ) -> Result<Vec<VerifyFailure<'s>>, Error>
     where
         H: Fn(ExtractedPart<'_>) + Send + Sync + Copy,
     {
-        self.add_and_verify(rom_sources, game_root, handle_failure)
-            .map(|(_, failures): (ExtendSink<_>, _)| failures)
+        self.process_parts(
+            game_root,
+            disk_root,
+            sink,
+            || {},
+            |failures| plan_fixes(failures, rom_sources, dry_run),
+            |failure| {
+                failure
+                    .try_fix(rom_sources, dry_run)
+                    .map(|r| r.map(handle_failure))
+            },
+        )
+        .map(|(_, failures): (ExtendSink<_>, _)| failures)
     }
+
+    #[cfg(feature = "native")]
+    #[inline]
+    pub fn add_and_verify<'s, S, F, H>(
+        &'s self,
+        rom_sources: &RomSources,
+        game_root: &Path,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+        handle_failure: H,
+    ) -> Result<(S, F), Error>
+    where
+        S: Default + ExtendOne<VerifySuccess<'s>> + Send,
+        F: Default + ExtendOne<VerifyFailure<'s>> + Send,
+        H: Fn(ExtractedPart<'_>) + Send + Sync + Copy,
+    {
+        self.add_and_verify_with_progress(
+            rom_sources,
+            game_root,
+            dry_run,
+            sink,
+            || {},
+            handle_failure,
+        )
+    }
+
+    #[cfg(feature = "native")]
+    #[inline]
+    pub fn add_and_verify_failures<'s, H>(
+        &'s self,
+        rom_sources: &RomSources,
+        game_root: &Path,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+        handle_failure: H,
+    ) -> Result<Vec<VerifyFailure<'s>>, Error>
+    where
+        H: Fn(ExtractedPart<'_>) + Send + Sync + Copy,
+    {
+        self.add_and_verify(rom_sources, game_root, dry_run, sink, handle_failure)
+            .map(|(_, failures): (ExtendSink<_>, _)| failures)
+    }
+}
+
+#[cfg(feature = "native")]
+impl GameParts {
+    // verifies ROMs against the members of the Zip archive at zip_path
+    // and disks against the files in disk_root
+    pub fn verify_zip<'s, S, F>(
+        &'s self,
+        zip_path: &Path,
+        disk_root: &Path,
+        sink: &dyn ProgressSink,
+    ) -> (S, F)
+    where
+        S: Default + ExtendOne<VerifySuccess<'s>>,
+        F: Default + ExtendOne<VerifyFailure<'s>>,
+    {
+        let mut successes = S::default();
+        let mut failures = F::default();
+
+        let mut members = match read_zip_members(zip_path) {
+            Ok(members) => members,
+            Err(err) => {
+                failures.extend_item(VerifyFailure::Error {
+                    path: zip_path.to_path_buf(),
+                    err,
+                });
+                return (successes, failures);
+            }
+        };
+
+        for (name, part) in self.parts.iter() {
+            match part {
+                Part::Rom { .. } => {
+                    let path = zip_path.join(name);
+
+                    match members.remove(name) {
+                        Some(Ok(ref actual)) if actual == part => {
+                            successes.extend_item(VerifySuccess { name, part })
+                        }
+                        Some(Ok(actual)) => failures.extend_item(VerifyFailure::Bad {
+                            path,
+                            name,
+                            expected: part,
+                            actual,
+                        }),
+                        Some(Err(err)) => failures.extend_item(VerifyFailure::Error { path, err }),
+                        None => failures.extend_item(VerifyFailure::Missing { path, name, part }),
+                    }
+                }
+
+                Part::Disk { .. } => {
+                    let path = disk_root.join(name);
+
+                    if path.is_file() {
+                        match part.verify(name, path, sink) {
+                            Ok(success) => successes.extend_item(success),
+                            Err(failure) => failures.extend_item(failure),
+                        }
+                    } else {
+                        failures.extend_item(VerifyFailure::Missing { path, name, part });
+                    }
+                }
+            }
+        }
+
+        // any leftover members are extras
+        for (name, part) in members {
+            failures.extend_item(VerifyFailure::Extra {
+                path: zip_path.join(name),
+                part,
+            });
+        }
+
+        (successes, failures)
+    }
+
+    // populates missing or bad ROMs by rebuilding the Zip archive at zip_path,
+    // carrying over any existing members which don't need replacing
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_and_verify_zip<'s, H>(
+        &'s self,
+        rom_sources: &RomSources,
+        zip_path: &Path,
+        disk_root: &Path,
+        stamp: Option<&crate::stamp::Stamp>,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+        handle_failure: H,
+    ) -> Result<Vec<VerifyFailure<'s>>, Error>
+    where
+        H: Fn(ExtractedPart<'_>),
+    {
+        let mut failures = Vec::new();
+        let mut replacements = Vec::new();
+
+        // pinned archives are left exactly as they are
+        let pinned = crate::pin::is_pinned(zip_path);
+
+        let (_, verified): (ExtendSink<_>, Vec<_>) = self.verify_zip(zip_path, disk_root, sink);
+
+        plan_fixes(&verified.iter().collect::<Vec<_>>(), rom_sources, dry_run)?;
+
+        for failure in verified {
+            match failure {
+                // disks are stored as-is alongside the archive
+                VerifyFailure::Missing {
+                    part: Part::Disk { .. },
+                    ..
+                }
+                | VerifyFailure::Bad {
+                    expected: Part::Disk { .. },
+                    ..
+                } => match failure.try_fix(rom_sources, dry_run)? {
+                    Ok(extracted) => handle_failure(extracted),
+                    Err(failure) => failures.push(failure),
+                },
+
+                VerifyFailure::Missing { name, part, .. }
+                | VerifyFailure::Bad {
+                    name,
+                    expected: part,
+                    ..
+                } if !pinned => match rom_sources.get(part) {
+                    Some(source) => replacements.push((name, source.clone())),
+                    None => failures.push(failure),
+                },
+
+                failure => failures.push(failure),
+            }
+        }
+
+        if replacements.is_empty() {
+            Ok(failures)
+        } else if dry_run {
+            for (name, source) in replacements {
+                handle_failure(ExtractedPart {
+                    extracted: Extracted::Planned,
+                    source,
+                    target: zip_path.join(name),
+                });
+            }
+
+            Ok(failures)
+        } else {
+            for (name, source, rate) in rebuild_zip(zip_path, replacements, stamp)? {
+                handle_failure(ExtractedPart {
+                    extracted: Extracted::Copied { rate },
+                    source,
+                    target: zip_path.join(name),
+                });
+            }
+
+            Ok(failures)
+        }
+    }
+}
+
+// bad parts which have a source to replace them are deleted first,
+// so all of those are tallied before any are
+#[cfg(feature = "native")]
+fn plan_fixes(
+    failures: &[&VerifyFailure],
+    rom_sources: &RomSources,
+    dry_run: bool,
+) -> Result<(), Error> {
+    if dry_run {
+        return Ok(());
+    }
+
+    crate::safety::before_deleting_all(failures.iter().filter_map(|failure| match failure {
+        VerifyFailure::Bad { path, expected, .. }
+            if rom_sources.contains_key(*expected) && !crate::pin::is_pinned(path) =>
+        {
+            Some(path.as_path())
+        }
+        _ => None,
+    }))
+}
+
+// returns the Part of each file in the Zip archive at path,
+// which is empty if the archive doesn't exist yet
+#[cfg(feature = "native")]
+fn read_zip_members(
+    path: &Path,
+) -> Result<HashMap<String, Result<Part, std::io::Error>>, std::io::Error> {
+    let mut members = HashMap::default();
+
+    let mut zip = match std::fs::File::open(path) {
+        Ok(f) => zip::ZipArchive::new(f).map_err(std::io::Error::other)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(members),
+        Err(err) => return Err(err),
+    };
+
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index).map_err(std::io::Error::other)?;
+
+        if entry.is_file() {
+            let name = entry.name().to_owned();
+
+            // reading through to the end also checks the member's CRC32
+            let part = Part::from_reader(&mut entry)
+                .and_then(|part| std::io::copy(&mut entry, &mut std::io::sink()).map(|_| part));
+
+            members.insert(name, part);
+        }
+    }
+
+    Ok(members)
+}
+
+// writes a new Zip archive with the given members replaced
+// and swaps it in place of the old one, if any,
+// where the old archive's comment no longer applies
+#[cfg(feature = "native")]
+fn rebuild_zip<'u>(
+    path: &Path,
+    replacements: Vec<(&str, RomSource<'u>)>,
+    stamp: Option<&crate::stamp::Stamp>,
+) -> Result<Vec<(String, RomSource<'u>, Option<Rate>)>, Error> {
+    use std::fs::{create_dir_all, remove_file, rename, File};
+    use zip::write::{FileOptions, ZipWriter};
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    // removes whatever's left of the staged archive and member
+    // if anything fails before the archive is renamed into place
+    struct Staged {
+        archive: PathBuf,
+        member: PathBuf,
+    }
+
+    impl Drop for Staged {
+        fn drop(&mut self) {
+            let _ = remove_file(&self.member);
+            let _ = remove_file(&self.archive);
+        }
+    }
+
+    let staged = Staged {
+        archive: path.with_extension("zip.tmp"),
+        member: path.with_extension("zip.part"),
+    };
+    let staging = staged.archive.as_path();
+    let extracted = staged.member.as_path();
+
+    let mut writer = ZipWriter::new(File::create(staging)?);
+
+    if path.is_file() {
+        let mut zip = zip::ZipArchive::new(File::open(path)?)?;
+
+        for index in 0..zip.len() {
+            let entry = zip.by_index(index)?;
+
+            if !replacements.iter().any(|(name, _)| *name == entry.name()) {
+                writer.raw_copy_file(entry)?;
+            }
+        }
+    }
+
+    let mut results = Vec::with_capacity(replacements.len());
+
+    for (name, source) in replacements {
+        // extraction handles all the different source types
+        // so use it to stage the member before compressing it
+        source.extract(extracted)?;
+
+        // members too large for 32-bit sizes need Zip64 extensions
+        writer.start_file(
+            name,
+            FileOptions::default().large_file(extracted.metadata()?.len() >= u64::from(u32::MAX)),
+        )?;
+
+        let rate = Rate::from_copy(|| {
+            File::open(extracted).and_then(|mut r| std::io::copy(&mut r, &mut writer))
+        })?;
+
+        remove_file(extracted)?;
+
+        results.push((name.to_owned(), source, rate));
+    }
+
+    if let Some(stamp) = stamp {
+        writer.set_comment(stamp.to_comment());
+    }
+
+    writer.finish()?;
+    drop(writer);
+
+    // journaled runs set the old archive aside so it can be put back
+    if crate::journal::is_journaling() && path.is_file() {
+        crate::trash::remove_file(path)?;
+    }
+
+    rename(staging, path)?;
+
+    crate::journal::record_created(path)?;
+
+    Ok(results)
 }
 
 pub struct GameRow<'a> {
@@ -625,6 +2338,10 @@ pub struct GameRow<'a> {
     pub creator: &'a str,
     pub year: &'a str,
     pub status: Status,
+    pub driver: Option<&'a DriverStatus>,
+    pub cloneof: Option<&'a str>,
+    pub category: Option<&'a str>,
+    pub players: Option<&'a str>,
 }
 
 impl<'a> GameRow<'a> {
@@ -635,6 +2352,12 @@ impl<'a> GameRow<'a> {
             || (self.year == search)
     }
 
+    // whether the game is the given parent or one of its clones
+    #[inline]
+    pub fn in_family(&self, parent: &str) -> bool {
+        self.name == parent || self.cloneof == Some(parent)
+    }
+
     fn sort_key(&self, sort: GameColumn) -> (&str, &str, &str) {
         match sort {
             GameColumn::Description => (self.description, self.creator, self.year),
@@ -661,6 +2384,12 @@ pub enum VerifyFailure<'s> {
         name: &'s str,
         part: &'s Part,
     },
+    // a part the DAT requires which was never dumped,
+    // so can only be looked for by name
+    MissingUndumped {
+        path: PathBuf,
+        name: &'s str,
+    },
     Extra {
         path: PathBuf,
         part: Result<Part, std::io::Error>,
@@ -675,9 +2404,48 @@ pub enum VerifyFailure<'s> {
         path: PathBuf,
         err: std::io::Error,
     },
+    MissingParent {
+        path: PathBuf,
+        parent: Part,
+    },
+    BadParent {
+        path: PathBuf,
+        parent_path: PathBuf,
+    },
+    Corrupt {
+        path: PathBuf,
+    },
+    // a Zip archive with correct contents
+    // but not in canonical form
+    NotCanonical {
+        path: PathBuf,
+    },
+    // a separately stored BIOS set the game needs
+    // which is missing or incomplete
+    MissingBios {
+        path: PathBuf,
+        bios: &'s str,
+    },
+    // a separately stored device set the game needs
+    // which is missing or incomplete
+    MissingDevice {
+        path: PathBuf,
+        device: &'s str,
+    },
 }
 
 impl VerifyFailure<'_> {
+    // whether the failure leaves its game without a usable copy of some part,
+    // unlike extra files or non-canonical archives
+    #[inline]
+    pub fn is_incomplete(&self) -> bool {
+        !matches!(
+            self,
+            VerifyFailure::Extra { .. } | VerifyFailure::NotCanonical { .. }
+        )
+    }
+
+    #[cfg(feature = "native")]
     #[inline]
     fn extra(path: PathBuf) -> Self {
         Self::Extra {
@@ -687,12 +2455,34 @@ impl VerifyFailure<'_> {
     }
 
     // attempt to fix failure by populating missing/bad ROMs from rom_sources
+    // or, if dry_run is set, only determine which source would be used
+    #[cfg(feature = "native")]
     fn try_fix<'u>(
         self,
         rom_sources: &RomSources<'u>,
+        dry_run: bool,
     ) -> Result<Result<ExtractedPart<'u>, Self>, Error> {
         use dashmap::mapref::entry::Entry;
 
+        if dry_run {
+            return Ok(match &self {
+                VerifyFailure::Bad { path, expected, .. }
+                | VerifyFailure::Missing {
+                    path,
+                    part: expected,
+                    ..
+                } => match rom_sources.get(*expected) {
+                    Some(source) => Ok(ExtractedPart {
+                        extracted: Extracted::Planned,
+                        source: source.clone(),
+                        target: path.clone(),
+                    }),
+                    None => Err(self),
+                },
+                _ => Err(self),
+            });
+        }
+
         match self {
             VerifyFailure::Bad {
                 path,
@@ -700,12 +2490,13 @@ impl VerifyFailure<'_> {
                 expected,
                 actual,
             } => match rom_sources.entry(expected.clone()) {
-                Entry::Occupied(entry) => {
-                    std::fs::remove_file(&path)?;
+                // already tallied by plan_fixes
+                Entry::Occupied(entry) if !crate::pin::is_pinned(&path) => {
+                    crate::trash::remove_file(&path)?;
                     Self::extract_to(entry, path, expected).map(Ok)
                 }
 
-                Entry::Vacant(_) => Ok(Err(VerifyFailure::Bad {
+                _ => Ok(Err(VerifyFailure::Bad {
                     path,
                     name,
                     expected,
@@ -722,22 +2513,59 @@ impl VerifyFailure<'_> {
                 Entry::Vacant(_) => Ok(Err(VerifyFailure::Missing { path, part, name })),
             },
 
+            undumped @ VerifyFailure::MissingUndumped { .. } => Ok(Err(undumped)),
+
             extra @ VerifyFailure::Extra { .. } => Ok(Err(extra)),
 
             err @ VerifyFailure::Error { .. } => Ok(Err(err)),
+
+            parent @ VerifyFailure::MissingParent { .. } => Ok(Err(parent)),
+
+            parent @ VerifyFailure::BadParent { .. } => Ok(Err(parent)),
+
+            corrupt @ VerifyFailure::Corrupt { .. } => Ok(Err(corrupt)),
+
+            not_canonical @ VerifyFailure::NotCanonical { .. } => Ok(Err(not_canonical)),
+
+            bios @ VerifyFailure::MissingBios { .. } => Ok(Err(bios)),
+
+            device @ VerifyFailure::MissingDevice { .. } => Ok(Err(device)),
         }
     }
 
+    #[cfg(feature = "native")]
     fn extract_to<'u, S: std::hash::BuildHasher>(
         mut entry: OccupiedEntry<'_, Part, RomSource<'u>, S>,
         target: PathBuf,
         part: &Part,
     ) -> Result<ExtractedPart<'u>, Error> {
         let source = entry.get();
+        let extracted = source.extract(target.as_ref())?;
+
+        if !matches!(extracted, Extracted::Planned) {
+            crate::journal::record_created(&target)?;
+        }
 
-        match source.extract(target.as_ref())? {
-            extracted @ Extracted::Copied { .. } => {
+        if crate::consume::is_consuming() && !matches!(extracted, Extracted::Planned) {
+            if let RomSource::File {
+                file, zip_parts, ..
+            } = source
+            {
+                crate::consume::consumed(file, zip_parts);
+            }
+        }
+
+        match extracted {
+            extracted @ (Extracted::Copied { .. } | Extracted::Cloned) => {
                 part.set_xattr(&target);
+                crate::provenance::record(
+                    &target,
+                    source.origin(),
+                    match extracted {
+                        Extracted::Cloned => "cloned",
+                        _ => "copied",
+                    },
+                );
 
                 Ok(ExtractedPart {
                     extracted,
@@ -754,6 +2582,20 @@ impl VerifyFailure<'_> {
                 if !has_xattr {
                     part.set_xattr(&target);
                 }
+                crate::provenance::record(&target, source.origin(), "linked");
+
+                // the source is going away, but its data is in the target
+                if crate::consume::is_consuming() {
+                    return Ok(ExtractedPart {
+                        extracted,
+                        source: entry.insert(RomSource::File {
+                            file: Arc::new(target.clone()),
+                            has_xattr: true,
+                            zip_parts: ZipParts::default(),
+                        }),
+                        target,
+                    });
+                }
 
                 Ok(ExtractedPart {
                     extracted,
@@ -761,6 +2603,53 @@ impl VerifyFailure<'_> {
                     target,
                 })
             }
+
+            extracted @ Extracted::Planned => Ok(ExtractedPart {
+                extracted,
+                source: source.clone(),
+                target,
+            }),
+        }
+    }
+}
+
+impl VerifyFailure<'_> {
+    #[inline]
+    pub fn path(&self) -> &Path {
+        match self {
+            VerifyFailure::Missing { path, .. }
+            | VerifyFailure::MissingUndumped { path, .. }
+            | VerifyFailure::Extra { path, .. }
+            | VerifyFailure::Bad { path, .. }
+            | VerifyFailure::Error { path, .. }
+            | VerifyFailure::MissingParent { path, .. }
+            | VerifyFailure::BadParent { path, .. }
+            | VerifyFailure::Corrupt { path }
+            | VerifyFailure::NotCanonical { path }
+            | VerifyFailure::MissingBios { path, .. }
+            | VerifyFailure::MissingDevice { path, .. } => path,
+        }
+    }
+
+    // a short name for the kind of failure, as used in JSON results
+    pub fn kind(&self) -> &'static str {
+        match self {
+            VerifyFailure::Extra { part: Ok(part), .. }
+            | VerifyFailure::Bad { actual: part, .. }
+                if matches!(crate::blocklist::is_blocked(part), Ok(true)) =>
+            {
+                "blocked"
+            }
+            VerifyFailure::Missing { .. } | VerifyFailure::MissingUndumped { .. } => "missing",
+            VerifyFailure::Extra { .. } => "extra",
+            VerifyFailure::Bad { .. } => "bad",
+            VerifyFailure::Error { .. } => "error",
+            VerifyFailure::MissingParent { .. } => "missing-parent",
+            VerifyFailure::BadParent { .. } => "bad-parent",
+            VerifyFailure::Corrupt { .. } => "corrupt",
+            VerifyFailure::NotCanonical { .. } => "not-canonical",
+            VerifyFailure::MissingBios { .. } => "missing-bios",
+            VerifyFailure::MissingDevice { .. } => "missing-device",
         }
     }
 }
@@ -771,21 +2660,61 @@ impl fmt::Display for VerifyFailure<'_> {
             VerifyFailure::Missing { path, .. } => {
                 write!(f, "MISSING : {}", path.display())
             }
+            VerifyFailure::MissingUndumped { path, .. } => {
+                write!(f, "MISSING : {} (undumped)", path.display())
+            }
+            VerifyFailure::Extra {
+                path,
+                part: Ok(part),
+            }
+            | VerifyFailure::Bad {
+                path, actual: part, ..
+            } if matches!(crate::blocklist::is_blocked(part), Ok(true)) => {
+                write!(f, "BLOCKED : {}", path.display())
+            }
             VerifyFailure::Extra { path, .. } => write!(f, "EXTRA : {}", path.display()),
             VerifyFailure::Bad { path, .. } => write!(f, "BAD : {}", path.display()),
             VerifyFailure::Error { path, err } => {
                 write!(f, "ERROR : {} : {}", path.display(), err)
             }
+            VerifyFailure::MissingParent { path, parent, .. } => {
+                write!(
+                    f,
+                    "MISSING PARENT : {} : {}",
+                    path.display(),
+                    parent.digest()
+                )
+            }
+            VerifyFailure::BadParent {
+                path, parent_path, ..
+            } => write!(
+                f,
+                "BAD PARENT : {} : {}",
+                path.display(),
+                parent_path.display()
+            ),
+            VerifyFailure::Corrupt { path } => write!(f, "CORRUPT : {}", path.display()),
+            VerifyFailure::NotCanonical { path } => {
+                write!(f, "NOT CANONICAL : {}", path.display())
+            }
+            VerifyFailure::MissingBios { path, .. } => {
+                write!(f, "MISSING BIOS : {}", path.display())
+            }
+            VerifyFailure::MissingDevice { path, .. } => {
+                write!(f, "MISSING DEVICE : {}", path.display())
+            }
         }
     }
 }
 
+#[cfg(feature = "native")]
 pub struct ExtractedPart<'u> {
     extracted: Extracted,
     source: RomSource<'u>,
     target: PathBuf,
 }
 
+#[cfg(feature = "native")]
 impl<'u> fmt::Display for ExtractedPart<'u> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.extracted {
@@ -801,13 +2730,59 @@ impl<'u> fmt::Display for ExtractedPart<'u> {
                     rate
                 )
             }
+            Extracted::Cloned => {
+                write!(
+                    f,
+                    "{} \u{21D2} {} (cloned)",
+                    self.source,
+                    self.target.display()
+                )
+            }
             Extracted::Linked { .. } => {
                 write!(f, "{} \u{2192} {}", self.source, self.target.display())
             }
+            Extracted::Planned => {
+                write!(
+                    f,
+                    "{} \u{21D2} {} (planned)",
+                    self.source,
+                    self.target.display()
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl<'u> ExtractedPart<'u> {
+    #[inline]
+    pub fn source(&self) -> &RomSource<'u> {
+        &self.source
+    }
+
+    #[inline]
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    // how the part got to its target
+    pub fn method(&self) -> &'static str {
+        match self.extracted {
+            Extracted::Copied { .. } => "copied",
+            Extracted::Cloned => "cloned",
+            Extracted::Linked { .. } => "linked",
+            Extracted::Planned => "planned",
         }
     }
 }
 
+// passes each failure found in game along to the sink
+pub fn report_failures(game: &str, failures: &[VerifyFailure], sink: &dyn ProgressSink) {
+    for failure in failures {
+        sink.failure_found(game, failure);
+    }
+}
+
 // a simple polyfill until extend_one stabilizes in the Extend trait
 pub trait ExtendOne<I>: Extend<I> {
     fn extend_item(&mut self, item: I);
@@ -835,8 +2810,10 @@ impl<K: Eq + std::hash::Hash, V> ExtendOne<(K, V)> for DashMap<K, V> {
     }
 }
 
+#[cfg(feature = "native")]
 struct ExtendSink<I>(std::marker::PhantomData<I>);
 
+#[cfg(feature = "native")]
 impl<I> Default for ExtendSink<I> {
     #[inline]
     fn default() -> Self {
@@ -844,6 +2821,7 @@ impl<I> Default for ExtendSink<I> {
     }
 }
 
+#[cfg(feature = "native")]
 impl<I> Extend<I> for ExtendSink<I> {
     #[inline]
     fn extend<T>(&mut self, _: T)
@@ -854,6 +2832,7 @@ impl<I> Extend<I> for ExtendSink<I> {
     }
 }
 
+#[cfg(feature = "native")]
 impl<I> ExtendOne<I> for ExtendSink<I> {
     #[inline]
     fn extend_item(&mut self, _: I) {
@@ -922,7 +2901,7 @@ impl FileId {
         })
     }
 
-    #[cfg(target_os = "unix")]
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "macos"))))]
     pub fn new(path: &Path) -> Result<Self, std::io::Error> {
         use std::os::unix::fs::MetadataExt;
 
@@ -961,10 +2940,15 @@ impl Part {
     }
 
     #[inline]
-    pub fn digest(&self) -> Digest {
+    pub fn digest(&self) -> Digest<'_> {
+        self.digest_as(DigestFormat::default())
+    }
+
+    #[inline]
+    pub fn digest_as(&self, format: DigestFormat) -> Digest<'_> {
         match self {
-            Part::Rom { sha1 } => Digest(sha1),
-            Part::Disk { sha1 } => Digest(sha1),
+            Part::Rom { sha1 } => Digest(sha1, format),
+            Part::Disk { sha1 } => Digest(sha1, format),
         }
     }
 
@@ -978,7 +2962,14 @@ impl Part {
             .and_then(|mut r| Part::from_reader(&mut r))
     }
 
+    #[cfg(feature = "native")]
+    #[inline]
     fn from_cached_path(path: &Path) -> Result<Self, std::io::Error> {
+        Self::from_cached_path_with(path, &crate::sink::Quiet)
+    }
+
+    #[cfg(feature = "native")]
+    fn from_cached_path_with(path: &Path, sink: &dyn ProgressSink) -> Result<Self, std::io::Error> {
         use fxhash::FxBuildHasher;
         use once_cell::sync::OnceCell;
 
@@ -995,13 +2986,14 @@ impl Part {
         match map.get(&file_id) {
             Some(part) => Ok(part.clone()),
             None => {
-                let part = Self::from_disk_cached_path(path)?;
+                let part = Self::from_disk_cached_path(path, sink)?;
                 map.insert(file_id, part.clone());
                 Ok(part)
             }
         }
     }
 
+    #[cfg(feature = "native")]
     #[inline]
     pub fn get_xattr(path: &Path) -> Option<Self> {
         xattr::get(path, CACHE_XATTR)
@@ -1024,8 +3016,13 @@ impl Part {
             })
     }
 
+    #[cfg(feature = "native")]
     #[inline]
     pub fn set_xattr(&self, path: &Path) {
+        if is_read_only() {
+            return;
+        }
+
         let mut attr = [0; 41];
         match self {
             Self::Rom { sha1 } => {
@@ -1041,44 +3038,60 @@ impl Part {
         let _ = xattr::set(path, CACHE_XATTR, &attr);
     }
 
+    #[cfg(feature = "native")]
     #[inline]
     pub fn has_xattr(path: &Path) -> Result<bool, std::io::Error> {
         xattr::list(path).map(|mut iter| iter.any(|s| s == CACHE_XATTR))
     }
 
+    #[cfg(feature = "native")]
     #[inline]
     pub fn remove_xattr(path: &Path) -> Result<(), std::io::Error> {
         xattr::remove(path, CACHE_XATTR)
     }
 
-    fn from_disk_cached_path(path: &Path) -> Result<Self, std::io::Error> {
+    #[cfg(feature = "native")]
+    fn from_disk_cached_path(path: &Path, sink: &dyn ProgressSink) -> Result<Self, std::io::Error> {
         match Part::get_xattr(path) {
             Some(part) => Ok(part),
             None => {
                 let part = Self::from_path(path)?;
                 part.set_xattr(path);
+                sink.file_hashed(path, &part);
                 Ok(part)
             }
         }
     }
 
     #[inline]
-    fn from_slice(bytes: &[u8]) -> Result<Self, std::io::Error> {
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, std::io::Error> {
         Self::from_reader(std::io::Cursor::new(bytes))
     }
 
-    fn from_reader<R: Read>(r: R) -> Result<Self, std::io::Error> {
+    pub fn from_reader<R: Read>(r: R) -> Result<Self, std::io::Error> {
         use std::io::{copy, sink};
 
         let mut r = Sha1Reader::new(r);
         match Part::disk_from_reader(&mut r) {
-            Ok(Some(part)) => Ok(part),
+            Ok(Some((part, _))) => Ok(part),
             Ok(None) => copy(&mut r, &mut sink()).map(|_| r.into()),
             Err(err) => Err(err),
         }
     }
 
-    fn disk_from_reader<R: Read>(mut r: R) -> Result<Option<Self>, std::io::Error> {
+    // returns the parent CHD's Part, if the CHD at path has one
+    pub fn disk_parent(path: &Path) -> Result<Option<Self>, std::io::Error> {
+        use std::fs::File;
+        use std::io::BufReader;
+
+        File::open(path)
+            .map(BufReader::new)
+            .and_then(Part::disk_from_reader)
+            .map(|disk| disk.and_then(|(_, parent)| parent))
+    }
+
+    // returns the CHD's Part along with its parent's Part, if any
+    fn disk_from_reader<R: Read>(mut r: R) -> Result<Option<(Self, Option<Self>)>, std::io::Error> {
         fn skip<R: Read>(mut r: R, to_skip: usize) -> Result<(), std::io::Error> {
             let mut buf = vec![0; to_skip];
             r.read_exact(buf.as_mut_slice())
@@ -1108,15 +3121,32 @@ impl Part {
 
         let mut sha1 = [0; 20];
         r.read_exact(&mut sha1)?;
-        Ok(Some(Part::Disk { sha1 }))
+
+        // the parent SHA1 follows the data SHA1 in all versions
+        // and is all zeroes if the CHD has no parent
+        let mut parent_sha1 = [0; 20];
+        r.read_exact(&mut parent_sha1)?;
+
+        Ok(Some((
+            Part::Disk { sha1 },
+            (parent_sha1 != [0; 20]).then_some(Part::Disk { sha1: parent_sha1 }),
+        )))
+    }
+
+    // where the part is kept in hashed storage under store
+    pub fn hashed_path(&self, store: &Path) -> PathBuf {
+        let digest = self.digest().to_string();
+        store.join(&digest[..2]).join(digest)
     }
 
+    #[cfg(feature = "native")]
     pub fn verify<'s>(
         &'s self,
         name: &'s str,
         path: PathBuf,
+        sink: &dyn ProgressSink,
     ) -> Result<VerifySuccess<'s>, VerifyFailure<'s>> {
-        match Part::from_cached_path(path.as_ref()) {
+        match Part::from_cached_path_with(path.as_ref(), sink) {
             Ok(ref disk_part) if self == disk_part => Ok(VerifySuccess { name, part: self }),
             Ok(disk_part) => Err(VerifyFailure::Bad {
                 path,
@@ -1128,24 +3158,87 @@ impl Part {
         }
     }
 
+    #[cfg(feature = "native")]
     #[inline]
     pub fn is_valid(&self, path: &Path) -> Result<bool, std::io::Error> {
         Part::from_path(path).map(|disk_part| self == &disk_part)
     }
 }
 
-struct Sha1Reader<R> {
-    reader: R,
-    sha1: Sha1,
-}
+// decompresses every hunk of the CHD at path
+// and returns whether the SHA1 of its data
+// matches the raw data SHA1 stored in its header
+#[cfg(feature = "native")]
+pub fn verify_chd_data(path: &Path, parent: Option<&Path>) -> Result<bool, std::io::Error> {
+    use chd::Chd;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn open_chd(path: &Path, parent: Option<&Path>) -> Result<Chd<BufReader<File>>, chd::Error> {
+        let parent = match parent {
+            Some(parent) => Some(Box::new(open_chd(parent, None)?)),
+            None => None,
+        };
 
-impl<R> Sha1Reader<R> {
-    #[inline]
-    fn new(reader: R) -> Self {
-        Sha1Reader {
-            reader,
-            sha1: Sha1::new(),
-        }
+        Chd::open(BufReader::new(File::open(path)?), parent)
+    }
+
+    fn verify(path: &Path, parent: Option<&Path>) -> Result<bool, chd::Error> {
+        let mut chd = open_chd(path, parent)?;
+
+        // CHDs older than v4 carry no raw data SHA1 to check against
+        let raw_sha1 = match chd.header().raw_sha1() {
+            Some(raw_sha1) => raw_sha1,
+            None => return Ok(true),
+        };
+
+        let mut remaining = chd.header().logical_bytes();
+        let mut sha1 = Sha1::new();
+        let mut compressed = Vec::new();
+        let mut hunk_data = chd.get_hunksized_buffer();
+
+        for hunk_num in 0..chd.header().hunk_count() {
+            if remaining == 0 {
+                break;
+            }
+            chd.hunk(hunk_num)?
+                .read_hunk_in(&mut compressed, &mut hunk_data)?;
+            let len = remaining.min(hunk_data.len() as u64) as usize;
+            sha1.update(&hunk_data[0..len]);
+            remaining -= len as u64;
+        }
+
+        Ok(sha1.digest().bytes() == raw_sha1)
+    }
+
+    verify(path, parent).map_err(std::io::Error::from)
+}
+
+static BYTES_PROCESSED: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+#[inline]
+fn count_processed(bytes: u64) {
+    BYTES_PROCESSED.fetch_add(bytes, std::sync::atomic::Ordering::Relaxed);
+}
+
+// the number of bytes hashed so far this run
+#[inline]
+pub fn bytes_processed() -> u64 {
+    BYTES_PROCESSED.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+struct Sha1Reader<R> {
+    reader: R,
+    sha1: Sha1,
+}
+
+impl<R> Sha1Reader<R> {
+    #[inline]
+    fn new(reader: R) -> Self {
+        Sha1Reader {
+            reader,
+            sha1: Sha1::new(),
+        }
     }
 }
 
@@ -1153,6 +3246,7 @@ impl<R: Read> Read for Sha1Reader<R> {
     fn read(&mut self, data: &mut [u8]) -> Result<usize, std::io::Error> {
         let bytes = self.reader.read(data)?;
         self.sha1.update(&data[0..bytes]);
+        count_processed(bytes as u64);
         Ok(bytes)
     }
 }
@@ -1166,18 +3260,114 @@ impl<R> From<Sha1Reader<R>> for Part {
     }
 }
 
-#[inline]
-pub fn parse_sha1(hex: &str) -> Result<[u8; 20], hex::FromHexError> {
+// along with plain hex digests in either case, accepts
+// "sha1:" or "sha1-" prefixed digests and base64 digests
+// as found in other tools' manifests
+pub fn parse_sha1(digest: &str) -> Result<[u8; 20], hex::FromHexError> {
+    let digest = digest.trim();
+    let digest = match digest.get(..5) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("sha1:") => &digest[5..],
+        Some(prefix) if prefix.eq_ignore_ascii_case("sha1-") => &digest[5..],
+        _ => digest,
+    };
+
     let mut bin = [0; 20];
 
-    hex::decode_to_slice(hex.trim().as_bytes(), &mut bin).map(|()| bin)
+    match hex::decode_to_slice(digest.as_bytes(), &mut bin) {
+        Ok(()) => Ok(bin),
+        Err(err) => [
+            base64::STANDARD,
+            base64::STANDARD_NO_PAD,
+            base64::URL_SAFE,
+            base64::URL_SAFE_NO_PAD,
+        ]
+        .iter()
+        .find_map(|config| {
+            base64::decode_config(digest, *config)
+                .ok()
+                .filter(|decoded| decoded.len() == bin.len())
+        })
+        .map(|decoded| {
+            bin.copy_from_slice(&decoded);
+            bin
+        })
+        .ok_or(err),
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub enum DigestFormat {
+    #[default]
+    Hex,
+    Upper,
+    Prefixed,
+    Base64,
+}
+
+impl FromStr for DigestFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "hex" => Ok(DigestFormat::Hex),
+            "upper" => Ok(DigestFormat::Upper),
+            "sha1" => Ok(DigestFormat::Prefixed),
+            "base64" => Ok(DigestFormat::Base64),
+            _ => Err("invalid digest format".to_string()),
+        }
+    }
 }
 
-pub struct Digest<'a>(&'a [u8]);
+pub struct Digest<'a>(&'a [u8], DigestFormat);
 
 impl<'a> fmt::Display for Digest<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+        match self.1 {
+            DigestFormat::Hex => self.0.iter().try_for_each(|b| write!(f, "{:02x}", b)),
+            DigestFormat::Upper => self.0.iter().try_for_each(|b| write!(f, "{:02X}", b)),
+            DigestFormat::Prefixed => {
+                f.write_str("sha1:")?;
+                self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            DigestFormat::Base64 => f.write_str(&base64::encode(self.0)),
+        }
+    }
+}
+
+// identifies a part the same way across runs and versions, like:
+//
+// pacman/pacman.6e@sha1:e87e059c5be45753f7e9f33dff851f16d6751181
+//
+// ordered by game, then part name, then digest
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PartId<'p> {
+    game: &'p str,
+    name: &'p str,
+    digest: String,
+}
+
+impl<'p> PartId<'p> {
+    #[inline]
+    pub fn new(game: &'p str, name: &'p str, part: &Part) -> Self {
+        Self {
+            game,
+            name,
+            digest: part.digest_as(DigestFormat::Prefixed).to_string(),
+        }
+    }
+}
+
+impl fmt::Display for PartId<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}@{}", self.game, self.name, self.digest)
+    }
+}
+
+impl serde::Serialize for PartId<'_> {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
@@ -1201,56 +3391,212 @@ impl FromStr for GameColumn {
     }
 }
 
+// how plain files are placed into sets when adding them
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LinkMode {
+    // hard links, falling back to copies where impossible
+    #[default]
+    Hard,
+    // symbolic links by absolute path
+    Symlink,
+    // symbolic links by path relative to the set
+    Relative,
+    // copy-on-write clones, falling back to copies where impossible
+    Reflink,
+}
+
+impl FromStr for LinkMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "hard" => Ok(LinkMode::Hard),
+            "symlink" => Ok(LinkMode::Symlink),
+            "relative" => Ok(LinkMode::Relative),
+            "reflink" => Ok(LinkMode::Reflink),
+            _ => Err("invalid link mode".to_string()),
+        }
+    }
+}
+
+// whether parts are kept once apiece by digest, as "<xx>/<digest>"
+// where "xx" is the digest's first two characters, rather than
+// under each game's name, so several frontends may share one store
+// while generating their own links into it
+static HASHED_STORAGE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
 #[inline]
-pub fn find_files_style() -> ProgressStyle {
-    ProgressStyle::default_spinner().template("{spinner} {wide_msg} {pos}")
+pub fn set_hashed_storage(hashed: bool) {
+    HASHED_STORAGE.store(hashed, std::sync::atomic::Ordering::Relaxed)
 }
 
 #[inline]
-pub fn verify_style() -> ProgressStyle {
-    ProgressStyle::default_bar().template("{spinner} {wide_msg} {pos} / {len}")
+pub fn is_hashed_storage() -> bool {
+    HASHED_STORAGE.load(std::sync::atomic::Ordering::Relaxed)
 }
 
-fn subdir_files(root: &Path) -> Vec<PathBuf> {
-    use indicatif::ProgressIterator;
-    use walkdir::WalkDir;
+// patterns are matched against the names of files and directories
+// beneath a source root, or their whole path relative to the root
+// if they contain a "/", while a trailing "/" matches only directories
+#[cfg(feature = "native")]
+#[derive(Default)]
+pub struct SourceFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
 
-    let pbar = ProgressBar::new_spinner().with_style(find_files_style());
-    pbar.set_message("locating files");
-    pbar.set_draw_delta(100);
+#[cfg(feature = "native")]
+impl SourceFilter {
+    // excluded directories aren't even listed
+    fn skips_dir(&self, relative: &Path) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| Self::matches(pattern, relative))
+    }
 
-    let walkdir = WalkDir::new(root).into_iter().progress_with(pbar.clone());
+    // with anything to include, only those files are scanned
+    fn skips_file(&self, relative: &Path) -> bool {
+        let file_patterns = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter(|pattern| !pattern.ends_with('/'))
+                .any(|pattern| Self::matches(pattern, relative))
+        };
 
-    let results = if cfg!(unix) {
-        use nohash_hasher::IntSet;
-        use walkdir::DirEntryExt;
+        file_patterns(&self.exclude) || !(self.include.is_empty() || file_patterns(&self.include))
+    }
 
-        let mut files = IntSet::default();
+    fn matches(pattern: &str, relative: &Path) -> bool {
+        let pattern = pattern.strip_suffix('/').unwrap_or(pattern);
 
-        walkdir
-            .filter_map(|e| {
-                e.ok()
-                    .filter(|e| e.file_type().is_file() && files.insert(e.ino()))
-                    .map(|e| e.into_path())
-            })
-            .collect()
-    } else {
-        walkdir
-            .filter_map(|e| {
-                e.ok()
-                    .filter(|e| e.file_type().is_file())
-                    .map(|e| e.into_path())
-            })
-            .collect()
+        if pattern.contains('/') {
+            relative
+                .to_str()
+                .is_some_and(|path| crate::collection::matches_pattern(pattern, path))
+        } else {
+            relative
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| crate::collection::matches_pattern(pattern, name))
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+static SOURCE_FILTER: once_cell::sync::OnceCell<SourceFilter> = once_cell::sync::OnceCell::new();
+
+#[cfg(feature = "native")]
+#[inline]
+pub fn set_source_filter(filter: SourceFilter) {
+    let _ = SOURCE_FILTER.set(filter);
+}
+
+#[cfg(feature = "native")]
+#[inline]
+fn source_filter() -> &'static SourceFilter {
+    SOURCE_FILTER.get_or_init(SourceFilter::default)
+}
+
+static LINK_MODE: once_cell::sync::OnceCell<LinkMode> = once_cell::sync::OnceCell::new();
+
+#[inline]
+pub fn set_link_mode(mode: LinkMode) {
+    let _ = LINK_MODE.set(mode);
+}
+
+#[cfg(feature = "native")]
+#[inline]
+fn link_mode() -> LinkMode {
+    LINK_MODE.get().copied().unwrap_or_default()
+}
+
+static READ_ONLY: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+// once set, files are only ever read,
+// leaving digests uncached rather than written as xattrs
+#[inline]
+pub fn set_read_only() {
+    READ_ONLY.store(true, std::sync::atomic::Ordering::Relaxed)
+}
+
+#[inline]
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+// files under root, leaving out those whose physical file
+// has already been seen, whether through a hard link or a bind mount,
+// under this root or any other scanned alongside it,
+// along with those the source filter skips
+#[cfg(feature = "native")]
+fn subdir_files(root: &Path, seen: &DashSet<FileId>, task: &Task) -> Vec<PathBuf> {
+    // like the root of a walk, a root which is a file is itself included,
+    // whatever the source filter says
+    match root.metadata() {
+        Ok(metadata) if metadata.is_dir() => walk_dir(root, root, seen, task),
+        Ok(metadata) if metadata.is_file() => {
+            task.advance(1);
+            walk_entry(root.to_path_buf(), seen).into_iter().collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// subdirectories are listed in parallel, since listing them one at a time
+// leaves a walk waiting on network filesystems far more than hashing does;
+// like any walk, symbolic links beneath the root aren't followed
+#[cfg(feature = "native")]
+fn walk_dir(root: &Path, dir: &Path, seen: &DashSet<FileId>, task: &Task) -> Vec<PathBuf> {
+    use rayon::prelude::*;
+
+    let filter = source_filter();
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
     };
 
-    pbar.finish_and_clear();
+    let mut subdirs = Vec::new();
+    let mut files = Vec::new();
 
-    results
+    for entry in entries.filter_map(|e| e.ok()) {
+        task.advance(1);
+
+        let path = entry.path();
+
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() && !filter.skips_dir(&relative(&path)) => {
+                subdirs.push(path)
+            }
+            Ok(file_type) if file_type.is_file() && !filter.skips_file(&relative(&path)) => {
+                files.extend(walk_entry(path, seen))
+            }
+            _ => {}
+        }
+    }
+
+    files.par_extend(
+        subdirs
+            .into_par_iter()
+            .flat_map_iter(|subdir| walk_dir(root, &subdir, seen, task)),
+    );
+
+    files
+}
+
+#[cfg(feature = "native")]
+#[inline]
+fn walk_entry(file: PathBuf, seen: &DashSet<FileId>) -> Option<PathBuf> {
+    FileId::new(&file)
+        .map_or(true, |id| seen.insert(id))
+        .then_some(file)
 }
 
+#[cfg(feature = "native")]
 type ZipParts = Vec<usize>;
 
+#[cfg(feature = "native")]
 #[derive(Clone, Debug)]
 pub enum RomSource<'u> {
     File {
@@ -1263,13 +3609,43 @@ pub enum RomSource<'u> {
         data: Arc<[u8]>,
         zip_parts: ZipParts,
     },
+    Remote {
+        url: &'u str,
+        len: u64,
+        zip_parts: ZipParts,
+    },
+    Rar {
+        file: Arc<PathBuf>,
+        index: usize,
+    },
+    Tar {
+        file: Arc<PathBuf>,
+        index: usize,
+    },
 }
 
+#[cfg(feature = "native")]
 impl<'u> RomSource<'u> {
+    // how readily the source's part can be put in place, best first:
+    // whole files which may be linked, then files needing extraction
+    // from an archive, and lastly anything fetched from elsewhere
+    fn tier(&self) -> u8 {
+        match self {
+            RomSource::File { zip_parts, .. } if zip_parts.is_empty() => 0,
+            RomSource::File { .. } | RomSource::Rar { .. } | RomSource::Tar { .. } => 1,
+            RomSource::Url { .. } | RomSource::Remote { .. } => 2,
+        }
+    }
+
+    #[cfg(feature = "native")]
     pub fn from_path(pb: PathBuf) -> Result<Vec<(Part, RomSource<'u>)>, Error> {
         use std::fs::File;
         use std::io::BufReader;
 
+        if is_zip_volume(&pb) {
+            return Ok(Vec::new());
+        }
+
         // if the file already has a cached xattr set,
         // return it as-is without any further parsing
         // and flag it so we don't attempt to set the xattr again
@@ -1296,181 +3672,1063 @@ impl<'u> RomSource<'u> {
             },
         )];
 
-        r.seek(std::io::SeekFrom::Start(0))?;
+        r.seek(std::io::SeekFrom::Start(0))?;
+
+        let zip_parts = match zip_volumes(&file) {
+            Some(volumes) => match join_zip_volumes(&volumes) {
+                Ok(joined) => unpack_zip_parts(joined),
+                Err(_) => Ok(Vec::new()),
+            },
+            None if is_zip(&mut r).unwrap_or(false) => {
+                if r.get_ref().metadata()?.len() >= PARALLEL_ZIP_SIZE {
+                    unpack_large_zip_parts(&file)
+                } else {
+                    unpack_zip_parts(&mut r)
+                }
+            }
+            None => Ok(Vec::new()),
+        }
+        .map_err(|entry| Error::CorruptZip(file.as_ref().clone(), entry))?;
+
+        r.seek(std::io::SeekFrom::Start(0))?;
+
+        if is_rar(&mut r).unwrap_or(false) {
+            result.extend(unpack_rar_parts(&file).into_iter().map(|(part, index)| {
+                (
+                    part,
+                    RomSource::Rar {
+                        file: file.clone(),
+                        index,
+                    },
+                )
+            }));
+        }
+
+        result.extend(unpack_tar_parts(&file).into_iter().map(|(part, index)| {
+            (
+                part,
+                RomSource::Tar {
+                    file: file.clone(),
+                    index,
+                },
+            )
+        }));
+
+        result.extend(zip_parts.into_iter().map(|(part, zip_parts)| {
+            (
+                part,
+                RomSource::File {
+                    file: file.clone(),
+                    has_xattr: false,
+                    zip_parts,
+                },
+            )
+        }));
+
+        Ok(result)
+    }
+
+    #[cfg(feature = "native")]
+    pub fn from_url(
+        url: &'u str,
+        sink: &dyn ProgressSink,
+    ) -> Result<Vec<(Part, RomSource<'u>)>, Error> {
+        let data: Arc<[u8]> = crate::http::fetch_url_data(url, sink).map(Arc::from)?;
+
+        let mut result = vec![(
+            Part::from_slice(&data)?,
+            RomSource::Url {
+                url,
+                data: data.clone(),
+                zip_parts: ZipParts::default(),
+            },
+        )];
+
+        if matches!(data[..], [0x50, 0x4B, 0x03, 0x04, ..]) {
+            result.extend(
+                unpack_zip_parts(std::io::Cursor::new(data.clone()))
+                    .map_err(|entry| Error::CorruptZip(PathBuf::from(url), entry))?
+                    .into_iter()
+                    .map(|(part, zip_parts)| {
+                        (
+                            part,
+                            RomSource::Url {
+                                url,
+                                data: data.clone(),
+                                zip_parts,
+                            },
+                        )
+                    }),
+            );
+        }
+
+        Ok(result)
+    }
+
+    // remote files are read piecemeal through range requests,
+    // so they're hashed without being held in memory
+    // and placing a Zip member later fetches only that member
+    #[cfg(feature = "native")]
+    pub fn from_remote(
+        url: &'u str,
+        len: u64,
+        sink: &dyn ProgressSink,
+    ) -> Result<Vec<(Part, RomSource<'u>)>, Error> {
+        use std::io::BufReader;
+
+        let mut r = BufReader::new(crate::http::RemoteFile::new(url, len));
+
+        let task = Task::start(sink, url, Total::Bytes(len));
+
+        // archives are only hashed by member,
+        // since hashing them whole would transfer them twice
+        let result = if is_zip(&mut r).unwrap_or(false) {
+            unpack_zip_parts(task.wrap_read(r))
+                .map_err(|entry| Error::CorruptZip(PathBuf::from(url), entry))?
+                .into_iter()
+                .map(|(part, zip_parts)| {
+                    (
+                        part,
+                        RomSource::Remote {
+                            url,
+                            len,
+                            zip_parts,
+                        },
+                    )
+                })
+                .collect()
+        } else {
+            vec![(
+                Part::from_reader(task.wrap_read(r))?,
+                RomSource::Remote {
+                    url,
+                    len,
+                    zip_parts: ZipParts::default(),
+                },
+            )]
+        };
+
+        Ok(result)
+    }
+
+    #[cfg(feature = "native")]
+    fn extract(&self, target: &Path) -> Result<Extracted, Error> {
+        use std::fs::{copy, hard_link, File};
+
+        match self {
+            RomSource::File {
+                file: source,
+                has_xattr,
+                zip_parts,
+            } => match zip_parts.split_first() {
+                None => match match link_mode() {
+                    LinkMode::Hard => hard_link(source.as_path(), target),
+                    mode @ (LinkMode::Symlink | LinkMode::Relative) => {
+                        symlink(source.as_path(), target, mode)
+                    }
+                    LinkMode::Reflink => reflink(source.as_path(), target),
+                } {
+                    Ok(()) if link_mode() == LinkMode::Reflink => Ok(Extracted::Cloned),
+
+                    Ok(()) => Ok(Extracted::Linked {
+                        has_xattr: *has_xattr,
+                    }),
+
+                    Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                        already_provided(source, target)
+                            .map(|()| Extracted::Linked { has_xattr: false })
+                    }
+
+                    Err(err) if cannot_link(&err) => {
+                        Rate::from_copy(|| copy(source.as_path(), target))
+                            .map(|rate| Extracted::Copied { rate })
+                            .map_err(Error::IO)
+                    }
+
+                    Err(err) => Err(Error::IO(std::io::Error::new(
+                        err.kind(),
+                        format!("{} : {}", target.display(), err),
+                    ))),
+                },
+
+                Some((index, rest)) => match zip_volumes(source) {
+                    Some(volumes) => with_joined_zip_volumes(source, &volumes, |joined| {
+                        extract_from_zip_file(
+                            rest,
+                            zip::ZipArchive::new(joined)?.by_index(*index)?,
+                            target,
+                        )
+                    }),
+                    None => extract_from_zip_file(
+                        rest,
+                        zip::ZipArchive::new(File::open(source.as_ref())?)?.by_index(*index)?,
+                        target,
+                    ),
+                },
+            },
+
+            RomSource::Url {
+                data, zip_parts, ..
+            } => extract_from_zip_file(zip_parts, std::io::Cursor::new(data), target),
+
+            RomSource::Remote {
+                url,
+                len,
+                zip_parts,
+            } => {
+                let remote = std::io::BufReader::new(crate::http::RemoteFile::new(url, *len));
+
+                match zip_parts.split_first() {
+                    None => extract_from_zip_file(&[], remote, target),
+                    Some((index, rest)) => extract_from_zip_file(
+                        rest,
+                        zip::ZipArchive::new(remote)?.by_index(*index)?,
+                        target,
+                    ),
+                }
+            }
+
+            RomSource::Rar { file, index } => extract_from_rar_file(file, *index, target),
+
+            RomSource::Tar { file, index } => extract_from_tar_file(file, *index, target),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl RomSource<'_> {
+    // like its display, but with files by absolute path
+    // so it's still meaningful from elsewhere
+    fn origin(&self) -> String {
+        let absolute = |file: &Path| std::path::absolute(file).unwrap_or_else(|_| file.into());
+
+        match self {
+            RomSource::File {
+                file, zip_parts, ..
+            } => zip_parts
+                .iter()
+                .fold(absolute(file).display().to_string(), |acc, part| {
+                    format!("{}:{}", acc, part)
+                }),
+            RomSource::Url { .. } | RomSource::Remote { .. } => self.to_string(),
+            RomSource::Rar { file, index } | RomSource::Tar { file, index } => {
+                format!("{}:{}", absolute(file).display(), index)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl fmt::Display for RomSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomSource::File {
+                file, zip_parts, ..
+            } => file
+                .display()
+                .fmt(f)
+                .and_then(|()| zip_parts.iter().try_for_each(|part| write!(f, ":{}", part))),
+            RomSource::Url { url, zip_parts, .. } | RomSource::Remote { url, zip_parts, .. } => url
+                .fmt(f)
+                .and_then(|()| zip_parts.iter().try_for_each(|part| write!(f, ":{}", part))),
+            RomSource::Rar { file, index } | RomSource::Tar { file, index } => {
+                write!(f, "{}:{}", file.display(), index)
+            }
+        }
+    }
+}
+
+// links and clones can't cross filesystems, and some filesystems
+// or permissions don't allow them at all,
+// so those files need copying instead
+#[cfg(feature = "native")]
+fn cannot_link(err: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    matches!(
+        err.kind(),
+        ErrorKind::CrossesDevices
+            | ErrorKind::PermissionDenied
+            | ErrorKind::Unsupported
+            | ErrorKind::TooManyLinks
+            | ErrorKind::InvalidInput
+    )
+}
+
+// creates target as a copy-on-write clone of source,
+// sharing its data until either is modified
+#[cfg(all(feature = "native", target_os = "linux"))]
+fn reflink(source: &Path, target: &Path) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let source = std::fs::File::open(source)?;
+    let target_file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)?;
+
+    match unsafe {
+        libc::ioctl(
+            target_file.as_raw_fd(),
+            libc::FICLONE as _,
+            source.as_raw_fd(),
+        )
+    } {
+        -1 => {
+            let err = std::io::Error::last_os_error();
+            drop(target_file);
+            let _ = std::fs::remove_file(target);
+            Err(err)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(feature = "native", target_os = "macos"))]
+fn reflink(source: &Path, target: &Path) -> std::io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let source = CString::new(source.as_os_str().as_bytes())?;
+    let target = CString::new(target.as_os_str().as_bytes())?;
+
+    match unsafe { libc::clonefile(source.as_ptr(), target.as_ptr(), 0) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(feature = "native", not(any(target_os = "linux", target_os = "macos"))))]
+fn reflink(_source: &Path, _target: &Path) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+// links target to source, by either its absolute path
+// or its path relative to target's directory
+#[cfg(feature = "native")]
+fn symlink(source: &Path, target: &Path, mode: LinkMode) -> std::io::Result<()> {
+    let source = source.canonicalize()?;
+
+    let source = match (mode, target.parent()) {
+        (LinkMode::Relative, Some(dir)) => relative_path(&dir.canonicalize()?, &source),
+        _ => source,
+    };
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(source, target)
+    }
+
+    #[cfg(windows)]
+    {
+        std::os::windows::fs::symlink_file(source, target)
+    }
+}
+
+// the path to "to" from the directory "from", where both are absolute
+#[cfg(feature = "native")]
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let mut from = from.components().peekable();
+    let mut to = to.components().peekable();
+
+    while let (Some(f), Some(t)) = (from.peek(), to.peek()) {
+        if f != t {
+            break;
+        }
+        from.next();
+        to.next();
+    }
+
+    from.map(|_| std::path::Component::ParentDir)
+        .chain(to)
+        .collect()
+}
+
+// when adding in parallel, another worker may have just provided
+// the same part at target, which may still be in progress
+// if it's being copied, so give it a few chances to finish
+#[cfg(feature = "native")]
+fn already_provided(source: &Path, target: &Path) -> Result<(), Error> {
+    const ATTEMPTS: u32 = 5;
+
+    let expected = Part::from_cached_path(source)?;
+
+    for attempt in 1..=ATTEMPTS {
+        if Part::from_path(target)? == expected {
+            return Ok(());
+        } else if attempt < ATTEMPTS {
+            std::thread::sleep(std::time::Duration::from_millis(100 * u64::from(attempt)));
+        }
+    }
+
+    Err(Error::IO(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("{} : already exists with other contents", target.display()),
+    )))
+}
+
+#[cfg(feature = "native")]
+fn extract_from_zip_file<R: Read>(
+    indexes: &[usize],
+    mut r: R,
+    target: &Path,
+) -> Result<Extracted, Error> {
+    match indexes.split_first() {
+        None => std::fs::File::create(target)
+            .and_then(|mut w| Rate::from_copy(|| std::io::copy(&mut r, &mut w)))
+            .map(|rate| Extracted::Copied { rate })
+            .map_err(Error::IO),
+
+        Some((index, rest)) => extract_from_zip_file(
+            rest,
+            zip::ZipArchive::new(Spooled::new(r)?)?.by_index(*index)?,
+            target,
+        ),
+    }
+}
+
+// nested Zip archives need to be seekable,
+// so small ones are buffered in memory
+// while larger ones are spooled to a temporary file
+// to keep memory use bounded regardless of archive size
+#[cfg(feature = "native")]
+enum Spooled {
+    Memory(std::io::Cursor<Vec<u8>>),
+    File(SpoolFile),
+}
+
+#[cfg(all(feature = "native", not(test)))]
+const SPOOL_MEMORY_LIMIT: u64 = 64 * 1024 * 1024;
+
+// small enough for tests to spool archives to disk
+#[cfg(all(feature = "native", test))]
+const SPOOL_MEMORY_LIMIT: u64 = 64;
+
+#[cfg(feature = "native")]
+impl Spooled {
+    fn new<R: Read>(r: R) -> Result<Self, std::io::Error> {
+        let mut r = r.take(SPOOL_MEMORY_LIMIT);
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+
+        let mut r = r.into_inner();
+        let mut overflow = [0; 1];
+
+        if r.read(&mut overflow)? == 0 {
+            return Ok(Spooled::Memory(std::io::Cursor::new(data)));
+        }
+
+        let mut spool = SpoolFile::new()?;
+
+        {
+            use std::io::Write;
+
+            let mut w = std::io::BufWriter::new(&mut spool.file);
+            w.write_all(&data)?;
+            w.write_all(&overflow)?;
+            std::io::copy(&mut r, &mut w)?;
+            w.flush()?;
+        }
+
+        spool.file.rewind()?;
+
+        Ok(Spooled::File(spool))
+    }
+}
+
+#[cfg(feature = "native")]
+impl Read for Spooled {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Spooled::Memory(c) => c.read(buf),
+            Spooled::File(f) => f.file.read(buf),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl Seek for Spooled {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        match self {
+            Spooled::Memory(c) => c.seek(pos),
+            Spooled::File(f) => f.file.seek(pos),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl std::io::Write for Spooled {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Spooled::Memory(c) => c.write(buf),
+            Spooled::File(f) => f.file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Spooled::Memory(c) => c.flush(),
+            Spooled::File(f) => f.file.flush(),
+        }
+    }
+}
+
+// a temporary file which is removed once dropped
+#[cfg(feature = "native")]
+struct SpoolFile {
+    file: std::fs::File,
+    path: PathBuf,
+}
+
+#[cfg(feature = "native")]
+impl SpoolFile {
+    fn new() -> Result<Self, std::io::Error> {
+        let path = Self::temp_path();
+
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map(|file| Self { file, path })
+    }
+
+    // spools whatever extract writes to the path it's given,
+    // for libraries which only extract to paths
+    fn extract_with<T, F>(extract: F) -> Result<(Self, T), Error>
+    where
+        F: FnOnce(&Path) -> Result<T, Error>,
+    {
+        let path = Self::temp_path();
+
+        let spooled = extract(&path).and_then(|extracted| {
+            std::fs::File::open(&path)
+                .map(|file| {
+                    (
+                        Self {
+                            file,
+                            path: path.clone(),
+                        },
+                        extracted,
+                    )
+                })
+                .map_err(Error::IO)
+        });
+
+        if spooled.is_err() {
+            let _ = std::fs::remove_file(&path);
+        }
+
+        spooled
+    }
+
+    fn temp_path() -> PathBuf {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static SPOOLED: AtomicUsize = AtomicUsize::new(0);
+
+        std::env::temp_dir().join(format!(
+            "emuman-{}-{}.tmp",
+            std::process::id(),
+            SPOOLED.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+}
+
+#[cfg(feature = "native")]
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// returns the name of the first damaged entry on failure
+#[cfg(feature = "native")]
+fn unpack_zip_parts<F: Read + Seek>(zip: F) -> Result<Vec<(Part, ZipParts)>, String> {
+    // a valid ROM might be an invalid Zip file
+    // so a failure to unpack Zip parts from a file
+    // should not be considered a fatal error,
+    // but a Zip entry failing its CRC check means
+    // the whole archive can't be trusted as a source
+
+    let mut zip = match zip::ZipArchive::new(zip) {
+        Ok(zip) => zip,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut results = Vec::new();
+
+    for index in 0..zip.len() {
+        match unpack_zip_entry(&mut zip, index)? {
+            Some(parts) => results.extend(parts),
+            None => return Ok(Vec::new()),
+        }
+    }
+
+    Ok(results)
+}
+
+// archives at least this large have their entries
+// hashed in parallel, each worker with its own handle
+#[cfg(feature = "native")]
+const PARALLEL_ZIP_SIZE: u64 = 256 * 1024 * 1024;
+
+// like unpack_zip_parts, but for large archives on disk
+#[cfg(feature = "native")]
+fn unpack_large_zip_parts(path: &Path) -> Result<Vec<(Part, ZipParts)>, String> {
+    use rayon::prelude::*;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn open(path: &Path) -> Option<zip::ZipArchive<BufReader<File>>> {
+        zip::ZipArchive::new(File::open(path).map(BufReader::new).ok()?).ok()
+    }
+
+    let len = match open(path) {
+        Some(zip) => zip.len(),
+        None => return Ok(Vec::new()),
+    };
+
+    let entries = (0..len)
+        .into_par_iter()
+        .map_init(
+            || open(path),
+            |zip, index| match zip {
+                Some(zip) => unpack_zip_entry(zip, index),
+                None => Ok(None),
+            },
+        )
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(entries
+        .into_iter()
+        .collect::<Option<Vec<_>>>()
+        .map(|entries| entries.into_iter().flatten().collect())
+        .unwrap_or_default())
+}
+
+// returns None if the entry can't be read at all
+#[cfg(feature = "native")]
+fn unpack_zip_entry<F: Read + Seek>(
+    zip: &mut zip::ZipArchive<F>,
+    index: usize,
+) -> Result<Option<Vec<(Part, ZipParts)>>, String> {
+    fn is_zip<R: Read>(mut reader: R) -> bool {
+        let mut buf = [0; 4];
+        match reader.read_exact(&mut buf) {
+            Ok(()) => &buf == b"\x50\x4b\x03\x04",
+            Err(_) => false,
+        }
+    }
+
+    // reading an entry through to the end
+    // checks its data against the stored CRC32
+    fn check_crc<R: Read>(mut r: R) -> Result<(), std::io::Error> {
+        std::io::copy(&mut r, &mut std::io::sink()).map(|_| ())
+    }
+
+    let nested = match zip.by_index(index) {
+        Ok(entry) => is_zip(entry),
+        Err(_) => return Ok(None),
+    };
+
+    let mut entry = zip.by_index(index).map_err(|_| index.to_string())?;
+    let name = entry.name().to_owned();
+
+    if nested {
+        let zip_data = Spooled::new(&mut entry).map_err(|_| name.clone())?;
+
+        Ok(Some(
+            unpack_zip_parts(zip_data)
+                .map_err(|inner| format!("{}/{}", name, inner))?
+                .into_iter()
+                .map(|(part, mut zip_parts)| {
+                    zip_parts.insert(0, index);
+                    (part, zip_parts)
+                })
+                .collect(),
+        ))
+    } else {
+        let part = Part::from_reader(&mut entry)
+            .and_then(|part| check_crc(&mut entry).map(|()| part))
+            .map_err(|_| name)?;
+
+        Ok(Some(vec![(part, vec![index])]))
+    }
+}
+
+// RAR entries are indexed by their position in the archive
+#[cfg(feature = "native")]
+fn unpack_rar_parts(path: &Path) -> Vec<(Part, usize)> {
+    // as with Zip files, a failure to unpack RAR parts
+    // should not be considered a fatal error
+
+    fn unpack(path: &Path) -> Result<Vec<(Part, usize)>, Error> {
+        let mut archive = unrar::Archive::new(path).open_for_processing()?;
+        let mut results = Vec::new();
+        let mut index = 0;
+
+        // the unrar library only reads whole entries into memory,
+        // so each is extracted to a spool file to be hashed instead
+        // since it may be larger than memory
+        while let Some(header) = archive.read_header()? {
+            archive = if header.entry().is_file() {
+                let (spool, archive) =
+                    SpoolFile::extract_with(|path| header.extract_to(path).map_err(Error::Rar))?;
+                results.push((
+                    Part::from_reader(std::io::BufReader::new(&spool.file))?,
+                    index,
+                ));
+                archive
+            } else {
+                header.skip()?
+            };
+
+            index += 1;
+        }
+
+        Ok(results)
+    }
+
+    unpack(path).unwrap_or_default()
+}
 
-        if is_zip(&mut r).unwrap_or(false) {
-            result.extend(unpack_zip_parts(r).into_iter().map(|(part, zip_parts)| {
-                (
-                    part,
-                    RomSource::File {
-                        file: file.clone(),
-                        has_xattr: false,
-                        zip_parts,
-                    },
-                )
-            }));
-        }
+#[cfg(feature = "native")]
+fn extract_from_rar_file(file: &Path, index: usize, target: &Path) -> Result<Extracted, Error> {
+    use std::io::{Error as IoError, ErrorKind};
 
-        Ok(result)
+    fn missing_entry() -> Error {
+        Error::IO(IoError::new(ErrorKind::NotFound, "missing RAR entry"))
     }
 
-    pub fn from_url(url: &'u str) -> Result<Vec<(Part, RomSource<'u>)>, Error> {
-        let data: Arc<[u8]> = crate::http::fetch_url_data(url).map(Arc::from)?;
+    let mut archive = unrar::Archive::new(file).open_for_processing()?;
 
-        let mut result = vec![(
-            Part::from_slice(&data)?,
-            RomSource::Url {
-                url,
-                data: data.clone(),
-                zip_parts: ZipParts::default(),
-            },
-        )];
+    for _ in 0..index {
+        archive = archive.read_header()?.ok_or_else(missing_entry)?.skip()?;
+    }
 
-        if matches!(data[..], [0x50, 0x4B, 0x03, 0x04, ..]) {
-            result.extend(
-                unpack_zip_parts(std::io::Cursor::new(data.clone()))
-                    .into_iter()
-                    .map(|(part, zip_parts)| {
-                        (
-                            part,
-                            RomSource::Url {
-                                url,
-                                data: data.clone(),
-                                zip_parts,
-                            },
-                        )
-                    }),
-            );
-        }
+    let header = archive.read_header()?.ok_or_else(missing_entry)?;
+    let size = header.entry().unpacked_size;
+
+    Rate::from_copy(|| {
+        header
+            .extract_to(target)
+            .map(|_| size)
+            .map_err(IoError::other)
+    })
+    .map(|rate| Extracted::Copied { rate })
+    .map_err(Error::IO)
+}
 
-        Ok(result)
+// tar archives may be compressed as a whole with gzip or zstd,
+// so they're streamed from the start whenever they're read
+#[cfg(feature = "native")]
+fn open_tar(path: &Path) -> Result<Option<tar::Archive<Box<dyn Read>>>, std::io::Error> {
+    use std::io::BufRead;
+
+    const GZIP: &[u8] = b"\x1f\x8b";
+    const ZSTD: &[u8] = b"\x28\xb5\x2f\xfd";
+
+    let mut r = std::io::BufReader::new(std::fs::File::open(path)?);
+    let magic = r.fill_buf()?;
+
+    let mut r: Box<dyn Read> = if magic.starts_with(ZSTD) {
+        Box::new(zstd::Decoder::with_buffer(r)?)
+    } else if magic.starts_with(GZIP) {
+        Box::new(flate2::bufread::GzDecoder::new(r))
+    } else {
+        Box::new(r)
+    };
+
+    // POSIX tar headers have a "ustar" magic number
+    let mut header = [0; 512];
+    if r.read_exact(&mut header).is_err() || &header[257..262] != b"ustar" {
+        return Ok(None);
     }
 
-    fn extract(&self, target: &Path) -> Result<Extracted, Error> {
-        use std::fs::{copy, hard_link, File};
+    Ok(Some(tar::Archive::new(Box::new(
+        std::io::Cursor::new(header).chain(r),
+    ))))
+}
 
-        match self {
-            RomSource::File {
-                file: source,
-                has_xattr,
-                zip_parts,
-            } => match zip_parts.split_first() {
-                None => hard_link(source.as_path(), &target)
-                    .map(|()| Extracted::Linked {
-                        has_xattr: *has_xattr,
-                    })
-                    .or_else(|_| {
-                        Rate::from_copy(|| copy(source.as_path(), &target))
-                            .map(|rate| Extracted::Copied { rate })
-                            .map_err(Error::IO)
-                    }),
+// tar entries are indexed by their position in the archive
+#[cfg(feature = "native")]
+fn unpack_tar_parts(path: &Path) -> Vec<(Part, usize)> {
+    // as with Zip files, a failure to unpack tar parts
+    // should not be considered a fatal error
 
-                Some((index, rest)) => extract_from_zip_file(
-                    rest,
-                    zip::ZipArchive::new(File::open(source.as_ref())?)?.by_index(*index)?,
-                    target,
-                ),
-            },
+    fn unpack(path: &Path) -> Result<Vec<(Part, usize)>, std::io::Error> {
+        let mut results = Vec::new();
 
-            RomSource::Url {
-                data, zip_parts, ..
-            } => extract_from_zip_file(zip_parts, std::io::Cursor::new(data), target),
+        if let Some(mut archive) = open_tar(path)? {
+            for (index, entry) in archive.entries()?.enumerate() {
+                let entry = entry?;
+                if entry.header().entry_type().is_file() {
+                    results.push((Part::from_reader(entry)?, index));
+                }
+            }
         }
+
+        Ok(results)
     }
+
+    unpack(path).unwrap_or_default()
 }
 
-impl fmt::Display for RomSource<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            RomSource::File {
-                file, zip_parts, ..
-            } => file
-                .display()
-                .fmt(f)
-                .and_then(|()| zip_parts.iter().try_for_each(|part| write!(f, ":{}", part))),
-            RomSource::Url { url, zip_parts, .. } => url
-                .fmt(f)
-                .and_then(|()| zip_parts.iter().try_for_each(|part| write!(f, ":{}", part))),
-        }
+#[cfg(feature = "native")]
+fn extract_from_tar_file(file: &Path, index: usize, target: &Path) -> Result<Extracted, Error> {
+    use std::io::{Error as IoError, ErrorKind};
+
+    fn missing_entry() -> IoError {
+        IoError::new(ErrorKind::NotFound, "missing tar entry")
     }
+
+    let mut archive = open_tar(file)?.ok_or_else(missing_entry)?;
+    let mut entry = archive.entries()?.nth(index).ok_or_else(missing_entry)??;
+
+    std::fs::File::create(target)
+        .and_then(|mut w| Rate::from_copy(|| std::io::copy(&mut entry, &mut w)))
+        .map(|rate| Extracted::Copied { rate })
+        .map_err(Error::IO)
 }
 
-fn extract_from_zip_file<R: Read>(
-    indexes: &[usize],
-    mut r: R,
-    target: &Path,
-) -> Result<Extracted, Error> {
-    match indexes.split_first() {
-        None => std::fs::File::create(target)
-            .and_then(|mut w| Rate::from_copy(|| std::io::copy(&mut r, &mut w)))
-            .map(|rate| Extracted::Copied { rate })
-            .map_err(Error::IO),
+// multi-volume Zip archives are split as "name.z01", "name.z02", ...
+// with the final "name.zip" volume holding the central directory
+#[cfg(feature = "native")]
+fn zip_volumes(path: &Path) -> Option<Vec<PathBuf>> {
+    if !path.extension()?.eq_ignore_ascii_case("zip") {
+        return None;
+    }
 
-        Some((index, rest)) => {
-            let mut zip_data = Vec::new();
-            r.read_to_end(&mut zip_data)?;
-            extract_from_zip_file(
-                rest,
-                zip::ZipArchive::new(std::io::Cursor::new(zip_data))?.by_index(*index)?,
-                target,
-            )
-        }
+    let mut volumes = (1..)
+        .map(|volume| path.with_extension(format!("z{:02}", volume)))
+        .take_while(|volume| volume.is_file())
+        .collect::<Vec<_>>();
+
+    if volumes.is_empty() {
+        None
+    } else {
+        volumes.push(path.to_path_buf());
+        Some(volumes)
     }
 }
 
-fn unpack_zip_parts<F: Read + Seek>(zip: F) -> Vec<(Part, ZipParts)> {
-    // a valid ROM might be an invalid Zip file
-    // so a failure to unpack Zip parts from a file
-    // should not be considered a fatal error
+// multi-volume archives recently joined for extraction, most recent last,
+// so extracting several parts from one only joins its volumes once
+#[cfg(feature = "native")]
+static JOINED: std::sync::Mutex<Vec<(PathBuf, JoinedVolumes)>> = std::sync::Mutex::new(Vec::new());
 
-    fn is_zip<R: Read>(mut reader: R) -> bool {
-        let mut buf = [0; 4];
-        match reader.read_exact(&mut buf) {
-            Ok(()) => &buf == b"\x50\x4b\x03\x04",
-            Err(_) => false,
+#[cfg(feature = "native")]
+const JOINED_LIMIT: usize = 8;
+
+#[cfg(feature = "native")]
+type JoinedVolumes = Arc<std::sync::Mutex<Option<Spooled>>>;
+
+// calls f with the joined volumes of the multi-volume archive at path,
+// joining them only if they haven't been already
+#[cfg(feature = "native")]
+fn with_joined_zip_volumes<T, F>(path: &Path, volumes: &[PathBuf], f: F) -> Result<T, Error>
+where
+    F: FnOnce(&mut Spooled) -> Result<T, Error>,
+{
+    let joined = {
+        let mut cache = JOINED.lock().unwrap();
+
+        let entry = match cache.iter().position(|(joined, _)| joined == path) {
+            Some(index) => cache.remove(index),
+            None => (path.to_path_buf(), JoinedVolumes::default()),
+        };
+        let joined = entry.1.clone();
+        cache.push(entry);
+
+        if cache.len() > JOINED_LIMIT {
+            cache.remove(0);
+        }
+
+        joined
+    };
+
+    // joining happens outside the cache's lock
+    // so other archives needn't wait on this one
+    let mut joined = joined.lock().unwrap();
+
+    let spooled = match joined.as_mut() {
+        Some(spooled) => {
+            spooled.rewind()?;
+            spooled
         }
+        None => joined.insert(join_zip_volumes(volumes)?),
+    };
+
+    f(spooled)
+}
+
+// a multi-volume archive's ".z01", ".z02", ... volumes
+// are only sources as part of their ".zip"
+#[cfg(feature = "native")]
+fn is_zip_volume(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(|extension| extension.strip_prefix(['z', 'Z']))
+        .is_some_and(|number| number.len() == 2 && number.bytes().all(|b| b.is_ascii_digit()))
+        && path.with_extension("zip").is_file()
+}
+
+// joins a multi-volume Zip archive's volumes into a single-volume archive
+// by rewriting its central directory's offsets to be relative
+// to the start of the joined data rather than each volume's start,
+// spooling the joined data to a temporary file if it's large
+#[cfg(feature = "native")]
+fn join_zip_volumes(volumes: &[PathBuf]) -> Result<Spooled, Error> {
+    use std::convert::TryInto;
+    use std::io::{SeekFrom, Write};
+
+    const END_SIGNATURE: &[u8] = b"\x50\x4b\x05\x06";
+    const END_SIZE: usize = 22;
+    const HEADER_SIGNATURE: &[u8] = b"\x50\x4b\x01\x02";
+    const HEADER_SIZE: usize = 46;
+
+    fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(
+            data.get(offset..offset + 2)?.try_into().ok()?,
+        ))
     }
 
-    fn unpack<F: Read + Seek>(zip: F) -> Result<Vec<(Part, ZipParts)>, Error> {
-        let mut zip = zip::ZipArchive::new(zip)?;
-        let mut results = Vec::new();
+    fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(
+            data.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    }
 
-        for index in 0..zip.len() {
-            if is_zip(zip.by_index(index)?) {
-                let mut zip_data = Vec::new();
+    fn write_u16(data: &mut [u8], offset: usize, value: u16) {
+        data[offset..offset + 2].copy_from_slice(&value.to_le_bytes())
+    }
 
-                zip.by_index(index)?.read_to_end(&mut zip_data)?;
+    fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes())
+    }
 
-                results.extend(
-                    unpack_zip_parts(std::io::Cursor::new(zip_data))
-                        .into_iter()
-                        .map(|(part, mut zip_parts)| {
-                            zip_parts.insert(0, index);
-                            (part, zip_parts)
-                        }),
-                )
-            } else {
-                results.push((Part::from_reader(zip.by_index(index)?)?, vec![index]))
+    // rebases a volume-relative offset onto the joined data
+    fn rebase(starts: &[u64], volume: u16, offset: u32) -> Option<u32> {
+        (starts.get(usize::from(volume))? + u64::from(offset))
+            .try_into()
+            .ok()
+    }
+
+    // finds the end of central directory record
+    // among the final bytes of the joined data,
+    // returning its position and the central directory's
+    fn locate(tail: &[u8], tail_start: u64, starts: &[u64]) -> Option<(u64, u32)> {
+        let end = (0..=tail.len().checked_sub(END_SIZE)?)
+            .rev()
+            .take(u16::MAX as usize + 1)
+            .find(|offset| tail[*offset..].starts_with(END_SIGNATURE))?;
+
+        Some((
+            tail_start + end as u64,
+            rebase(starts, read_u16(tail, end + 6)?, read_u32(tail, end + 16)?)?,
+        ))
+    }
+
+    // rewrites the central directory and end record in data,
+    // which starts at the central directory
+    fn join(data: &mut [u8], end: usize, directory: u32, starts: &[u64]) -> Option<()> {
+        let entries = read_u16(data, end + 10)?;
+
+        let mut header = 0;
+        for _ in 0..entries {
+            if !data.get(header..)?.starts_with(HEADER_SIGNATURE) {
+                return None;
             }
+
+            let local = rebase(
+                starts,
+                read_u16(data, header + 34)?,
+                read_u32(data, header + 42)?,
+            )?;
+            write_u16(data, header + 34, 0);
+            write_u32(data, header + 42, local);
+
+            header += HEADER_SIZE
+                + usize::from(read_u16(data, header + 28)?)
+                + usize::from(read_u16(data, header + 30)?)
+                + usize::from(read_u16(data, header + 32)?);
         }
 
-        Ok(results)
+        write_u16(data, end + 4, 0);
+        write_u16(data, end + 6, 0);
+        write_u16(data, end + 8, entries);
+        write_u32(data, end + 16, directory);
+
+        Some(())
+    }
+
+    // reads from offset through to the end of the joined data
+    fn read_from(joined: &mut Spooled, offset: u64) -> Result<Vec<u8>, std::io::Error> {
+        let mut data = Vec::new();
+        joined.seek(SeekFrom::Start(offset))?;
+        joined.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    let invalid = || {
+        Error::Zip(zip::result::ZipError::InvalidArchive(
+            "invalid multi-volume archive",
+        ))
+    };
+
+    let mut starts = Vec::with_capacity(volumes.len());
+    let mut len = 0;
+
+    for volume in volumes {
+        starts.push(len);
+        len += volume.metadata()?.len();
     }
 
-    unpack(zip).unwrap_or_default()
+    let mut joined = Spooled::new(volumes.iter().try_fold(
+        Box::new(std::io::empty()) as Box<dyn Read>,
+        |joined, volume| {
+            std::fs::File::open(volume)
+                .map(|volume| Box::new(joined.chain(volume)) as Box<dyn Read>)
+        },
+    )?)?;
+
+    // the end record is within its maximum size of the end,
+    // and only it and the central directory need rewriting
+    let tail_start = len.saturating_sub((END_SIZE + u16::MAX as usize) as u64);
+    let tail = read_from(&mut joined, tail_start)?;
+    let (end, directory) = locate(&tail, tail_start, &starts).ok_or_else(invalid)?;
+
+    let mut data = read_from(&mut joined, directory.into())?;
+    let end = end
+        .checked_sub(directory.into())
+        .and_then(|end| end.try_into().ok())
+        .ok_or_else(invalid)?;
+    join(&mut data, end, directory, &starts).ok_or_else(invalid)?;
+
+    joined.seek(SeekFrom::Start(directory.into()))?;
+    joined.write_all(&data)?;
+    joined.rewind()?;
+
+    Ok(joined)
 }
 
+#[cfg(feature = "native")]
 #[derive(Copy, Clone)]
 enum Extracted {
     Copied { rate: Option<Rate> },
+    Cloned,
     Linked { has_xattr: bool },
+    Planned,
 }
 
+#[cfg(feature = "native")]
 #[derive(Copy, Clone)]
 pub struct Rate {
     bytes_per_sec: f64,
 }
 
+#[cfg(feature = "native")]
 impl Rate {
     #[inline]
     fn new(bytes: u64, dur: std::time::Duration) -> Rate {
@@ -1495,6 +4753,7 @@ impl Rate {
     }
 }
 
+#[cfg(feature = "native")]
 impl fmt::Display for Rate {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         const K: f64 = (1 << 10) as f64;
@@ -1511,108 +4770,335 @@ impl fmt::Display for Rate {
     }
 }
 
+#[cfg(feature = "native")]
 pub type RomSources<'u> = DashMap<Part, RomSource<'u>>;
 
-fn file_rom_sources<F>(root: &Path, part_filter: F) -> RomSources
+// the sources found under each root, in the order given
+//
+// roots are often on separate drives, so all of them are listed at once
+// and then their files hashed together, taking turns between roots
+// so that every drive stays busy for as long as it has files left
+#[cfg(feature = "native")]
+fn file_rom_sources<'u, F>(
+    roots: &[PathBuf],
+    seen: &DashSet<FileId>,
+    part_filter: F,
+    sink: &dyn ProgressSink,
+) -> Vec<RomSources<'u>>
 where
     F: Fn(&Part) -> bool + Sync + Send,
 {
-    use indicatif::ParallelProgressIterator;
     use rayon::prelude::*;
 
-    let files = subdir_files(root);
+    let listed = roots
+        .par_iter()
+        .map(|root| {
+            sink.scanning(root);
+            let task = Task::start(
+                sink,
+                &format!("locating files in {}", root.display()),
+                Total::Unknown,
+            );
+            subdir_files(root, seen, &task).into_iter()
+        })
+        .collect::<Vec<_>>();
+
+    let total = listed.iter().map(|files| files.len()).sum::<usize>();
+    let mut listed = listed.into_iter().enumerate().collect::<Vec<_>>();
+    let mut files = Vec::with_capacity(total);
 
-    let pbar = ProgressBar::new(files.len() as u64).with_style(verify_style());
-    pbar.set_message("cataloging files");
-    pbar.set_draw_delta(files.len() as u64 / 1000);
+    while !listed.is_empty() {
+        listed.retain_mut(|(index, root_files)| match root_files.next() {
+            Some(file) => {
+                files.push((*index, file));
+                true
+            }
+            None => false,
+        });
+    }
 
-    let results = files
+    let task = Task::start(
+        sink,
+        &match roots {
+            [root] => format!("cataloging {}", root.display()),
+            roots => format!("cataloging {} roots", roots.len()),
+        },
+        Total::Items(total as u64),
+    );
+
+    let results = roots
+        .iter()
+        .map(|_| RomSources::default())
+        .collect::<Vec<_>>();
+
+    files
         .into_par_iter()
-        .progress_with(pbar.clone())
-        .flat_map(|pb| {
-            RomSource::from_path(pb)
-                .unwrap_or_else(|_| Vec::new())
-                .into_par_iter()
-        })
-        .filter(|(part, _)| part_filter(part))
-        .collect();
+        .inspect(|_| task.advance(1))
+        .flat_map_iter(|(index, pb)| {
+            let sources = match crate::catalog::rom_sources(pb) {
+                Ok(sources) => sources,
+                Err(err @ Error::CorruptZip(..)) => {
+                    sink.warning(&err.to_string());
+                    Vec::new()
+                }
+                Err(_) => Vec::new(),
+            };
 
-    pbar.finish_and_clear();
+            sources
+                .into_iter()
+                .map(move |(part, source)| (index, part, source))
+        })
+        .filter(|(_, part, _)| part_filter(part))
+        .for_each(|(index, part, source)| {
+            use dashmap::mapref::entry::Entry;
+
+            // of the same part in a root, the readiest source wins
+            match results[index].entry(part) {
+                Entry::Vacant(entry) => {
+                    entry.insert(source);
+                }
+                Entry::Occupied(mut entry) => {
+                    if source.tier() < entry.get().tier() {
+                        entry.insert(source);
+                    }
+                }
+            }
+        });
 
     results
 }
 
+#[cfg(feature = "native")]
 #[inline]
-fn url_rom_sources<F>(url: &str, part_filter: F) -> RomSources
+fn url_rom_sources<'u, F>(url: &'u str, part_filter: F, sink: &dyn ProgressSink) -> RomSources<'u>
 where
     F: Fn(&Part) -> bool + Sync + Send,
 {
-    RomSource::from_url(url)
-        .map(|v| {
-            v.into_iter()
-                .filter(|(part, _)| part_filter(part))
-                .collect()
-        })
-        .unwrap_or_default()
+    // servers which don't honor range requests have their files fetched whole
+    let sources = match crate::http::remote_len(url) {
+        Ok(Some(len)) => RomSource::from_remote(url, len, sink),
+        Ok(None) | Err(_) => RomSource::from_url(url, sink),
+    };
+
+    match sources {
+        Ok(sources) => sources
+            .into_iter()
+            .filter(|(part, _)| part_filter(part))
+            .collect(),
+        Err(err @ Error::CorruptZip(..)) => {
+            sink.warning(&err.to_string());
+            RomSources::default()
+        }
+        Err(_) => RomSources::default(),
+    }
 }
 
+#[cfg(feature = "native")]
 fn multi_rom_sources<'u, F>(
     roots: &'u [PathBuf],
     urls: &'u [String],
     part_filter: F,
+    sink: &dyn ProgressSink,
 ) -> RomSources<'u>
 where
     F: Fn(&Part) -> bool + Sync + Send + Copy,
 {
-    urls.iter()
-        .map(|url| url_rom_sources(url, part_filter))
-        .chain(roots.iter().map(|root| file_rom_sources(root, part_filter)))
-        .reduce(|mut acc, item| {
-            acc.extend(item);
-            acc
-        })
-        .unwrap_or_else(|| file_rom_sources(Path::new("."), part_filter))
+    // blocked parts are never propagated into sets,
+    // where callers have already surfaced a blocklist that won't load
+    let part_filter = move |part: &Part| {
+        part_filter(part) && !matches!(crate::blocklist::is_blocked(part), Ok(true))
+    };
+
+    // physical files are only ever hashed once,
+    // even if reachable from more than one root
+    let seen = DashSet::default();
+
+    // with nothing else to scan, the current directory is
+    let default_roots = [PathBuf::from(".")];
+    let roots = if roots.is_empty() && urls.is_empty() {
+        &default_roots
+    } else {
+        roots
+    };
+
+    let mut scanned = roots
+        .iter()
+        .map(|root| is_network_fs(root))
+        .zip(file_rom_sources(roots, &seen, part_filter, sink))
+        .collect::<Vec<_>>();
+
+    scanned.extend(
+        urls.iter()
+            .map(|url| (true, url_rom_sources(url, part_filter, sink))),
+    );
+
+    // parts found in more than one place come from local sources
+    // before network ones, then whole files before those in archives,
+    // and then from whichever root was given first, followed by URLs
+    let mut ranked: FxHashMap<Part, ((bool, u8, usize), RomSource<'u>)> = FxHashMap::default();
+
+    for (index, (network, found)) in scanned.into_iter().enumerate() {
+        for (part, source) in found {
+            let rank = (network, source.tier(), index);
+
+            match ranked.entry(part) {
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert((rank, source));
+                }
+                std::collections::hash_map::Entry::Occupied(mut entry) => {
+                    if rank < entry.get().0 {
+                        entry.insert((rank, source));
+                    }
+                }
+            }
+        }
+    }
+
+    let sources = ranked
+        .into_iter()
+        .map(|(part, (_, source))| (part, source))
+        .collect();
+
+    // a catalog which can't be saved only means rereading files next time
+    if let Err(err) = crate::catalog::save() {
+        sink.warning(&format!("unable to save source catalog : {}", err));
+    }
+
+    sources
+}
+
+// whether path is on a filesystem mounted from across a network,
+// which is slower to read from and can't be linked to from local sets
+#[cfg(all(feature = "native", target_os = "linux"))]
+fn is_network_fs(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    const NFS: u32 = 0x6969;
+    const SMB: u32 = 0x517b;
+    const CIFS: u32 = 0xff534d42;
+    const SMB2: u32 = 0xfe534d42;
+    const CEPH: u32 = 0x00c36400;
+    const AFS: u32 = 0x5346414f;
+    const V9FS: u32 = 0x01021997;
+
+    let path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+
+    let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+
+    match unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) } {
+        0 => matches!(
+            // magic numbers are 32 bits, however wide the field
+            unsafe { stat.assume_init() }.f_type as u32,
+            NFS | SMB | CIFS | SMB2 | CEPH | AFS | V9FS
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(all(feature = "native", not(target_os = "linux")))]
+fn is_network_fs(_path: &Path) -> bool {
+    false
 }
 
+#[cfg(feature = "native")]
 #[inline]
-pub fn all_rom_sources<'u>(roots: &'u [PathBuf], urls: &'u [String]) -> RomSources<'u> {
-    multi_rom_sources(roots, urls, |_| true)
+pub fn all_rom_sources<'u>(
+    roots: &'u [PathBuf],
+    urls: &'u [String],
+    sink: &dyn ProgressSink,
+) -> RomSources<'u> {
+    multi_rom_sources(roots, urls, |_| true, sink)
 }
 
+#[cfg(feature = "native")]
 #[inline]
 pub fn get_rom_sources<'u>(
     roots: &'u [PathBuf],
     urls: &'u [String],
     required: FxHashSet<Part>,
+    sink: &dyn ProgressSink,
 ) -> RomSources<'u> {
-    multi_rom_sources(roots, urls, |part| required.contains(part))
+    multi_rom_sources(roots, urls, |part| required.contains(part), sink)
 }
 
-pub fn display_all_results(game: &str, failures: &[VerifyFailure]) {
-    if failures.is_empty() {
-        println!("OK : {}", game);
-    } else {
-        display_bad_results(game, failures)
-    }
+#[derive(Default)]
+pub struct VerifyResultsSummary {
+    pub successes: usize,
+    pub total: usize,
+    pub missing: usize,
+    pub bad: usize,
+    pub extra: usize,
+    pub errors: usize,
+    pub acknowledged: usize,
 }
 
-pub fn display_bad_results(game: &str, failures: &[VerifyFailure]) {
-    if !failures.is_empty() {
-        use std::io::{stdout, Write};
+impl VerifyResultsSummary {
+    pub fn new<'r, 'f: 'r, I>(results: I) -> Self
+    where
+        I: IntoIterator<Item = &'r Vec<VerifyFailure<'f>>>,
+    {
+        let mut summary = Self::default();
+
+        for failures in results {
+            summary.total += 1;
+
+            if failures.is_empty() {
+                summary.successes += 1;
+            } else {
+                record_outcome(Outcome::Failures);
+            }
 
-        // ensure results are generated as a unit
-        let stdout = stdout();
-        let mut handle = stdout.lock();
-        for failure in failures {
-            writeln!(&mut handle, "{failure} : {game}").unwrap();
+            for failure in failures {
+                match failure {
+                    VerifyFailure::Missing { .. }
+                    | VerifyFailure::MissingUndumped { .. }
+                    | VerifyFailure::MissingParent { .. }
+                    | VerifyFailure::MissingBios { .. }
+                    | VerifyFailure::MissingDevice { .. } => summary.missing += 1,
+                    VerifyFailure::Bad { .. }
+                    | VerifyFailure::BadParent { .. }
+                    | VerifyFailure::Corrupt { .. } => summary.bad += 1,
+                    VerifyFailure::Extra { .. } => summary.extra += 1,
+                    VerifyFailure::Error { .. } => {
+                        record_outcome(Outcome::Errors);
+                        summary.errors += 1
+                    }
+                    VerifyFailure::NotCanonical { .. } => {}
+                }
+            }
         }
+
+        summary
     }
 }
 
-#[derive(Default)]
-pub struct VerifyResultsSummary {
-    pub successes: usize,
-    pub total: usize,
+// how a run went overall, from best to worst
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    Ok = 0,
+    Failures = 1,
+    Errors = 2,
+}
+
+static OUTCOME: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(Outcome::Ok as u8);
+
+// keeps the worst outcome seen so far
+#[inline]
+pub fn record_outcome(outcome: Outcome) {
+    OUTCOME.fetch_max(outcome as u8, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[inline]
+pub fn outcome() -> Outcome {
+    match OUTCOME.load(std::sync::atomic::Ordering::Relaxed) {
+        0 => Outcome::Ok,
+        1 => Outcome::Failures,
+        _ => Outcome::Errors,
+    }
 }
 
 impl fmt::Display for VerifyResultsSummary {
@@ -1632,37 +5118,14 @@ impl std::ops::AddAssign for VerifyResultsSummary {
     fn add_assign(&mut self, rhs: Self) {
         self.successes += rhs.successes;
         self.total += rhs.total;
+        self.missing += rhs.missing;
+        self.bad += rhs.bad;
+        self.extra += rhs.extra;
+        self.errors += rhs.errors;
+        self.acknowledged += rhs.acknowledged;
     }
 }
 
-pub fn display_dat_results(
-    table: &mut prettytable::Table,
-    dat: &crate::dat::DatFile,
-    results: BTreeMap<&str, Vec<VerifyFailure>>,
-    failures_only: bool,
-) -> VerifyResultsSummary {
-    use prettytable::{cell, row};
-
-    let summary = VerifyResultsSummary {
-        successes: results.values().filter(|v| v.is_empty()).count(),
-        total: results.len(),
-    };
-
-    if failures_only {
-        for (name, failures) in results {
-            display_bad_results(name, &failures);
-        }
-    } else {
-        for (name, failures) in results {
-            display_all_results(name, &failures);
-        }
-    }
-
-    table.add_row(row![r->summary.total, r->summary.successes, dat.name()]);
-
-    summary
-}
-
 #[inline]
 pub fn parse_int(s: &str) -> Result<u64, ParseIntError> {
     // MAME's use of integer values is a horror show
@@ -1680,5 +5143,65 @@ pub fn parse_int(s: &str) -> Result<u64, ParseIntError> {
 }
 
 // an error that never happens
+#[cfg(feature = "native")]
 #[derive(Debug)]
 enum Never {}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    fn stored_zip(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in members {
+            zip.start_file(
+                *name,
+                zip::write::FileOptions::default()
+                    .compression_method(zip::CompressionMethod::Stored),
+            )
+            .unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn spool_in_memory() {
+        let data = [0x5a; SPOOL_MEMORY_LIMIT as usize];
+        let mut spooled = Spooled::new(&data[..]).unwrap();
+        assert!(matches!(spooled, Spooled::Memory(_)));
+
+        let mut read = Vec::new();
+        spooled.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    fn spool_to_disk() {
+        let inner = stored_zip(&[("game.bin", &[0x5a; 1024])]);
+        let outer = stored_zip(&[("inner.zip", &inner)]);
+
+        let mut outer = zip::ZipArchive::new(Cursor::new(outer)).unwrap();
+        let spooled = Spooled::new(outer.by_index(0).unwrap()).unwrap();
+
+        let spool = match &spooled {
+            Spooled::File(spool) => spool.path.clone(),
+            Spooled::Memory(_) => panic!("archive wasn't spooled to disk"),
+        };
+        assert!(spool.is_file());
+
+        // the spooled archive is as seekable as the original
+        let mut inner = zip::ZipArchive::new(spooled).unwrap();
+        let mut data = Vec::new();
+        inner
+            .by_name("game.bin")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, [0x5a; 1024]);
+
+        drop(inner);
+        assert!(!spool.exists());
+    }
+}
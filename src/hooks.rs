@@ -0,0 +1,159 @@
+use crate::game::{PartId, VerifyFailure};
+use crate::sink::ProgressSink;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const HOOKS_CONFIG_FILE: &str = "hooks.toml";
+
+// hooks are shell commands which receive an event's JSON on stdin, like:
+//
+// verify-complete = ["notify-send \"emuman\" \"verification finished\""]
+// corruption-detected = ["logger -t emuman"]
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct HookConfig {
+    #[serde(default)]
+    verify_complete: Vec<String>,
+    #[serde(default)]
+    corruption_detected: Vec<String>,
+    #[serde(default)]
+    add_finished: Vec<String>,
+}
+
+impl HookConfig {
+    fn new(sink: &dyn ProgressSink) -> Option<Self> {
+        use std::io::Read;
+
+        let mut toml = Vec::new();
+
+        std::fs::File::open(Self::location())
+            .and_then(|mut f| f.read_to_end(&mut toml))
+            .ok()?;
+
+        match toml::from_slice(&toml) {
+            Ok(config) => Some(config),
+            Err(err) => {
+                sink.warning(&format!("{} : {}", Self::location().display(), err));
+                None
+            }
+        }
+    }
+
+    fn location() -> PathBuf {
+        crate::data_dir().join(HOOKS_CONFIG_FILE)
+    }
+
+    fn commands(&self, event: &Event) -> &[String] {
+        match event {
+            Event::VerifyComplete { .. } => &self.verify_complete,
+            Event::CorruptionDetected { .. } => &self.corruption_detected,
+            Event::AddFinished { .. } => &self.add_finished,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event<'e> {
+    VerifyComplete {
+        name: &'e str,
+        root: &'e Path,
+        tested: usize,
+        ok: usize,
+    },
+    CorruptionDetected {
+        name: &'e str,
+        game: &'e str,
+        path: &'e Path,
+        part: PartId<'e>,
+    },
+    AddFinished {
+        name: &'e str,
+        root: &'e Path,
+        added: usize,
+        ok: usize,
+    },
+}
+
+impl Event<'_> {
+    fn fire_with(&self, config: &HookConfig, sink: &dyn ProgressSink) {
+        let commands = config.commands(self);
+
+        if !commands.is_empty() {
+            let json = serde_json::to_vec(self).expect("unable to serialize hook event");
+
+            for command in commands {
+                if let Err(err) = run_hook(command, &json) {
+                    sink.warning(&format!("hook \"{}\" : {}", command, err));
+                }
+            }
+        }
+    }
+}
+
+pub fn run_hook(command: &str, stdin: &[u8]) -> Result<(), std::io::Error> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()?
+    } else {
+        Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()?
+    };
+
+    // a hook is free to ignore its input entirely
+    if let Some(mut child_stdin) = child.stdin.take() {
+        let _ = child_stdin.write_all(stdin);
+    }
+
+    let status = child.wait()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("exited with {}", status)))
+    }
+}
+
+// fires corruption-detected for each bad part
+// followed by the given completion event,
+// reporting hooks which fail to sink
+pub fn fire_results(
+    name: &str,
+    results: &BTreeMap<&str, Vec<VerifyFailure>>,
+    complete: Event,
+    sink: &dyn ProgressSink,
+) {
+    if let Some(config) = HookConfig::new(sink) {
+        for (game, failures) in results {
+            for failure in failures {
+                if let VerifyFailure::Bad {
+                    path,
+                    name: part_name,
+                    expected,
+                    ..
+                } = failure
+                {
+                    Event::CorruptionDetected {
+                        name,
+                        game,
+                        path,
+                        part: PartId::new(game, part_name, expected),
+                    }
+                    .fire_with(&config, sink);
+                }
+            }
+        }
+
+        complete.fire_with(&config, sink);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::dat::escape;
+use crate::game::{Game, VerifyFailure};
+use crate::Error;
+use std::io::Write;
+use std::path::PathBuf;
+
+pub const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table.summary td { padding: 0.2em 1em 0.2em 0; }
+progress { width: 20em; }
+details { margin: 0.2em 0; }
+summary { cursor: pointer; }
+.ok { color: #2a7d2a; }
+.failed { color: #b02020; }
+.best { color: #a06000; }
+table.failures { border-collapse: collapse; margin: 0.5em 0 0.5em 2em; }
+table.failures td { border-bottom: 1px solid #ddd; padding: 0.2em 0.8em; font-family: monospace; }
+";
+
+struct GameResult {
+    name: String,
+    description: String,
+    best_available: bool,
+    // each failure's kind, description and hint, if any
+    failures: Vec<(&'static str, String, Option<String>)>,
+}
+
+// a self-contained HTML page of a verify run's results
+pub struct HtmlReport {
+    path: PathBuf,
+    roots: Vec<PathBuf>,
+    games: Vec<GameResult>,
+    parts: usize,
+    failed_parts: usize,
+}
+
+impl HtmlReport {
+    #[inline]
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            roots: Vec::new(),
+            games: Vec::new(),
+            parts: 0,
+            failed_parts: 0,
+        }
+    }
+
+    #[inline]
+    pub fn add_root(&mut self, root: PathBuf) {
+        self.roots.push(root);
+    }
+
+    pub fn add_results<H>(
+        &mut self,
+        name: &str,
+        game: Option<&Game>,
+        failures: &[VerifyFailure],
+        hint: H,
+    ) where
+        H: Fn(&VerifyFailure) -> Option<String>,
+    {
+        if let Some(game) = game {
+            self.parts += game.parts.len();
+        }
+
+        self.failed_parts += failures
+            .iter()
+            .filter(|failure| {
+                matches!(
+                    failure,
+                    VerifyFailure::Missing { .. }
+                        | VerifyFailure::MissingUndumped { .. }
+                        | VerifyFailure::Bad { .. }
+                )
+            })
+            .count();
+
+        self.games.push(GameResult {
+            name: name.to_owned(),
+            description: game.map(|g| g.description.clone()).unwrap_or_default(),
+            best_available: game.map(|g| g.has_bad_dumps()).unwrap_or(false),
+            failures: failures
+                .iter()
+                .map(|failure| (failure.kind(), failure.to_string(), hint(failure)))
+                .collect(),
+        });
+    }
+
+    pub fn write(mut self, description: &str) -> Result<(), Error> {
+        self.games.sort_unstable_by(|x, y| x.name.cmp(&y.name));
+
+        let total = self.games.len();
+        let ok = self.games.iter().filter(|g| g.failures.is_empty()).count();
+
+        let mut w = std::io::BufWriter::new(std::fs::File::create(&self.path)?);
+
+        writeln!(w, "<!DOCTYPE html>")?;
+        writeln!(w, "<html lang=\"en\">")?;
+        writeln!(w, "<head>")?;
+        writeln!(w, "<meta charset=\"utf-8\">")?;
+        writeln!(w, "<title>{} verify report</title>", escape(description))?;
+        writeln!(w, "<style>\n{}</style>", STYLE)?;
+        writeln!(w, "</head>")?;
+        writeln!(w, "<body>")?;
+        writeln!(w, "<h1>{}</h1>", escape(description))?;
+
+        writeln!(w, "<table class=\"summary\">")?;
+        writeln!(w, "<tr><td>Verified</td><td>{}</td></tr>", utc_now())?;
+        for root in self.roots.iter() {
+            writeln!(
+                w,
+                "<tr><td>Root</td><td>{}</td></tr>",
+                escape(&root.display().to_string())
+            )?;
+        }
+        writeln!(
+            w,
+            "<tr><td>Games OK</td><td>{} of {} ({})</td></tr>",
+            ok,
+            total,
+            percent(ok, total)
+        )?;
+        writeln!(
+            w,
+            "<tr><td>Parts OK</td><td>{} of {} ({})</td></tr>",
+            self.parts.saturating_sub(self.failed_parts),
+            self.parts,
+            percent(self.parts.saturating_sub(self.failed_parts), self.parts)
+        )?;
+        writeln!(w, "</table>")?;
+        writeln!(
+            w,
+            "<p><progress max=\"{}\" value=\"{}\"></progress></p>",
+            total.max(1),
+            ok
+        )?;
+
+        let (failed, succeeded): (Vec<_>, Vec<_>) =
+            self.games.iter().partition(|g| !g.failures.is_empty());
+
+        writeln!(w, "<h2>Failures ({})</h2>", failed.len())?;
+        for game in failed {
+            writeln!(
+                w,
+                "<details><summary><span class=\"failed\">FAILED</span> {} <small>{}</small> ({})</summary>",
+                escape(&game.name),
+                escape(&game.description),
+                game.failures.len()
+            )?;
+            writeln!(w, "<table class=\"failures\">")?;
+            for (kind, failure, hint) in game.failures.iter() {
+                writeln!(
+                    w,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    kind,
+                    escape(failure),
+                    escape(hint.as_deref().unwrap_or_default())
+                )?;
+            }
+            writeln!(w, "</table>")?;
+            writeln!(w, "</details>")?;
+        }
+
+        writeln!(w, "<h2>OK ({})</h2>", succeeded.len())?;
+        writeln!(w, "<details><summary>show all</summary>")?;
+        writeln!(w, "<ul>")?;
+        for game in succeeded {
+            writeln!(
+                w,
+                "<li><span class=\"{}\">{}</span> {} <small>{}</small></li>",
+                if game.best_available { "best" } else { "ok" },
+                if game.best_available {
+                    "BEST AVAILABLE"
+                } else {
+                    "OK"
+                },
+                escape(&game.name),
+                escape(&game.description)
+            )?;
+        }
+        writeln!(w, "</ul>")?;
+        writeln!(w, "</details>")?;
+
+        writeln!(w, "</body>")?;
+        writeln!(w, "</html>")?;
+
+        w.flush().map_err(Error::IO)
+    }
+}
+
+#[inline]
+pub fn percent(count: usize, total: usize) -> String {
+    match total {
+        0 => "100%".to_owned(),
+        total => format!("{:.1}%", count as f64 * 100.0 / total as f64),
+    }
+}
+
+// the current time as "YYYY-MM-DD HH:MM:SS UTC"
+pub fn utc_now() -> String {
+    utc_time(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    )
+}
+
+// seconds since the epoch as a UTC date and time
+pub fn utc_time(secs: u64) -> String {
+    // days since the epoch to a civil date
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
@@ -1,16 +1,19 @@
+use crate::sink::{ProgressSink, Task, Total};
 use crate::Error;
-use indicatif::ProgressBar;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 const RETRIES: u32 = 10;
 
-pub fn fetch_url_data(source: &str) -> Result<Box<[u8]>, Error> {
+pub fn fetch_url_data(source: &str, sink: &dyn ProgressSink) -> Result<Box<[u8]>, Error> {
     let mut data = Vec::new();
-    retry(|| fetch(source, &mut data), RETRIES).map(|()| data.into_boxed_slice())
+    retry(|| fetch(source, &mut data, sink), RETRIES).map(|()| data.into_boxed_slice())
 }
 
-fn fetch(source: &str, zip_data: &mut Vec<u8>) -> Result<(), Error> {
+fn fetch(source: &str, zip_data: &mut Vec<u8>, sink: &dyn ProgressSink) -> Result<(), Error> {
     use attohttpc::header::CONTENT_LENGTH;
-    use std::io::Read;
 
     let builder = if zip_data.is_empty() {
         attohttpc::get(source)
@@ -25,9 +28,54 @@ fn fetch(source: &str, zip_data: &mut Vec<u8>) -> Result<(), Error> {
                 .and_then(|v| v.to_str().ok())
                 .and_then(|s| s.parse::<u64>().ok());
 
-            progress_bar(source, length)
-                .wrap_read(reader)
-                .read_to_end(zip_data)
+            Task::start(
+                sink,
+                source,
+                length.map(Total::Bytes).unwrap_or(Total::Unknown),
+            )
+            .wrap_read(reader)
+            .read_to_end(zip_data)
+            .map(|_| ())
+            .map_err(Error::IO)
+        }
+        (code, _, _) => Err(Error::HttpCode(code)),
+    }
+}
+
+// fetches source to the file at path, resuming from
+// whatever the file already contains, with reads throttled by limiter
+pub fn fetch_url_file(
+    source: &str,
+    path: &Path,
+    limiter: Option<&RateLimiter>,
+    retries: u32,
+) -> Result<(), Error> {
+    retry(|| fetch_file(source, path, limiter), retries)
+}
+
+fn fetch_file(source: &str, path: &Path, limiter: Option<&RateLimiter>) -> Result<(), Error> {
+    use attohttpc::StatusCode;
+    use std::fs::OpenOptions;
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let resume_from = file.metadata()?.len();
+
+    let builder = if resume_from == 0 {
+        attohttpc::get(source)
+    } else {
+        attohttpc::get(source).header("Range", format!("bytes={}-", resume_from))
+    };
+
+    match builder.send()?.split() {
+        // the file is already complete
+        (StatusCode::RANGE_NOT_SATISFIABLE, _, _) if resume_from > 0 => Ok(()),
+        (code, _, reader) if code.is_success() => {
+            // servers which ignore ranges send the whole file again
+            if code != StatusCode::PARTIAL_CONTENT {
+                file.set_len(0)?;
+            }
+
+            std::io::copy(&mut Throttled { reader, limiter }, &mut file)
                 .map(|_| ())
                 .map_err(Error::IO)
         }
@@ -35,6 +83,173 @@ fn fetch(source: &str, zip_data: &mut Vec<u8>) -> Result<(), Error> {
     }
 }
 
+// the length of source, if its server honors range requests
+// so that it can be read piecemeal rather than fetched whole
+pub fn remote_len(source: &str) -> Result<Option<u64>, Error> {
+    use attohttpc::header::{HeaderName, ACCEPT_RANGES, CONTENT_LENGTH};
+
+    let response = retry(|| attohttpc::head(source).send(), RETRIES)?;
+
+    if !response.is_success() {
+        return Err(Error::HttpCode(response.status()));
+    }
+
+    let header = |name: HeaderName| response.headers().get(name).and_then(|v| v.to_str().ok());
+
+    Ok(match header(ACCEPT_RANGES) {
+        Some("bytes") => header(CONTENT_LENGTH).and_then(|s| s.parse().ok()),
+        _ => None,
+    })
+}
+
+// seeking no further ahead than this reads through
+// the current response instead of starting another
+const SKIP_AHEAD: u64 = 64 * 1024;
+
+// a file whose server honors range requests, where reads
+// continue a single response until a seek moves elsewhere
+pub struct RemoteFile<'u> {
+    source: &'u str,
+    len: u64,
+    pos: u64,
+    response: Option<attohttpc::ResponseReader>,
+}
+
+impl<'u> RemoteFile<'u> {
+    #[inline]
+    pub fn new(source: &'u str, len: u64) -> Self {
+        Self {
+            source,
+            len,
+            pos: 0,
+            response: None,
+        }
+    }
+
+    fn request(&self) -> Result<attohttpc::ResponseReader, Error> {
+        use attohttpc::StatusCode;
+
+        match attohttpc::get(self.source)
+            .header("Range", format!("bytes={}-", self.pos))
+            .send()?
+            .split()
+        {
+            (StatusCode::PARTIAL_CONTENT, _, reader) => Ok(reader),
+            // servers which ignore ranges are fine when starting out
+            (code, _, reader) if code.is_success() && self.pos == 0 => Ok(reader),
+            (code, _, _) => Err(Error::HttpCode(code)),
+        }
+    }
+}
+
+impl Read for RemoteFile<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::io::{Error as IoError, ErrorKind};
+
+        if buf.is_empty() || self.pos >= self.len {
+            return Ok(0);
+        }
+
+        if self.response.is_none() {
+            self.response = Some(retry(|| self.request(), RETRIES).map_err(|err| match err {
+                Error::IO(err) => err,
+                err => IoError::other(err.to_string()),
+            })?);
+        }
+
+        let result = match self.response.as_mut().map(|response| response.read(buf)) {
+            Some(Ok(0)) => Err(IoError::new(
+                ErrorKind::UnexpectedEof,
+                format!("{} : connection closed early", self.source),
+            )),
+            Some(result) => result,
+            None => Ok(0),
+        };
+
+        match result {
+            Ok(bytes) => {
+                self.pos += bytes as u64;
+                Ok(bytes)
+            }
+            Err(err) => {
+                // the next read starts a fresh response
+                self.response = None;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Seek for RemoteFile<'_> {
+    fn seek(&mut self, from: SeekFrom) -> std::io::Result<u64> {
+        let pos = match from {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek out of range")
+        })?;
+
+        match pos.checked_sub(self.pos) {
+            Some(0) => {}
+            Some(ahead) if ahead <= SKIP_AHEAD && pos <= self.len && self.response.is_some() => {
+                std::io::copy(&mut self.take(ahead), &mut std::io::sink())?;
+            }
+            _ => {
+                self.pos = pos;
+                self.response = None;
+            }
+        }
+
+        Ok(self.pos)
+    }
+}
+
+// limits the combined throughput of everything sharing it
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    state: Mutex<(Instant, u64)>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            state: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    // waits until transferring another "bytes" keeps us under our limit
+    fn consume(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap();
+        let (start, transferred) = &mut *state;
+
+        *transferred += bytes as u64;
+
+        let due = Duration::from_secs_f64(*transferred as f64 / self.bytes_per_sec as f64);
+
+        if let Some(wait) = due.checked_sub(start.elapsed()) {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+struct Throttled<'l, R> {
+    reader: R,
+    limiter: Option<&'l RateLimiter>,
+}
+
+impl<'l, R: Read> Read for Throttled<'l, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let bytes = self.reader.read(buf)?;
+        if let Some(limiter) = self.limiter {
+            limiter.consume(bytes);
+        }
+        Ok(bytes)
+    }
+}
+
 #[inline]
 fn retry<T, E, F>(mut f: F, mut retries: u32) -> Result<T, E>
 where
@@ -48,17 +263,3 @@ where
         }
     }
 }
-
-fn progress_bar(source: &str, total_bytes: Option<u64>) -> ProgressBar {
-    use indicatif::ProgressStyle;
-
-    match total_bytes {
-        Some(total_bytes) => ProgressBar::new(total_bytes).with_style(
-            ProgressStyle::default_bar().template("{wide_msg} {bytes} ({bytes_per_sec}) {eta}"),
-        ),
-        None => ProgressBar::new_spinner().with_style(
-            ProgressStyle::default_spinner().template("{wide_msg} {bytes} ({bytes_per_sec})"),
-        ),
-    }
-    .with_message(source.to_owned())
-}
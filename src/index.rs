@@ -0,0 +1,492 @@
+use crate::game::{Game, GameDb, Part};
+use crate::sink::ProgressSink;
+use crate::Error;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// interactive queries like "parts", "why" and "identify --lookup"
+// only ever need a handful of games or parts, so rather than
+// deserialize whole databases each time, they're answered from tables
+// built the first time they're needed after those databases change
+//
+// each table is a header noting what it was built from,
+// then fixed-width records sorted by key, then the CBOR values
+// those records point to, so finding a value is a binary search by seeking
+
+// bumped whenever what's indexed changes,
+// so tables from older versions are simply rebuilt
+const VERSION: u32 = 1;
+
+const KEY_LEN: usize = 16;
+const RECORD_LEN: usize = KEY_LEN + 8 + 4;
+
+type Key = [u8; KEY_LEN];
+
+// kinds of key, so a game and a device can share a name
+const GAME: &str = "game";
+const PART: &str = "part";
+const USERS: &str = "users";
+const DESCRIPTION: &str = "description";
+
+const LOOKUP: &str = "lookup";
+
+fn key(kind: &str, bytes: &[u8]) -> Key {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(kind.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(bytes);
+
+    let mut key = Key::default();
+    key.copy_from_slice(&hasher.finalize().as_bytes()[..KEY_LEN]);
+    key
+}
+
+#[inline]
+fn part_key(part: &Part) -> Result<Key, Error> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(part, &mut bytes).map_err(Error::CborWrite)?;
+    Ok(key(PART, &bytes))
+}
+
+fn entry<V: Serialize>(key: Key, value: V) -> Result<(Key, Vec<u8>), Error> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(&value, &mut bytes).map_err(Error::CborWrite)?;
+    Ok((key, bytes))
+}
+
+// size, and modification time in seconds and nanoseconds
+type Stamp = (u64, u64, u32);
+
+// tables are current while every database they were built from
+// has the same size and modification time, or is still missing
+#[derive(PartialEq, Eq, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    sources: Vec<(PathBuf, Option<Stamp>)>,
+}
+
+impl Header {
+    fn new(sources: Vec<PathBuf>) -> Self {
+        fn stamp(path: &Path) -> Option<Stamp> {
+            let metadata = path.metadata().ok()?;
+            let modified = metadata
+                .modified()
+                .ok()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()?;
+
+            Some((metadata.len(), modified.as_secs(), modified.subsec_nanos()))
+        }
+
+        Self {
+            version: VERSION,
+            sources: sources
+                .into_iter()
+                .map(|path| {
+                    let stamp = stamp(&path);
+                    (path, stamp)
+                })
+                .collect(),
+        }
+    }
+}
+
+struct Table {
+    file: File,
+    records: u64,
+    count: u64,
+}
+
+impl Table {
+    // the table at path, if it was built from its sources as they are now
+    fn open(path: &Path, header: &Header) -> Option<Self> {
+        let mut file = File::open(path).ok()?;
+        let header_len = read_u64(&mut file)?;
+
+        if ciborium::de::from_reader::<Header, _>((&mut file).take(header_len)).ok()? != *header {
+            return None;
+        }
+
+        let count = read_u64(&mut file)?;
+
+        Some(Self {
+            file,
+            records: 8 + header_len + 8,
+            count,
+        })
+    }
+
+    fn write(path: &Path, header: &Header, mut entries: Vec<(Key, Vec<u8>)>) -> Result<(), Error> {
+        use std::io::BufWriter;
+
+        entries.sort_unstable_by_key(|(key, _)| *key);
+
+        let mut header_bytes = Vec::new();
+        ciborium::ser::into_writer(header, &mut header_bytes).map_err(Error::CborWrite)?;
+
+        let mut offset = (8 + header_bytes.len() + 8 + entries.len() * RECORD_LEN) as u64;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // built aside and moved into place,
+        // so a table is never seen half-written
+        let partial = path.with_extension("partial");
+        let mut w = BufWriter::new(File::create(&partial)?);
+
+        w.write_all(&(header_bytes.len() as u64).to_le_bytes())?;
+        w.write_all(&header_bytes)?;
+        w.write_all(&(entries.len() as u64).to_le_bytes())?;
+
+        for (key, value) in entries.iter() {
+            w.write_all(key)?;
+            w.write_all(&offset.to_le_bytes())?;
+            w.write_all(&(value.len() as u32).to_le_bytes())?;
+            offset += value.len() as u64;
+        }
+
+        for (_, value) in entries.iter() {
+            w.write_all(value)?;
+        }
+
+        w.flush()?;
+        drop(w);
+
+        std::fs::rename(partial, path).map_err(Error::IO)
+    }
+
+    fn get<V: DeserializeOwned>(&mut self, key: &Key) -> Result<Option<V>, Error> {
+        use std::cmp::Ordering;
+
+        let mut record = [0; RECORD_LEN];
+        let (mut low, mut high) = (0, self.count);
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+
+            self.file
+                .seek(SeekFrom::Start(self.records + mid * RECORD_LEN as u64))?;
+            self.file.read_exact(&mut record)?;
+
+            match record[..KEY_LEN].cmp(&key[..]) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => {
+                    let mut offset = [0; 8];
+                    let mut len = [0; 4];
+                    offset.copy_from_slice(&record[KEY_LEN..KEY_LEN + 8]);
+                    len.copy_from_slice(&record[KEY_LEN + 8..]);
+
+                    self.file
+                        .seek(SeekFrom::Start(u64::from_le_bytes(offset)))?;
+
+                    return ciborium::de::from_reader(std::io::BufReader::new(
+                        (&mut self.file).take(u32::from_le_bytes(len).into()),
+                    ))
+                    .map(Some)
+                    .map_err(|_| {
+                        Error::IO(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "corrupt index entry",
+                        ))
+                    });
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+fn read_u64(r: &mut File) -> Option<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf).ok()?;
+    Some(u64::from_le_bytes(buf))
+}
+
+// either a current table, or the whole database
+// when the table needed rebuilding anyway
+enum Indexed<D> {
+    Table(Table),
+    Whole(D),
+}
+
+fn indexed<D, L, E>(
+    name: &str,
+    sources: Vec<PathBuf>,
+    load: L,
+    entries: E,
+    sink: &dyn ProgressSink,
+) -> Result<Indexed<D>, Error>
+where
+    L: FnOnce() -> Result<D, Error>,
+    E: FnOnce(&D) -> Result<Vec<(Key, Vec<u8>)>, Error>,
+{
+    let path = crate::named_db_path(crate::DIR_INDEX, name);
+    let header = Header::new(sources);
+
+    if let Some(table) = Table::open(&path, &header) {
+        return Ok(Indexed::Table(table));
+    }
+
+    let db = load()?;
+
+    // a table which can't be saved only means
+    // loading the whole database again next time
+    if !crate::game::is_read_only() {
+        if let Err(err) = entries(&db).and_then(|entries| Table::write(&path, &header, entries)) {
+            sink.warning(&format!("unable to save index : {}", err));
+        }
+    }
+
+    Ok(Indexed::Whole(db))
+}
+
+// a database of the given MAME game, its parents and its devices,
+// along with every game sharing its parts or devices if sharing
+pub fn mame_db(game: &str, sharing: bool, sink: &dyn ProgressSink) -> Result<GameDb, Error> {
+    let mut table = match indexed(
+        crate::MAME,
+        vec![crate::named_db_dir(crate::DB_MAME)],
+        || crate::read_game_db::<GameDb>(crate::MAME, crate::DB_MAME),
+        mame_entries,
+        sink,
+    )? {
+        Indexed::Table(table) => table,
+        Indexed::Whole(db) => return Ok(db),
+    };
+
+    let mut games: HashMap<String, Game> = HashMap::default();
+    let mut pending = vec![game.to_owned()];
+
+    while let Some(name) = pending.pop() {
+        if games.contains_key(&name) {
+            continue;
+        }
+
+        if let Some(game) = table.get::<Game>(&key(GAME, name.as_bytes()))? {
+            pending.extend(game.devices.iter().cloned());
+            pending.extend(game.romof.iter().cloned());
+            games.insert(name, game);
+        }
+    }
+
+    if sharing {
+        let mut sharers = BTreeSet::new();
+
+        if let Some(game) = games.get(game) {
+            for part in game.parts.iter().map(|(_, part)| part) {
+                sharers.extend(
+                    table
+                        .get::<Vec<String>>(&part_key(part)?)?
+                        .into_iter()
+                        .flatten(),
+                );
+            }
+        }
+
+        for device in games.keys() {
+            sharers.extend(
+                table
+                    .get::<Vec<String>>(&key(USERS, device.as_bytes()))?
+                    .into_iter()
+                    .flatten(),
+            );
+        }
+
+        for name in sharers {
+            if let Entry::Vacant(entry) = games.entry(name) {
+                if let Some(game) = table.get::<Game>(&key(GAME, entry.key().as_bytes()))? {
+                    entry.insert(game);
+                }
+            }
+        }
+    }
+
+    let description = table
+        .get::<String>(&key(DESCRIPTION, &[]))?
+        .unwrap_or_default();
+
+    Ok(GameDb::new(description, games))
+}
+
+fn mame_entries(db: &GameDb) -> Result<Vec<(Key, Vec<u8>)>, Error> {
+    let mut holders: HashMap<&Part, Vec<&str>> = HashMap::default();
+    let mut users: HashMap<&str, Vec<&str>> = HashMap::default();
+
+    for game in db.games_iter() {
+        for (_, part) in game.parts.iter() {
+            holders.entry(part).or_default().push(&game.name);
+        }
+        for device in game.devices.iter() {
+            users.entry(device).or_default().push(&game.name);
+        }
+    }
+
+    let mut entries = vec![entry(key(DESCRIPTION, &[]), db.description())?];
+
+    for game in db.games_iter() {
+        entries.push(entry(key(GAME, game.name.as_bytes()), game)?);
+    }
+
+    for (part, mut games) in holders {
+        games.dedup();
+        entries.push(entry(part_key(part)?, games)?);
+    }
+
+    for (device, games) in users {
+        entries.push(entry(key(USERS, device.as_bytes()), games)?);
+    }
+
+    Ok(entries)
+}
+
+// a database of only the given game from the software list
+pub fn software_list_db(
+    software_list: &str,
+    game: &str,
+    sink: &dyn ProgressSink,
+) -> Result<GameDb, Error> {
+    let mut table = match indexed(
+        &format!("{}:{}", crate::DIR_SL, software_list),
+        vec![crate::named_db_path(crate::DIR_SL, software_list)],
+        || crate::read_named_db::<GameDb>(crate::MESS, crate::DIR_SL, software_list),
+        |db| {
+            db.games_iter()
+                .map(|game| entry(key(GAME, game.name.as_bytes()), game))
+                .chain(std::iter::once(entry(
+                    key(DESCRIPTION, &[]),
+                    db.description(),
+                )))
+                .collect()
+        },
+        sink,
+    )? {
+        Indexed::Table(table) => table,
+        Indexed::Whole(db) => return Ok(db),
+    };
+
+    let description = table
+        .get::<String>(&key(DESCRIPTION, &[]))?
+        .unwrap_or_default();
+
+    Ok(GameDb::new(
+        description,
+        table
+            .get::<Game>(&key(GAME, game.as_bytes()))?
+            .into_iter()
+            .map(|game| (game.name.clone(), game))
+            .collect(),
+    ))
+}
+
+// category, system, game and part name
+pub type Identifier = [String; 4];
+
+// every database's parts, for finding where parts belong
+pub struct Lookup(Indexed<HashMap<Part, BTreeSet<Identifier>>>);
+
+impl Lookup {
+    pub fn open(sink: &dyn ProgressSink) -> Result<Self, Error> {
+        // every database is a source, so those added later are noticed
+        let mut sources = vec![crate::named_db_dir(crate::DB_MAME)];
+
+        for dir in [
+            crate::DIR_SL,
+            crate::DIR_EXTRA,
+            crate::DIR_NOINTRO,
+            crate::DIR_REDUMP,
+        ]
+        .iter()
+        {
+            let mut files = std::fs::read_dir(crate::named_db_dir(dir))
+                .map(|dir| {
+                    dir.filter_map(|e| e.ok().map(|e| e.path()))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            files.sort_unstable();
+            sources.push(crate::named_db_dir(dir));
+            sources.extend(files);
+        }
+
+        indexed(
+            LOOKUP,
+            sources,
+            load_lookup,
+            |lookup| {
+                lookup
+                    .iter()
+                    .map(|(part, identifiers)| entry(part_key(part)?, identifiers))
+                    .collect()
+            },
+            sink,
+        )
+        .map(Self)
+    }
+
+    pub fn get(&mut self, part: &Part) -> Result<BTreeSet<Identifier>, Error> {
+        match &mut self.0 {
+            Indexed::Table(table) => table
+                .get(&part_key(part)?)
+                .map(|identifiers| identifiers.unwrap_or_default()),
+            Indexed::Whole(lookup) => Ok(lookup.get(part).cloned().unwrap_or_default()),
+        }
+    }
+}
+
+fn load_lookup() -> Result<HashMap<Part, BTreeSet<Identifier>>, Error> {
+    use crate::dat::DatFile;
+    use std::collections::BTreeMap;
+
+    let mut lookup: HashMap<Part, BTreeSet<Identifier>> = HashMap::default();
+
+    let mut add = |part: &Part, category: &str, system: &str, game: &str, rom: &str| {
+        lookup.entry(part.clone()).or_default().insert([
+            category.to_owned(),
+            system.to_owned(),
+            game.to_owned(),
+            rom.to_owned(),
+        ]);
+    };
+
+    let mame_db: GameDb = crate::read_game_db(crate::MAME, crate::DB_MAME).unwrap_or_default();
+    let mess_db: BTreeMap<String, GameDb> = crate::read_collected_dbs(crate::DIR_SL);
+
+    let dat_parts: [(&str, BTreeMap<String, DatFile>); 3] = [
+        ("extra", crate::read_collected_dbs(crate::DIR_EXTRA)),
+        ("nointro", crate::read_collected_dbs(crate::DIR_NOINTRO)),
+        ("redump", crate::read_collected_dbs(crate::DIR_REDUMP)),
+    ];
+
+    for game in mame_db.games_iter() {
+        for (rom, part) in game.parts.iter() {
+            add(part, "mame", "", &game.name, rom);
+        }
+    }
+
+    for (system, game_db) in mess_db.iter() {
+        for game in game_db.games_iter() {
+            for (rom, part) in game.parts.iter() {
+                add(part, "mess", system, &game.name, rom);
+            }
+        }
+    }
+
+    for (category, datfiles) in &dat_parts {
+        for (system, datfile) in datfiles.iter() {
+            for (game, parts) in datfile.game_parts() {
+                for (rom, part) in parts.iter() {
+                    add(part, category, system, game, rom);
+                }
+            }
+        }
+    }
+
+    Ok(lookup)
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+// long-form game descriptions imported from history.dat and mameinfo.dat
+#[derive(Default, Serialize, Deserialize)]
+pub struct InfoDb {
+    // game name -> section -> text
+    games: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl InfoDb {
+    // imports entries from history.dat or mameinfo.dat files, like:
+    //
+    // $info=pacman,puckman,
+    // $bio
+    //
+    // Pac-Man (c) 1980 Namco.
+    // $end
+    //
+    // where software list entries are named by their list,
+    // as in "$nes=smb,", and stored as "nes/smb"
+    //
+    // returns the number of entries imported
+    pub fn import<R: BufRead>(&mut self, r: R) -> Result<usize, std::io::Error> {
+        let mut names: Vec<String> = Vec::new();
+        let mut section: Option<(String, String)> = None;
+        let mut imported = 0;
+
+        for line in r.split(b'\n') {
+            let line = line?;
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches('\r');
+
+            match section.as_mut() {
+                Some(_) if line == "$end" => {
+                    if let Some((name, text)) = section.take() {
+                        let text = text.trim();
+
+                        for game in names.drain(..) {
+                            self.games
+                                .entry(game)
+                                .or_default()
+                                .insert(name.clone(), text.to_owned());
+                        }
+
+                        imported += 1;
+                    }
+                }
+
+                Some((_, text)) => {
+                    text.push_str(line);
+                    text.push('\n');
+                }
+
+                None => match line.strip_prefix('$').map(|l| l.split_once('=')) {
+                    Some(Some((list, games))) => names.extend(
+                        games
+                            .split(',')
+                            .map(|game| game.trim())
+                            .filter(|game| !game.is_empty())
+                            .map(|game| match list {
+                                "info" => game.to_owned(),
+                                list => format!("{}/{}", list, game),
+                            }),
+                    ),
+
+                    Some(None) if !names.is_empty() => {
+                        section = Some((line[1..].to_owned(), String::new()))
+                    }
+
+                    // comments and anything else between entries
+                    _ => {}
+                },
+            }
+        }
+
+        Ok(imported)
+    }
+
+    // the game's sections as (label, text) pairs
+    pub fn sections<'i>(&'i self, game: &str) -> impl Iterator<Item = (&'i str, &'i str)> {
+        self.games
+            .get(game)
+            .into_iter()
+            .flatten()
+            .map(|(section, text)| (section_label(section), text.as_str()))
+    }
+
+    #[inline]
+    pub fn contains(&self, game: &str) -> bool {
+        self.games.contains_key(game)
+    }
+}
+
+fn section_label(section: &str) -> &str {
+    match section {
+        "bio" => "history",
+        "mame" => "mameinfo",
+        "drv" => "driver",
+        section => section,
+    }
+}
@@ -0,0 +1,241 @@
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const JOURNAL_DIR: &str = "journal";
+const JOURNAL_FILE: &str = "journal.jsonl";
+const SAVED_DIR: &str = "saved";
+
+// what a run changed on disk, one entry per line as it happens,
+// so "emuman undo" can replay them in reverse
+// even if the run was interrupted partway
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Entry {
+    // a file which didn't exist before, whether linked, copied or extracted,
+    // with its size and modification time in nanoseconds when created
+    // so it's only removed if it hasn't been changed since
+    Created {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        modified: Option<u64>,
+    },
+    // a file set aside rather than deleted
+    Removed {
+        path: PathBuf,
+        saved: PathBuf,
+    },
+    // a file or directory moved somewhere else
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+struct Journal {
+    dir: PathBuf,
+    file: std::fs::File,
+}
+
+static JOURNAL: Mutex<Option<Journal>> = Mutex::new(None);
+
+fn journals_dir() -> PathBuf {
+    crate::data_dir().join(JOURNAL_DIR)
+}
+
+// starts journaling this run's changes
+pub fn start() -> Result<(), Error> {
+    // runs starting within the same second each get their own journal
+    let dir = journals_dir().join(format!(
+        "{}-{}",
+        crate::collection::unix_now(),
+        std::process::id()
+    ));
+    std::fs::create_dir_all(dir.join(SAVED_DIR))?;
+
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(JOURNAL_FILE))?;
+
+    *JOURNAL.lock().unwrap() = Some(Journal { dir, file });
+
+    Ok(())
+}
+
+#[inline]
+pub fn is_journaling() -> bool {
+    JOURNAL.lock().unwrap().is_some()
+}
+
+pub fn record(entry: Entry) -> Result<(), std::io::Error> {
+    match JOURNAL.lock().unwrap().as_mut() {
+        Some(journal) => {
+            let mut line = serde_json::to_vec(&entry)?;
+            line.push(b'\n');
+            journal.file.write_all(&line)
+        }
+        None => Ok(()),
+    }
+}
+
+// records a newly created file,
+// along with enough about it to tell whether it's changed by the time it's undone
+pub fn record_created(path: &Path) -> Result<(), std::io::Error> {
+    if !is_journaling() {
+        return Ok(());
+    }
+
+    let (size, modified) = match fingerprint(path) {
+        Some((size, modified)) => (Some(size), Some(modified)),
+        None => (None, None),
+    };
+
+    record(Entry::Created {
+        path: crate::trash::absolute(path),
+        size,
+        modified,
+    })
+}
+
+fn fingerprint(path: &Path) -> Option<(u64, u64)> {
+    use std::convert::TryFrom;
+
+    let metadata = path.metadata().ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?;
+
+    Some((metadata.len(), u64::try_from(modified.as_nanos()).ok()?))
+}
+
+// where a file about to be deleted is kept instead, if journaling
+pub fn saved_path(path: &Path) -> Option<PathBuf> {
+    JOURNAL.lock().unwrap().as_ref().map(|journal| {
+        journal
+            .dir
+            .join(SAVED_DIR)
+            .join(crate::trash::relative(&crate::trash::absolute(path)))
+    })
+}
+
+// journals of earlier runs, oldest first
+pub fn journals() -> Vec<(u64, PathBuf)> {
+    let current = JOURNAL
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|journal| journal.dir.clone());
+
+    let mut journals = std::fs::read_dir(journals_dir())
+        .map(|dir| {
+            dir.filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    // named by when they started, along with the run's process ID
+                    let name = e.file_name();
+                    let name = name.to_str()?;
+                    let started = name.split_once('-').map_or(name, |(t, _)| t).parse().ok()?;
+                    Some((started, e.path()))
+                })
+                .filter(|(_, journal)| Some(journal) != current.as_ref())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    journals.sort_unstable();
+    journals
+}
+
+pub fn entries(journal: &Path) -> Result<Vec<Entry>, Error> {
+    std::io::BufReader::new(std::fs::File::open(journal.join(JOURNAL_FILE))?)
+        .lines()
+        .filter_map(|line| match line {
+            // a run interrupted mid-write may leave a partial last line
+            Ok(line) => serde_json::from_str(&line).ok().map(Ok),
+            Err(err) => Some(Err(Error::IO(err))),
+        })
+        .collect()
+}
+
+// puts back what the given journal's run changed, newest change first,
+// leaving alone anything which has since been changed again,
+// and removes the journal once everything's been undone
+// or keeps only what couldn't be, to try again later
+//
+// each change is passed to undone along with whether it was put back
+pub fn undo<F>(journal: &Path, mut undone: F) -> Result<(), Error>
+where
+    F: FnMut(&Entry, &Result<(), std::io::Error>),
+{
+    let mut failed = Vec::new();
+
+    for entry in entries(journal)?.into_iter().rev() {
+        let result = match &entry {
+            Entry::Created {
+                path,
+                size: Some(size),
+                modified: Some(modified),
+            } if path.exists() && fingerprint(path) != Some((*size, *modified)) => Err(
+                std::io::Error::other(format!("{} : changed since, leaving it", path.display())),
+            ),
+            Entry::Created { path, .. } => match std::fs::remove_file(path) {
+                Ok(()) => {
+                    // game directories created along with it go too, if empty
+                    if let Some(parent) = path.parent() {
+                        let _ = std::fs::remove_dir(parent);
+                    }
+                    Ok(())
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => Err(err),
+            },
+            Entry::Removed { path, saved } => restore(saved, path),
+            Entry::Renamed { from, to } => restore(to, from),
+        };
+
+        undone(&entry, &result);
+
+        if result.is_err() {
+            failed.push(entry);
+        }
+    }
+
+    if failed.is_empty() {
+        std::fs::remove_dir_all(journal)?;
+        return Ok(());
+    }
+
+    let mut remaining = Vec::new();
+    for entry in failed.iter().rev() {
+        remaining.extend(serde_json::to_vec(entry).map_err(std::io::Error::from)?);
+        remaining.push(b'\n');
+    }
+    std::fs::write(journal.join(JOURNAL_FILE), remaining)?;
+
+    Err(Error::FailedUndo(failed.len()))
+}
+
+// moves current back to original, unless something's there already
+fn restore(current: &Path, original: &Path) -> Result<(), std::io::Error> {
+    if original.exists() {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} : already exists, leaving it", original.display()),
+        ))
+    } else if !current.exists() {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} : no longer exists", current.display()),
+        ))
+    } else if current.is_dir() {
+        std::fs::rename(current, original)
+    } else {
+        crate::trash::move_to(current, original)
+    }
+}
@@ -0,0 +1,541 @@
+#[cfg(feature = "native")]
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+#[cfg(feature = "native")]
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "native")]
+pub mod acks;
+pub mod blocklist;
+#[cfg(feature = "native")]
+pub mod catalog;
+#[cfg(feature = "native")]
+pub mod chdman;
+pub mod check;
+#[cfg(feature = "native")]
+pub mod collection;
+#[cfg(feature = "native")]
+pub mod consume;
+// DAT parsing, verification and rebuilding, for embedding elsewhere,
+// which return their results and leave displaying them to the caller
+pub mod dat;
+#[cfg(feature = "native")]
+pub mod dirs;
+#[cfg(feature = "native")]
+pub mod download;
+#[cfg(feature = "native")]
+pub mod duplicates;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod game;
+#[cfg(feature = "native")]
+pub mod hooks;
+#[cfg(feature = "native")]
+pub mod html;
+#[cfg(feature = "native")]
+pub mod http;
+#[cfg(feature = "native")]
+pub mod index;
+#[cfg(feature = "native")]
+pub mod info;
+#[cfg(feature = "native")]
+pub mod journal;
+pub mod locale;
+#[cfg(feature = "native")]
+pub mod mame;
+#[cfg(feature = "native")]
+pub mod mess;
+pub mod patch;
+#[cfg(feature = "native")]
+pub mod pin;
+#[cfg(feature = "native")]
+pub mod provenance;
+#[cfg(feature = "native")]
+pub mod safety;
+#[cfg(feature = "native")]
+pub mod samples;
+pub mod select;
+pub mod sink;
+#[cfg(feature = "native")]
+pub mod snapshot;
+#[cfg(feature = "native")]
+pub mod split;
+#[cfg(feature = "native")]
+pub mod stamp;
+#[cfg(feature = "native")]
+pub mod state;
+pub mod tags;
+#[cfg(feature = "native")]
+pub mod torrentzip;
+#[cfg(feature = "native")]
+pub mod trash;
+#[cfg(feature = "native")]
+pub mod triage;
+
+pub static MAME: &str = "mame";
+pub static MESS: &str = "mess";
+pub static EXTRA: &str = "extra";
+pub static REDUMP: &str = "redump";
+pub static NOINTRO: &str = "nointro";
+pub static INFO: &str = "info";
+pub static SAMPLES: &str = "samples";
+pub static TAGS: &str = "tags";
+pub static COLLECTION: &str = "collection";
+pub static BLOCKLIST: &str = "blocklist";
+pub static ACKS: &str = "acks";
+pub static CATALOG: &str = "catalog";
+
+pub static DB_MAME: &str = "mame.cbor";
+pub static DB_MESS_SPLIT: &str = "mess-split.cbor";
+pub static DB_REDUMP_SPLIT: &str = "redump-split.cbor";
+pub static DB_INFO: &str = "info.cbor";
+pub static DB_SAMPLES: &str = "samples.cbor";
+pub static DB_TAGS: &str = "tags.cbor";
+pub static DB_BLOCKLIST: &str = "blocklist.cbor";
+pub static DB_ACKS: &str = "acks.cbor";
+pub static DB_CATALOG: &str = "catalog.cbor";
+
+pub static DIR_SL: &str = "sl";
+pub static DIR_EXTRA: &str = "extra";
+pub static DIR_NOINTRO: &str = "nointro";
+pub static DIR_REDUMP: &str = "redump";
+pub static DIR_INDEX: &str = "index";
+pub static DIR_DAT: &str = "dat";
+
+// used to add context about which file caused a given error
+#[derive(Debug)]
+pub struct FileError<E> {
+    pub file: PathBuf,
+    pub error: E,
+}
+
+impl<E: std::error::Error> std::error::Error for FileError<E> {}
+
+impl<E: std::error::Error> std::fmt::Display for FileError<E> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}: {}", self.file.display(), self.error)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    IO(std::io::Error),
+    Xml(quick_xml::de::DeError),
+    XmlFile(FileError<quick_xml::de::DeError>),
+    ClrMamePro(FileError<dat::ClrMameProError>),
+    CborWrite(ciborium::ser::Error<std::io::Error>),
+    TomlRead(FileError<toml::de::Error>),
+    TomlWrite(toml::ser::Error),
+    #[cfg(feature = "native")]
+    Zip(zip::result::ZipError),
+    #[cfg(feature = "native")]
+    Rar(unrar::error::UnrarError),
+    #[cfg(feature = "native")]
+    Http(attohttpc::Error),
+    #[cfg(feature = "native")]
+    HttpCode(attohttpc::StatusCode),
+    #[cfg(feature = "native")]
+    Inquire(inquire::error::InquireError),
+    #[cfg(feature = "native")]
+    Watch(notify::Error),
+    Patch(FileError<patch::PatchError>),
+    WatchStopped,
+    NoSuchDatFile(String),
+    NoDatFiles,
+    NoSuchSoftwareList(String),
+    NoSoftwareLists,
+    NoSuchSoftware(String),
+    NoSuchPart(String, String),
+    ConsumeSymlinks,
+    MissingCache(&'static str),
+    InvalidCache(&'static str),
+    InvalidPath,
+    InvalidName(String),
+    InvalidSha1(FileError<hex::FromHexError>),
+    InvalidDat(hex::FromHexError),
+    OverlappingRoots(PathBuf, PathBuf),
+    TargetExists(PathBuf),
+    TargetPinned(PathBuf),
+    CorruptZip(PathBuf, String),
+    FailedGames(usize),
+    NoSuchCollection(String),
+    InvalidCollectionDb(String),
+    NoMirrors,
+    MameExit(std::process::ExitStatus),
+    ChdmanExit(std::process::ExitStatus, String),
+    ChecksumMismatch(String),
+    FailedDownloads(usize),
+    FailedChecks(usize),
+    DeletionsNotConfirmed(usize),
+    NoIniEntries(&'static str),
+    InvalidStateArchive(PathBuf),
+    InvalidSnapshot(PathBuf),
+    StateExists(usize),
+    FailedUndo(usize),
+    NoJournal,
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IO(err)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Self {
+        Error::Zip(err)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<unrar::error::UnrarError> for Error {
+    fn from(err: unrar::error::UnrarError) -> Self {
+        Error::Rar(err)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<attohttpc::Error> for Error {
+    #[inline]
+    fn from(err: attohttpc::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    #[inline]
+    fn from(err: toml::ser::Error) -> Self {
+        Error::TomlWrite(err)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<inquire::error::InquireError> for Error {
+    #[inline]
+    fn from(err: inquire::error::InquireError) -> Self {
+        Error::Inquire(err)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<notify::Error> for Error {
+    #[inline]
+    fn from(err: notify::Error) -> Self {
+        Error::Watch(err)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::IO(err) => err.fmt(f),
+            Error::Xml(err) => err.fmt(f),
+            Error::XmlFile(err) => err.fmt(f),
+            Error::ClrMamePro(err) => err.fmt(f),
+            Error::CborWrite(err) => err.fmt(f),
+            Error::TomlRead(err) => err.fmt(f),
+            Error::TomlWrite(err) => err.fmt(f),
+            #[cfg(feature = "native")]
+            Error::Zip(err) => err.fmt(f),
+            #[cfg(feature = "native")]
+            Error::Rar(err) => err.fmt(f),
+            #[cfg(feature = "native")]
+            Error::Http(err) => err.fmt(f),
+            #[cfg(feature = "native")]
+            Error::HttpCode(code) => match code.canonical_reason() {
+                Some(reason) => write!(f, "HTTP error {} - {}", code.as_str(), reason),
+                None => write!(f, "HTTP error {}", code.as_str()),
+            },
+            #[cfg(feature = "native")]
+            Error::Inquire(err) => err.fmt(f),
+            #[cfg(feature = "native")]
+            Error::Watch(err) => err.fmt(f),
+            Error::Patch(err) => err.fmt(f),
+            Error::WatchStopped => write!(f, "stopped receiving filesystem events"),
+            Error::NoSuchDatFile(s) => write!(f, "no such dat file \"{}\"", s),
+            Error::NoDatFiles => write!(f, "no dat files have been initialized"),
+            Error::NoSuchSoftwareList(s) => write!(f, "no such software list \"{}\"", s),
+            Error::NoSuchSoftware(s) => write!(f, "no such software \"{}\"", s),
+            Error::ConsumeSymlinks => {
+                write!(f, "sources can't be consumed when symlinking to them")
+            }
+            Error::NoSuchPart(game, part) => {
+                write!(f, "no part \"{}\" in \"{}\" or its devices", part, game)
+            }
+            Error::NoSoftwareLists => write!(f, "no software lists initialized"),
+            Error::MissingCache(s) => write!(
+                f,
+                "missing cache files, please run \"emuman {} init\" to populate",
+                s
+            ),
+            Error::InvalidCache(s) => write!(
+                f,
+                "outdated or invalid cache files, please run \"emuman {} init\" to repopulate",
+                s
+            ),
+            Error::InvalidPath => write!(f, "invalid UTF-8 path"),
+            Error::InvalidName(name) => {
+                write!(f, "\"{}\" must be a relative path without \"..\"", name)
+            }
+            Error::InvalidSha1(err) => err.fmt(f),
+            Error::InvalidDat(err) => write!(f, "invalid DAT : {}", err),
+            Error::CorruptZip(file, entry) => write!(
+                f,
+                "{}: damaged Zip entry \"{}\", skipping archive",
+                file.display(),
+                entry
+            ),
+            Error::OverlappingRoots(source, target) => write!(
+                f,
+                "input \"{}\" overlaps output directory \"{}\"",
+                source.display(),
+                target.display()
+            ),
+            Error::TargetExists(file) => write!(
+                f,
+                "{}: already exists, use --force to replace it",
+                file.display()
+            ),
+            Error::TargetPinned(file) => write!(f, "{}: pinned, unpin it first", file.display()),
+            Error::FailedGames(1) => write!(f, "1 game could not be added"),
+            Error::FailedGames(n) => write!(f, "{} games could not be added", n),
+            Error::NoSuchCollection(s) => write!(f, "no such collection \"{}\"", s),
+            Error::InvalidCollectionDb(s) => write!(
+                f,
+                "invalid collection database \"{}\", use \"mame\", \"sl:<software list>\" or \"dat:<name>\"",
+                s
+            ),
+            Error::NoMirrors => write!(f, "no mirrors to download from"),
+            Error::MameExit(status) => write!(f, "MAME {}", status),
+            Error::ChdmanExit(status, stderr) if stderr.is_empty() => {
+                write!(f, "chdman {}", status)
+            }
+            Error::ChdmanExit(status, stderr) => write!(f, "chdman {} : {}", status, stderr),
+            Error::ChecksumMismatch(url) => write!(f, "{}: SHA1 mismatch", url),
+            Error::FailedDownloads(1) => write!(f, "1 part could not be downloaded"),
+            Error::FailedDownloads(n) => write!(f, "{} parts could not be downloaded", n),
+            Error::FailedChecks(1) => write!(f, "1 self-test check failed"),
+            Error::FailedChecks(n) => write!(f, "{} self-test checks failed", n),
+            Error::DeletionsNotConfirmed(n) => write!(
+                f,
+                "stopped before deleting {} files, use --yes to allow it",
+                n
+            ),
+            Error::NoIniEntries(section) => write!(f, "no \"[{}]\" entries found", section),
+            Error::InvalidStateArchive(file) => {
+                write!(f, "{}: not an emuman state archive", file.display())
+            }
+            Error::InvalidSnapshot(file) => {
+                write!(f, "{}: not an emuman snapshot", file.display())
+            }
+            Error::StateExists(1) => write!(
+                f,
+                "1 existing file would be replaced, use --overwrite to allow it"
+            ),
+            Error::StateExists(n) => write!(
+                f,
+                "{} existing files would be replaced, use --overwrite to allow it",
+                n
+            ),
+            Error::FailedUndo(1) => write!(f, "1 change could not be undone"),
+            Error::FailedUndo(n) => write!(f, "{} changes could not be undone", n),
+            Error::NoJournal => write!(f, "no journaled runs to undo"),
+        }
+    }
+}
+pub fn is_zip<R>(mut reader: R) -> Result<bool, std::io::Error>
+where
+    R: Read + Seek,
+{
+    use std::io::SeekFrom;
+
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    reader.seek(SeekFrom::Start(0))?;
+    Ok(&buf == b"\x50\x4b\x03\x04")
+}
+
+pub fn is_rar<R>(mut reader: R) -> Result<bool, std::io::Error>
+where
+    R: Read + Seek,
+{
+    use std::io::SeekFrom;
+
+    // RAR 1.5 through RAR 5.0 archives share this prefix
+    let mut buf = [0; 6];
+    reader.read_exact(&mut buf)?;
+    reader.seek(SeekFrom::Start(0))?;
+    Ok(&buf == b"Rar!\x1a\x07")
+}
+
+#[cfg(feature = "native")]
+pub fn write_game_db<S>(db_file: &'static str, db: S) -> Result<(), Error>
+where
+    S: Serialize,
+{
+    use std::fs::create_dir_all;
+    use std::io::BufWriter;
+
+    let dir = data_dir();
+    create_dir_all(&dir)?;
+    let path = dir.join(db_file);
+    let f = BufWriter::new(File::create(&path)?);
+    ciborium::ser::into_writer(&db, f).map_err(Error::CborWrite)?;
+    Ok(())
+}
+
+#[cfg(feature = "native")]
+pub fn read_game_db<D>(utility: &'static str, db_file: &'static str) -> Result<D, Error>
+where
+    D: DeserializeOwned,
+{
+    use std::io::BufReader;
+
+    let f = BufReader::new(
+        File::open(data_dir().join(db_file)).map_err(|_| Error::MissingCache(utility))?,
+    );
+    ciborium::de::from_reader(f).map_err(|_| Error::InvalidCache(utility))
+}
+
+// configuration, databases, tags, pins, journals and the like
+// all live together in one directory
+#[cfg(feature = "native")]
+pub fn data_dir() -> PathBuf {
+    directories::ProjectDirs::from("", "", "EmuMan")
+        .expect("no valid home directory")
+        .data_local_dir()
+        .to_path_buf()
+}
+
+#[cfg(feature = "native")]
+pub fn named_db_dir(db_dir: &'static str) -> PathBuf {
+    data_dir().join(db_dir)
+}
+
+// names might contain slashes, so we'll encode them
+// into base64 to ensure they stay in the directory we put them in
+#[cfg(feature = "native")]
+pub fn named_db_path(db_dir: &'static str, name: &str) -> PathBuf {
+    named_db_dir(db_dir).join(base64::encode_config(name, base64::URL_SAFE))
+}
+
+// extracts database name from existing path, if any
+pub fn path_db_name(path: &Path) -> Option<String> {
+    String::from_utf8(base64::decode_config(path.file_name()?.to_str()?, base64::URL_SAFE).ok()?)
+        .ok()
+}
+
+#[cfg(feature = "native")]
+pub fn write_named_db<S: Serialize>(
+    db_dir: &'static str,
+    name: &str,
+    cache: S,
+) -> Result<(), Error> {
+    use std::fs::create_dir_all;
+    use std::io::BufWriter;
+
+    let path = named_db_path(db_dir, name);
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    ciborium::ser::into_writer(&cache, BufWriter::new(File::create(&path)?))
+        .map_err(Error::CborWrite)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "native")]
+pub fn read_named_db<D: DeserializeOwned>(
+    utility: &'static str,
+    db_dir: &'static str,
+    name: &str,
+) -> Result<D, Error> {
+    ciborium::de::from_reader(
+        File::open(named_db_path(db_dir, name))
+            .map(std::io::BufReader::new)
+            .map_err(|_| Error::MissingCache(utility))?,
+    )
+    .map_err(|_| Error::InvalidCache(utility))
+}
+
+#[cfg(feature = "native")]
+pub fn clear_named_dbs(db_dir: &'static str) -> Result<(), Error> {
+    let files: Vec<_> = std::fs::read_dir(named_db_dir(db_dir))
+        .map(|dir| dir.filter_map(|e| e.map(|e| e.path()).ok()).collect())
+        .unwrap_or_default();
+
+    files
+        .into_iter()
+        .try_for_each(std::fs::remove_file)
+        .map_err(Error::IO)
+}
+
+#[cfg(feature = "native")]
+pub fn destroy_named_db(db_dir: &'static str, name: &str) -> Result<(), Error> {
+    let path = named_db_path(db_dir, name);
+    if path.is_file() {
+        std::fs::remove_file(path).map_err(Error::IO)
+    } else {
+        Err(Error::NoSuchDatFile(name.to_owned()))
+    }
+}
+
+#[cfg(feature = "native")]
+pub fn read_named_dbs<D>(db_dir: &'static str) -> Option<impl Iterator<Item = (String, D)>>
+where
+    D: DeserializeOwned,
+{
+    #[inline]
+    fn read_game_db<D: DeserializeOwned>(path: &Path) -> Option<(String, D)> {
+        Some((
+            path_db_name(path)?,
+            File::open(path)
+                .ok()
+                .map(std::io::BufReader::new)
+                .and_then(|f| ciborium::de::from_reader(f).ok())?,
+        ))
+    }
+
+    match std::fs::read_dir(named_db_dir(db_dir)) {
+        Ok(dir) => Some(dir.filter_map(|entry| {
+            entry
+                .ok()
+                .map(|entry| entry.path())
+                .and_then(|path| read_game_db(&path))
+        })),
+        Err(_) => None,
+    }
+}
+
+#[cfg(feature = "native")]
+pub fn read_collected_dbs<C, D>(db_dir: &'static str) -> C
+where
+    C: std::iter::FromIterator<(String, D)>,
+    D: DeserializeOwned,
+{
+    read_named_dbs(db_dir).into_iter().flatten().collect()
+}
+
+// the game stored under a ROMs directory entry,
+// which is a game.zip archive when verifying Zip collections
+#[cfg(feature = "native")]
+pub fn entry_game(entry: std::fs::DirEntry, zip: bool) -> Option<String> {
+    let name = entry.file_name().into_string().ok()?;
+
+    if zip {
+        name.strip_suffix(".zip").map(|game| game.to_owned())
+    } else {
+        Some(name)
+    }
+}
+
+// games which couldn't be added, along with why
+pub type FailedGames = Vec<(String, Error)>;
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+// regions as No-Intro and Redump spell them in game names
+const REGIONS: &[&str] = &[
+    "Argentina",
+    "Asia",
+    "Australia",
+    "Austria",
+    "Belgium",
+    "Brazil",
+    "Canada",
+    "China",
+    "Croatia",
+    "Denmark",
+    "Europe",
+    "Finland",
+    "France",
+    "Germany",
+    "Greece",
+    "Hong Kong",
+    "India",
+    "Ireland",
+    "Israel",
+    "Italy",
+    "Japan",
+    "Korea",
+    "Latin America",
+    "Mexico",
+    "Netherlands",
+    "New Zealand",
+    "Norway",
+    "Poland",
+    "Portugal",
+    "Russia",
+    "Scandinavia",
+    "South Africa",
+    "Spain",
+    "Sweden",
+    "Switzerland",
+    "Taiwan",
+    "UK",
+    "USA",
+    "Unknown",
+    "World",
+];
+
+// names without a language tag are in their region's only language
+const REGION_LANGUAGES: &[(&str, &str)] = &[
+    ("Australia", "En"),
+    ("Brazil", "Pt"),
+    ("China", "Zh"),
+    ("France", "Fr"),
+    ("Germany", "De"),
+    ("Italy", "It"),
+    ("Japan", "Ja"),
+    ("Korea", "Ko"),
+    ("Netherlands", "Nl"),
+    ("Russia", "Ru"),
+    ("Spain", "Es"),
+    ("Sweden", "Sv"),
+    ("UK", "En"),
+    ("USA", "En"),
+];
+
+// the regions a game was released in and the languages it supports
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Locale {
+    #[serde(default)]
+    pub regions: Vec<String>,
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+impl Locale {
+    // parses names like "Game (USA, Europe) (En,Fr,De) (Rev 1)"
+    // whose first group of regions and first group of languages are used
+    pub fn from_name(name: &str) -> Self {
+        let mut locale = Locale::default();
+
+        for group in name
+            .split('(')
+            .skip(1)
+            .filter_map(|group| group.split_once(')'))
+            .map(|(group, _)| group.split(',').map(|s| s.trim()).collect::<Vec<_>>())
+        {
+            if locale.regions.is_empty() && group.iter().all(|s| REGIONS.contains(s)) {
+                locale.regions = group.iter().map(|s| s.to_string()).collect();
+            } else if locale.languages.is_empty() && group.iter().all(|s| is_language(s)) {
+                locale.languages = group.iter().map(|s| s.to_string()).collect();
+            }
+        }
+
+        if locale.languages.is_empty() {
+            if let [region] = &locale.regions[..] {
+                locale.languages.extend(
+                    REGION_LANGUAGES
+                        .iter()
+                        .filter(|(r, _)| r == region)
+                        .map(|(_, language)| language.to_string()),
+                );
+            }
+        }
+
+        locale
+    }
+
+    // metadata given by the DAT itself takes precedence
+    // over whatever can be parsed from the game's name
+    pub fn from_metadata(name: &str, regions: Vec<String>, languages: Vec<String>) -> Self {
+        let parsed = Locale::from_name(name);
+
+        Locale {
+            regions: if regions.is_empty() {
+                parsed.regions
+            } else {
+                regions
+            },
+            languages: if languages.is_empty() {
+                parsed.languages
+            } else {
+                languages
+            },
+        }
+    }
+}
+
+// language tags like "En", "Ja" or "Zh-Hant"
+fn is_language(s: &str) -> bool {
+    let code = |part: &str| {
+        let mut chars = part.chars();
+        part.len() >= 2
+            && chars.next().is_some_and(|c| c.is_ascii_uppercase())
+            && chars.all(|c| c.is_ascii_lowercase())
+    };
+
+    let mut parts = s.split('-');
+
+    parts
+        .next()
+        .is_some_and(|part| part.len() == 2 && code(part))
+        && parts.all(code)
+}
+
+// which regions and languages are wanted,
+// where empty lists allow any
+#[derive(Copy, Clone, Default)]
+pub struct LocaleFilter<'f> {
+    pub regions: &'f [String],
+    pub languages: &'f [String],
+}
+
+impl LocaleFilter<'_> {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty() && self.languages.is_empty()
+    }
+
+    // "World" releases are wanted in any region
+    pub fn allows(&self, locale: &Locale) -> bool {
+        let any = |wanted: &[String], have: &[String]| {
+            have.iter()
+                .any(|have| wanted.iter().any(|want| want.eq_ignore_ascii_case(have)))
+        };
+
+        (self.regions.is_empty()
+            || any(self.regions, &locale.regions)
+            || locale.regions.iter().any(|region| region == "World"))
+            && (self.languages.is_empty() || any(self.languages, &locale.languages))
+    }
+}
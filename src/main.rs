@@ -1,143 +1,30 @@
 use clap::{Args, Parser, Subcommand};
+use emuman::{
+    acks, blocklist, catalog, chdman, clear_named_dbs, collection, consume, dat, destroy_named_db,
+    dirs, download, duplicates, entry_game, game, hooks, html, http, index, info, is_zip, journal,
+    locale, mame, mess, named_db_dir, patch, pin, provenance, read_collected_dbs, read_game_db,
+    read_named_db, safety, samples, select, sink, snapshot, split, stamp, state, tags, torrentzip,
+    trash, triage, write_game_db, write_named_db, Error, FailedGames, FileError, ACKS, BLOCKLIST,
+    DB_ACKS, DB_BLOCKLIST, DB_INFO, DB_MAME, DB_MESS_SPLIT, DB_REDUMP_SPLIT, DB_SAMPLES, DB_TAGS,
+    DIR_DAT, DIR_EXTRA, DIR_NOINTRO, DIR_REDUMP, DIR_SL, EXTRA, INFO, MAME, MESS, NOINTRO, REDUMP,
+    SAMPLES, TAGS,
+};
+use progress::{BarSink, Notices};
 use serde::{de::DeserializeOwned, Serialize};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
 use std::fs::File;
-use std::io::{Read, Seek};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-mod dat;
-mod dirs;
-mod duplicates;
-mod game;
-mod http;
-mod mame;
-mod mess;
-mod split;
-
-static MAME: &str = "mame";
-static MESS: &str = "mess";
-static EXTRA: &str = "extra";
-static REDUMP: &str = "redump";
-static NOINTRO: &str = "nointro";
-
-static DB_MAME: &str = "mame.cbor";
-static DB_MESS_SPLIT: &str = "mess-split.cbor";
-static DB_REDUMP_SPLIT: &str = "redump-split.cbor";
-
-static DIR_SL: &str = "sl";
-static DIR_EXTRA: &str = "extra";
-static DIR_NOINTRO: &str = "nointro";
-static DIR_REDUMP: &str = "redump";
-
-// used to add context about which file caused a given error
-#[derive(Debug)]
-pub struct FileError<E> {
-    file: PathBuf,
-    error: E,
-}
-
-impl<E: std::error::Error> std::error::Error for FileError<E> {}
-
-impl<E: std::error::Error> std::fmt::Display for FileError<E> {
-    #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "{}: {}", self.file.display(), self.error)
-    }
-}
-
-#[derive(Debug)]
-pub enum Error {
-    IO(std::io::Error),
-    Xml(quick_xml::de::DeError),
-    XmlFile(FileError<quick_xml::de::DeError>),
-    CborWrite(ciborium::ser::Error<std::io::Error>),
-    TomlWrite(toml::ser::Error),
-    Zip(zip::result::ZipError),
-    Http(attohttpc::Error),
-    HttpCode(attohttpc::StatusCode),
-    Inquire(inquire::error::InquireError),
-    NoSuchDatFile(String),
-    NoDatFiles,
-    NoSuchSoftwareList(String),
-    NoSoftwareLists,
-    NoSuchSoftware(String),
-    MissingCache(&'static str),
-    InvalidCache(&'static str),
-    InvalidPath,
-    InvalidSha1(FileError<hex::FromHexError>),
-}
-
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
-        Error::IO(err)
-    }
-}
-
-impl From<zip::result::ZipError> for Error {
-    fn from(err: zip::result::ZipError) -> Self {
-        Error::Zip(err)
-    }
-}
-
-impl From<attohttpc::Error> for Error {
-    #[inline]
-    fn from(err: attohttpc::Error) -> Self {
-        Error::Http(err)
-    }
-}
-
-impl From<toml::ser::Error> for Error {
-    #[inline]
-    fn from(err: toml::ser::Error) -> Self {
-        Error::TomlWrite(err)
-    }
-}
-
-impl From<inquire::error::InquireError> for Error {
-    #[inline]
-    fn from(err: inquire::error::InquireError) -> Self {
-        Error::Inquire(err)
-    }
-}
-
-impl std::error::Error for Error {}
-
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Error::IO(err) => err.fmt(f),
-            Error::Xml(err) => err.fmt(f),
-            Error::XmlFile(err) => err.fmt(f),
-            Error::CborWrite(err) => err.fmt(f),
-            Error::TomlWrite(err) => err.fmt(f),
-            Error::Zip(err) => err.fmt(f),
-            Error::Http(err) => err.fmt(f),
-            Error::HttpCode(code) => match code.canonical_reason() {
-                Some(reason) => write!(f, "HTTP error {} - {}", code.as_str(), reason),
-                None => write!(f, "HTTP error {}", code.as_str()),
-            },
-            Error::Inquire(err) => err.fmt(f),
-            Error::NoSuchDatFile(s) => write!(f, "no such dat file \"{}\"", s),
-            Error::NoDatFiles => write!(f, "no dat files have been initialized"),
-            Error::NoSuchSoftwareList(s) => write!(f, "no such software list \"{}\"", s),
-            Error::NoSuchSoftware(s) => write!(f, "no such software \"{}\"", s),
-            Error::NoSoftwareLists => write!(f, "no software lists initialized"),
-            Error::MissingCache(s) => write!(
-                f,
-                "missing cache files, please run \"emuman {} init\" to populate",
-                s
-            ),
-            Error::InvalidCache(s) => write!(
-                f,
-                "outdated or invalid cache files, please run \"emuman {} init\" to repopulate",
-                s
-            ),
-            Error::InvalidPath => write!(f, "invalid UTF-8 path"),
-            Error::InvalidSha1(err) => err.fmt(f),
-        }
-    }
-}
+mod audit;
+mod progress;
+mod report;
+mod selftest;
+mod serve;
+mod stats;
+mod tui;
+mod watch;
 
 enum Resource {
     File(PathBuf),
@@ -148,7 +35,7 @@ impl Resource {
     fn open(&self) -> Result<ResourceFile, Error> {
         match self {
             Resource::File(f) => File::open(f).map(ResourceFile::File).map_err(Error::IO),
-            Resource::Url(u) => http::fetch_url_data(u.as_str())
+            Resource::Url(u) => http::fetch_url_data(u.as_str(), &BarSink::default())
                 .map(|data| ResourceFile::Url(std::io::Cursor::new(data))),
         }
     }
@@ -169,6 +56,34 @@ impl Resource {
     }
 }
 
+// adding from an input which overlaps the output directory
+// could remove the only copy of a file before it's extracted
+fn check_roots(input: &[PathBuf], input_url: &[String], output: &Path) -> Result<(), Error> {
+    // nothing can be in an output directory which doesn't exist yet
+    let target = match output.canonicalize() {
+        Ok(target) => target,
+        Err(_) => return Ok(()),
+    };
+
+    // with no inputs, parts are sourced from the current directory
+    let default_input = [PathBuf::from(".")];
+    let input = if input.is_empty() && input_url.is_empty() {
+        &default_input[..]
+    } else {
+        input
+    };
+
+    for root in input {
+        if let Ok(source) = root.canonicalize() {
+            if source.starts_with(&target) || target.starts_with(&source) {
+                return Err(Error::OverlappingRoots(root.clone(), output.to_path_buf()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl From<String> for Resource {
     #[inline]
     fn from(s: String) -> Self {
@@ -230,33 +145,121 @@ struct OptMameInit {
     /// MAME's XML file or URL
     #[clap(parse(from_os_str))]
     xml: Option<Resource>,
+
+    /// run MAME to list its machines, optionally giving its path
+    #[clap(
+        long = "run",
+        parse(from_os_str),
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "mame",
+        conflicts_with = "xml"
+    )]
+    run: Option<PathBuf>,
 }
 
 impl OptMameInit {
     fn execute(self) -> Result<(), Error> {
-        let xml_data = match self.xml {
-            Some(resource) => {
-                let mut f = resource.open()?;
-                let mut data = String::new();
-                if is_zip(&mut f)? {
-                    zip::ZipArchive::new(f)?
-                        .by_index(0)?
-                        .read_to_string(&mut data)?;
-                } else {
-                    f.read_to_string(&mut data)?;
-                }
-                data
-            }
-            None => {
-                let mut xml_data = String::new();
-                std::io::stdin().read_to_string(&mut xml_data)?;
-                xml_data
-            }
-        };
+        if let Some(mame) = self.run {
+            return Self::run_mame(&mame);
+        }
+
+        let xml_data = read_resource(self.xml)?;
 
         quick_xml::de::from_str(&xml_data)
             .map_err(Error::Xml)
-            .and_then(|mame: mame::Mame| write_game_db(DB_MAME, mame.into_game_db()))
+            .and_then(|mame: mame::Mame| {
+                let db = mame.into_game_db();
+                check_case_collisions(&db);
+                write_game_db(DB_MAME, db)
+            })
+    }
+
+    // streams "mame -listxml" output straight into the database
+    fn run_mame(mame: &Path) -> Result<(), Error> {
+        use indicatif::ProgressStyle;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new(mame)
+            .arg("-listxml")
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                Error::IO(std::io::Error::new(
+                    err.kind(),
+                    format!("{}: {}", mame.display(), err),
+                ))
+            })?;
+
+        let pb = progress::spinner()
+            .with_style(ProgressStyle::default_spinner().template("{spinner} {wide_msg} {bytes}"))
+            .with_message(format!("reading machines from {}", mame.display()));
+
+        let parsed: Result<mame::Mame, _> = quick_xml::de::from_reader(std::io::BufReader::new(
+            pb.wrap_read(child.stdout.take().expect("MAME's output not captured")),
+        ));
+
+        pb.finish_and_clear();
+
+        // MAME failing is more informative than its truncated output
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Error::MameExit(status));
+        }
+
+        let db = parsed.map_err(Error::Xml)?.into_game_db();
+        check_case_collisions(&db);
+        write_game_db(DB_MAME, db)
+    }
+}
+
+#[derive(Args)]
+struct OptMameCatver {
+    /// catver.ini file or URL
+    #[clap(parse(from_os_str))]
+    ini: Option<Resource>,
+}
+
+impl OptMameCatver {
+    fn execute(self) -> Result<(), Error> {
+        let categories = mame::parse_ini_section(&read_resource(self.ini)?, "Category");
+        if categories.is_empty() {
+            return Err(Error::NoIniEntries("Category"));
+        }
+
+        let mut db = read_game_db::<game::GameDb>(MAME, DB_MAME)?;
+        let total = categories.len();
+        let categorized = db.categorize(categories);
+        println!(
+            "{} machines categorized, from {} entries",
+            categorized, total
+        );
+        write_game_db(DB_MAME, db)
+    }
+}
+
+#[derive(Args)]
+struct OptMameNplayers {
+    /// nplayers.ini file or URL
+    #[clap(parse(from_os_str))]
+    ini: Option<Resource>,
+}
+
+impl OptMameNplayers {
+    fn execute(self) -> Result<(), Error> {
+        let players = mame::parse_ini_section(&read_resource(self.ini)?, "NPlayers");
+        if players.is_empty() {
+            return Err(Error::NoIniEntries("NPlayers"));
+        }
+
+        let mut db = read_game_db::<game::GameDb>(MAME, DB_MAME)?;
+        let total = players.len();
+        let counted = db.set_players(players);
+        println!(
+            "{} machines given player counts, from {} entries",
+            counted, total
+        );
+        write_game_db(DB_MAME, db)
     }
 }
 
@@ -270,14 +273,72 @@ struct OptMameList {
     #[clap(short = 'S', long = "simple")]
     simple: bool,
 
+    /// only include the given parent and its clones
+    #[clap(short = 'f', long = "family")]
+    family: Option<String>,
+
+    /// only include games of the given class, like "arcade", "mechanical", "bios", "device" or "console"
+    #[clap(long = "class")]
+    classes: Vec<game::Class>,
+
+    /// only include games with the given tag
+    #[clap(long = "tag")]
+    tags: Vec<String>,
+
+    /// only include games in the given category, like "Shooter/*"
+    #[clap(long = "category")]
+    categories: Vec<String>,
+
+    /// only include games playable the given way, like "2P sim" or "4P*"
+    #[clap(long = "players")]
+    players: Vec<String>,
+
+    /// only include games whose driver has the given status,
+    /// like "good", "imperfect" or "preliminary"
+    #[clap(long = "driver")]
+    driver_status: Option<String>,
+
+    /// only include games whose emulation has the given status,
+    /// like "good", "imperfect" or "preliminary"
+    #[clap(long = "emulation")]
+    emulation: Option<String>,
+
+    /// only include games whose save states are "supported" or "unsupported"
+    #[clap(long = "savestate")]
+    savestate: Option<String>,
+
     /// search term for querying specific machines
     search: Option<String>,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptMameList {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+
         let db = read_game_db::<game::GameDb>(MAME, DB_MAME)?;
-        db.list(self.search.as_deref(), self.sort, self.simple);
+        let curation = read_game_db(TAGS, DB_TAGS).unwrap_or_default();
+        report::display_games(
+            &db.list(
+                self.search.as_deref(),
+                self.family.as_deref(),
+                &game::Filter {
+                    classes: &self.classes,
+                    curation: Some(&curation),
+                    tags: &self.tags,
+                    categories: &self.categories,
+                    players: &self.players,
+                    driver_status: self.driver_status.as_deref(),
+                    emulation: self.emulation.as_deref(),
+                    savestate: self.savestate.as_deref(),
+                },
+                self.sort,
+                self.simple,
+            ),
+            Some(&curation),
+        );
         Ok(())
     }
 }
@@ -295,7 +356,7 @@ struct OptMameGames {
 impl OptMameGames {
     fn execute(self) -> Result<(), Error> {
         let db = read_game_db::<game::GameDb>(MAME, DB_MAME)?;
-        db.games(&self.games, self.simple);
+        report::display_games(&db.game_rows(&self.games, self.simple), None);
         Ok(())
     }
 }
@@ -304,12 +365,16 @@ impl OptMameGames {
 struct OptMameParts {
     /// game's parts to search for
     game: String,
+
+    /// digest format, use "hex", "upper", "sha1" or "base64"
+    #[clap(short = 'D', long = "digest", default_value = "hex")]
+    digest: game::DigestFormat,
 }
 
 impl OptMameParts {
     fn execute(self) -> Result<(), Error> {
-        let db = read_game_db::<game::GameDb>(MAME, DB_MAME)?;
-        db.display_parts(&self.game)
+        let db = index::mame_db(&self.game, false, &Notices)?;
+        report::display_parts(&db, &self.game, self.digest)
     }
 }
 
@@ -327,20 +392,107 @@ struct OptMameReport {
     #[clap(short = 'S', long = "simple")]
     simple: bool,
 
+    /// only include the given parent and its clones
+    #[clap(short = 'f', long = "family")]
+    family: Option<String>,
+
+    /// list the names of complete games instead, as determined by verifying them
+    #[clap(long = "have", conflicts_with = "missing")]
+    have: bool,
+
+    /// list the names of incomplete games instead, as determined by verifying them
+    #[clap(long = "missing")]
+    missing: bool,
+
+    /// with --have or --missing, also list each game's part names
+    #[clap(long = "parts")]
+    parts: bool,
+
+    /// only include games of the given class, like "arcade", "mechanical", "bios", "device" or "console"
+    #[clap(long = "class")]
+    classes: Vec<game::Class>,
+
+    /// only include games with the given tag
+    #[clap(long = "tag")]
+    tags: Vec<String>,
+
+    /// only include games in the given category, like "Shooter/*"
+    #[clap(long = "category")]
+    categories: Vec<String>,
+
+    /// only include games playable the given way, like "2P sim" or "4P*"
+    #[clap(long = "players")]
+    players: Vec<String>,
+
+    /// only include games whose driver has the given status,
+    /// like "good", "imperfect" or "preliminary"
+    #[clap(long = "driver")]
+    driver_status: Option<String>,
+
+    /// only include games whose emulation has the given status,
+    /// like "good", "imperfect" or "preliminary"
+    #[clap(long = "emulation")]
+    emulation: Option<String>,
+
+    /// only include games whose save states are "supported" or "unsupported"
+    #[clap(long = "savestate")]
+    savestate: Option<String>,
+
     /// search term for querying specific machines
     search: Option<String>,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptMameReport {
     fn execute(self) -> Result<(), Error> {
-        let machines: HashSet<String> = dirs::mame_roms(self.roms)
+        self.output.redirect()?;
+
+        let curation = read_game_db(TAGS, DB_TAGS).unwrap_or_default();
+        let filter = game::Filter {
+            classes: &self.classes,
+            curation: Some(&curation),
+            tags: &self.tags,
+            categories: &self.categories,
+            players: &self.players,
+            driver_status: self.driver_status.as_deref(),
+            emulation: self.emulation.as_deref(),
+            savestate: self.savestate.as_deref(),
+        };
+
+        if self.have || self.missing {
+            let db = read_game_db::<game::GameDb>(MAME, DB_MAME)?;
+            report_have_missing(
+                &db,
+                dirs::mame_roms(self.roms, &Notices).as_ref(),
+                self.search.as_deref(),
+                self.family.as_deref(),
+                &filter,
+                self.have,
+                self.parts,
+            );
+            return Ok(());
+        }
+
+        let machines: HashSet<String> = dirs::mame_roms(self.roms, &Notices)
             .as_ref()
             .read_dir()?
             .filter_map(|e| e.ok().and_then(|e| e.file_name().into_string().ok()))
             .collect();
 
         let db = read_game_db::<game::GameDb>(MAME, DB_MAME)?;
-        db.report(&machines, self.search.as_deref(), self.sort, self.simple);
+        report::display_games(
+            &db.report(
+                &machines,
+                self.search.as_deref(),
+                self.family.as_deref(),
+                &filter,
+                self.sort,
+                self.simple,
+            ),
+            filter.curation,
+        );
 
         Ok(())
     }
@@ -352,6 +504,10 @@ struct OptMameVerify {
     #[clap(short = 'r', long = "roms", parse(from_os_str))]
     roms: Option<PathBuf>,
 
+    /// CHD disks directory, if kept apart from ROMs
+    #[clap(short = 'd', long = "disks", parse(from_os_str))]
+    disks: Option<PathBuf>,
+
     /// verify all possible machines
     #[clap(long = "all")]
     all: bool,
@@ -364,20 +520,71 @@ struct OptMameVerify {
     #[clap(long = "failures")]
     failures: bool,
 
+    /// decompress CHDs and verify their data
+    #[clap(long = "deep")]
+    deep: bool,
+
     /// game to verify
     #[clap(short = 'g', long = "game")]
     machines: Vec<String>,
+
+    /// verify games stored as Zip archives
+    #[clap(long = "zip")]
+    zip: bool,
+
+    /// set layout, use "non-merged", "split" or "merged"
+    #[clap(long = "layout", default_value = "non-merged")]
+    layout: dat::Merging,
+
+    /// suggest what to do about each failure
+    #[clap(long = "hints")]
+    hints: bool,
+
+    /// write a DAT of only the missing and bad parts to the given file
+    #[clap(long = "fixdat", parse(from_os_str))]
+    fixdat: Option<PathBuf>,
+
+    /// write an HTML report of the results to the given file
+    #[clap(long = "html", parse(from_os_str))]
+    html: Option<PathBuf>,
+
+    /// report Zip archives in neither TorrentZip nor emuman's canonical form
+    #[clap(long = "canonical")]
+    canonical: bool,
+
+    /// verify device sets once in their own directories
+    /// rather than within every machine using them
+    #[clap(long = "shared-devices")]
+    shared_devices: bool,
+
+    /// results format, use "text" or "json"
+    #[clap(long = "format", default_value = "text")]
+    format: report::ResultsFormat,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptMameVerify {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+        self.no_write.apply();
+
         let mut db: game::GameDb = read_game_db(MAME, DB_MAME)?;
 
+        db.apply_layout(self.layout);
+
         if self.working {
             db.retain_working();
         }
 
-        let roms_dir = dirs::mame_roms(self.roms);
+        let zip = self.zip;
+
+        let roms_dir = dirs::mame_roms(self.roms, &Notices);
+        let disks_dir = dirs::mame_disks(self.disks, &Notices);
 
         let games: HashSet<String> = if self.all {
             db.all_games()
@@ -393,15 +600,34 @@ impl OptMameVerify {
                 .read_dir()?
                 .filter_map(|e| {
                     e.ok()
-                        .and_then(|e| e.file_name().into_string().ok())
+                        .and_then(|e| entry_game(e, zip))
                         .filter(|s| db.is_game(s))
                 })
                 .collect()
         };
 
-        verify(&db, roms_dir, &games, self.failures);
+        let mut report = VerifyReport::new(
+            self.failures,
+            self.hints,
+            self.fixdat,
+            self.html,
+            self.canonical,
+            self.shared_devices,
+            self.format,
+        )
+        .with_acks(MAME);
+
+        verify(
+            &db,
+            roms_dir,
+            disks_dir.get(),
+            &games,
+            self.deep,
+            zip,
+            &mut report,
+        );
 
-        Ok(())
+        report.finish(MAME, db.description())
     }
 }
 
@@ -411,6 +637,10 @@ struct OptMameAdd {
     #[clap(short = 'r', long = "roms", parse(from_os_str))]
     roms: Option<PathBuf>,
 
+    /// output directory for CHD disks, if kept apart from ROMs
+    #[clap(short = 'd', long = "disks", parse(from_os_str))]
+    disks: Option<PathBuf>,
+
     /// game to add
     #[clap(short = 'g', long = "game")]
     machines: Vec<String>,
@@ -418,33 +648,98 @@ struct OptMameAdd {
     /// input file, directory, or URL
     #[clap(parse(from_os_str))]
     input: Vec<Resource>,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+
+    /// store each game's ROMs in a single Zip archive
+    #[clap(long = "zip")]
+    zip: bool,
+
+    /// set layout, use "non-merged", "split" or "merged"
+    #[clap(long = "layout", default_value = "non-merged")]
+    layout: dat::Merging,
+
+    /// only include games of the given class, like "arcade", "mechanical", "bios", "device" or "console"
+    #[clap(long = "class")]
+    classes: Vec<game::Class>,
+
+    /// only include games playable the given way, like "2P sim" or "4P*"
+    #[clap(long = "players")]
+    players: Vec<String>,
 }
 
 impl OptMameAdd {
     fn execute(self) -> Result<(), Error> {
-        let db: game::GameDb = read_game_db(MAME, DB_MAME)?;
+        let mut db: game::GameDb = read_game_db(MAME, DB_MAME)?;
+
+        db.apply_layout(self.layout);
 
-        let roms_dir = dirs::mame_roms(self.roms);
+        let roms_dir = dirs::mame_roms(self.roms, &Notices);
+        let disks_dir = dirs::mame_disks(self.disks, &Notices);
 
         let (input, input_url) = Resource::partition(self.input);
 
-        let mut roms = if self.machines.is_empty() {
-            game::all_rom_sources(&input, &input_url)
+        check_roots(&input, &input_url, roms_dir.as_ref())?;
+        if let Some(disks) = disks_dir.get() {
+            check_roots(&input, &input_url, disks)?;
+        }
+
+        let classes = self.classes;
+        let players = self.players;
+        let selected = |game: &game::Game| game.in_classes(&classes) && game.has_players(&players);
+        let unfiltered = classes.is_empty() && players.is_empty();
+
+        let machines: Vec<String> = match (self.machines.is_empty(), unfiltered) {
+            (true, true) => Vec::new(),
+            (true, false) => db
+                .games_iter()
+                .filter(|game| selected(game))
+                .map(|game| game.name.clone())
+                .collect(),
+            (false, _) => self.machines,
+        };
+
+        let mut roms = if machines.is_empty() && unfiltered {
+            game::all_rom_sources(&input, &input_url, &BarSink::default())
         } else {
-            game::get_rom_sources(&input, &input_url, db.required_parts(&self.machines)?)
+            game::get_rom_sources(
+                &input,
+                &input_url,
+                db.required_parts(&machines)?,
+                &BarSink::default(),
+            )
         };
 
-        if self.machines.is_empty() {
-            add_and_verify(&mut roms, &roms_dir, db.games_iter())?;
+        report_failed_games(if machines.is_empty() && unfiltered {
+            add_and_verify(
+                db.description(),
+                db.description(),
+                &mut roms,
+                &roms_dir,
+                disks_dir.get(),
+                db.games_iter(),
+                self.dry_run,
+                self.zip,
+            )?
         } else {
             add_and_verify(
+                db.description(),
+                db.description(),
                 &mut roms,
                 &roms_dir,
-                self.machines.iter().filter_map(|game| db.game(game)),
-            )?;
-        }
-
-        Ok(())
+                disks_dir.get(),
+                db.with_bios(
+                    machines
+                        .iter()
+                        .filter_map(|game| db.game(game))
+                        .filter(|game| selected(game)),
+                ),
+                self.dry_run,
+                self.zip,
+            )?
+        })
     }
 }
 
@@ -454,6 +749,14 @@ enum OptMame {
     #[clap(name = "init")]
     Init(OptMameInit),
 
+    /// import game categories from catver.ini
+    #[clap(name = "catver")]
+    Catver(OptMameCatver),
+
+    /// import game player counts from nplayers.ini
+    #[clap(name = "nplayers")]
+    Nplayers(OptMameNplayers),
+
     /// list all games
     #[clap(name = "list")]
     List(OptMameList),
@@ -483,6 +786,8 @@ impl OptMame {
     fn execute(self) -> Result<(), Error> {
         match self {
             OptMame::Init(o) => o.execute(),
+            OptMame::Catver(o) => o.execute(),
+            OptMame::Nplayers(o) => o.execute(),
             OptMame::List(o) => o.execute(),
             OptMame::Parts(o) => o.execute(),
             OptMame::Games(o) => o.execute(),
@@ -495,7 +800,7 @@ impl OptMame {
 
 #[derive(Args)]
 struct OptMessInit {
-    /// XML files from hash database
+    /// XML files from hash database, or MAME hash directories
     #[clap(parse(from_os_str))]
     xml: Vec<PathBuf>,
 }
@@ -504,13 +809,16 @@ impl OptMessInit {
     fn execute(self) -> Result<(), Error> {
         let mut split_db = split::SplitDb::new();
 
-        for file in self.xml.into_iter() {
+        for file in self.xml.into_iter().flat_map(hash_files) {
             let sl: mess::Softwarelist =
                 quick_xml::de::from_reader(File::open(&file).map(std::io::BufReader::new)?)
                     .map_err(|error| Error::XmlFile(FileError { error, file }))?;
 
             sl.populate_split_db(&mut split_db);
-            write_named_db(DIR_SL, &sl.name().to_owned(), sl.into_game_db())?;
+            let name = sl.name().to_owned();
+            let db = sl.into_game_db();
+            check_case_collisions(&db);
+            write_named_db(DIR_SL, &name, db)?;
         }
 
         write_game_db(DB_MESS_SPLIT, &split_db)?;
@@ -533,22 +841,40 @@ struct OptMessList {
     #[clap(short = 'S', long = "simple")]
     simple: bool,
 
+    /// only include the given parent and its clones
+    #[clap(short = 'f', long = "family")]
+    family: Option<String>,
+
     /// search term for querying specific items
     search: Option<String>,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptMessList {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+
         match self.software_list.as_deref() {
-            Some("any") => mess::list(
+            Some("any") => report::display_software_results(&mess::list_results(
                 &read_collected_dbs(DIR_SL),
                 self.search.as_deref(),
+                self.family.as_deref(),
                 self.sort,
                 self.simple,
+            )),
+            Some(software_list) => report::display_games(
+                &read_named_db::<game::GameDb>(MESS, DIR_SL, software_list)?.list(
+                    self.search.as_deref(),
+                    self.family.as_deref(),
+                    &game::Filter::default(),
+                    self.sort,
+                    self.simple,
+                ),
+                None,
             ),
-            Some(software_list) => read_named_db::<game::GameDb>(MESS, DIR_SL, software_list)?
-                .list(self.search.as_deref(), self.sort, self.simple),
-            None => mess::list_all(&read_collected_dbs(DIR_SL)),
+            None => report::display_software_lists(&read_collected_dbs(DIR_SL)),
         }
 
         Ok(())
@@ -577,9 +903,9 @@ impl OptMessGames {
         };
 
         if self.games.is_empty() {
-            software_list.display_all_games(self.simple);
+            report::display_games(&software_list.all_game_rows(self.simple), None);
         } else {
-            software_list.games(&self.games, self.simple);
+            report::display_games(&software_list.game_rows(&self.games, self.simple), None);
         }
         Ok(())
     }
@@ -593,15 +919,22 @@ struct OptMessParts {
 
     /// game's parts to search for
     game: Option<String>,
+
+    /// digest format, use "hex", "upper", "sha1" or "base64"
+    #[clap(short = 'D', long = "digest", default_value = "hex")]
+    digest: game::DigestFormat,
 }
 
 impl OptMessParts {
     fn execute(self) -> Result<(), Error> {
         use prettytable::{format, Table};
 
-        let mut software_list = match self.software_list {
-            Some(software_list) => read_named_db(MESS, DIR_SL, &software_list)?,
-            None => select_software_list()?,
+        let mut software_list = match (self.software_list, &self.game) {
+            (Some(software_list), Some(game)) => {
+                index::software_list_db(&software_list, game, &Notices)?
+            }
+            (Some(software_list), None) => read_named_db(MESS, DIR_SL, &software_list)?,
+            (None, _) => select_software_list()?,
         };
 
         let game = match self.game {
@@ -614,7 +947,7 @@ impl OptMessParts {
         let mut table = Table::new();
         table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
         table.get_format().column_separator('\u{2502}');
-        game.display_parts(&mut table);
+        report::add_parts(&mut table, &game, self.digest);
         table.printstd();
         Ok(())
     }
@@ -638,12 +971,33 @@ struct OptMessReport {
     #[clap(short = 'S', long = "simple")]
     simple: bool,
 
+    /// only include the given parent and its clones
+    #[clap(short = 'f', long = "family")]
+    family: Option<String>,
+
+    /// list the names of complete games instead, as determined by verifying them
+    #[clap(long = "have", conflicts_with = "missing")]
+    have: bool,
+
+    /// list the names of incomplete games instead, as determined by verifying them
+    #[clap(long = "missing")]
+    missing: bool,
+
+    /// with --have or --missing, also list each game's part names
+    #[clap(long = "parts")]
+    parts: bool,
+
     /// search term for querying specific software
     search: Option<String>,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptMessReport {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+
         let (db, software_list) = match self.software_list {
             Some(software_list) => (
                 read_named_db::<game::GameDb>(MESS, DIR_SL, &software_list)?,
@@ -652,13 +1006,36 @@ impl OptMessReport {
             None => select_software_list_and_name()?,
         };
 
-        let software: HashSet<String> = dirs::mess_roms(self.roms, &software_list)
+        if self.have || self.missing {
+            report_have_missing(
+                &db,
+                dirs::mess_roms(self.roms, &software_list, &Notices).as_ref(),
+                self.search.as_deref(),
+                self.family.as_deref(),
+                &game::Filter::default(),
+                self.have,
+                self.parts,
+            );
+            return Ok(());
+        }
+
+        let software: HashSet<String> = dirs::mess_roms(self.roms, &software_list, &Notices)
             .as_ref()
             .read_dir()?
             .filter_map(|e| e.ok().and_then(|e| e.file_name().into_string().ok()))
             .collect();
 
-        db.report(&software, self.search.as_deref(), self.sort, self.simple);
+        report::display_games(
+            &db.report(
+                &software,
+                self.search.as_deref(),
+                self.family.as_deref(),
+                &game::Filter::default(),
+                self.sort,
+                self.simple,
+            ),
+            None,
+        );
 
         Ok(())
     }
@@ -682,6 +1059,10 @@ struct OptMessVerify {
     #[clap(long = "failures")]
     failures: bool,
 
+    /// decompress CHDs and verify their data
+    #[clap(long = "deep")]
+    deep: bool,
+
     /// software list to use
     #[clap(short = 'L', long = "software")]
     software_list: Option<String>,
@@ -689,10 +1070,43 @@ struct OptMessVerify {
     /// game to verify
     #[clap(short = 'g', long = "game")]
     software: Vec<String>,
+
+    /// verify games stored as Zip archives
+    #[clap(long = "zip")]
+    zip: bool,
+
+    /// suggest what to do about each failure
+    #[clap(long = "hints")]
+    hints: bool,
+
+    /// write a DAT of only the missing and bad parts to the given file
+    #[clap(long = "fixdat", parse(from_os_str))]
+    fixdat: Option<PathBuf>,
+
+    /// write an HTML report of the results to the given file
+    #[clap(long = "html", parse(from_os_str))]
+    html: Option<PathBuf>,
+
+    /// report Zip archives in neither TorrentZip nor emuman's canonical form
+    #[clap(long = "canonical")]
+    canonical: bool,
+
+    /// results format, use "text" or "json"
+    #[clap(long = "format", default_value = "text")]
+    format: report::ResultsFormat,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptMessVerify {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+        self.no_write.apply();
+
         let (mut db, software_list) = match self.software_list {
             Some(software_list) => (
                 read_named_db::<game::GameDb>(MESS, DIR_SL, &software_list)?,
@@ -701,7 +1115,9 @@ impl OptMessVerify {
             None => select_software_list_and_name()?,
         };
 
-        let roms_dir = dirs::mess_roms(self.roms, &software_list);
+        let zip = self.zip;
+
+        let roms_dir = dirs::mess_roms(self.roms, &software_list, &Notices);
 
         if self.working {
             db.retain_working();
@@ -719,15 +1135,26 @@ impl OptMessVerify {
                 .read_dir()?
                 .filter_map(|e| {
                     e.ok()
-                        .and_then(|e| e.file_name().into_string().ok())
+                        .and_then(|e| entry_game(e, zip))
                         .filter(|s| db.is_game(s))
                 })
                 .collect()
         };
 
-        verify(&db, &roms_dir, &software, self.failures);
+        let mut report = VerifyReport::new(
+            self.failures,
+            self.hints,
+            self.fixdat,
+            self.html,
+            self.canonical,
+            false,
+            self.format,
+        )
+        .with_acks(&software_list);
+
+        verify(&db, &roms_dir, None, &software, self.deep, zip, &mut report);
 
-        Ok(())
+        report.finish(&software_list, db.description())
     }
 }
 
@@ -748,11 +1175,30 @@ struct OptMessVerifyAll {
     /// display only failures
     #[clap(long = "failures")]
     failures: bool,
+
+    /// decompress CHDs and verify their data
+    #[clap(long = "deep")]
+    deep: bool,
+
+    /// verify games stored as Zip archives
+    #[clap(long = "zip")]
+    zip: bool,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptMessVerifyAll {
     fn execute(self) -> Result<(), Error> {
-        let roms_dir = dirs::mess_roms_all(self.roms);
+        self.output.redirect()?;
+        self.no_write.apply();
+
+        let zip = self.zip;
+
+        let roms_dir = dirs::mess_roms_all(self.roms, &Notices);
 
         for (software_list, mut db) in read_collected_dbs::<BTreeMap<_, _>, game::GameDb>(DIR_SL) {
             let roms_path = roms_dir.as_ref().join(&software_list);
@@ -769,7 +1215,7 @@ impl OptMessVerifyAll {
                     .map(|dir| {
                         dir.filter_map(|e| {
                             e.ok()
-                                .and_then(|e| e.file_name().into_string().ok())
+                                .and_then(|e| entry_game(e, zip))
                                 .filter(|s| db.is_game(s))
                         })
                         .collect()
@@ -777,7 +1223,15 @@ impl OptMessVerifyAll {
                     .unwrap_or_default()
             };
 
-            verify_all(&software_list, &db, &roms_path, &software, self.failures);
+            verify_all(
+                &software_list,
+                &db,
+                &roms_path,
+                &software,
+                self.failures,
+                self.deep,
+                zip,
+            );
         }
 
         Ok(())
@@ -801,6 +1255,14 @@ struct OptMessAdd {
     /// input file, directory, or URL
     #[clap(parse(from_os_str))]
     input: Vec<Resource>,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+
+    /// store each game's ROMs in a single Zip archive
+    #[clap(long = "zip")]
+    zip: bool,
 }
 
 impl OptMessAdd {
@@ -813,25 +1275,46 @@ impl OptMessAdd {
             None => select_software_list_and_name()?,
         };
 
-        let roms_dir = dirs::mess_roms(self.roms, &software_list);
+        let roms_dir = dirs::mess_roms(self.roms, &software_list, &Notices);
 
         let (input, input_url) = Resource::partition(self.input);
 
+        check_roots(&input, &input_url, roms_dir.as_ref())?;
+
         let mut roms = if self.software.is_empty() {
-            game::all_rom_sources(&input, &input_url)
+            game::all_rom_sources(&input, &input_url, &BarSink::default())
         } else {
-            game::get_rom_sources(&input, &input_url, db.required_parts(&self.software)?)
+            game::get_rom_sources(
+                &input,
+                &input_url,
+                db.required_parts(&self.software)?,
+                &BarSink::default(),
+            )
         };
 
-        if self.software.is_empty() {
-            add_and_verify(&mut roms, &roms_dir, db.games_iter())
+        report_failed_games(if self.software.is_empty() {
+            add_and_verify(
+                &software_list,
+                db.description(),
+                &mut roms,
+                &roms_dir,
+                None,
+                db.games_iter(),
+                self.dry_run,
+                self.zip,
+            )?
         } else {
             add_and_verify(
+                &software_list,
+                db.description(),
                 &mut roms,
                 &roms_dir,
+                None,
                 self.software.iter().filter_map(|game| db.game(game)),
-            )
-        }
+                self.dry_run,
+                self.zip,
+            )?
+        })
     }
 }
 
@@ -844,26 +1327,43 @@ struct OptMessAddAll {
     /// input file, directory, or URL
     #[clap(parse(from_os_str))]
     input: Vec<Resource>,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+
+    /// store each game's ROMs in a single Zip archive
+    #[clap(long = "zip")]
+    zip: bool,
 }
 
 impl OptMessAddAll {
     fn execute(self) -> Result<(), Error> {
         let db = read_collected_dbs::<BTreeMap<_, _>, game::GameDb>(DIR_SL);
 
-        let roms_dir = dirs::mess_roms_all(self.roms);
+        let roms_dir = dirs::mess_roms_all(self.roms, &Notices);
 
         let (input, input_url) = Resource::partition(self.input);
 
-        let mut roms = game::all_rom_sources(&input, &input_url);
+        check_roots(&input, &input_url, roms_dir.as_ref())?;
+
+        let mut roms = game::all_rom_sources(&input, &input_url, &BarSink::default());
+
+        let mut failed = Vec::new();
 
-        db.into_iter().try_for_each(|(software, db)| {
-            add_and_verify_all(
+        for (software, db) in db {
+            failed.extend(add_and_verify_all(
                 &software,
+                db.description(),
                 &mut roms,
-                &roms_dir.as_ref().join(&software),
+                roms_dir.as_ref().join(&software),
                 db.games_iter(),
-            )
-        })
+                self.dry_run,
+                self.zip,
+            )?);
+        }
+
+        report_failed_games(failed)
     }
 }
 
@@ -885,7 +1385,7 @@ impl OptMessSplit {
         let db = read_game_db::<split::SplitDb>(MESS, DB_MESS_SPLIT)?;
 
         self.roms.par_iter().try_for_each(|rom| {
-            let mut f = File::open(&rom)?;
+            let mut f = File::open(rom)?;
 
             let roms: Vec<Vec<u8>> = if is_zip(&mut f)? {
                 let mut zip = zip::ZipArchive::new(f)?;
@@ -910,7 +1410,9 @@ impl OptMessSplit {
                     .iter()
                     .find(|m| m.matches(data))
                 {
-                    exact_match.extract(&self.output, data)?;
+                    for path in exact_match.extract(&self.output, data)? {
+                        println!("* {}", path.display());
+                    }
                 }
             }
 
@@ -981,25 +1483,199 @@ impl OptMess {
 }
 
 #[derive(Args)]
-struct OptExtraInit {
-    /// extras .DAT file files
-    #[clap(parse(from_os_str))]
-    dats: Vec<PathBuf>,
+struct OptDirectives {
+    /// override DAT's merging directive (none, split, full)
+    #[clap(long = "merging")]
+    merging: Option<dat::Merging>,
+
+    /// override DAT's nodump directive (obsolete, required, ignore)
+    #[clap(long = "nodump")]
+    nodump: Option<dat::NoDump>,
+
+    /// override DAT's packing directive (zip, unzip)
+    #[clap(long = "packing")]
+    packing: Option<dat::Packing>,
+}
+
+impl OptDirectives {
+    fn overrides(&self) -> dat::DirectiveOverrides {
+        dat::DirectiveOverrides {
+            merging: self.merging,
+            nodump: self.nodump,
+            packing: self.packing,
+        }
+    }
+}
 
-    /// completely replace old dat files
-    #[clap(long = "replace")]
-    replace: bool,
+#[derive(Args)]
+struct OptOutput {
+    /// write results to the given file instead of standard output
+    #[clap(long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
 }
 
-impl OptExtraInit {
-    fn execute(self) -> Result<(), Error> {
-        if self.replace {
-            clear_named_dbs(DIR_EXTRA)?;
+impl OptOutput {
+    // progress bars already go to standard error,
+    // so only results end up in the file
+    fn redirect(&self) -> Result<(), Error> {
+        match &self.output {
+            Some(path) => redirect_stdout(File::create(path)?).map_err(Error::IO),
+            None => Ok(()),
         }
+    }
+}
 
-        for dats in self.dats.into_iter().map(dat::read_unflattened_dats) {
-            for dat in dats? {
-                write_named_db(DIR_EXTRA, &dat.name().to_owned(), dat)?;
+#[derive(Args, Default)]
+struct OptLocale {
+    /// only include games released in the given region, like "USA" or "Europe"
+    #[clap(long = "region")]
+    regions: Vec<String>,
+
+    /// only include games in the given language, like "En" or "Ja"
+    #[clap(long = "language")]
+    languages: Vec<String>,
+
+    /// only include one game per title, preferring regions in the given order, like "USA,Europe,Japan"
+    #[clap(long = "1g1r", use_value_delimiter = true)]
+    one_game: Vec<String>,
+}
+
+impl OptLocale {
+    #[inline]
+    fn filter(&self) -> locale::LocaleFilter<'_> {
+        locale::LocaleFilter {
+            regions: &self.regions,
+            languages: &self.languages,
+        }
+    }
+
+    // narrows the DAT to the wanted games,
+    // returning which were chosen for each title if 1G1R
+    fn apply(&self, datfile: &mut dat::DatFile) -> Option<Vec<select::Choice>> {
+        datfile.retain_locale(&self.filter());
+
+        (!self.one_game.is_empty()).then(|| datfile.retain_one_game_one_rom(&self.one_game))
+    }
+
+    fn select_games<'g>(&self, games: Vec<&'g game::Game>) -> Vec<&'g game::Game> {
+        let filter = self.filter();
+        let games = games
+            .into_iter()
+            .filter(|game| filter.allows(&game.locale))
+            .collect::<Vec<_>>();
+
+        if self.one_game.is_empty() {
+            return games;
+        }
+
+        let chosen: HashSet<String> = select::one_game_one_rom(
+            games.iter().map(|game| select::Candidate {
+                key: &game.name,
+                name: &game.name,
+                parent: game.cloneof.as_deref(),
+                locale: game.locale.clone(),
+            }),
+            &self.one_game,
+        )
+        .into_iter()
+        .map(|choice| choice.key)
+        .collect();
+
+        games
+            .into_iter()
+            .filter(|game| chosen.contains(&game.name))
+            .collect()
+    }
+}
+
+#[derive(Args)]
+struct OptNoWrite {
+    /// never modify anything being verified, not even cached digests
+    #[clap(long = "no-write")]
+    no_write: bool,
+}
+
+impl OptNoWrite {
+    #[inline]
+    fn apply(&self) {
+        if self.no_write {
+            game::set_read_only();
+        }
+    }
+}
+
+// redirecting the descriptor itself, rather than a handle to it,
+// catches everything written to standard output, tables included
+#[cfg(unix)]
+fn redirect_stdout(file: File) -> Result<(), std::io::Error> {
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+
+    std::io::stdout().flush()?;
+
+    match unsafe { libc::dup2(file.as_raw_fd(), libc::STDOUT_FILENO) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn redirect_stdout(_file: File) -> Result<(), std::io::Error> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "--output is not supported on this platform",
+    ))
+}
+
+// warns about parts which would collide on case-insensitive filesystems
+#[inline]
+fn check_case_collisions(db: &game::GameDb) {
+    report::warn_case_collisions(
+        db.games_iter()
+            .map(|game| (game.name.as_str(), &game.parts)),
+    );
+}
+
+// warns about any of the imported DAT's parts
+// which would collide on case-insensitive filesystems
+fn check_dat_case_collisions(dat: &dat::DatFile) {
+    report::warn_case_collisions(dat.game_parts().map(|(game, parts)| match game {
+        "" => (dat.name(), parts),
+        game => (game, parts),
+    }));
+}
+
+#[derive(Args)]
+struct OptExtraInit {
+    /// extras .DAT file files
+    #[clap(parse(from_os_str))]
+    dats: Vec<PathBuf>,
+
+    /// completely replace old dat files
+    #[clap(long = "replace")]
+    replace: bool,
+
+    #[clap(flatten)]
+    directives: OptDirectives,
+}
+
+impl OptExtraInit {
+    fn execute(self) -> Result<(), Error> {
+        if self.replace {
+            clear_named_dbs(DIR_EXTRA)?;
+        }
+
+        let overrides = self.directives.overrides();
+
+        for dats in self
+            .dats
+            .into_iter()
+            .map(|file| dat::read_unflattened_dats(file, &overrides))
+        {
+            for dat in dats? {
+                check_dat_case_collisions(&dat);
+                let name = dat.name().to_owned();
+                write_named_db(DIR_EXTRA, &name, dat)?;
             }
         }
 
@@ -1046,13 +1722,20 @@ impl OptExtraDirs {
 struct OptExtraList {
     /// extras name
     name: Option<String>,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptExtraList {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+
         match self.name.as_deref() {
-            Some(name) => read_named_db::<dat::DatFile>(EXTRA, DIR_EXTRA, name)?.list(),
-            None => dat::DatFile::list_all(read_collected_dbs::<BTreeMap<_, _>, _>(DIR_EXTRA)),
+            Some(name) => {
+                report::display_dat_games(&read_named_db::<dat::DatFile>(EXTRA, DIR_EXTRA, name)?)
+            }
+            None => report::display_dats(read_collected_dbs::<BTreeMap<_, _>, _>(DIR_EXTRA)),
         }
 
         Ok(())
@@ -1076,27 +1759,40 @@ struct OptExtraVerify {
     /// verify all possible entries
     #[clap(long = "all")]
     all: bool,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptExtraVerify {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+        self.no_write.apply();
+
         let extra = match self.extra {
             Some(extra) => extra,
-            None => dirs::select_extra_name()?,
+            None => select_extra_name()?,
         };
 
         let datfile = read_named_db(EXTRA, DIR_EXTRA, &extra)?;
 
         let mut table = init_dat_table();
 
-        game::display_dat_results(
+        let summary = report::display_dat_results(
             &mut table,
             &datfile,
-            datfile.verify(dirs::extra_dir(self.dir, &extra).as_ref(), self.all),
+            datfile.verify(
+                dirs::extra_dir(self.dir, &extra, &Notices).as_ref(),
+                self.all,
+                &BarSink::default(),
+            ),
             self.failures,
         );
 
-        display_dat_table(table, None);
+        display_dat_table(table, summary, "tested");
 
         Ok(())
     }
@@ -1111,26 +1807,35 @@ struct OptExtraVerifyAll {
     /// verify all possible entries
     #[clap(long = "all")]
     all: bool,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptExtraVerifyAll {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+        self.no_write.apply();
+
         let mut total = game::VerifyResultsSummary::default();
 
         let mut table = init_dat_table();
 
         for (name, dir) in dirs::extra_dirs() {
             if let Ok(datfile) = read_named_db(EXTRA, DIR_EXTRA, &name) {
-                total += game::display_dat_results(
+                total += report::display_dat_results(
                     &mut table,
                     &datfile,
-                    datfile.verify(&dir, self.all),
+                    datfile.verify(&dir, self.all, &BarSink::default()),
                     self.failures,
                 );
             }
         }
 
-        display_dat_table(table, Some(total));
+        display_dat_table(table, total, "tested");
 
         Ok(())
     }
@@ -1153,37 +1858,49 @@ struct OptExtraAdd {
     /// verify all possible machines
     #[clap(long = "all")]
     all: bool,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
 }
 
 impl OptExtraAdd {
     fn execute(self) -> Result<(), Error> {
         let extra = match self.extra {
             Some(extra) => extra,
-            None => dirs::select_extra_name()?,
+            None => select_extra_name()?,
         };
 
         let datfile = read_named_db::<dat::DatFile>(EXTRA, DIR_EXTRA, &extra)?;
 
+        let extra_dir = dirs::extra_dir(self.dir, &extra, &Notices);
+
         let (input, input_url) = Resource::partition(self.input);
 
-        let mut roms = game::get_rom_sources(&input, &input_url, datfile.required_parts());
+        check_roots(&input, &input_url, extra_dir.as_ref())?;
+
+        let mut roms = game::get_rom_sources(
+            &input,
+            &input_url,
+            datfile.required_parts(),
+            &BarSink::default(),
+        );
 
         let mut table = init_dat_table();
 
-        game::display_dat_results(
-            &mut table,
-            &datfile,
-            datfile.add_and_verify(
-                &mut roms,
-                dirs::extra_dir(self.dir, &extra).as_ref(),
-                self.all,
-            )?,
-            true,
-        );
+        let (results, failed) = datfile.add_and_verify(
+            &mut roms,
+            extra_dir.as_ref(),
+            self.all,
+            self.dry_run,
+            &BarSink::default(),
+        )?;
 
-        display_dat_table(table, None);
+        let summary = report::display_dat_results(&mut table, &datfile, results, true);
 
-        Ok(())
+        display_dat_table(table, summary, "added");
+
+        report_failed_games(failed)
     }
 }
 
@@ -1196,31 +1913,51 @@ struct OptExtraAddAll {
     /// input file, directory, or URL
     #[clap(parse(from_os_str))]
     input: Vec<Resource>,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
 }
 
 impl OptExtraAddAll {
     fn execute(self) -> Result<(), Error> {
         let (input, input_url) = Resource::partition(self.input);
 
-        let mut parts = game::all_rom_sources(&input, &input_url);
+        let dirs = dirs::extra_dirs().collect::<Vec<_>>();
+
+        dirs.iter()
+            .try_for_each(|(_, dir)| check_roots(&input, &input_url, dir))?;
+
+        let mut parts = game::all_rom_sources(&input, &input_url, &BarSink::default());
 
         let mut total = game::VerifyResultsSummary::default();
 
         let mut table = init_dat_table();
 
-        for (name, dir) in dirs::extra_dirs() {
-            if let Ok(datfile) = read_named_db(EXTRA, DIR_EXTRA, &name) {
-                total += game::display_dat_results(
-                    &mut table,
-                    &datfile,
-                    datfile.add_and_verify(&mut parts, &dir, self.all)?,
-                    true,
+        let mut failed = Vec::new();
+
+        for (name, dir) in dirs {
+            if let Ok(datfile) = read_named_db::<dat::DatFile>(EXTRA, DIR_EXTRA, &name) {
+                let (results, dat_failed) = datfile.add_and_verify(
+                    &mut parts,
+                    &dir,
+                    self.all,
+                    self.dry_run,
+                    &BarSink::default(),
+                )?;
+
+                total += report::display_dat_results(&mut table, &datfile, results, true);
+
+                failed.extend(
+                    dat_failed
+                        .into_iter()
+                        .map(|(game, err)| (format!("{}/{}", name, game), err)),
                 );
             }
         }
-        display_dat_table(table, Some(total));
+        display_dat_table(table, total, "added");
 
-        Ok(())
+        report_failed_games(failed)
     }
 }
 
@@ -1277,29 +2014,32 @@ impl OptExtra {
 
 #[derive(Args)]
 struct OptRedumpInit {
-    /// Redump XML or Zip file
+    /// Redump XML or clrmamepro DAT, or Zip file
     #[clap(parse(from_os_str))]
     xml: Vec<PathBuf>,
+
+    #[clap(flatten)]
+    directives: OptDirectives,
 }
 
 impl OptRedumpInit {
     fn execute(self) -> Result<(), Error> {
         let mut split_db = split::SplitDb::new();
+        let overrides = self.directives.overrides();
 
         for file in self.xml.into_iter() {
             for (file, data) in dat::read_dats_from_file(file)? {
-                let datafile: crate::dat::Datafile =
-                    match quick_xml::de::from_reader(std::io::Cursor::new(data)) {
-                        Ok(dat) => dat,
-                        Err(error) => return Err(Error::XmlFile(FileError { file, error })),
-                    };
+                let datafile = dat::parse_datafile(&file, &data)?;
 
                 split_db.populate(&datafile);
 
-                let dat = crate::dat::DatFile::new_flattened(datafile)
+                let dat = crate::dat::DatFile::new_flattened(datafile, &overrides)
                     .map_err(|error| Error::InvalidSha1(FileError { file, error }))?;
 
-                write_named_db(DIR_REDUMP, &dat.name().to_owned(), dat)?;
+                check_dat_case_collisions(&dat);
+
+                let name = dat.name().to_owned();
+                write_named_db(DIR_REDUMP, &name, dat)?;
             }
         }
 
@@ -1348,13 +2088,27 @@ impl OptRedumpDirs {
 struct OptRedumpList {
     /// software list to use
     software_list: Option<String>,
+
+    #[clap(flatten)]
+    locale: OptLocale,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptRedumpList {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+
         match self.software_list.as_deref() {
-            Some(name) => read_named_db::<dat::DatFile>(REDUMP, DIR_REDUMP, name)?.list(),
-            None => dat::DatFile::list_all(read_collected_dbs::<BTreeMap<_, _>, _>(DIR_REDUMP)),
+            Some(name) => {
+                let mut datfile = read_named_db::<dat::DatFile>(REDUMP, DIR_REDUMP, name)?;
+                match self.locale.apply(&mut datfile) {
+                    Some(choices) => report::display_choices(&choices),
+                    None => report::display_dat_games(&datfile),
+                }
+            }
+            None => report::display_dats(read_collected_dbs::<BTreeMap<_, _>, _>(DIR_REDUMP)),
         }
 
         Ok(())
@@ -1378,30 +2132,44 @@ struct OptRedumpVerify {
     /// verify all possible entries
     #[clap(long = "all")]
     all: bool,
+
+    #[clap(flatten)]
+    locale: OptLocale,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptRedumpVerify {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+        self.no_write.apply();
+
         let software_list = match self.software_list {
             Some(software_list) => software_list,
-            None => dirs::select_redump_name()?,
+            None => select_redump_name()?,
         };
 
-        let datfile = read_named_db(REDUMP, DIR_REDUMP, &software_list)?;
+        let mut datfile: dat::DatFile = read_named_db(REDUMP, DIR_REDUMP, &software_list)?;
+        self.locale.apply(&mut datfile);
 
         let mut table = init_dat_table();
 
-        game::display_dat_results(
+        let summary = report::display_dat_results(
             &mut table,
             &datfile,
             datfile.verify(
-                dirs::redump_roms(self.root, &software_list).as_ref(),
+                dirs::redump_roms(self.root, &software_list, &Notices).as_ref(),
                 self.all,
+                &BarSink::default(),
             ),
             self.failures,
         );
 
-        display_dat_table(table, None);
+        display_dat_table(table, summary, "tested");
 
         Ok(())
     }
@@ -1424,36 +2192,52 @@ struct OptRedumpAdd {
     /// verify all possible machines
     #[clap(long = "all")]
     all: bool,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    locale: OptLocale,
 }
 
 impl OptRedumpAdd {
     fn execute(self) -> Result<(), Error> {
         let software_list = match self.software_list {
             Some(software_list) => software_list,
-            None => dirs::select_redump_name()?,
+            None => select_redump_name()?,
         };
 
-        let datfile = read_named_db::<dat::DatFile>(REDUMP, DIR_REDUMP, &software_list)?;
+        let mut datfile = read_named_db::<dat::DatFile>(REDUMP, DIR_REDUMP, &software_list)?;
+        self.locale.apply(&mut datfile);
+
+        let roms_dir = dirs::redump_roms(self.output, &software_list, &Notices);
 
         let (input, input_url) = Resource::partition(self.input);
 
-        let mut roms = game::get_rom_sources(&input, &input_url, datfile.required_parts());
+        check_roots(&input, &input_url, roms_dir.as_ref())?;
+
+        let mut roms = game::get_rom_sources(
+            &input,
+            &input_url,
+            datfile.required_parts(),
+            &BarSink::default(),
+        );
 
         let mut table = init_dat_table();
 
-        game::display_dat_results(
-            &mut table,
-            &datfile,
-            datfile.add_and_verify(
-                &mut roms,
-                dirs::redump_roms(self.output, &software_list).as_ref(),
-                self.all,
-            )?,
-            true,
-        );
-        display_dat_table(table, None);
+        let (results, failed) = datfile.add_and_verify(
+            &mut roms,
+            roms_dir.as_ref(),
+            self.all,
+            self.dry_run,
+            &BarSink::default(),
+        )?;
 
-        Ok(())
+        let summary = report::display_dat_results(&mut table, &datfile, results, true);
+        display_dat_table(table, summary, "added");
+
+        report_failed_games(failed)
     }
 }
 
@@ -1481,7 +2265,9 @@ impl OptRedumpSplit {
                 let mut bin_data = Vec::new();
                 File::open(bin_path).and_then(|mut f| f.read_to_end(&mut bin_data))?;
                 if let Some(exact_match) = matches.iter().find(|m| m.matches(&bin_data)) {
-                    exact_match.extract(&self.root, &bin_data)?;
+                    for path in exact_match.extract(&self.root, &bin_data)? {
+                        println!("* {}", path.display());
+                    }
                 }
             }
             Ok(())
@@ -1595,6 +2381,9 @@ struct OptNointroInit {
     /// completely replace old dat files
     #[clap(long = "replace")]
     replace: bool,
+
+    #[clap(flatten)]
+    directives: OptDirectives,
 }
 
 impl OptNointroInit {
@@ -1603,9 +2392,17 @@ impl OptNointroInit {
             clear_named_dbs(DIR_NOINTRO)?;
         }
 
-        for dats in self.dats.into_iter().map(dat::read_dats) {
+        let overrides = self.directives.overrides();
+
+        for dats in self
+            .dats
+            .into_iter()
+            .map(|file| dat::read_dats(file, &overrides))
+        {
             for dat in dats? {
-                write_named_db(DIR_NOINTRO, &dat.name().to_owned(), dat)?;
+                check_dat_case_collisions(&dat);
+                let name = dat.name().to_owned();
+                write_named_db(DIR_NOINTRO, &name, dat)?;
             }
         }
 
@@ -1652,13 +2449,27 @@ impl OptNointroDirs {
 struct OptNointroList {
     /// category name
     name: Option<String>,
+
+    #[clap(flatten)]
+    locale: OptLocale,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptNointroList {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+
         match self.name.as_deref() {
-            Some(name) => read_named_db::<dat::DatFile>(NOINTRO, DIR_NOINTRO, name)?.list(),
-            None => dat::DatFile::list_all(read_collected_dbs::<BTreeMap<_, _>, _>(DIR_NOINTRO)),
+            Some(name) => {
+                let mut datfile = read_named_db::<dat::DatFile>(NOINTRO, DIR_NOINTRO, name)?;
+                match self.locale.apply(&mut datfile) {
+                    Some(choices) => report::display_choices(&choices),
+                    None => report::display_dat_games(&datfile),
+                }
+            }
+            None => report::display_dats(read_collected_dbs::<BTreeMap<_, _>, _>(DIR_NOINTRO)),
         }
 
         Ok(())
@@ -1682,25 +2493,42 @@ struct OptNointroVerify {
     /// verify all possible entries
     #[clap(long = "all")]
     all: bool,
+
+    #[clap(flatten)]
+    locale: OptLocale,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptNointroVerify {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+        self.no_write.apply();
+
         let name = match self.name {
             Some(name) => name,
-            None => dirs::select_nointro_name()?,
+            None => select_nointro_name()?,
         };
 
-        let datfile = read_named_db(NOINTRO, DIR_NOINTRO, &name)?;
+        let mut datfile: dat::DatFile = read_named_db(NOINTRO, DIR_NOINTRO, &name)?;
+        self.locale.apply(&mut datfile);
 
         let mut table = init_dat_table();
-        game::display_dat_results(
+        let summary = report::display_dat_results(
             &mut table,
             &datfile,
-            datfile.verify(dirs::nointro_roms(self.roms, &name).as_ref(), self.all),
+            datfile.verify(
+                dirs::nointro_roms(self.roms, &name, &Notices).as_ref(),
+                self.all,
+                &BarSink::default(),
+            ),
             self.failures,
         );
-        display_dat_table(table, None);
+        display_dat_table(table, summary, "tested");
 
         Ok(())
     }
@@ -1715,23 +2543,32 @@ struct OptNointroVerifyAll {
     /// verify all possible entries
     #[clap(long = "all")]
     all: bool,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
 }
 
 impl OptNointroVerifyAll {
     fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+        self.no_write.apply();
+
         let mut total = game::VerifyResultsSummary::default();
         let mut table = init_dat_table();
         for (name, dir) in dirs::nointro_dirs() {
             if let Ok(datfile) = read_named_db(NOINTRO, DIR_NOINTRO, &name) {
-                total += game::display_dat_results(
+                total += report::display_dat_results(
                     &mut table,
                     &datfile,
-                    datfile.verify(&dir, self.all),
+                    datfile.verify(&dir, self.all, &BarSink::default()),
                     self.failures,
                 );
             }
         }
-        display_dat_table(table, Some(total));
+        display_dat_table(table, total, "tested");
 
         Ok(())
     }
@@ -1754,35 +2591,51 @@ struct OptNointroAdd {
     /// verify all possible machines
     #[clap(long = "all")]
     all: bool,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    locale: OptLocale,
 }
 
 impl OptNointroAdd {
     fn execute(self) -> Result<(), Error> {
         let name = match self.name {
             Some(name) => name,
-            None => dirs::select_nointro_name()?,
+            None => select_nointro_name()?,
         };
 
-        let datfile = read_named_db::<dat::DatFile>(NOINTRO, DIR_NOINTRO, &name)?;
+        let mut datfile = read_named_db::<dat::DatFile>(NOINTRO, DIR_NOINTRO, &name)?;
+        self.locale.apply(&mut datfile);
+
+        let roms_dir = dirs::nointro_roms(self.roms, &name, &Notices);
 
         let (input, input_url) = Resource::partition(self.input);
 
-        let mut roms = game::get_rom_sources(&input, &input_url, datfile.required_parts());
+        check_roots(&input, &input_url, roms_dir.as_ref())?;
 
-        let mut table = init_dat_table();
-        game::display_dat_results(
-            &mut table,
-            &datfile,
-            datfile.add_and_verify(
-                &mut roms,
-                dirs::nointro_roms(self.roms, &name).as_ref(),
-                self.all,
-            )?,
-            true,
+        let mut roms = game::get_rom_sources(
+            &input,
+            &input_url,
+            datfile.required_parts(),
+            &BarSink::default(),
         );
-        display_dat_table(table, None);
 
-        Ok(())
+        let mut table = init_dat_table();
+        let (results, failed) = datfile.add_and_verify(
+            &mut roms,
+            roms_dir.as_ref(),
+            self.all,
+            self.dry_run,
+            &BarSink::default(),
+        )?;
+
+        let summary = report::display_dat_results(&mut table, &datfile, results, true);
+        display_dat_table(table, summary, "added");
+
+        report_failed_games(failed)
     }
 }
 
@@ -1799,112 +2652,110 @@ struct OptNointroAddAll {
     /// verify all possible machines
     #[clap(long = "all")]
     all: bool,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
 }
 
 impl OptNointroAddAll {
     fn execute(self) -> Result<(), Error> {
         let (input, input_url) = Resource::partition(self.input);
 
-        let mut parts = game::all_rom_sources(&input, &input_url);
+        let dirs = dirs::extra_dirs().collect::<Vec<_>>();
+
+        dirs.iter()
+            .try_for_each(|(_, dir)| check_roots(&input, &input_url, dir))?;
+
+        let mut parts = game::all_rom_sources(&input, &input_url, &BarSink::default());
 
         let mut table = init_dat_table();
         let mut total = game::VerifyResultsSummary::default();
-        for (name, dir) in dirs::extra_dirs() {
-            if let Ok(datfile) = read_named_db(NOINTRO, DIR_NOINTRO, &name) {
-                total += game::display_dat_results(
-                    &mut table,
-                    &datfile,
-                    datfile.add_and_verify(&mut parts, &dir, self.all)?,
-                    self.failures,
+        let mut failed = Vec::new();
+        for (name, dir) in dirs {
+            if let Ok(datfile) = read_named_db::<dat::DatFile>(NOINTRO, DIR_NOINTRO, &name) {
+                let (results, dat_failed) = datfile.add_and_verify(
+                    &mut parts,
+                    &dir,
+                    self.all,
+                    self.dry_run,
+                    &BarSink::default(),
+                )?;
+
+                total += report::display_dat_results(&mut table, &datfile, results, self.failures);
+
+                failed.extend(
+                    dat_failed
+                        .into_iter()
+                        .map(|(game, err)| (format!("{}/{}", name, game), err)),
                 );
             }
         }
-        display_dat_table(table, Some(total));
+        display_dat_table(table, total, "added");
 
-        Ok(())
+        report_failed_games(failed)
     }
 }
 
 #[derive(Args)]
 struct OptIdentify {
-    /// ROMs or CHDs to identify
+    /// ROMs or CHDs to identify, or their SHA1 digests
     parts: Vec<PathBuf>,
 
     /// perform reverse lookup
     #[clap(short = 'l', long = "lookup")]
     lookup: bool,
+
+    /// digest format, use "hex", "upper", "sha1" or "base64"
+    #[clap(short = 'D', long = "digest", default_value = "hex")]
+    digest: game::DigestFormat,
 }
 
 impl OptIdentify {
     fn execute(self) -> Result<(), Error> {
-        use crate::dat::DatFile;
-        use crate::game::{GameDb, Part, RomSource};
+        use crate::game::{Part, RomSource};
         use prettytable::{cell, format, row, Table};
         use rayon::iter::{IntoParallelIterator, ParallelIterator};
-        use std::collections::{BTreeSet, HashMap};
+
+        let lookup_digests = self.lookup;
 
         let sources = self
             .parts
             .into_par_iter()
-            .map(RomSource::from_path)
-            .collect::<Result<Vec<_>, _>>()?
+            .map(|path| match path.to_str().map(game::parse_sha1) {
+                // a digest matches either kind of part
+                Some(Ok(sha1)) if !path.exists() => {
+                    let digest = path.to_string_lossy().into_owned();
+
+                    Ok(if lookup_digests {
+                        vec![
+                            (Part::Rom { sha1 }, digest.clone()),
+                            (Part::Disk { sha1 }, digest),
+                        ]
+                    } else {
+                        vec![(Part::Rom { sha1 }, digest)]
+                    })
+                }
+                _ => RomSource::from_path(path).map(|sources| {
+                    sources
+                        .into_iter()
+                        .map(|(part, source)| (part, source.to_string()))
+                        .collect()
+                }),
+            })
+            .collect::<Result<Vec<Vec<_>>, Error>>()?
             .into_iter()
             .flatten();
 
         if self.lookup {
-            let mut lookup: HashMap<&Part, BTreeSet<[&str; 4]>> = HashMap::default();
-
-            let mame_db: GameDb = read_game_db(MAME, DB_MAME).unwrap_or_default();
-            let mess_db: BTreeMap<String, GameDb> = read_collected_dbs(DIR_SL);
-
-            let dat_parts: [(&str, BTreeMap<String, DatFile>); 3] = [
-                ("extra", read_collected_dbs(DIR_EXTRA)),
-                ("nointro", read_collected_dbs(DIR_NOINTRO)),
-                ("redump", read_collected_dbs(DIR_REDUMP)),
-            ];
-
-            for game in mame_db.games_iter() {
-                for (rom, part) in game.parts.iter() {
-                    lookup
-                        .entry(part)
-                        .or_default()
-                        .insert(["mame", "", game.name.as_str(), rom]);
-                }
-            }
-
-            // invert caches into a Part -> [identifiers] lookup table
-            for (system, game_db) in mess_db.iter() {
-                for game in game_db.games_iter() {
-                    for (rom, part) in game.parts.iter() {
-                        lookup.entry(part).or_default().insert([
-                            "mess",
-                            system,
-                            game.name.as_str(),
-                            rom,
-                        ]);
-                    }
-                }
-            }
-
-            for (category, datfiles) in &dat_parts {
-                for (system, datfile) in datfiles.iter() {
-                    for (game, parts) in datfile.game_parts() {
-                        for (rom, part) in parts.iter() {
-                            lookup
-                                .entry(part)
-                                .or_default()
-                                .insert([category, system, game, rom]);
-                        }
-                    }
-                }
-            }
+            let mut lookup = index::Lookup::open(&Notices)?;
 
             let mut table = Table::new();
             table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
             table.get_format().column_separator('\u{2502}');
 
             for (part, source) in sources {
-                for [category, system, game, rom] in lookup.get(&part).into_iter().flatten() {
+                for [category, system, game, rom] in lookup.get(&part)? {
                     table.add_row(row![source, category, system, game, rom]);
                 }
             }
@@ -1912,10 +2763,221 @@ impl OptIdentify {
             table.printstd();
         } else {
             for (part, source) in sources {
-                println!("{}  {}", part.digest(), source);
+                println!("{}  {}", part.digest_as(self.digest), source);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptDownload {
+    /// file listing missing parts, one SHA1 and optional name per line
+    #[clap(parse(from_os_str))]
+    missing: PathBuf,
+
+    /// mirror URL, with optional "{sha1}" and "{name}" placeholders
+    #[clap(short = 'm', long = "mirror", required = true)]
+    mirrors: Vec<String>,
+
+    /// directory to download parts to
+    #[clap(short = 'd', long = "dir", parse(from_os_str), default_value = ".")]
+    dir: PathBuf,
+
+    /// number of simultaneous downloads
+    #[clap(short = 'j', long = "jobs", default_value = "4")]
+    jobs: usize,
+
+    /// number of times to retry each mirror
+    #[clap(long = "retries", default_value = "3")]
+    retries: u32,
+
+    /// combined download limit, in bytes per second
+    #[clap(long = "limit")]
+    limit: Option<u64>,
+
+    /// add downloaded parts to the named collection
+    #[clap(short = 'a', long = "add")]
+    add: Option<String>,
+}
+
+impl OptDownload {
+    fn execute(self) -> Result<(), Error> {
+        use rayon::iter::Either;
+        use rayon::prelude::*;
+
+        let missing = download::read_missing_list(&self.missing)?;
+        let downloader = download::Downloader::new(self.mirrors, self.retries, self.limit);
+
+        let pbar = progress::bar(missing.len() as u64)
+            .with_style(progress::verify_style())
+            .with_message("downloading");
+
+        let dir = &self.dir;
+
+        let (downloaded, failures): (Vec<PathBuf>, Vec<(String, Error)>) =
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(self.jobs.max(1))
+                .build()
+                .expect("unable to build download thread pool")
+                .install(|| {
+                    missing.par_iter().partition_map(|missing| {
+                        let result = downloader.download(missing, dir);
+                        pbar.inc(1);
+                        match result {
+                            Ok(path) => {
+                                progress::message(&pbar, format_args!("{}", path.display()));
+                                Either::Left(path)
+                            }
+                            Err(err) => Either::Right((missing.name.clone(), err)),
+                        }
+                    })
+                });
+
+        pbar.finish_and_clear();
+
+        for (name, err) in failures.iter() {
+            eprintln!("* {} : {}", name, err);
+        }
+
+        eprintln!(
+            "{} requested, {} downloaded",
+            missing.len(),
+            missing.len() - failures.len()
+        );
+
+        // only what was just downloaded is added,
+        // not whatever else happens to be in the directory
+        if let (Some(name), false) = (self.add, downloaded.is_empty()) {
+            OptCollectionAdd {
+                name,
+                input: downloaded.into_iter().map(Resource::File).collect(),
+                dry_run: false,
+                classes: Vec::new(),
+                locale: OptLocale::default(),
+            }
+            .execute()?;
+        }
+
+        match failures.len() {
+            0 => Ok(()),
+            n => Err(Error::FailedDownloads(n)),
+        }
+    }
+}
+
+#[derive(Args)]
+struct OptDetect {
+    /// directory to examine
+    #[clap(parse(from_os_str))]
+    root: PathBuf,
+
+    /// maximum number of files to sample
+    #[clap(short = 'n', long = "samples", default_value = "100")]
+    samples: usize,
+}
+
+impl OptDetect {
+    fn execute(self) -> Result<(), Error> {
+        use crate::dat::DatFile;
+        use crate::game::{GameDb, Part, RomSource};
+        use prettytable::{cell, format, row, Table};
+        use rayon::prelude::*;
+
+        let files = sub_files(self.root).collect::<Vec<_>>();
+
+        // spread samples evenly across the whole root
+        // since sets tend to be sorted by name
+        let step = (files.len() / self.samples.max(1)).max(1);
+
+        let sampled: HashSet<Part> = files
+            .into_par_iter()
+            .step_by(step)
+            .take(self.samples)
+            .flat_map_iter(|file| RomSource::from_path(file).unwrap_or_default())
+            .map(|(part, _)| part)
+            .collect();
+
+        if sampled.is_empty() {
+            eprintln!("* no files to sample");
+            return Ok(());
+        }
+
+        // the number of distinct sampled parts each database knows about
+        fn score<'p, I: Iterator<Item = &'p Part>>(sampled: &HashSet<Part>, parts: I) -> usize {
+            parts
+                .filter(|part| sampled.contains(part))
+                .collect::<HashSet<_>>()
+                .len()
+        }
+
+        let mut scores: Vec<(usize, &str, String)> = Vec::new();
+
+        if let Ok(db) = read_game_db::<GameDb>(MAME, DB_MAME) {
+            scores.push((
+                score(
+                    &sampled,
+                    db.games_iter().flat_map(|game| game.parts.values()),
+                ),
+                MAME,
+                String::new(),
+            ));
+        }
+
+        for (name, db) in read_collected_dbs::<Vec<_>, GameDb>(DIR_SL) {
+            scores.push((
+                score(
+                    &sampled,
+                    db.games_iter().flat_map(|game| game.parts.values()),
+                ),
+                MESS,
+                name,
+            ));
+        }
+
+        for (category, db_dir) in [
+            (EXTRA, DIR_EXTRA),
+            (NOINTRO, DIR_NOINTRO),
+            (REDUMP, DIR_REDUMP),
+        ] {
+            for (name, datfile) in read_collected_dbs::<Vec<_>, DatFile>(db_dir) {
+                scores.push((
+                    score(
+                        &sampled,
+                        datfile.game_parts().flat_map(|(_, parts)| parts.values()),
+                    ),
+                    category,
+                    name,
+                ));
             }
         }
 
+        scores.retain(|(matched, _, _)| *matched > 0);
+        scores.sort_by(|(a, _, _), (b, _, _)| b.cmp(a));
+
+        if scores.is_empty() {
+            eprintln!("* no databases match {} sampled parts", sampled.len());
+            return Ok(());
+        }
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.get_format().column_separator('\u{2502}');
+        table.set_titles(row![r->"Matched", "", ""]);
+
+        for (matched, category, name) in scores {
+            table.add_row(row![
+                r->matched,
+                category,
+                name
+            ]);
+        }
+
+        table.printstd();
+
+        eprintln!("{} parts sampled", sampled.len());
+
         Ok(())
     }
 }
@@ -1936,6 +2998,10 @@ enum OptCache {
     /// find duplicate files and link them together
     #[clap(name = "link-dupes")]
     LinkDupes(OptCacheLinkDupes),
+
+    /// forget the parts found in source files on earlier runs
+    #[clap(name = "clear-catalog")]
+    ClearCatalog,
 }
 
 impl OptCache {
@@ -1945,6 +3011,7 @@ impl OptCache {
             OptCache::Delete(o) => o.execute(),
             OptCache::Verify(o) => o.execute(),
             OptCache::LinkDupes(o) => o.execute(),
+            OptCache::ClearCatalog => catalog::clear(),
         }
     }
 }
@@ -1959,10 +3026,10 @@ struct OptCacheAdd {
 impl OptCacheAdd {
     fn execute(self) -> Result<(), Error> {
         use crate::game::Part;
-        use indicatif::{ParallelProgressIterator, ProgressBar};
+        use indicatif::ParallelProgressIterator;
         use rayon::prelude::*;
 
-        let pb = ProgressBar::new_spinner().with_message("locating files");
+        let pb = progress::spinner().with_message("locating files");
         let files = {
             pb.wrap_iter(
                 self.paths
@@ -1974,8 +3041,8 @@ impl OptCacheAdd {
         };
         pb.finish_and_clear();
 
-        let pb = ProgressBar::new(files.len() as u64)
-            .with_style(crate::game::verify_style())
+        let pb = progress::bar(files.len() as u64)
+            .with_style(progress::verify_style())
             .with_message("adding cache entries");
 
         files
@@ -1983,7 +3050,7 @@ impl OptCacheAdd {
             .progress_with(pb.clone())
             .for_each(|file: PathBuf| match Part::from_path(&file) {
                 Ok(part) => part.set_xattr(&file),
-                Err(err) => pb.println(format!("{} : {}", file.display(), err)),
+                Err(err) => progress::message(&pb, format_args!("{} : {}", file.display(), err)),
             });
 
         pb.finish_and_clear();
@@ -2002,9 +3069,8 @@ struct OptCacheDelete {
 impl OptCacheDelete {
     fn execute(self) -> Result<(), Error> {
         use crate::game::Part;
-        use indicatif::ProgressBar;
 
-        let pb = ProgressBar::new_spinner().with_message("removing cache entries");
+        let pb = progress::spinner().with_message("removing cache entries");
 
         for file in pb.wrap_iter(
             self.paths
@@ -2031,19 +3097,19 @@ struct OptCacheVerify {
 impl OptCacheVerify {
     fn execute(self) -> Result<(), Error> {
         use crate::game::Part;
-        use indicatif::{ParallelProgressIterator, ProgressBar};
+        use indicatif::ParallelProgressIterator;
         use rayon::prelude::*;
         use std::collections::HashMap;
 
-        let pb = ProgressBar::new_spinner().with_message("locating files");
+        let pb = progress::spinner().with_message("locating files");
         let files = {
             pb.wrap_iter(self.paths.into_iter().flat_map(unique_sub_files))
                 .collect::<Vec<PathBuf>>()
         };
         pb.finish_and_clear();
 
-        let pb = ProgressBar::new(files.len() as u64)
-            .with_style(crate::game::verify_style())
+        let pb = progress::bar(files.len() as u64)
+            .with_style(progress::verify_style())
             .with_message("reading cache entries");
 
         let cache = files
@@ -2054,8 +3120,8 @@ impl OptCacheVerify {
 
         pb.finish_and_clear();
 
-        let pb = ProgressBar::new(cache.len() as u64)
-            .with_style(crate::game::verify_style())
+        let pb = progress::bar(cache.len() as u64)
+            .with_style(progress::verify_style())
             .with_message("verifying cache entries");
 
         cache
@@ -2063,8 +3129,10 @@ impl OptCacheVerify {
             .progress_with(pb.clone())
             .for_each(|(file, part)| match part.is_valid(file) {
                 Ok(true) => { /* do nothing*/ }
-                Ok(false) => pb.println(format!("BAD : {}", file.display())),
-                Err(err) => pb.println(format!("ERROR : {} : {}", file.display(), err)),
+                Ok(false) => progress::message(&pb, format_args!("BAD : {}", file.display())),
+                Err(err) => {
+                    progress::message(&pb, format_args!("ERROR : {} : {}", file.display(), err))
+                }
             });
 
         pb.finish_and_clear();
@@ -2082,45 +3150,2613 @@ struct OptCacheLinkDupes {
 
 impl OptCacheLinkDupes {
     fn execute(self) -> Result<(), Error> {
-        use crate::duplicates::{DuplicateFiles, Duplicates};
-        use indicatif::ProgressBar;
+        for path in self.paths.iter() {
+            snapshot::before_modifying(path, &Notices)?;
+        }
 
-        let mut db = DuplicateFiles::default();
+        link_duplicates(self.paths, "linking duplicate files", false);
 
-        let pb = ProgressBar::new_spinner()
-            .with_style(crate::game::find_files_style())
-            .with_message("linking duplicate files");
+        Ok(())
+    }
+}
+
+// replaces each duplicate file under roots with a hard link
+// to the first file found with the same contents on the same device,
+// or if dry_run is set, only tallies what linking would reclaim,
+// returning the number of files linked and the bytes reclaimed
+fn link_duplicates(roots: Vec<PathBuf>, message: &'static str, dry_run: bool) -> (usize, u64) {
+    use crate::duplicates::{DuplicateFiles, Duplicates};
+
+    let mut db = DuplicateFiles::default();
+    let mut linked = 0;
+    let mut reclaimed = 0;
+
+    let pb = progress::spinner()
+        .with_style(progress::find_files_style())
+        .with_message(message);
+
+    for file in pb.wrap_iter(roots.into_iter().flat_map(sub_files)) {
+        let result = match db.get_or_add(file) {
+            Ok(None) => continue,
+            Ok(Some((duplicate, _))) if pin::is_pinned(&duplicate) => {
+                progress::message(&pb, format_args!("{} : pinned", duplicate.display()));
+                continue;
+            }
+            Ok(Some((duplicate, original))) => match if dry_run {
+                duplicates::reclaimable(&duplicate)
+            } else {
+                duplicates::link(original, &duplicate)
+            } {
+                Ok(bytes) => Ok((duplicate, original, bytes)),
+                Err(err) => Err((duplicate, err)),
+            },
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok((duplicate, original, bytes)) => {
+                linked += 1;
+                reclaimed += bytes;
+                progress::message(
+                    &pb,
+                    format_args!("{} \u{2192} {}", original.display(), duplicate.display()),
+                );
+            }
+            Err((file, err)) => progress::message(&pb, format_args!("{}: {}", file.display(), err)),
+        }
+    }
+
+    pb.finish_and_clear();
+
+    (linked, reclaimed)
+}
+
+#[derive(Args)]
+struct OptCollectionList {}
+
+impl OptCollectionList {
+    fn execute(self) -> Result<(), Error> {
+        for (name, config) in collection::configs()? {
+            println!(
+                "{} : {} : {}",
+                name,
+                config.db,
+                config
+                    .roots
+                    .iter()
+                    .map(|root| root.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptCollectionVerify {
+    /// collection to verify
+    name: String,
+
+    /// verify all possible machines
+    #[clap(long = "all")]
+    all: bool,
+
+    /// display only failures
+    #[clap(long = "failures")]
+    failures: bool,
+
+    /// decompress CHDs and verify their data
+    #[clap(long = "deep")]
+    deep: bool,
+
+    /// suggest what to do about each failure
+    #[clap(long = "hints")]
+    hints: bool,
+
+    /// write a DAT of only the missing and bad parts to the given file
+    #[clap(long = "fixdat", parse(from_os_str))]
+    fixdat: Option<PathBuf>,
+
+    /// write an HTML report of the results to the given file
+    #[clap(long = "html", parse(from_os_str))]
+    html: Option<PathBuf>,
+
+    /// report Zip archives in neither TorrentZip nor emuman's canonical form
+    #[clap(long = "canonical")]
+    canonical: bool,
+
+    /// results format, use "text" or "json"
+    #[clap(long = "format", default_value = "text")]
+    format: report::ResultsFormat,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
+}
+
+impl OptCollectionVerify {
+    fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+        self.no_write.apply();
+
+        let collection = collection::Collection::open(&self.name)?;
+
+        let mut total = game::VerifyResultsSummary::default();
+        let mut report = VerifyReport::new(
+            self.failures,
+            self.hints,
+            self.fixdat,
+            self.html,
+            self.canonical,
+            false,
+            self.format,
+        )
+        .with_acks(&collection.name);
+
+        for root in collection.roots.iter() {
+            let games = if self.all {
+                collection.all_games()
+            } else {
+                collection.games_in(root)?
+            };
+
+            total += verify(
+                &collection.db,
+                root,
+                None,
+                &games,
+                self.deep,
+                collection.is_zip(),
+                &mut report,
+            );
+        }
+
+        report.finish(&collection.name, collection.db.description())?;
+
+        collection.record_verify(&total)
+    }
+}
+
+#[derive(Args)]
+struct OptCollectionAdd {
+    /// collection to add to
+    name: String,
+
+    /// input file, directory, or URL
+    #[clap(parse(from_os_str))]
+    input: Vec<Resource>,
+
+    /// show which source would provide each part without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+
+    /// only include games of the given class, like "arcade", "mechanical", "bios", "device" or "console"
+    #[clap(long = "class")]
+    classes: Vec<game::Class>,
+
+    #[clap(flatten)]
+    locale: OptLocale,
+}
+
+impl OptCollectionAdd {
+    fn execute(self) -> Result<(), Error> {
+        let collection = collection::Collection::open(&self.name)?;
+        let classes = self.classes;
+
+        let (input, input_url) = Resource::partition(self.input);
+
+        collection
+            .roots
+            .iter()
+            .try_for_each(|root| check_roots(&input, &input_url, root))?;
+
+        let root = match collection.primary_root() {
+            Some(root) => root,
+            None => return Ok(()),
+        };
+
+        let mut roms = game::all_rom_sources(&input, &input_url, &BarSink::default());
+
+        report_failed_games(add_and_verify(
+            &collection.name,
+            collection.db.description(),
+            &mut roms,
+            root,
+            None,
+            self.locale
+                .select_games(
+                    collection
+                        .db
+                        .games_iter()
+                        .filter(|game| !collection.is_ignored(&game.name))
+                        .filter(|game| game.in_classes(&classes))
+                        .collect(),
+                )
+                .into_iter(),
+            self.dry_run,
+            collection.is_zip(),
+        )?)
+    }
+}
+
+#[derive(Args)]
+struct OptCollectionInit {
+    /// Logiqx XML or clrmamepro DAT, or Zip file of them
+    #[clap(parse(from_os_str))]
+    dats: Vec<PathBuf>,
+}
+
+impl OptCollectionInit {
+    fn execute(self) -> Result<(), Error> {
+        for file in self.dats.into_iter() {
+            for (file, data) in dat::read_dats_from_file(file)? {
+                let datafile = dat::parse_datafile(&file, &data)?;
+
+                let name = datafile.name().to_owned();
+
+                let (db, skipped) = datafile
+                    .into_game_db()
+                    .map_err(|error| Error::InvalidSha1(FileError { file, error }))?;
+
+                write_named_db(DIR_DAT, &name, db)?;
+
+                match skipped {
+                    0 => eprintln!("* imported \"{}\"", name),
+                    1 => eprintln!("* imported \"{}\", skipping 1 ROM without SHA1", name),
+                    n => eprintln!("* imported \"{}\", skipping {} ROMs without SHA1", name, n),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptCollectionRepack {
+    /// rewrite archives with correct contents into canonical form
+    #[clap(long = "canonical", required = true)]
+    canonical: bool,
+
+    /// collection name
+    name: String,
+}
+
+impl OptCollectionRepack {
+    fn execute(self) -> Result<(), Error> {
+        let collection = collection::Collection::open(&self.name)?;
+
+        if !collection.is_zip() {
+            eprintln!(
+                "* {} : games are not stored as Zip archives",
+                collection.name
+            );
+            return Ok(());
+        }
+
+        let mut repacked = 0;
+
+        for root in collection.roots.iter() {
+            let games = collection.games_in(root)?;
+            let mut results = collection
+                .db
+                .verify(root, &games, false, true, &BarSink::default());
+            collection.db.verify_canonical(root, &mut results);
+
+            for failure in results.values().flatten() {
+                if let game::VerifyFailure::NotCanonical { path } = failure {
+                    if pin::is_pinned(path) {
+                        println!("PINNED : {}", path.display());
+                        continue;
+                    }
+
+                    snapshot::before_modifying(root, &Notices)?;
+
+                    match torrentzip::canonicalize(path) {
+                        Ok(()) => {
+                            println!("{} \u{2192} canonical", path.display());
+                            repacked += 1;
+                        }
+                        Err(err) => println!("ERROR : {} : {}", path.display(), err),
+                    }
+                }
+            }
+        }
+
+        eprintln!("{} repacked", repacked);
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptCollectionMisfiled {
+    /// collection name
+    name: String,
+
+    /// offer to move each misfiled game to the first root
+    /// of the collection it belongs to
+    #[clap(long = "move")]
+    move_games: bool,
+}
+
+impl OptCollectionMisfiled {
+    fn execute(self) -> Result<(), Error> {
+        use game::{Game, Part, RomSource};
+        use indicatif::ParallelProgressIterator;
+        use rayon::prelude::*;
+        use std::cmp::Reverse;
+
+        let collection = collection::Collection::open(&self.name)?;
+
+        if collection.is_hashed() {
+            eprintln!("* {} : games are not stored by name", collection.name);
+            return Ok(());
+        }
+
+        let others = collection::configs()?
+            .into_keys()
+            .filter(|name| name != &collection.name)
+            .map(|name| collection::Collection::open(&name))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // the other collections' games each part is found in
+        let mut owners: HashMap<&Part, Vec<(usize, &Game)>> = HashMap::default();
+
+        for (index, other) in others.iter().enumerate() {
+            for game in other.db.games_iter() {
+                if !other.is_ignored(&game.name) {
+                    for part in game.parts.values() {
+                        owners.entry(part).or_default().push((index, game));
+                    }
+                }
+            }
+        }
+
+        // entries which aren't games of this collection, under any name
+        let mut entries = Vec::new();
+
+        for root in collection.roots.iter() {
+            for entry in root.read_dir()?.filter_map(|e| e.ok()) {
+                let path = entry.path();
+
+                if !entry_game(entry, collection.is_zip()).is_some_and(|game| {
+                    collection.db.is_game(&game) || collection.is_ignored(&game)
+                }) {
+                    entries.push(path);
+                }
+            }
+        }
+
+        let pb = progress::bar(entries.len() as u64)
+            .with_style(progress::verify_style())
+            .with_message("identifying entries");
+
+        // an entry belongs to the largest game it holds every part of,
+        // so a parent isn't mistaken for the smaller clone it contains
+        let mut misfiled = entries
+            .into_par_iter()
+            .progress_with(pb.clone())
+            .filter_map(|path| {
+                let parts = sub_files(path.clone())
+                    .flat_map(|file| RomSource::from_path(file).unwrap_or_default())
+                    .map(|(part, _)| part)
+                    .collect::<HashSet<_>>();
+
+                parts
+                    .iter()
+                    .filter_map(|part| owners.get(part))
+                    .flatten()
+                    .filter(|(_, game)| game.parts.values().all(|part| parts.contains(part)))
+                    .max_by_key(|(index, game)| {
+                        (game.parts.len(), Reverse(*index), Reverse(&game.name))
+                    })
+                    .map(|(index, game)| (path, *index, *game))
+            })
+            .collect::<Vec<_>>();
+
+        pb.finish_and_clear();
+
+        misfiled.sort_unstable_by(|(a, _, _), (b, _, _)| a.cmp(b));
+
+        for (path, index, game) in misfiled {
+            let other = &others[index];
+
+            println!(
+                "MISFILED : {} \u{2192} {}/{}",
+                path.display(),
+                other.name,
+                game.name
+            );
+
+            if self.move_games {
+                if pin::contains_pinned(&path) {
+                    println!("PINNED : {}", path.display());
+                } else if report::confirm(&format!("move to {} as {}?", other.name, game.name))? {
+                    move_misfiled(&path, other, game)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// moves a misfiled game into the first root of the collection it belongs to
+// under its proper name and verifies it there, where an entry already stored
+// the way that collection stores games need only be renamed
+// while any other has its parts added and is then removed if complete
+fn move_misfiled(path: &Path, to: &collection::Collection, game: &game::Game) -> Result<(), Error> {
+    let root = match to.primary_root() {
+        Some(root) => root,
+        None => return Ok(()),
+    };
+
+    to.use_storage();
+
+    let target = if to.is_zip() {
+        root.join(format!("{}.zip", game.name))
+    } else {
+        root.join(&game.name)
+    };
+
+    let stored_alike = if to.is_zip() {
+        path.is_file() && is_zip(File::open(path)?)?
+    } else {
+        path.is_dir()
+    };
+
+    snapshot::before_modifying(path, &Notices)?;
+    snapshot::before_modifying(root, &Notices)?;
+
+    // renaming across filesystems fails, leaving it to be added instead
+    let renamed = stored_alike
+        && !to.is_hashed()
+        && !target.exists()
+        && std::fs::rename(path, &target).is_ok();
+
+    if renamed {
+        journal::record(journal::Entry::Renamed {
+            from: trash::absolute(path),
+            to: trash::absolute(&target),
+        })?;
+    }
+
+    if !renamed {
+        let sources = [path.to_path_buf()];
+        let mut roms = game::all_rom_sources(&sources, &[], &BarSink::default());
+
+        report_failed_games(add_and_verify(
+            &to.name,
+            to.db.description(),
+            &mut roms,
+            root,
+            None,
+            std::iter::once(game),
+            false,
+            to.is_zip(),
+        )?)?;
+    }
+
+    let games = HashSet::from([game.name.clone()]);
+    let results = to
+        .db
+        .verify(root, &games, false, to.is_zip(), &BarSink::default());
+
+    for (game, failures) in results.iter() {
+        report::display_all_results(game, failures);
+    }
+
+    if !renamed && results.values().all(|failures| failures.is_empty()) {
+        let files: Vec<PathBuf> = sub_files(path.to_path_buf()).collect();
+
+        // the entry stays as long as anything in it is pinned
+        if files.iter().any(|file| pin::is_pinned(file)) {
+            println!("PINNED : {}", path.display());
+            return Ok(());
+        }
+
+        safety::before_deleting_all(files.iter().map(|file| file.as_path()))?;
+
+        if path.is_dir() {
+            trash::remove_dir_all(path)?;
+        } else {
+            trash::remove_file(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Subcommand)]
+enum OptCollection {
+    /// import Logiqx or clrmamepro DAT files as collection databases
+    #[clap(name = "init")]
+    Init(OptCollectionInit),
+
+    /// list defined collections
+    #[clap(name = "list")]
+    List(OptCollectionList),
+
+    /// verify games in all of a collection's roots
+    #[clap(name = "verify")]
+    Verify(OptCollectionVerify),
+
+    /// add games to a collection's first root
+    #[clap(name = "add")]
+    Add(OptCollectionAdd),
+
+    /// rewrite a collection's Zip archives
+    #[clap(name = "repack")]
+    Repack(OptCollectionRepack),
+
+    /// find games in a collection's roots which belong to another collection
+    #[clap(name = "misfiled")]
+    Misfiled(OptCollectionMisfiled),
+}
+
+impl OptCollection {
+    fn execute(self) -> Result<(), Error> {
+        match self {
+            OptCollection::Init(o) => o.execute(),
+            OptCollection::List(o) => o.execute(),
+            OptCollection::Verify(o) => o.execute(),
+            OptCollection::Add(o) => o.execute(),
+            OptCollection::Repack(o) => o.execute(),
+            OptCollection::Misfiled(o) => o.execute(),
+        }
+    }
+}
+
+#[derive(Args)]
+struct OptVerifyAll {
+    /// decompress CHDs and verify their data
+    #[clap(long = "deep")]
+    deep: bool,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+
+    #[clap(flatten)]
+    output: OptOutput,
+}
+
+impl OptVerifyAll {
+    fn execute(self) -> Result<(), Error> {
+        use prettytable::{cell, format, row, Table};
+
+        self.output.redirect()?;
+        self.no_write.apply();
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+        table.get_format().column_separator('\u{2502}');
+        table.set_titles(row![
+            r->"Tested",
+            r->"OK",
+            r->"Missing",
+            r->"Bad",
+            r->"Extra",
+            r->"Errors",
+            ""
+        ]);
+
+        let mut total = game::VerifyResultsSummary::default();
+        let mut collections = 0;
+        let mut unhealthy = 0;
+
+        for name in collection::configs()?.into_keys() {
+            collections += 1;
+
+            // a collection which can't be opened shouldn't
+            // keep the rest of the library from being checked
+            let collection = match collection::Collection::open(&name) {
+                Ok(collection) => collection,
+                Err(err) => {
+                    eprintln!("* {} : {}", name, err);
+                    game::record_outcome(game::Outcome::Errors);
+                    table.add_row(row![r->"", r->"", r->"", r->"", r->"", r->"", name]);
+                    unhealthy += 1;
+                    continue;
+                }
+            };
+
+            let mut summary = game::VerifyResultsSummary::default();
+
+            for root in collection.roots.iter() {
+                let games = collection.games_in(root)?;
+                let results = collection.db.verify(
+                    root,
+                    &games,
+                    self.deep,
+                    collection.is_zip(),
+                    &BarSink::default(),
+                );
+
+                for (game, failures) in results.iter() {
+                    report::display_bad_results(&format!("{}/{}", name, game), failures);
+                }
+
+                summary += game::VerifyResultsSummary::new(results.values());
+            }
+
+            collection.record_verify(&summary)?;
+
+            if summary.successes < summary.total {
+                unhealthy += 1;
+            }
+
+            table.add_row(row![
+                r->summary.total,
+                r->summary.successes,
+                r->summary.missing,
+                r->summary.bad,
+                r->summary.extra,
+                r->summary.errors,
+                name
+            ]);
+
+            total += summary;
+        }
+
+        if collections == 0 {
+            eprintln!("* no collections defined");
+            return Ok(());
+        }
+
+        if table.len() > 1 {
+            table.add_row(row![
+                r->total.total,
+                r->total.successes,
+                r->total.missing,
+                r->total.bad,
+                r->total.extra,
+                r->total.errors,
+                "Total"
+            ]);
+        }
+        table.printstd();
+
+        report::display_totals(&total, "tested");
+
+        match unhealthy {
+            0 => eprintln!("* all {} collections OK", collections),
+            n => eprintln!("* {} of {} collections need attention", n, collections),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptStats {
+    /// collections to summarize, or all of them
+    names: Vec<String>,
+
+    /// results format, use "text" or "json"
+    #[clap(long = "format", default_value = "text")]
+    format: report::ResultsFormat,
+
+    #[clap(flatten)]
+    output: OptOutput,
+}
+
+impl OptStats {
+    fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+
+        let names = if self.names.is_empty() {
+            collection::configs()?.into_keys().collect()
+        } else {
+            self.names
+        };
+
+        let stats = names
+            .iter()
+            .map(|name| collection::Collection::open(name).and_then(|c| stats::Stats::new(&c)))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        match self.format {
+            report::ResultsFormat::Text => stats::display_stats(&stats),
+            report::ResultsFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&stats).expect("unable to serialize stats")
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptInfoInit {
+    /// history.dat or mameinfo.dat files
+    #[clap(parse(from_os_str))]
+    dats: Vec<PathBuf>,
+}
+
+impl OptInfoInit {
+    fn execute(self) -> Result<(), Error> {
+        let mut db = info::InfoDb::default();
+
+        for file in self.dats {
+            let imported = File::open(&file)
+                .map(std::io::BufReader::new)
+                .and_then(|r| db.import(r))?;
+
+            if imported == 0 {
+                eprintln!("* {} : no entries found", file.display());
+            }
+        }
+
+        write_game_db(DB_INFO, db)
+    }
+}
+
+#[derive(Subcommand)]
+enum OptInfoCommand {
+    /// initialize internal database from history.dat or mameinfo.dat
+    #[clap(name = "init")]
+    Init(OptInfoInit),
+}
+
+#[derive(Args)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct OptInfo {
+    #[clap(subcommand)]
+    command: Option<OptInfoCommand>,
+
+    /// game to describe, or "list/software" for software list entries
+    #[clap(required = true)]
+    game: Option<String>,
+}
+
+impl OptInfo {
+    fn execute(self) -> Result<(), Error> {
+        match (self.command, self.game) {
+            (Some(OptInfoCommand::Init(o)), _) => o.execute(),
+            (None, Some(game)) => {
+                report::display_info(&read_game_db::<info::InfoDb>(INFO, DB_INFO)?, &game)
+                    .ok_or(Error::NoSuchSoftware(game))
+            }
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[derive(Args)]
+struct OptSamplesInit {
+    /// "mame -listxml" output, "mame -listsamples" output or samples DAT files
+    #[clap(parse(from_os_str))]
+    files: Vec<PathBuf>,
+}
+
+impl OptSamplesInit {
+    fn execute(self) -> Result<(), Error> {
+        let mut db = samples::SampleDb::default();
+
+        for file in self.files {
+            let imported = std::fs::read_to_string(&file)
+                .map_err(Error::IO)
+                .and_then(|data| db.import(&data))?;
+
+            if imported == 0 {
+                eprintln!("* {} : no sample sets found", file.display());
+            }
+        }
+
+        write_game_db(DB_SAMPLES, db)
+    }
+}
+
+#[derive(Args)]
+struct OptSamplesVerify {
+    /// samples directory
+    #[clap(short = 's', long = "samples", parse(from_os_str))]
+    samples: PathBuf,
+
+    /// verify all possible sample sets
+    #[clap(long = "all")]
+    all: bool,
+
+    /// display only failures
+    #[clap(long = "failures")]
+    failures: bool,
+
+    #[clap(flatten)]
+    output: OptOutput,
+}
+
+impl OptSamplesVerify {
+    fn execute(self) -> Result<(), Error> {
+        self.output.redirect()?;
+
+        let db: samples::SampleDb = read_game_db(SAMPLES, DB_SAMPLES)?;
+
+        let sets = if self.all {
+            db.all_sets()
+        } else {
+            db.sets_in(&self.samples)?
+        };
+
+        let results = db.verify(&self.samples, &sets);
+
+        display_sample_results(&results, self.failures);
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptSamplesAdd {
+    /// output directory
+    #[clap(short = 's', long = "samples", parse(from_os_str))]
+    samples: PathBuf,
+
+    /// sample set to add
+    #[clap(short = 'g', long = "game")]
+    sets: Vec<String>,
+
+    /// input files or directories
+    #[clap(parse(from_os_str))]
+    input: Vec<PathBuf>,
+
+    /// show which source would provide each sample without adding anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+}
+
+impl OptSamplesAdd {
+    fn execute(self) -> Result<(), Error> {
+        let db: samples::SampleDb = read_game_db(SAMPLES, DB_SAMPLES)?;
+
+        let all = db.all_sets();
+
+        let sets = if self.sets.is_empty() {
+            all
+        } else {
+            self.sets
+                .iter()
+                .map(|set| {
+                    all.get(set.as_str())
+                        .copied()
+                        .ok_or_else(|| Error::NoSuchSoftware(set.clone()))
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        let results = db.add(
+            &self.samples,
+            &self.input,
+            &sets,
+            self.dry_run,
+            &Notices,
+            |source, target| println!("{} \u{2192} {}", source, target.display()),
+        )?;
+
+        // only sets which have some samples present are worth reporting
+        let results = results
+            .into_iter()
+            .filter(|(set, failures)| {
+                !self.sets.is_empty()
+                    || failures.len() < db.set_len(set)
+                    || failures
+                        .iter()
+                        .any(|f| !matches!(f, samples::SampleFailure::Missing { .. }))
+            })
+            .collect();
+
+        display_sample_results(&results, true);
+
+        Ok(())
+    }
+}
+
+fn display_sample_results(
+    results: &BTreeMap<&str, Vec<samples::SampleFailure>>,
+    only_failures: bool,
+) {
+    let successes = report::display_sample_results(results, only_failures);
+
+    eprintln!(
+        "{} tested, {} OK, {} with failures",
+        results.len(),
+        successes,
+        results.len() - successes
+    );
+
+    if successes < results.len() {
+        game::record_outcome(game::Outcome::Failures);
+    }
+}
+
+#[derive(Subcommand)]
+enum OptSamples {
+    /// initialize internal database from sample lists
+    #[clap(name = "init")]
+    Init(OptSamplesInit),
+
+    /// verify sample sets, reported apart from ROMs
+    #[clap(name = "verify")]
+    Verify(OptSamplesVerify),
+
+    /// add samples to sample sets from other sets
+    #[clap(name = "add")]
+    Add(OptSamplesAdd),
+}
+
+impl OptSamples {
+    fn execute(self) -> Result<(), Error> {
+        match self {
+            OptSamples::Init(o) => o.execute(),
+            OptSamples::Verify(o) => o.execute(),
+            OptSamples::Add(o) => o.execute(),
+        }
+    }
+}
+
+#[derive(Args)]
+struct OptTag {
+    /// remove the given tags rather than adding them
+    #[clap(long = "remove")]
+    remove: bool,
+
+    /// MAME machine to tag
+    game: String,
+
+    /// tags to add, or to display the game's tags if none
+    tags: Vec<String>,
+}
+
+impl OptTag {
+    fn execute(self) -> Result<(), Error> {
+        let mut db: tags::TagDb = read_game_db(TAGS, DB_TAGS).unwrap_or_default();
+
+        if self.tags.is_empty() {
+            for tag in db.get(&self.game).into_iter().flat_map(|c| c.tags.iter()) {
+                println!("{}", tag);
+            }
+            return Ok(());
+        }
+
+        if self.remove {
+            db.untag(&self.game, &self.tags);
+        } else {
+            validate_mame_game(&self.game)?;
+            db.tag(&self.game, self.tags);
+        }
+
+        write_game_db(DB_TAGS, &db)
+    }
+}
+
+#[derive(Args)]
+struct OptNote {
+    /// remove the game's note
+    #[clap(long = "clear", conflicts_with = "note")]
+    clear: bool,
+
+    /// MAME machine to add a note to
+    game: String,
+
+    /// note to set, or to display the game's note if none
+    note: Vec<String>,
+}
+
+impl OptNote {
+    fn execute(self) -> Result<(), Error> {
+        let mut db: tags::TagDb = read_game_db(TAGS, DB_TAGS).unwrap_or_default();
+
+        if self.clear {
+            db.set_note(&self.game, None);
+        } else if self.note.is_empty() {
+            if let Some(note) = db.get(&self.game).and_then(|c| c.note.as_ref()) {
+                println!("{}", note);
+            }
+            return Ok(());
+        } else {
+            validate_mame_game(&self.game)?;
+            db.set_note(&self.game, Some(self.note.join(" ")));
+        }
+
+        write_game_db(DB_TAGS, &db)
+    }
+}
+
+#[derive(Args)]
+struct OptServe {
+    /// address to listen on
+    #[clap(long = "listen", default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// collections to show, or all if none are given;
+    /// their databases are read once, so restart after re-importing them
+    names: Vec<String>,
+}
+
+impl OptServe {
+    fn execute(self) -> Result<(), Error> {
+        let names = if self.names.is_empty() {
+            collection::configs()?.into_keys().collect()
+        } else {
+            self.names
+        };
+
+        let collections = names
+            .iter()
+            .map(|name| collection::Collection::open(name))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        serve::run(&collections, &self.listen)
+    }
+}
+
+#[derive(Args)]
+struct OptWatch {
+    /// collection to watch
+    name: String,
+
+    /// directories to fill incomplete games from as files appear,
+    /// instead of the collection's configured sources
+    #[clap(parse(from_os_str))]
+    input: Vec<PathBuf>,
+
+    /// decompress CHDs and verify their data
+    #[clap(long = "deep")]
+    deep: bool,
+}
+
+impl OptWatch {
+    fn execute(self) -> Result<(), Error> {
+        let collection = collection::Collection::open(&self.name)?;
+
+        // only local sources can be watched for new files
+        let sources = if self.input.is_empty() {
+            Resource::partition(
+                collection
+                    .sources
+                    .iter()
+                    .cloned()
+                    .map(Resource::from)
+                    .collect(),
+            )
+            .0
+        } else {
+            self.input
+        };
+
+        collection
+            .roots
+            .iter()
+            .try_for_each(|root| check_roots(&sources, &[], root))?;
+
+        watch::run(&collection, &sources, self.deep)
+    }
+}
+
+#[derive(Args)]
+struct OptTui {
+    /// browse the given collection rather than MAME
+    #[clap(long = "collection")]
+    collection: Option<String>,
+
+    /// MAME ROMs directory
+    #[clap(
+        short = 'r',
+        long = "roms",
+        parse(from_os_str),
+        conflicts_with = "collection"
+    )]
+    roms: Option<PathBuf>,
+
+    /// MAME games are stored as Zip archives
+    #[clap(long = "zip", conflicts_with = "collection")]
+    zip: bool,
+
+    /// input files, directories or URLs to add games from,
+    /// where collections otherwise use their own sources
+    #[clap(parse(from_os_str))]
+    input: Vec<Resource>,
+}
+
+impl OptTui {
+    fn execute(self) -> Result<(), Error> {
+        match self.collection {
+            Some(name) => {
+                let collection = collection::Collection::open(&name)?;
+
+                let (sources, urls) = Resource::partition(if self.input.is_empty() {
+                    collection
+                        .sources
+                        .iter()
+                        .cloned()
+                        .map(Resource::from)
+                        .collect()
+                } else {
+                    self.input
+                });
+
+                tui::run(
+                    &collection.db,
+                    &tui::Storage {
+                        roots: collection.roots.clone(),
+                        zip: collection.is_zip(),
+                        sources,
+                        urls,
+                        info: read_game_db(INFO, DB_INFO).unwrap_or_default(),
+                    },
+                )
+            }
+            None => {
+                let db = read_game_db::<game::GameDb>(MAME, DB_MAME)?;
+                let (sources, urls) = Resource::partition(self.input);
+
+                tui::run(
+                    &db,
+                    &tui::Storage {
+                        roots: vec![dirs::mame_roms(self.roms, &Notices).as_ref().to_path_buf()],
+                        zip: self.zip,
+                        sources,
+                        urls,
+                        info: read_game_db(INFO, DB_INFO).unwrap_or_default(),
+                    },
+                )
+            }
+        }
+    }
+}
+
+#[derive(Args)]
+struct OptWhy {
+    /// MAME machine needing the part
+    game: String,
+
+    /// name of the part, like "rom.bin" or "disk.chd"
+    part: String,
+}
+
+impl OptWhy {
+    fn execute(self) -> Result<(), Error> {
+        let db = index::mame_db(&self.game, true, &Notices)?;
+        report::display_why(&db, &self.game, &self.part)
+    }
+}
+
+#[derive(Args)]
+struct OptAck {
+    /// where the game is verified, like "mame",
+    /// a software list's name or a collection's name
+    #[clap(long = "in", default_value = "mame")]
+    scope: String,
+
+    /// stop acknowledging the game's failures, or only the part's
+    #[clap(long = "remove")]
+    remove: bool,
+
+    /// game whose failures to acknowledge,
+    /// or to list every acknowledged game if none
+    game: Option<String>,
+
+    /// part whose failures to acknowledge, or all the game's if none
+    part: Option<String>,
+}
+
+impl OptAck {
+    fn execute(self) -> Result<(), Error> {
+        let mut db: acks::AckDb = read_game_db(ACKS, DB_ACKS).unwrap_or_default();
+
+        let game = match self.game {
+            Some(game) => game,
+            None => {
+                for (game, parts) in db.scope(&self.scope).iter() {
+                    if parts.is_empty() {
+                        println!("{}", game);
+                    }
+                    for part in parts {
+                        println!("{} : {}", game, part);
+                    }
+                }
+                return Ok(());
+            }
+        };
+
+        if self.remove {
+            db.unack(&self.scope, &game, self.part.as_deref());
+        } else {
+            db.ack(&self.scope, &game, self.part);
+        }
+
+        write_game_db(DB_ACKS, &db)
+    }
+}
+
+#[derive(Args)]
+struct OptMaintain {
+    /// collection to maintain
+    name: String,
+
+    /// input files, directories, or URLs to fix games from,
+    /// instead of the collection's configured sources
+    #[clap(parse(from_os_str))]
+    input: Vec<Resource>,
+
+    /// leave missing and bad parts as they are
+    #[clap(long = "no-fix")]
+    no_fix: bool,
+
+    /// leave extra files where they are rather than quarantining them
+    #[clap(long = "no-cleanup")]
+    no_cleanup: bool,
+
+    /// decompress CHDs and verify their data
+    #[clap(long = "deep")]
+    deep: bool,
+
+    /// write an HTML report of the results to the given file
+    #[clap(long = "html", parse(from_os_str))]
+    html: Option<PathBuf>,
+
+    #[clap(flatten)]
+    output: OptOutput,
+}
+
+impl OptMaintain {
+    fn execute(self) -> Result<(), Error> {
+        use game::VerifyFailure;
+
+        self.output.redirect()?;
+
+        let collection = collection::Collection::open(&self.name)?;
+        let zip = collection.is_zip();
+        let last = collection.last_maintain();
+
+        let roots = collection
+            .roots
+            .iter()
+            .map(|root| {
+                collection
+                    .games_in(root)
+                    .map(|games| (root.as_path(), games))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // verification is cached, so this first pass
+        // only rereads files which have changed since the last run
+        let mut required = HashSet::default();
+        let mut incomplete = Vec::new();
+        let mut extras = Vec::new();
+
+        for (root, games) in roots.iter() {
+            let results = collection
+                .db
+                .verify(root, games, self.deep, zip, &BarSink::default());
+            let mut root_incomplete = Vec::new();
+            let mut root_extras = Vec::new();
+
+            for (game, failures) in results.iter() {
+                for failure in failures {
+                    match failure {
+                        VerifyFailure::Missing { part, .. }
+                        | VerifyFailure::Bad { expected: part, .. } => {
+                            required.insert((*part).clone());
+                        }
+                        // extra Zip members go away whenever their archive is rebuilt
+                        VerifyFailure::Extra { path, .. } if path.is_file() => {
+                            root_extras.push(path.clone())
+                        }
+                        _ => {}
+                    }
+                }
+
+                if failures.iter().any(|failure| failure.is_incomplete()) {
+                    root_incomplete.push(game.to_string());
+                }
+            }
+
+            incomplete.push((*root, root_incomplete));
+            extras.push((*root, root_extras));
+        }
+
+        let sources: Vec<Resource> = if self.input.is_empty() {
+            collection
+                .sources
+                .iter()
+                .cloned()
+                .map(Resource::from)
+                .collect()
+        } else {
+            self.input
+        };
+
+        let mut failed = FailedGames::default();
+
+        // sources are only scanned if there's something to find in them
+        if !self.no_fix && !required.is_empty() && !sources.is_empty() {
+            let (input, input_url) = Resource::partition(sources);
+
+            collection
+                .roots
+                .iter()
+                .try_for_each(|root| check_roots(&input, &input_url, root))?;
+
+            let mut roms = game::get_rom_sources(&input, &input_url, required, &BarSink::default());
+
+            for (root, games) in incomplete.iter() {
+                failed.extend(add_and_verify(
+                    &collection.name,
+                    collection.db.description(),
+                    &mut roms,
+                    root,
+                    None,
+                    games.iter().filter_map(|game| collection.db.game(game)),
+                    false,
+                    zip,
+                )?);
+            }
+        }
+
+        let mut quarantined = 0;
+
+        if !self.no_cleanup && !game::is_read_only() {
+            for (root, files) in extras.iter() {
+                quarantined += collection.quarantine(root, files)?.len();
+            }
+        }
+
+        let mut total = game::VerifyResultsSummary::default();
+        let mut report = VerifyReport::new(
+            true,
+            false,
+            None,
+            self.html,
+            false,
+            false,
+            report::ResultsFormat::Text,
+        )
+        .with_acks(&collection.name);
+
+        for (root, games) in roots.iter() {
+            total += verify(
+                &collection.db,
+                root,
+                None,
+                games,
+                self.deep,
+                zip,
+                &mut report,
+            );
+        }
+
+        let failing = std::mem::take(&mut report.failing);
+
+        report.finish(&collection.name, collection.db.description())?;
+        collection.record_verify(&total)?;
+
+        let state = collection::MaintainState {
+            timestamp: collection::unix_now(),
+            tested: total.total,
+            ok: total.successes,
+            failing,
+        };
+
+        println!(
+            "{} : {} tested, {} OK",
+            collection.name, state.tested, state.ok
+        );
+
+        if quarantined > 0 {
+            println!("  {} extra files quarantined", quarantined);
+        }
+
+        if let Some(last) = last {
+            println!(
+                "  since {} : {:+} OK",
+                html::utc_time(last.timestamp),
+                state.ok as i64 - last.ok as i64
+            );
+            for game in state.failing.difference(&last.failing) {
+                println!("  newly failing : {}", game);
+            }
+            for game in last.failing.difference(&state.failing) {
+                println!("  no longer failing : {}", game);
+            }
+        }
+
+        collection.record_maintain(&state)?;
+
+        report_failed_games(failed)
+    }
+}
+
+// catches mistyped names, if MAME's database is present to check against
+fn validate_mame_game(game: &str) -> Result<(), Error> {
+    match read_game_db::<game::GameDb>(MAME, DB_MAME) {
+        Ok(db) if !db.is_game(game) => Err(Error::NoSuchSoftware(game.to_owned())),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Args)]
+struct OptCanonicalize {
+    /// only verify archives are already in TorrentZip or canonical form
+    #[clap(long = "check")]
+    check: bool,
+
+    /// Zip files or directories
+    #[clap(parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+impl OptCanonicalize {
+    fn execute(self) -> Result<(), Error> {
+        use indicatif::ParallelProgressIterator;
+        use rayon::prelude::*;
+
+        if !self.check {
+            for path in self.paths.iter() {
+                snapshot::before_modifying(path, &Notices)?;
+            }
+        }
+
+        let pb = progress::spinner().with_message("locating files");
+        let files = {
+            pb.wrap_iter(
+                self.paths
+                    .into_iter()
+                    .flat_map(unique_sub_files)
+                    .filter(|file| {
+                        file.extension()
+                            .map(|ext| ext.eq_ignore_ascii_case("zip"))
+                            .unwrap_or(false)
+                    }),
+            )
+            .collect::<Vec<PathBuf>>()
+        };
+        pb.finish_and_clear();
+
+        let pb = progress::bar(files.len() as u64)
+            .with_style(progress::verify_style())
+            .with_message(if self.check {
+                "verifying archives"
+            } else {
+                "canonicalizing archives"
+            });
+
+        let check = self.check;
+
+        let canonical = files
+            .par_iter()
+            .progress_with(pb.clone())
+            .filter(|file| match torrentzip::is_canonical(file) {
+                Ok(true) => true,
+                Ok(false) if check => {
+                    progress::message(&pb, format_args!("BAD : {}", file.display()));
+                    false
+                }
+                Ok(false) if pin::is_pinned(file) => {
+                    progress::message(&pb, format_args!("PINNED : {}", file.display()));
+                    false
+                }
+                Ok(false) => match torrentzip::canonicalize(file) {
+                    Ok(()) => {
+                        progress::message(
+                            &pb,
+                            format_args!("{} \u{2192} canonical", file.display()),
+                        );
+                        true
+                    }
+                    Err(err) => {
+                        progress::message(
+                            &pb,
+                            format_args!("ERROR : {} : {}", file.display(), err),
+                        );
+                        false
+                    }
+                },
+                Err(err) => {
+                    progress::message(&pb, format_args!("ERROR : {} : {}", file.display(), err));
+                    false
+                }
+            })
+            .count();
+
+        pb.finish_and_clear();
+
+        eprintln!("{} tested, {} OK", files.len(), canonical);
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptChdVerify {
+    /// collection to verify
+    name: String,
+
+    /// chdman's path
+    #[clap(long = "chdman", parse(from_os_str), default_value = "chdman")]
+    chdman: PathBuf,
+}
+
+impl OptChdVerify {
+    fn execute(self) -> Result<(), Error> {
+        use game::{Part, VerifyFailure};
+
+        let collection = collection::Collection::open(&self.name)?;
+        let chdman = chdman::Chdman::new(self.chdman);
+
+        for root in collection.roots.iter() {
+            let games = collection.games_in(root)?;
+            let results = collection.db.verify(
+                root,
+                &games,
+                false,
+                collection.is_zip(),
+                &BarSink::default(),
+            );
+
+            for (game, failures) in results.iter() {
+                for failure in failures {
+                    if let VerifyFailure::Bad {
+                        path,
+                        expected: Part::Disk { .. },
+                        ..
+                    } = failure
+                    {
+                        if chdman.verify(path)? {
+                            println!("INTACT, WRONG DISK : {} : {}", game, path.display());
+                        } else {
+                            println!("DAMAGED : {} : {}", game, path.display());
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptChdCreate {
+    /// collection whose missing disks to create
+    name: String,
+
+    /// directories to find disc images in,
+    /// instead of the collection's configured sources
+    #[clap(parse(from_os_str))]
+    input: Vec<PathBuf>,
+
+    /// try every disc image rather than only those
+    /// named after a missing disk or its game
+    #[clap(long = "all")]
+    all: bool,
+
+    /// chdman's path
+    #[clap(long = "chdman", parse(from_os_str), default_value = "chdman")]
+    chdman: PathBuf,
+
+    #[clap(flatten)]
+    no_write: OptNoWrite,
+}
+
+impl OptChdCreate {
+    fn execute(self) -> Result<(), Error> {
+        use game::{Part, VerifyFailure};
+
+        self.no_write.apply();
+
+        let collection = collection::Collection::open(&self.name)?;
+        let chdman = chdman::Chdman::new(self.chdman);
+
+        // disks which are missing, by where they belong
+        let mut missing: Vec<(String, PathBuf, Part)> = Vec::new();
+
+        for root in collection.roots.iter() {
+            let games = collection.games_in(root)?;
+            let results = collection.db.verify(
+                root,
+                &games,
+                false,
+                collection.is_zip(),
+                &BarSink::default(),
+            );
+
+            for (game, failures) in results.iter() {
+                for failure in failures {
+                    if let VerifyFailure::Missing {
+                        path,
+                        part: part @ Part::Disk { .. },
+                        ..
+                    } = failure
+                    {
+                        missing.push((game.to_string(), path.clone(), Part::clone(part)));
+                    }
+                }
+            }
+        }
+
+        let input = if self.input.is_empty() {
+            Resource::partition(
+                collection
+                    .sources
+                    .iter()
+                    .cloned()
+                    .map(Resource::from)
+                    .collect(),
+            )
+            .0
+        } else {
+            self.input
+        };
+
+        // images can only be identified by converting them,
+        // which takes a while, so by default only likely ones are
+        let is_likely = |image: &Path, missing: &[(String, PathBuf, Part)]| {
+            let stem = image.file_stem();
+            missing.iter().any(|(game, path, _)| {
+                stem == path.file_stem() || stem == Some(std::ffi::OsStr::new(game))
+            })
+        };
+
+        let images = input
+            .into_iter()
+            .flat_map(sub_files)
+            .filter(|file| chdman::is_image(file))
+            .collect::<Vec<_>>();
+
+        let mut created = 0;
+
+        for image in images {
+            if missing.is_empty() {
+                break;
+            } else if !self.all && !is_likely(&image, &missing) {
+                continue;
+            }
+
+            // built alongside where it'll go, so it can simply be renamed into place,
+            // unless nothing may be written there, in which case it's only
+            // built to learn which disk it is and then thrown away
+            let staging_name = format!(".emuman-{}.chd", std::process::id());
+            let staging = if game::is_read_only() {
+                std::env::temp_dir().join(staging_name)
+            } else {
+                match missing[0].1.parent() {
+                    Some(parent) => {
+                        std::fs::create_dir_all(parent)?;
+                        parent.join(staging_name)
+                    }
+                    None => continue,
+                }
+            };
+
+            let part = match chdman.create(&image, &staging) {
+                Ok(part) => part,
+                Err(err) => {
+                    eprintln!("* {} : {}", image.display(), err);
+                    continue;
+                }
+            };
+
+            match missing.iter().position(|(_, _, disk)| *disk == part) {
+                Some(index) if game::is_read_only() => {
+                    let (game, target, _) = missing.remove(index);
+                    std::fs::remove_file(&staging)?;
+
+                    println!("WOULD CREATE : {} : {}", game, target.display());
+                    created += 1;
+                }
+                Some(index) => {
+                    let (game, target, part) = missing.remove(index);
+
+                    if let Some(parent) = target.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    trash::move_to(&staging, &target)?;
+                    part.set_xattr(&target);
+                    journal::record_created(&target)?;
+
+                    println!("CREATED : {} : {}", game, target.display());
+                    created += 1;
+                }
+                None => {
+                    std::fs::remove_file(&staging)?;
+                    eprintln!("* {} : no disk needs it", image.display());
+                }
+            }
+        }
+
+        eprintln!(
+            "{} disks {}, {} still missing",
+            created,
+            if game::is_read_only() {
+                "would be created"
+            } else {
+                "created"
+            },
+            missing.len()
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Subcommand)]
+enum OptChd {
+    /// check whether CHDs which don't match the database are damaged
+    #[clap(name = "verify")]
+    Verify(OptChdVerify),
+
+    /// create missing CHDs from disc images in sources
+    #[clap(name = "create")]
+    Create(OptChdCreate),
+}
+
+impl OptChd {
+    fn execute(self) -> Result<(), Error> {
+        match self {
+            OptChd::Verify(o) => o.execute(),
+            OptChd::Create(o) => o.execute(),
+        }
+    }
+}
+
+#[derive(Args)]
+struct OptSnapshot {
+    /// collection to snapshot
+    name: String,
+
+    /// file to write the snapshot to
+    #[clap(parse(from_os_str))]
+    output: PathBuf,
+
+    /// decompress CHDs and verify their data
+    #[clap(long = "deep")]
+    deep: bool,
+}
+
+impl OptSnapshot {
+    fn execute(self) -> Result<(), Error> {
+        let collection = collection::Collection::open(&self.name)?;
+        let snapshot = audit::Snapshot::take(&collection, self.deep)?;
+
+        snapshot.write(&self.output)?;
+
+        eprintln!(
+            "{} : {} games snapshotted",
+            collection.name,
+            snapshot.games.len()
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptDiffSnapshot {
+    /// earlier snapshot
+    #[clap(parse(from_os_str))]
+    old: PathBuf,
+
+    /// later snapshot, or the snapshotted collection as it is now
+    #[clap(parse(from_os_str))]
+    new: Option<PathBuf>,
+
+    /// decompress CHDs and verify their data
+    /// when comparing against the collection as it is now
+    #[clap(long = "deep")]
+    deep: bool,
+}
+
+impl OptDiffSnapshot {
+    fn execute(self) -> Result<(), Error> {
+        let old = audit::Snapshot::read(&self.old)?;
+        let new = match self.new {
+            Some(new) => audit::Snapshot::read(&new)?,
+            None => {
+                audit::Snapshot::take(&collection::Collection::open(&old.collection)?, self.deep)?
+            }
+        };
+
+        let differences = audit::diff(&old, &new);
+
+        eprintln!(
+            "{} differences from {} to {}",
+            differences,
+            html::utc_time(old.taken),
+            html::utc_time(new.taken)
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptUndo {
+    /// list journaled runs rather than undoing the latest
+    #[clap(short = 'l', long = "list")]
+    list: bool,
+}
+
+impl OptUndo {
+    fn execute(self) -> Result<(), Error> {
+        let mut journals = journal::journals();
+
+        if self.list {
+            for (started, journal) in journals {
+                println!(
+                    "{} : {} changes",
+                    html::utc_time(started),
+                    journal::entries(&journal)?.len()
+                );
+            }
+            return Ok(());
+        }
+
+        match journals.pop() {
+            Some((_, journal)) => journal::undo(&journal, report::display_undone),
+            None => Err(Error::NoJournal),
+        }
+    }
+}
+
+#[derive(Args)]
+struct OptDedupe {
+    /// show which files would be linked without linking them
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+
+    /// collections to deduplicate, or all if none are given
+    names: Vec<String>,
+}
+
+impl OptDedupe {
+    fn execute(self) -> Result<(), Error> {
+        let mut configs = collection::configs()?;
+        let names = if self.names.is_empty() {
+            configs.keys().cloned().collect()
+        } else {
+            self.names
+        };
+
+        // games' files are only ever linked in directory mode,
+        // where each part is a file of its own
+        let mut roots = Vec::new();
+
+        for name in names {
+            let config = configs
+                .remove(&name)
+                .ok_or_else(|| Error::NoSuchCollection(name.clone()))?;
+
+            match config.mode {
+                collection::SetMode::Directory => roots.extend(config.roots),
+                collection::SetMode::Zip => {
+                    eprintln!("* {} : parts in Zip archives can't be linked", name)
+                }
+                collection::SetMode::Hashed => {
+                    eprintln!("* {} : parts are already stored once apiece", name)
+                }
+            }
+        }
+
+        if !self.dry_run {
+            for root in roots.iter() {
+                snapshot::before_modifying(root, &Notices)?;
+            }
+        }
+
+        let (linked, reclaimed) = link_duplicates(roots, "linking duplicate parts", self.dry_run);
+
+        println!(
+            "{} files {}, {} reclaimed",
+            linked,
+            if self.dry_run { "to link" } else { "linked" },
+            indicatif::HumanBytes(reclaimed)
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptPatch {
+    /// IPS, BPS or xdelta patch
+    #[clap(parse(from_os_str))]
+    patch: PathBuf,
+
+    /// base ROM to apply the patch to
+    #[clap(parse(from_os_str))]
+    base: PathBuf,
+
+    /// directory to write the patched ROM to
+    #[clap(short = 'd', long = "dir", parse(from_os_str))]
+    dir: PathBuf,
+
+    /// patched ROM's name, or the patch's with the base ROM's extension
+    #[clap(short = 'n', long = "name")]
+    name: Option<String>,
+
+    /// SHA1 digest the base ROM must have
+    #[clap(long = "base-sha1")]
+    base_sha1: Option<String>,
+
+    /// SHA1 digest the patched ROM must have
+    #[clap(long = "sha1")]
+    sha1: Option<String>,
+
+    /// replace an existing patched ROM
+    #[clap(short = 'f', long = "force")]
+    force: bool,
+}
+
+impl OptPatch {
+    fn execute(self) -> Result<(), Error> {
+        // mistyped digests are caught before anything is read
+        let expected = |digest: Option<String>| {
+            digest
+                .map(|digest| {
+                    game::Part::new_rom(&digest).map_err(|error| {
+                        Error::InvalidSha1(FileError {
+                            file: PathBuf::from(digest),
+                            error,
+                        })
+                    })
+                })
+                .transpose()
+        };
+        let base_sha1 = expected(self.base_sha1)?;
+        let sha1 = expected(self.sha1)?;
+
+        let base = std::fs::read(&self.base)?;
+
+        if let Some(part) = base_sha1 {
+            if game::Part::from_slice(&base)? != part {
+                return Err(Error::ChecksumMismatch(self.base.display().to_string()));
+            }
+        }
+
+        let patch_file = self.patch.clone();
+        let (format, patched) =
+            patch::apply(&base, &std::fs::read(&patch_file)?).map_err(|error| {
+                Error::Patch(FileError {
+                    file: patch_file,
+                    error,
+                })
+            })?;
+
+        let name = match self.name {
+            Some(name) => PathBuf::from(name),
+            None => {
+                let mut name = PathBuf::from(self.patch.file_stem().ok_or(Error::InvalidPath)?);
+                if let Some(extension) = self.base.extension() {
+                    name.set_extension(extension);
+                }
+                name
+            }
+        };
+        let target = self.dir.join(name);
+
+        if let Some(part) = sha1 {
+            if game::Part::from_slice(&patched)? != part {
+                return Err(Error::ChecksumMismatch(target.display().to_string()));
+            }
+        }
+
+        // patched ROMs never replace their bases
+        std::fs::create_dir_all(&self.dir)?;
+        if target.canonicalize().ok() == self.base.canonicalize().ok() {
+            return Err(Error::OverlappingRoots(self.base, self.dir));
+        }
+
+        if pin::is_pinned(&target) {
+            return Err(Error::TargetPinned(target));
+        } else if !self.force && target.exists() {
+            return Err(Error::TargetExists(target));
+        }
+
+        // written aside and moved into place whole,
+        // so an interrupted write leaves any old target intact
+        let staging = self.dir.join(format!(
+            ".{}.tmp",
+            target
+                .file_name()
+                .ok_or(Error::InvalidPath)?
+                .to_string_lossy()
+        ));
+        if let Err(err) =
+            std::fs::write(&staging, patched).and_then(|()| std::fs::rename(&staging, &target))
+        {
+            let _ = std::fs::remove_file(&staging);
+            return Err(Error::IO(err));
+        }
+
+        println!("{} : {}", format, target.display());
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptPin {
+    /// list pinned files instead of pinning them
+    #[clap(short = 'l', long = "list")]
+    list: bool,
+
+    /// files or directories
+    #[clap(parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+impl OptPin {
+    fn execute(self) -> Result<(), Error> {
+        for file in self.paths.into_iter().flat_map(sub_files) {
+            if self.list {
+                if pin::is_pinned(&file) {
+                    println!("{}", file.display());
+                }
+            } else if let Err(err) = pin::pin(&file) {
+                eprintln!("* {} : {}", file.display(), err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptProvenance {
+    /// files or directories
+    #[clap(parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+impl OptProvenance {
+    fn execute(self) -> Result<(), Error> {
+        for file in self.paths.into_iter().flat_map(sub_files) {
+            match provenance::get(&file) {
+                Ok(Some(provenance)) => println!("{}\n{}", file.display(), provenance),
+                Ok(None) => println!("{}\n  no provenance recorded", file.display()),
+                Err(err) => eprintln!("* {} : {}", file.display(), err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptStamps {
+    /// Zip files or directories
+    #[clap(parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+impl OptStamps {
+    fn execute(self) -> Result<(), Error> {
+        for file in self.paths.into_iter().flat_map(sub_files).filter(|file| {
+            file.extension()
+                .map(|ext| ext.eq_ignore_ascii_case("zip"))
+                .unwrap_or(false)
+        }) {
+            match stamp::get(&file) {
+                Ok(Some(stamp)) => println!("{}\n{}", file.display(), stamp),
+                Ok(None) => println!("{}\n  no stamp", file.display()),
+                Err(err) => eprintln!("* {} : {}", file.display(), err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptUnpin {
+    /// files or directories
+    #[clap(parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+impl OptUnpin {
+    fn execute(self) -> Result<(), Error> {
+        for file in self.paths.into_iter().flat_map(sub_files) {
+            if let Err(err) = pin::unpin(&file) {
+                eprintln!("* {} : {}", file.display(), err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptBlocklistAdd {
+    /// lists of SHA1 digests, one per line, followed by an optional reason
+    #[clap(parse(from_os_str))]
+    lists: Vec<PathBuf>,
+}
+
+impl OptBlocklistAdd {
+    fn execute(self) -> Result<(), Error> {
+        let mut db: blocklist::Blocklist =
+            read_game_db(BLOCKLIST, DB_BLOCKLIST).unwrap_or_default();
+
+        for list in self.lists {
+            db.extend(blocklist::read_blocklist(&list)?);
+        }
+
+        write_game_db(DB_BLOCKLIST, &db)
+    }
+}
+
+#[derive(Args)]
+struct OptBlocklistRemove {
+    /// SHA1 digests to unblock
+    digests: Vec<String>,
+}
+
+impl OptBlocklistRemove {
+    fn execute(self) -> Result<(), Error> {
+        let mut db: blocklist::Blocklist = read_game_db(BLOCKLIST, DB_BLOCKLIST)?;
+
+        for digest in self.digests {
+            db.remove(&game::parse_sha1(&digest).map_err(|error| {
+                Error::InvalidSha1(FileError {
+                    file: PathBuf::from(digest),
+                    error,
+                })
+            })?);
+        }
+
+        write_game_db(DB_BLOCKLIST, &db)
+    }
+}
+
+#[derive(Args)]
+struct OptBlocklistList {}
+
+impl OptBlocklistList {
+    fn execute(self) -> Result<(), Error> {
+        for (sha1, reason) in read_game_db::<blocklist::Blocklist>(BLOCKLIST, DB_BLOCKLIST)? {
+            if reason.is_empty() {
+                println!("{}", hex::encode(sha1));
+            } else {
+                println!("{} {}", hex::encode(sha1), reason);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Subcommand)]
+enum OptBlocklist {
+    /// add hashes from blocklist files
+    #[clap(name = "add")]
+    Add(OptBlocklistAdd),
+
+    /// remove hashes from blocklist
+    #[clap(name = "remove")]
+    Remove(OptBlocklistRemove),
+
+    /// list blocked hashes
+    #[clap(name = "list")]
+    List(OptBlocklistList),
+}
+
+impl OptBlocklist {
+    fn execute(self) -> Result<(), Error> {
+        match self {
+            OptBlocklist::Add(o) => o.execute(),
+            OptBlocklist::Remove(o) => o.execute(),
+            OptBlocklist::List(o) => o.execute(),
+        }
+    }
+}
+
+#[derive(Args)]
+struct OptDatExport {
+    /// database to export, use "mame", "sl:<software list>" or "dat:<name>"
+    db: String,
+
+    /// name in exported DAT's header
+    #[clap(short = 'n', long = "name")]
+    name: Option<String>,
+
+    /// version in exported DAT's header
+    #[clap(long = "version", default_value = "1")]
+    version: String,
+
+    /// output file, or standard output if not given
+    #[clap(short = 'o', long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// only include the given parent and its clones
+    #[clap(short = 'f', long = "family")]
+    family: Option<String>,
+
+    /// search term for querying specific items
+    search: Option<String>,
+}
+
+impl OptDatExport {
+    fn execute(self) -> Result<(), Error> {
+        let db_spec = self.db;
+        let name = self.name.unwrap_or_else(|| match db_spec.split_once(':') {
+            Some((_, name)) => name.to_owned(),
+            None => db_spec.clone(),
+        });
+
+        let db = collection::read_db(db_spec)?;
+        let games = db.export_games(self.search.as_deref(), self.family.as_deref());
+
+        match self.output {
+            Some(output) => dat::write_logiqx(
+                std::io::BufWriter::new(File::create(output)?),
+                &name,
+                db.description(),
+                &self.version,
+                games.into_values(),
+            ),
+            None => dat::write_logiqx(
+                std::io::stdout().lock(),
+                &name,
+                db.description(),
+                &self.version,
+                games.into_values(),
+            ),
+        }
+        .map_err(Error::IO)
+    }
+}
+
+#[derive(Args)]
+struct OptDatDiff {
+    /// old database, as "mame", "sl:<software list>", "dat:<name>" or a file
+    old: String,
+
+    /// new database, as "mame", "sl:<software list>", "dat:<name>" or a file
+    new: String,
+}
+
+impl OptDatDiff {
+    fn execute(self) -> Result<(), Error> {
+        let old = read_db_or_file(self.old)?;
+        let new = read_db_or_file(self.new)?;
+        report::display_diff(&old.diff(&new));
+
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct OptDatMigration {
+    /// old database, as "mame", "sl:<software list>", "dat:<name>" or a file
+    old: String,
+
+    /// new database, as "mame", "sl:<software list>", "dat:<name>" or a file
+    new: String,
+
+    /// directory of games to verify against both databases
+    #[clap(short = 'r', long = "roms", parse(from_os_str))]
+    roms: PathBuf,
+
+    /// verify games stored as Zip archives
+    #[clap(long = "zip")]
+    zip: bool,
+
+    /// set layout, use "non-merged", "split" or "merged"
+    #[clap(long = "layout", default_value = "non-merged")]
+    layout: dat::Merging,
+
+    /// display only games not yet valid for the new database
+    #[clap(long = "pending")]
+    pending: bool,
+}
+
+impl OptDatMigration {
+    fn execute(self) -> Result<(), Error> {
+        let mut old = read_db_or_file(self.old)?;
+        let mut new = read_db_or_file(self.new)?;
+        old.apply_layout(self.layout);
+        new.apply_layout(self.layout);
+
+        let zip = self.zip;
+        let roms = self.roms;
+
+        // anything on disk which is a game in either database
+        let on_disk: BTreeSet<String> = roms
+            .read_dir()?
+            .filter_map(|e| {
+                e.ok()
+                    .and_then(|e| entry_game(e, zip))
+                    .filter(|s| old.is_game(s) || new.is_game(s))
+            })
+            .collect();
+
+        let valid_games = |db: &game::GameDb| -> HashSet<String> {
+            let games = on_disk.iter().filter(|s| db.is_game(s)).cloned().collect();
+
+            db.verify(&roms, &games, false, zip, &BarSink::default())
+                .into_iter()
+                .filter(|(_, failures)| failures.is_empty())
+                .map(|(game, _)| game.to_owned())
+                .collect()
+        };
+
+        let valid_old = valid_games(&old);
+        let valid_new = valid_games(&new);
+
+        let (mut both, mut only_old, mut only_new, mut neither) = (0, 0, 0, 0);
+
+        for game in on_disk.iter() {
+            let status = match (valid_old.contains(game), valid_new.contains(game)) {
+                (true, true) => {
+                    both += 1;
+                    "BOTH"
+                }
+                (true, false) => {
+                    only_old += 1;
+                    "OLD"
+                }
+                (false, true) => {
+                    only_new += 1;
+                    "NEW"
+                }
+                (false, false) => {
+                    neither += 1;
+                    "NEITHER"
+                }
+            };
+
+            if !(self.pending && valid_new.contains(game)) {
+                println!("{} : {}", status, game);
+            }
+        }
+
+        eprintln!(
+            "{} valid for both, {} for old only, {} for new only, {} for neither",
+            both, only_old, only_new, neither
+        );
+
+        // the migration is done once everything is valid for the new database
+        if only_old + neither > 0 {
+            game::record_outcome(game::Outcome::Failures);
+        }
+
+        Ok(())
+    }
+}
+
+// files may be MAME's -listxml output, software lists or DATs,
+// while anything else is an already-imported database
+fn read_db_or_file(db: String) -> Result<game::GameDb, Error> {
+    let file = PathBuf::from(&db);
+
+    if !file.is_file() {
+        return collection::read_db(db);
+    }
+
+    let (file, data) = dat::read_dats_from_file(file)?
+        .into_iter()
+        .next()
+        .ok_or(Error::NoDatFiles)?;
+
+    let start = &data[..data.len().min(1024)];
+    let contains = |tag: &[u8]| start.windows(tag.len()).any(|w| w == tag);
+
+    if contains(b"<mame") {
+        quick_xml::de::from_reader(&data[..])
+            .map(|mame: mame::Mame| mame.into_game_db())
+            .map_err(|error| Error::XmlFile(FileError { error, file }))
+    } else if contains(b"<softwarelist") {
+        quick_xml::de::from_reader(&data[..])
+            .map(|sl: mess::Softwarelist| sl.into_game_db())
+            .map_err(|error| Error::XmlFile(FileError { error, file }))
+    } else {
+        dat::parse_datafile(&file, &data)?
+            .into_game_db()
+            .map(|(db, _)| db)
+            .map_err(|error| Error::InvalidSha1(FileError { file, error }))
+    }
+}
 
-        for file in pb.wrap_iter(self.paths.into_iter().flat_map(sub_files)) {
-            use std::fs;
+#[derive(Args)]
+struct OptExportState {
+    /// archive to write
+    #[clap(parse(from_os_str))]
+    output: PathBuf,
+}
 
-            match db.get_or_add(file) {
-                Ok(None) => {}
-                Ok(Some((duplicate, original))) => {
-                    match fs::remove_file(&duplicate)
-                        .and_then(|()| fs::hard_link(&original, &duplicate))
-                    {
-                        Ok(()) => pb.println(format!(
-                            "{} \u{2192} {}",
-                            original.display(),
-                            duplicate.display()
-                        )),
-                        Err(err) => pb.println(format!("{}: {}", duplicate.display(), err)),
-                    }
-                }
-                Err((source, err)) => pb.println(format!("{}: {}", source.display(), err)),
-            }
-        }
+impl OptExportState {
+    fn execute(self) -> Result<(), Error> {
+        let exported = state::export(&self.output)?;
+        println!("{} files exported to {}", exported, self.output.display());
+        Ok(())
+    }
+}
 
-        pb.finish_and_clear();
+#[derive(Args)]
+struct OptImportState {
+    /// archive written by export-state
+    #[clap(parse(from_os_str))]
+    archive: PathBuf,
 
+    /// replace files which are already present
+    #[clap(long = "overwrite")]
+    overwrite: bool,
+}
+
+impl OptImportState {
+    fn execute(self) -> Result<(), Error> {
+        let imported = state::import(&self.archive, self.overwrite)?;
+        println!(
+            "{} files imported from {}",
+            imported,
+            self.archive.display()
+        );
         Ok(())
     }
 }
 
+#[derive(Args)]
+struct OptSelftest {
+    /// directory to build test files in, defaulting to a temporary directory
+    #[clap(parse(from_os_str))]
+    dir: Option<PathBuf>,
+
+    /// keep test files afterward
+    #[clap(long = "keep")]
+    keep: bool,
+
+    /// also check archives holding a ROM too large to be buffered in memory
+    #[clap(long = "large")]
+    large: bool,
+}
+
+impl OptSelftest {
+    fn execute(self) -> Result<(), Error> {
+        selftest::run(&selftest::test_dir(self.dir), self.keep, self.large)
+    }
+}
+
+#[derive(Subcommand)]
+enum OptDat {
+    /// export database as a Logiqx XML DAT
+    #[clap(name = "export")]
+    Export(OptDatExport),
+
+    /// compare two databases' games and parts
+    #[clap(name = "diff")]
+    Diff(OptDatDiff),
+
+    /// verify games against an old and a new database at once,
+    /// showing which each game is valid for while migrating between them
+    #[clap(name = "migration")]
+    Migration(OptDatMigration),
+}
+
+impl OptDat {
+    fn execute(self) -> Result<(), Error> {
+        match self {
+            OptDat::Export(o) => o.execute(),
+            OptDat::Diff(o) => o.execute(),
+            OptDat::Migration(o) => o.execute(),
+        }
+    }
+}
+
 /// Emulation Database Manager
 #[derive(Parser)]
-enum Opt {
+#[clap(
+    after_help = "Exits with 0 if everything is OK, 1 if any failures were found and 2 if any errors occurred."
+)]
+struct Opt {
+    /// progress display, "bars" or "json" events on stderr
+    #[clap(long = "progress", global = true, default_value = "bars")]
+    progress: progress::ProgressFormat,
+
+    /// how added files are linked into sets, "hard", "symlink", "relative"
+    /// or "reflink", where files inside archives are always extracted
+    #[clap(long = "link", global = true, default_value = "hard")]
+    link: game::LinkMode,
+
+    /// remove source files once added to sets,
+    /// and source Zip archives once all their files are
+    #[clap(long = "consume", global = true)]
+    consume: bool,
+
+    /// stamp game Zip archives built with the database they were built
+    /// against, which keeps them from being in canonical form
+    #[clap(long = "stamp", global = true)]
+    stamp: bool,
+
+    /// answer yes to confirmations, like deleting more files
+    /// than safety.toml's limits or moving misfiled games
+    #[clap(long = "yes", global = true)]
+    yes: bool,
+
+    /// move files to the given directory rather than deleting them,
+    /// under their original paths, or to the desktop's trash with "system"
+    #[clap(long = "trash", global = true)]
+    trash: Option<trash::Trash>,
+
+    /// journal files added, replaced, moved and removed,
+    /// setting removed ones aside, so "emuman undo" can revert the run
+    #[clap(long = "journal", global = true)]
+    journal: bool,
+
+    /// skip source files and directories matching the given pattern,
+    /// like "*.bak" or "backup/", where a trailing "/" matches only directories
+    #[clap(long = "exclude", global = true)]
+    exclude: Vec<String>,
+
+    /// only scan source files matching the given pattern, like "*.zip"
+    #[clap(long = "include", global = true)]
+    include: Vec<String>,
+
+    #[clap(subcommand)]
+    command: OptCommand,
+}
+
+impl Opt {
+    fn execute(self) -> Result<(), Error> {
+        progress::set_format(self.progress);
+        progress::start();
+        game::set_link_mode(self.link);
+        game::set_source_filter(game::SourceFilter {
+            include: self.include,
+            exclude: self.exclude,
+        });
+        if self.stamp {
+            stamp::set_stamping();
+        }
+        if self.yes {
+            safety::assume_yes();
+        }
+        safety::set_confirmation(report::confirm_deletions);
+        if let Some(trash) = self.trash {
+            trash::set_trash(trash);
+        }
+        if self.journal {
+            journal::start()?;
+        }
+
+        if self.consume {
+            // links to removed sources would lead nowhere
+            if matches!(
+                self.link,
+                game::LinkMode::Symlink | game::LinkMode::Relative
+            ) {
+                return Err(Error::ConsumeSymlinks);
+            }
+            consume::set_consuming();
+        }
+
+        // an unreadable blocklist fails up front,
+        // except for the commands that replace it
+        if !matches!(self.command, OptCommand::Blocklist(_)) {
+            blocklist::load()?;
+        }
+
+        self.command.execute()?;
+
+        if self.consume {
+            match consume::remove_consumed(&Notices)? {
+                0 => {}
+                removed => eprintln!("* {} consumed sources removed", removed),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Subcommand)]
+enum OptCommand {
     /// arcade software management
     #[clap(subcommand)]
     Mame(OptMame),
@@ -2144,177 +5780,204 @@ enum Opt {
     /// identify ROM or CHD by hash
     Identify(OptIdentify),
 
+    /// guess which database a directory of ROMs belongs to
+    Detect(OptDetect),
+
     /// file cache management
     #[clap(subcommand)]
     Cache(OptCache),
-}
 
-impl Opt {
-    fn execute(self) -> Result<(), Error> {
-        promote_dbs()?;
+    /// rewrite Zip archives in emuman's canonical form,
+    /// which shares TorrentZip's layout but isn't TorrentZip's output
+    Canonicalize(OptCanonicalize),
 
-        match self {
-            Opt::Mame(o) => o.execute(),
-            Opt::Sl(o) => o.execute(),
-            Opt::Extra(o) => o.execute(),
-            Opt::Redump(o) => o.execute(),
-            Opt::Nointro(o) => o.execute(),
-            Opt::Identify(o) => o.execute(),
-            Opt::Cache(o) => o.execute(),
-        }
-    }
-}
+    /// apply an IPS, BPS or xdelta patch to a base ROM
+    Patch(OptPatch),
 
-fn main() {
-    if let Err(err) = Opt::parse().execute() {
-        eprintln!("* {}", err);
-    }
-}
+    /// replace identical parts across collections with hard links
+    Dedupe(OptDedupe),
 
-fn is_zip<R>(mut reader: R) -> Result<bool, std::io::Error>
-where
-    R: Read + Seek,
-{
-    use std::io::SeekFrom;
+    /// revert the latest run made with --journal
+    Undo(OptUndo),
 
-    let mut buf = [0; 4];
-    reader.read_exact(&mut buf)?;
-    reader.seek(SeekFrom::Start(0))?;
-    Ok(&buf == b"\x50\x4b\x03\x04")
-}
+    /// check and create CHDs with MAME's chdman
+    #[clap(subcommand)]
+    Chd(OptChd),
 
-fn write_game_db<S>(db_file: &'static str, db: S) -> Result<(), Error>
-where
-    S: Serialize,
-{
-    use directories::ProjectDirs;
-    use std::fs::create_dir_all;
-    use std::io::BufWriter;
-
-    let dirs = ProjectDirs::from("", "", "EmuMan").expect("no valid home directory found");
-    let dir = dirs.data_local_dir();
-    create_dir_all(dir)?;
-    let path = dir.join(db_file);
-    let f = BufWriter::new(File::create(&path)?);
-    ciborium::ser::into_writer(&db, f).map_err(Error::CborWrite)?;
-    Ok(())
-}
+    /// record what's on disk in a collection's roots for later comparison
+    Snapshot(OptSnapshot),
 
-fn read_game_db<D>(utility: &'static str, db_file: &'static str) -> Result<D, Error>
-where
-    D: DeserializeOwned,
-{
-    use directories::ProjectDirs;
-    use std::io::BufReader;
+    /// compare two snapshots, or a snapshot with its collection now
+    DiffSnapshot(OptDiffSnapshot),
 
-    let dirs = ProjectDirs::from("", "", "EmuMan").expect("no valid home directory");
-    let f = BufReader::new(
-        File::open(dirs.data_local_dir().join(db_file))
-            .map_err(|_| Error::MissingCache(utility))?,
-    );
-    ciborium::de::from_reader(f).map_err(|_| Error::InvalidCache(utility))
-}
+    /// named collection management
+    #[clap(subcommand)]
+    Collection(OptCollection),
 
-fn named_db_dir(db_dir: &'static str) -> PathBuf {
-    directories::ProjectDirs::from("", "", "EmuMan")
-        .expect("no valid home directory found")
-        .data_local_dir()
-        .join(db_dir)
-}
+    /// verify every collection and summarize each one
+    #[clap(name = "verify-all")]
+    VerifyAll(OptVerifyAll),
 
-// names might contain slashes, so we'll encode them
-// into base64 to ensure they stay in the directory we put them in
-fn named_db_path(db_dir: &'static str, name: &str) -> PathBuf {
-    named_db_dir(db_dir).join(base64::encode_config(name, base64::URL_SAFE))
-}
+    /// display how complete collections are
+    #[clap(name = "stats")]
+    Stats(OptStats),
 
-// extracts database name from existing path, if any
-fn path_db_name(path: &Path) -> Option<String> {
-    String::from_utf8(base64::decode_config(path.file_name()?.to_str()?, base64::URL_SAFE).ok()?)
-        .ok()
-}
+    /// display game history and trivia
+    Info(OptInfo),
 
-fn write_named_db<S: Serialize>(db_dir: &'static str, name: &str, cache: S) -> Result<(), Error> {
-    use std::fs::create_dir_all;
-    use std::io::BufWriter;
+    /// MAME sample management
+    #[clap(subcommand)]
+    Samples(OptSamples),
 
-    let path = named_db_path(db_dir, name);
+    /// tag games to curate personal subsets of them
+    Tag(OptTag),
 
-    if let Some(parent) = path.parent() {
-        create_dir_all(parent)?;
-    }
+    /// add personal notes to games
+    Note(OptNote),
 
-    ciborium::ser::into_writer(&cache, BufWriter::new(File::create(&path)?))
-        .map_err(Error::CborWrite)?;
+    /// acknowledge known failures so they're reported apart from new ones
+    Ack(OptAck),
 
-    Ok(())
-}
+    /// verify a collection, fix it from its sources, quarantine extra files
+    /// and summarize what's changed since last time, like from a weekly cron job
+    Maintain(OptMaintain),
 
-fn read_named_db<D: DeserializeOwned>(
-    utility: &'static str,
-    db_dir: &'static str,
-    name: &str,
-) -> Result<D, Error> {
-    ciborium::de::from_reader(
-        File::open(named_db_path(db_dir, name))
-            .map(std::io::BufReader::new)
-            .map_err(|_| Error::MissingCache(utility))?,
-    )
-    .map_err(|_| Error::InvalidCache(utility))
-}
+    /// explain where a MAME machine's part comes from and what shares it
+    Why(OptWhy),
 
-fn clear_named_dbs(db_dir: &'static str) -> Result<(), Error> {
-    let files: Vec<_> = std::fs::read_dir(named_db_dir(db_dir))
-        .map(|dir| dir.filter_map(|e| e.map(|e| e.path()).ok()).collect())
-        .unwrap_or_default();
+    /// browse, verify and add games interactively
+    Tui(OptTui),
 
-    files
-        .into_iter()
-        .try_for_each(std::fs::remove_file)
-        .map_err(Error::IO)
+    /// serve a web dashboard of collections' status,
+    /// with a compact page for phones at "/status"
+    Serve(OptServe),
+
+    /// re-verify a collection's games as their files change
+    Watch(OptWatch),
+
+    /// download missing parts from mirrors
+    Download(OptDownload),
+
+    /// protect files from being modified or removed
+    Pin(OptPin),
+
+    /// remove protection from pinned files
+    Unpin(OptUnpin),
+
+    /// display where files added to sets came from
+    Provenance(OptProvenance),
+
+    /// display which database game Zip archives were built against
+    Stamps(OptStamps),
+
+    /// hashes never used as sources or accepted in sets
+    #[clap(subcommand)]
+    Blocklist(OptBlocklist),
+
+    /// Logiqx DAT management
+    #[clap(subcommand)]
+    Dat(OptDat),
+
+    /// save configuration, databases, tags and journals to one archive
+    #[clap(name = "export-state")]
+    ExportState(OptExportState),
+
+    /// restore everything saved by export-state
+    #[clap(name = "import-state")]
+    ImportState(OptImportState),
+
+    /// build synthetic sets end-to-end and check the results
+    #[clap(hide = true)]
+    Selftest(OptSelftest),
 }
 
-fn destroy_named_db(db_dir: &'static str, name: &str) -> Result<(), Error> {
-    let path = named_db_path(db_dir, name);
-    if path.is_file() {
-        std::fs::remove_file(path).map_err(Error::IO)
-    } else {
-        Err(Error::NoSuchDatFile(name.to_owned()))
+impl OptCommand {
+    fn execute(self) -> Result<(), Error> {
+        promote_dbs()?;
+
+        match self {
+            OptCommand::Mame(o) => o.execute(),
+            OptCommand::Sl(o) => o.execute(),
+            OptCommand::Extra(o) => o.execute(),
+            OptCommand::Redump(o) => o.execute(),
+            OptCommand::Nointro(o) => o.execute(),
+            OptCommand::Identify(o) => o.execute(),
+            OptCommand::Detect(o) => o.execute(),
+            OptCommand::Cache(o) => o.execute(),
+            OptCommand::Canonicalize(o) => o.execute(),
+            OptCommand::Patch(o) => o.execute(),
+            OptCommand::Dedupe(o) => o.execute(),
+            OptCommand::Undo(o) => o.execute(),
+            OptCommand::Chd(o) => o.execute(),
+            OptCommand::Snapshot(o) => o.execute(),
+            OptCommand::DiffSnapshot(o) => o.execute(),
+            OptCommand::Collection(o) => o.execute(),
+            OptCommand::VerifyAll(o) => o.execute(),
+            OptCommand::Stats(o) => o.execute(),
+            OptCommand::Info(o) => o.execute(),
+            OptCommand::Samples(o) => o.execute(),
+            OptCommand::Tag(o) => o.execute(),
+            OptCommand::Note(o) => o.execute(),
+            OptCommand::Ack(o) => o.execute(),
+            OptCommand::Maintain(o) => o.execute(),
+            OptCommand::Why(o) => o.execute(),
+            OptCommand::Tui(o) => o.execute(),
+            OptCommand::Serve(o) => o.execute(),
+            OptCommand::Watch(o) => o.execute(),
+            OptCommand::Download(o) => o.execute(),
+            OptCommand::Pin(o) => o.execute(),
+            OptCommand::Unpin(o) => o.execute(),
+            OptCommand::Provenance(o) => o.execute(),
+            OptCommand::Stamps(o) => o.execute(),
+            OptCommand::Blocklist(o) => o.execute(),
+            OptCommand::Dat(o) => o.execute(),
+            OptCommand::ExportState(o) => o.execute(),
+            OptCommand::ImportState(o) => o.execute(),
+            OptCommand::Selftest(o) => o.execute(),
+        }
     }
 }
 
-fn read_named_dbs<D>(db_dir: &'static str) -> Option<impl Iterator<Item = (String, D)>>
-where
-    D: DeserializeOwned,
-{
-    #[inline]
-    fn read_game_db<D: DeserializeOwned>(path: &Path) -> Option<(String, D)> {
-        Some((
-            path_db_name(path)?,
-            File::open(path)
-                .ok()
-                .map(std::io::BufReader::new)
-                .and_then(|f| ciborium::de::from_reader(f).ok())?,
-        ))
+// exits with 0 if everything is OK, 1 if any failures were found
+// and 2 if any errors occurred, so scripts can tell them apart
+fn main() {
+    let result = Opt::parse().execute();
+    progress::finish();
+
+    match result {
+        Ok(()) => match game::outcome() {
+            game::Outcome::Ok => {}
+            outcome => std::process::exit(outcome as i32),
+        },
+        Err(err) => {
+            eprintln!("* {}", err);
+            std::process::exit(game::Outcome::Errors as i32);
+        }
     }
+}
+
+// reads a text file, URL or the first file in a Zip of either,
+// or standard input if none is given
+fn read_resource(resource: Option<Resource>) -> Result<String, Error> {
+    let mut data = String::new();
 
-    match std::fs::read_dir(named_db_dir(db_dir)) {
-        Ok(dir) => Some(dir.filter_map(|entry| {
-            entry
-                .ok()
-                .map(|entry| entry.path())
-                .and_then(|path| read_game_db(&path))
-        })),
-        Err(_) => None,
+    match resource {
+        Some(resource) => {
+            let mut f = resource.open()?;
+            if is_zip(&mut f)? {
+                zip::ZipArchive::new(f)?
+                    .by_index(0)?
+                    .read_to_string(&mut data)?;
+            } else {
+                f.read_to_string(&mut data)?;
+            }
+        }
+        None => {
+            std::io::stdin().read_to_string(&mut data)?;
+        }
     }
-}
 
-fn read_collected_dbs<C, D>(db_dir: &'static str) -> C
-where
-    C: std::iter::FromIterator<(String, D)>,
-    D: DeserializeOwned,
-{
-    read_named_dbs(db_dir).into_iter().flatten().collect()
+    Ok(data)
 }
 
 fn select_software_list_and_name() -> Result<(game::GameDb, String), Error> {
@@ -2348,6 +6011,36 @@ fn select_software_list_and_name() -> Result<(game::GameDb, String), Error> {
     }
 }
 
+fn select_extra_name() -> Result<String, Error> {
+    dirs::extra_dir_names()
+        .ok_or(Error::NoDatFiles)
+        .and_then(|names| {
+            inquire::Select::new("select extras category", names)
+                .prompt()
+                .map_err(Error::Inquire)
+        })
+}
+
+fn select_nointro_name() -> Result<String, Error> {
+    dirs::nointro_dir_names()
+        .ok_or(Error::NoDatFiles)
+        .and_then(|names| {
+            inquire::Select::new("select DAT", names)
+                .prompt()
+                .map_err(Error::Inquire)
+        })
+}
+
+fn select_redump_name() -> Result<String, Error> {
+    dirs::redump_dir_names()
+        .ok_or(Error::NoDatFiles)
+        .and_then(|names| {
+            inquire::Select::new("select DAT", names)
+                .prompt()
+                .map_err(Error::Inquire)
+        })
+}
+
 #[inline]
 fn select_software_list() -> Result<game::GameDb, Error> {
     select_software_list_and_name().map(|(db, _)| db)
@@ -2417,27 +6110,205 @@ fn promote_dbs() -> Result<(), Error> {
     Ok(())
 }
 
+// how verification results are reported
+struct VerifyReport {
+    only_failures: bool,
+    hints: bool,
+    fixdat: Option<dat::Fixdat>,
+    html: Option<html::HtmlReport>,
+    canonical: bool,
+    shared_devices: bool,
+    format: report::ResultsFormat,
+    acks: acks::Acks,
+    // games with unacknowledged failures in any root verified
+    failing: BTreeSet<String>,
+}
+
+impl VerifyReport {
+    #[inline]
+    fn new(
+        only_failures: bool,
+        hints: bool,
+        fixdat: Option<PathBuf>,
+        html: Option<PathBuf>,
+        canonical: bool,
+        shared_devices: bool,
+        format: report::ResultsFormat,
+    ) -> Self {
+        Self {
+            only_failures,
+            hints,
+            fixdat: fixdat.map(dat::Fixdat::new),
+            html: html.map(html::HtmlReport::new),
+            canonical,
+            shared_devices,
+            format,
+            acks: acks::Acks::default(),
+            failing: BTreeSet::default(),
+        }
+    }
+
+    // reports failures acknowledged in scope apart from the rest
+    fn with_acks(self, scope: &str) -> Self {
+        let db: acks::AckDb = read_game_db(ACKS, DB_ACKS).unwrap_or_default();
+
+        Self {
+            acks: db.scope(scope),
+            ..self
+        }
+    }
+
+    // writes any files requested once all roots are verified
+    fn finish(self, name: &str, description: &str) -> Result<(), Error> {
+        if let Some(html) = self.html {
+            html.write(description)?;
+        }
+
+        match self.fixdat {
+            Some(fixdat) if fixdat.is_empty() => {
+                eprintln!("* nothing missing or bad, no fixdat written");
+                Ok(())
+            }
+            Some(fixdat) => fixdat.write(name, description),
+            None => Ok(()),
+        }
+    }
+}
+
+// disks are looked for under disks, if given, rather than among ROMs
 fn verify<P: AsRef<Path>>(
     db: &game::GameDb,
     root: P,
+    disks: Option<&Path>,
     games: &HashSet<String>,
-    only_failures: bool,
-) {
-    let results = db.verify(root.as_ref(), games);
+    deep: bool,
+    zip: bool,
+    report: &mut VerifyReport,
+) -> game::VerifyResultsSummary {
+    let mut results = db.verify_with_disks(
+        root.as_ref(),
+        disks.unwrap_or_else(|| root.as_ref()),
+        games,
+        deep,
+        zip,
+        report.shared_devices,
+        &BarSink::default(),
+    );
+
+    if zip && report.canonical {
+        db.verify_canonical(root.as_ref(), &mut results);
+    }
+
+    let acknowledged = report.acks.separate(&mut results);
+
+    report.failing.extend(
+        results
+            .iter()
+            .filter(|(_, failures)| !failures.is_empty())
+            .map(|(game, _)| game.to_string()),
+    );
+
+    let mut summary = game::VerifyResultsSummary::new(results.values());
+    summary.acknowledged = acknowledged.values().map(|failures| failures.len()).sum();
+    let successes = summary.successes;
+
+    let only_failures = report.only_failures;
+
+    let triage =
+        (report.hints && successes < results.len()).then(|| triage::Triage::new(db, root.as_ref()));
+
+    if let Some(fixdat) = report.fixdat.as_mut() {
+        for (game, failures) in results.iter() {
+            if let Some(game) = db.game(game) {
+                fixdat.add_failures(game, failures);
+            }
+        }
+    }
 
-    let successes = results.iter().filter(|(_, v)| v.is_empty()).count();
+    if let Some(html) = report.html.as_mut() {
+        html.add_root(root.as_ref().to_path_buf());
+
+        for (game, failures) in results.iter() {
+            html.add_results(game, db.game(game), failures, |failure| {
+                triage.as_ref().and_then(|triage| triage.suggest(failure))
+            });
+        }
+    }
 
     let display = if only_failures {
-        game::display_bad_results
+        report::display_bad_results
     } else {
-        game::display_all_results
+        report::display_all_results
     };
 
     for (game, failures) in results.iter() {
-        display(game, failures);
+        match db.game(game) {
+            _ if matches!(report.format, report::ResultsFormat::Json) => {
+                report::display_json_results(game, failures, only_failures, |failure| {
+                    triage.as_ref().and_then(|triage| triage.suggest(failure))
+                })
+            }
+            // like "mame -verifyroms", note sets which can't be any better
+            Some(g) if !only_failures && failures.is_empty() && g.has_bad_dumps() => {
+                println!("OK : {} (best available)", game)
+            }
+            _ => match &triage {
+                Some(triage) => {
+                    report::display_triaged_results(triage, game, failures, only_failures)
+                }
+                None => display(game, failures),
+            },
+        }
+    }
+
+    if !acknowledged.is_empty() {
+        match report.format {
+            report::ResultsFormat::Json => {
+                for (game, failures) in acknowledged.iter() {
+                    report::display_json_acknowledged(game, failures);
+                }
+            }
+            report::ResultsFormat::Text => report::display_acknowledged(&acknowledged),
+        }
     }
 
-    eprintln!("{} tested, {} OK", games.len(), successes);
+    report::display_totals(&summary, "tested");
+
+    hooks::fire_results(
+        db.description(),
+        &results,
+        hooks::Event::VerifyComplete {
+            name: db.description(),
+            root: root.as_ref(),
+            tested: games.len(),
+            ok: successes,
+        },
+        &Notices,
+    );
+
+    summary
+}
+
+// verifies every game matching search and family
+// and lists those which are complete, or incomplete
+fn report_have_missing(
+    db: &game::GameDb,
+    root: &Path,
+    search: Option<&str>,
+    family: Option<&str>,
+    filter: &game::Filter,
+    have: bool,
+    parts: bool,
+) {
+    let games: HashSet<String> = db
+        .report_results(&db.all_games(), search, family, filter, true)
+        .into_iter()
+        .map(|row| row.name.to_owned())
+        .collect();
+
+    let results = db.verify(root, &games, false, false, &BarSink::default());
+
+    report::display_have_missing(db, &results, have, parts);
 }
 
 fn verify_all(
@@ -2446,90 +6317,203 @@ fn verify_all(
     root: &Path,
     games: &HashSet<String>,
     only_failures: bool,
+    deep: bool,
+    zip: bool,
 ) {
-    let results = db.verify(root, games);
+    let results = db.verify(root, games, deep, zip, &BarSink::default());
 
-    let successes = results.iter().filter(|(_, v)| v.is_empty()).count();
+    let summary = game::VerifyResultsSummary::new(results.values());
+    let successes = summary.successes;
 
     let display = if only_failures {
-        game::display_bad_results
+        report::display_bad_results
     } else {
-        game::display_all_results
+        report::display_all_results
     };
 
     for (game, failures) in results.iter() {
-        display(&format!("{software_list}/{game}"), failures);
+        match db.game(game) {
+            Some(g) if !only_failures && failures.is_empty() && g.has_bad_dumps() => {
+                println!("OK : {software_list}/{game} (best available)")
+            }
+            _ => display(&format!("{software_list}/{game}"), failures),
+        }
     }
 
-    eprintln!("{} tested, {} OK", games.len(), successes);
+    report::display_totals(&summary, "tested");
+
+    hooks::fire_results(
+        software_list,
+        &results,
+        hooks::Event::VerifyComplete {
+            name: software_list,
+            root,
+            tested: games.len(),
+            ok: successes,
+        },
+        &Notices,
+    );
 }
 
+// where name is what's being added to and dat is the database it's
+// added from, which Zip archives are stamped with if they're stamped
+#[allow(clippy::too_many_arguments)]
 fn add_and_verify_games<'g, I, F, P>(
+    name: &str,
+    dat: &str,
     mut display: F,
     roms: &mut game::RomSources,
     root: P,
+    disks: Option<&Path>,
     games: I,
-) -> Result<(), Error>
+    dry_run: bool,
+    zip: bool,
+) -> Result<FailedGames, Error>
 where
     P: AsRef<Path>,
     F: FnMut(&str, &[game::VerifyFailure]),
     I: Iterator<Item = &'g game::Game>,
 {
-    use indicatif::{ProgressBar, ProgressStyle};
+    let total = match games.size_hint().1 {
+        Some(total) => sink::Total::Items(total as u64),
+        None => sink::Total::Unknown,
+    };
 
-    let pb = match games.size_hint() {
-        (_, Some(total)) => ProgressBar::new(total as u64)
-            .with_style(ProgressStyle::default_bar().template("{wide_msg} {pos} / {len}")),
-        (_, None) => ProgressBar::new_spinner(),
+    let mut results = BTreeMap::new();
+    let mut failed = Vec::new();
+
+    let disks = disks.unwrap_or_else(|| root.as_ref());
+    let stamp = stamp::Stamp::new(dat);
+
+    let sink = BarSink::default();
+
+    if !dry_run {
+        snapshot::before_modifying(root.as_ref(), &sink)?;
+        if disks != root.as_ref() {
+            snapshot::before_modifying(disks, &sink)?;
+        }
     }
-    .with_message("adding and verifying");
+    let task = sink::Task::start(&sink, "adding and verifying", total);
 
-    let results = pb
-        .wrap_iter(games.map(|game| {
-            game.add_and_verify(roms, root.as_ref(), |p| pb.println(p.to_string()))
-                .map(|failures| (game.name.as_str(), failures))
-        }))
-        .collect::<Result<BTreeMap<_, _>, Error>>()?;
+    // an error building one game shouldn't keep the rest from being added
+    for game in games {
+        match if zip {
+            game.add_and_verify_zip(roms, root.as_ref(), disks, stamp.as_ref(), dry_run, &sink)
+        } else {
+            game.add_and_verify(roms, root.as_ref(), disks, dry_run, &sink)
+        } {
+            Ok(failures) => {
+                game::report_failures(&game.name, &failures, &sink);
+                results.insert(game.name.as_str(), failures);
+            }
+            Err(err) => failed.push((game.name.clone(), err)),
+        }
 
-    pb.finish_and_clear();
+        task.advance(1);
+    }
+
+    drop(task);
 
-    let successes = results.values().filter(|v| v.is_empty()).count();
+    let summary = game::VerifyResultsSummary::new(results.values());
+    let successes = summary.successes;
 
     for (game, failures) in results.iter() {
         display(game, failures);
     }
 
-    eprintln!("{} added, {} OK", results.len(), successes);
+    report::display_totals(&summary, "added");
+
+    if !dry_run {
+        hooks::fire_results(
+            name,
+            &results,
+            hooks::Event::AddFinished {
+                name,
+                root: root.as_ref(),
+                added: results.len(),
+                ok: successes,
+            },
+            &sink,
+        );
+    }
+
+    Ok(failed)
+}
 
-    Ok(())
+// summarizes games which couldn't be added
+// once all the others have been
+fn report_failed_games(failed: FailedGames) -> Result<(), Error> {
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        for (game, err) in failed.iter() {
+            eprintln!("* {} : {}", game, err);
+        }
+
+        Err(Error::FailedGames(failed.len()))
+    }
 }
 
 #[inline]
-fn add_and_verify<'g, I, P>(roms: &mut game::RomSources, root: P, games: I) -> Result<(), Error>
+#[allow(clippy::too_many_arguments)]
+fn add_and_verify<'g, I, P>(
+    name: &str,
+    dat: &str,
+    roms: &mut game::RomSources,
+    root: P,
+    disks: Option<&Path>,
+    games: I,
+    dry_run: bool,
+    zip: bool,
+) -> Result<FailedGames, Error>
 where
     P: AsRef<Path>,
     I: Iterator<Item = &'g game::Game>,
 {
-    add_and_verify_games(game::display_bad_results, roms, root, games)
+    add_and_verify_games(
+        name,
+        dat,
+        report::display_bad_results,
+        roms,
+        root,
+        disks,
+        games,
+        dry_run,
+        zip,
+    )
 }
 
 #[inline]
 fn add_and_verify_all<'g, I, P>(
     software_list: &str,
+    dat: &str,
     roms: &mut game::RomSources,
     root: P,
     games: I,
-) -> Result<(), Error>
+    dry_run: bool,
+    zip: bool,
+) -> Result<FailedGames, Error>
 where
     P: AsRef<Path>,
     I: Iterator<Item = &'g game::Game>,
 {
     add_and_verify_games(
-        |game, failures| game::display_bad_results(&format!("{software_list}/{game}"), failures),
+        software_list,
+        dat,
+        |game, failures| report::display_bad_results(&format!("{software_list}/{game}"), failures),
         roms,
         root,
+        None,
         games,
+        dry_run,
+        zip,
     )
+    .map(|failed| {
+        failed
+            .into_iter()
+            .map(|(game, err)| (format!("{software_list}/{game}"), err))
+            .collect()
+    })
 }
 
 fn display_dirs<D>(dirs: D, db: BTreeMap<String, dat::DatFile>, sort_by_version: bool)
@@ -2573,13 +6557,38 @@ fn init_dat_table() -> prettytable::Table {
     table
 }
 
-fn display_dat_table(mut table: prettytable::Table, summary: Option<game::VerifyResultsSummary>) {
-    if let Some(summary) = summary {
+// tables of more than one DAT get a row of totals
+fn display_dat_table(
+    mut table: prettytable::Table,
+    summary: game::VerifyResultsSummary,
+    verb: &str,
+) {
+    if table.len() > 1 {
         use prettytable::{cell, row};
 
         table.add_row(row![r->summary.total, r->summary.successes, "Total"]);
     }
     table.printstd();
+
+    report::display_totals(&summary, verb);
+}
+
+// MAME's hash path has one software list XML file per list,
+// alongside other files which aren't software lists
+fn hash_files(path: PathBuf) -> Vec<PathBuf> {
+    if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&path)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && p.extension().map(|e| e == "xml").unwrap_or(false))
+            .collect();
+        files.sort_unstable();
+        files
+    } else {
+        vec![path]
+    }
 }
 
 fn sub_files(root: PathBuf) -> Box<dyn Iterator<Item = PathBuf>> {
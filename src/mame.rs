@@ -1,5 +1,7 @@
-use super::game::{Game, GameDb, Part, Status};
+use super::game::{parse_int, Class, DriverStatus, Game, GameDb, Part, Status};
+use crate::locale::Locale;
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize)]
 pub struct Mame {
@@ -23,7 +25,11 @@ impl Mame {
 #[derive(Debug, Deserialize)]
 pub struct Machine {
     name: String,
+    cloneof: Option<String>,
+    romof: Option<String>,
+    isbios: Option<String>,
     isdevice: Option<String>,
+    ismechanical: Option<String>,
     description: String,
     year: Option<String>,
     manufacturer: Option<String>,
@@ -34,15 +40,78 @@ pub struct Machine {
 }
 
 impl Machine {
+    #[inline]
+    fn class(&self) -> Class {
+        let yes = |attr: &Option<String>| matches!(attr.as_deref(), Some("yes"));
+
+        if yes(&self.isdevice) {
+            Class::Device
+        } else if yes(&self.isbios) {
+            Class::Bios
+        } else if yes(&self.ismechanical) {
+            Class::Mechanical
+        } else {
+            Class::Arcade
+        }
+    }
+
     #[inline]
     fn into_game(self) -> Game {
+        let class = self.class();
+
         Game {
             name: self.name,
             description: self.description,
             creator: self.manufacturer.unwrap_or_default(),
             year: self.year.unwrap_or_default(),
-            status: self.driver.map(|d| d.status()).unwrap_or(Status::Working),
+            status: self
+                .driver
+                .as_ref()
+                .map(|d| d.status())
+                .unwrap_or(Status::Working),
+            driver: self.driver.map(Driver::into_driver_status),
             is_device: matches!(self.isdevice.as_deref(), Some("yes")),
+            class,
+            merged: self
+                .rom
+                .iter()
+                .flatten()
+                .filter(|rom| rom.merge.is_some())
+                .map(|rom| rom.name.clone())
+                .chain(
+                    self.disk
+                        .iter()
+                        .flatten()
+                        .filter(|disk| disk.merge.is_some())
+                        .map(|disk| disk.name.clone() + ".chd"),
+                )
+                .collect(),
+            sizes: self
+                .rom
+                .iter()
+                .flatten()
+                .filter_map(|rom| Some((rom.name.clone(), parse_int(rom.size.as_deref()?).ok()?)))
+                .collect(),
+            cloneof: self.cloneof,
+            romof: self.romof,
+            requires: Vec::default(),
+            category: None,
+            players: None,
+            locale: Locale::default(),
+            bad_dumps: self
+                .rom
+                .iter()
+                .flatten()
+                .filter(|rom| rom.is_bad_dump())
+                .map(|rom| rom.name.clone())
+                .chain(
+                    self.disk
+                        .iter()
+                        .flatten()
+                        .filter(|disk| disk.is_bad_dump())
+                        .map(|disk| disk.name.clone() + ".chd"),
+                )
+                .collect(),
             parts: self
                 .rom
                 .into_iter()
@@ -63,9 +132,20 @@ impl Machine {
 #[derive(Debug, Deserialize)]
 struct Driver {
     status: String,
+    emulation: Option<String>,
+    savestate: Option<String>,
 }
 
 impl Driver {
+    #[inline]
+    fn into_driver_status(self) -> DriverStatus {
+        DriverStatus {
+            status: self.status,
+            emulation: self.emulation.unwrap_or_default(),
+            savestate: self.savestate.unwrap_or_default(),
+        }
+    }
+
     fn status(&self) -> Status {
         match self.status.as_str() {
             "good" => Status::Working,
@@ -79,12 +159,25 @@ impl Driver {
 #[derive(Debug, Deserialize)]
 struct Rom {
     name: String,
+    size: Option<String>,
     sha1: Option<String>,
+    status: Option<String>,
+    merge: Option<String>,
 }
 
 impl Rom {
+    #[inline]
+    fn is_bad_dump(&self) -> bool {
+        matches!(self.status.as_deref(), Some("baddump"))
+    }
+
     #[inline]
     fn into_part(self) -> Option<(String, Part)> {
+        // undumped ROMs can't be verified, so skip them entirely
+        if matches!(self.status.as_deref(), Some("nodump")) {
+            return None;
+        }
+
         Some((self.name, Part::new_rom(self.sha1.as_deref()?).ok()?))
     }
 }
@@ -93,11 +186,22 @@ impl Rom {
 struct Disk {
     name: String,
     sha1: Option<String>,
+    status: Option<String>,
+    merge: Option<String>,
 }
 
 impl Disk {
+    #[inline]
+    fn is_bad_dump(&self) -> bool {
+        matches!(self.status.as_deref(), Some("baddump"))
+    }
+
     #[inline]
     fn into_part(self) -> Option<(String, Part)> {
+        if matches!(self.status.as_deref(), Some("nodump")) {
+            return None;
+        }
+
         Some((
             self.name + ".chd",
             Part::new_disk(self.sha1.as_deref()?).ok()?,
@@ -109,3 +213,28 @@ impl Disk {
 struct DeviceRef {
     name: String,
 }
+
+// machine values from one section of an INI file
+// like catver.ini's "[Category]" or nplayers.ini's "[NPlayers]",
+// given as "machine=value" lines
+pub fn parse_ini_section(data: &str, section: &str) -> HashMap<String, String> {
+    let mut in_section = false;
+    let mut values = HashMap::new();
+
+    for line in data.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        } else if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name.trim().eq_ignore_ascii_case(section);
+        } else if in_section {
+            if let Some((machine, value)) = line.split_once('=') {
+                let (machine, value) = (machine.trim(), value.trim());
+                if !machine.is_empty() && !value.is_empty() {
+                    values.insert(machine.to_owned(), value.to_owned());
+                }
+            }
+        }
+    }
+
+    values
+}
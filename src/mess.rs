@@ -1,10 +1,11 @@
 use super::{
-    game::{Game, GameColumn, GameDb, GameParts, GameRow, Part as GamePart, Status},
+    game::{Class, Filter, Game, GameColumn, GameDb, GameParts, GameRow, Part as GamePart, Status},
     split::{SplitDb, SplitGame, SplitPart},
 };
 use crate::game::parse_int;
+use crate::locale::Locale;
 use serde::Deserialize;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Debug, Deserialize)]
 pub struct Softwarelist {
@@ -50,11 +51,15 @@ pub struct Software {
     year: String,
     publisher: String,
     supported: Option<String>,
+    cloneof: Option<String>,
+    sharedfeat: Option<Vec<Feature>>,
     part: Option<Vec<Part>>,
 }
 
 impl Software {
     fn into_game(self) -> Game {
+        let requires = self.requirements();
+
         Game {
             name: self.name,
             description: self.description,
@@ -65,21 +70,70 @@ impl Software {
                 Some("no") => Status::NotWorking,
                 _ => Status::Working,
             },
+            driver: None,
             is_device: false,
+            class: Class::Console,
             devices: Vec::default(),
+            cloneof: self.cloneof,
+            romof: None,
+            merged: BTreeSet::default(),
+            requires,
+            category: None,
+            players: None,
+            locale: Locale::default(),
+            sizes: self
+                .part
+                .iter()
+                .flatten()
+                .flat_map(|part| part.dataarea.iter().flatten())
+                .flat_map(|dataarea| dataarea.rom.iter().flatten())
+                .filter_map(|rom| rom.to_size())
+                .map(|RomSize { name, size, .. }| (name.to_owned(), size))
+                .collect(),
+            bad_dumps: self
+                .part
+                .iter()
+                .flatten()
+                .flat_map(|part| part.bad_dumps())
+                .collect(),
             parts: self
                 .part
                 .into_iter()
                 .flatten()
                 .map(|part| part.into_parts())
                 .reduce(|mut acc, item| {
-                    acc.extend(item.into_iter());
+                    acc.extend(item);
                     acc
                 })
                 .unwrap_or_default(),
         }
     }
 
+    // requirements may be shared by all parts
+    // or given for individual parts
+    fn requirements(&self) -> Vec<String> {
+        let mut requires: Vec<String> = Vec::new();
+
+        for requirement in self
+            .sharedfeat
+            .iter()
+            .flatten()
+            .chain(
+                self.part
+                    .iter()
+                    .flatten()
+                    .flat_map(|part| part.feature.iter().flatten()),
+            )
+            .filter_map(|feature| feature.requirement())
+        {
+            if !requires.iter().any(|r| r == requirement) {
+                requires.push(requirement.to_owned());
+            }
+        }
+
+        requires
+    }
+
     fn to_split_db(&self) -> Option<(u64, SplitGame)> {
         let rom_sizes = self
             .part
@@ -113,11 +167,29 @@ impl Software {
 
 #[derive(Debug, Deserialize)]
 pub struct Part {
+    feature: Option<Vec<Feature>>,
     dataarea: Option<Vec<Dataarea>>,
     diskarea: Option<Vec<Diskarea>>,
 }
 
 impl Part {
+    fn bad_dumps(&self) -> impl Iterator<Item = String> + '_ {
+        self.dataarea
+            .iter()
+            .flatten()
+            .flat_map(|dataarea| dataarea.rom.iter().flatten())
+            .filter(|rom| rom.is_bad_dump())
+            .filter_map(|rom| rom.name.clone())
+            .chain(
+                self.diskarea
+                    .iter()
+                    .flatten()
+                    .flat_map(|diskarea| diskarea.disk.iter().flatten())
+                    .filter(|disk| disk.is_bad_dump())
+                    .map(|disk| disk.name.clone() + ".chd"),
+            )
+    }
+
     fn into_parts(self) -> GameParts {
         self.dataarea
             .into_iter()
@@ -133,6 +205,21 @@ impl Part {
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct Feature {
+    name: String,
+    value: Option<String>,
+}
+
+impl Feature {
+    #[inline]
+    fn requirement(&self) -> Option<&str> {
+        (self.name == "requirement")
+            .then_some(self.value.as_deref())
+            .flatten()
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Dataarea {
     rom: Option<Vec<Rom>>,
@@ -152,16 +239,27 @@ pub struct Rom {
     name: Option<String>,
     size: Option<String>,
     sha1: Option<String>,
+    status: Option<String>,
 }
 
 impl Rom {
+    #[inline]
+    fn is_bad_dump(&self) -> bool {
+        matches!(self.status.as_deref(), Some("baddump"))
+    }
+
     #[inline]
     fn into_part(self) -> Option<(String, GamePart)> {
+        // undumped ROMs can't be verified, so skip them entirely
+        if matches!(self.status.as_deref(), Some("nodump")) {
+            return None;
+        }
+
         Some((self.name?, GamePart::new_rom(&self.sha1?).ok()?))
     }
 
     #[inline]
-    fn to_size(&self) -> Option<RomSize> {
+    fn to_size(&self) -> Option<RomSize<'_>> {
         Some(RomSize {
             name: self.name.as_deref()?,
             size: parse_int(self.size.as_deref()?).ok()?,
@@ -194,23 +292,40 @@ impl Diskarea {
 pub struct Disk {
     name: String,
     sha1: Option<String>,
+    status: Option<String>,
 }
 
 impl Disk {
+    #[inline]
+    fn is_bad_dump(&self) -> bool {
+        matches!(self.status.as_deref(), Some("baddump"))
+    }
+
     #[inline]
     fn into_part(self) -> Option<(String, GamePart)> {
+        if matches!(self.status.as_deref(), Some("nodump")) {
+            return None;
+        }
+
         Some((self.name + ".chd", GamePart::new_disk(&self.sha1?).ok()?))
     }
 }
 
 pub type MessDb = BTreeMap<String, GameDb>;
 
-pub fn list(db: &MessDb, search: Option<&str>, sort: GameColumn, simple: bool) {
+// the games of every software list, sorted, with the list each is in
+pub fn list_results<'d>(
+    db: &'d MessDb,
+    search: Option<&str>,
+    family: Option<&str>,
+    sort: GameColumn,
+    simple: bool,
+) -> Vec<(&'d str, GameRow<'d>)> {
     let mut results: Vec<(&str, GameRow)> = db
         .iter()
         .flat_map(|(name, game_db)| {
             game_db
-                .list_results(search, simple)
+                .list_results(search, family, &Filter::default(), simple)
                 .into_iter()
                 .map(move |row| (name.as_str(), row))
         })
@@ -218,44 +333,7 @@ pub fn list(db: &MessDb, search: Option<&str>, sort: GameColumn, simple: bool) {
 
     results.sort_by(|(_, a), (_, b)| a.compare(b, sort));
 
-    display_results(&results);
-}
-
-pub fn display_results(results: &[(&str, GameRow)]) {
-    use prettytable::{cell, format, row, Table};
-
-    let mut table = Table::new();
-
-    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-    table.get_format().column_separator('\u{2502}');
-
-    for (db_name, game) in results {
-        let description = game.description;
-        let creator = game.creator;
-        let year = game.year;
-        let name = game.name;
-
-        table.add_row(match game.status {
-            Status::Working => row![description, creator, year, db_name, name],
-            Status::Partial => row![FY => description, creator, year, db_name, name],
-            Status::NotWorking => row![FR => description, creator, year, db_name, name],
-        });
-    }
-
-    table.printstd();
-}
-
-pub fn list_all(db: &MessDb) {
-    use prettytable::{cell, format, row, Table};
-    let mut table = Table::new();
-
-    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-
-    for (name, game_db) in db.iter() {
-        table.add_row(row![game_db.description(), name]);
-    }
-
-    table.printstd();
+    results
 }
 
 pub fn strip_ines_header(data: &[u8]) -> &[u8] {
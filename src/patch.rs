@@ -0,0 +1,751 @@
+// applies IPS, BPS and VCDIFF (xdelta3) patches to base ROMs,
+// each format recognized by its magic number rather than file extension
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: usize = 0x454F46;
+const BPS_MAGIC: &[u8] = b"BPS1";
+const VCDIFF_MAGIC: &[u8] = &[0xD6, 0xC3, 0xC4, 0x00];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ips,
+    Bps,
+    Vcdiff,
+}
+
+impl Format {
+    pub fn of(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(IPS_MAGIC) {
+            Some(Format::Ips)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Some(Format::Bps)
+        } else if patch.starts_with(VCDIFF_MAGIC) {
+            Some(Format::Vcdiff)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Format::Ips => "IPS".fmt(f),
+            Format::Bps => "BPS".fmt(f),
+            Format::Vcdiff => "xdelta".fmt(f),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PatchError {
+    message: &'static str,
+}
+
+impl PatchError {
+    #[inline]
+    fn new(message: &'static str) -> Self {
+        Self { message }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+const TRUNCATED: PatchError = PatchError {
+    message: "truncated patch",
+};
+
+const OUT_OF_RANGE: PatchError = PatchError {
+    message: "patch reads outside of its input",
+};
+
+// returns the patched copy of base
+pub fn apply(base: &[u8], patch: &[u8]) -> Result<(Format, Vec<u8>), PatchError> {
+    match Format::of(patch) {
+        Some(Format::Ips) => apply_ips(base, patch).map(|target| (Format::Ips, target)),
+        Some(Format::Bps) => apply_bps(base, patch).map(|target| (Format::Bps, target)),
+        Some(Format::Vcdiff) => apply_vcdiff(base, patch).map(|target| (Format::Vcdiff, target)),
+        None => Err(PatchError::new("unknown patch format")),
+    }
+}
+
+// a patch being read from start to finish
+struct Reader<'p> {
+    data: &'p [u8],
+    pos: usize,
+}
+
+impl<'p> Reader<'p> {
+    #[inline]
+    fn new(data: &'p [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'p [u8], PatchError> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(TRUNCATED)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn byte(&mut self) -> Result<u8, PatchError> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    // big-endian integers of the given width, as IPS uses
+    fn be(&mut self, width: usize) -> Result<usize, PatchError> {
+        Ok(self
+            .bytes(width)?
+            .iter()
+            .fold(0, |n, b| (n << 8) | usize::from(*b)))
+    }
+
+    // BPS numbers, least significant group first,
+    // with each continuation offset so no number has two encodings
+    fn bps_number(&mut self) -> Result<usize, PatchError> {
+        let mut number: usize = 0;
+        let mut shift: usize = 1;
+
+        loop {
+            let b = self.byte()?;
+            number = usize::from(b & 0x7F)
+                .checked_mul(shift)
+                .and_then(|n| n.checked_add(number))
+                .ok_or(OUT_OF_RANGE)?;
+            if b & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift.checked_mul(128).ok_or(OUT_OF_RANGE)?;
+            number = number.checked_add(shift).ok_or(OUT_OF_RANGE)?;
+        }
+    }
+
+    // VCDIFF integers, most significant group first
+    fn vcdiff_number(&mut self) -> Result<usize, PatchError> {
+        let mut number: usize = 0;
+
+        loop {
+            let b = self.byte()?;
+            number = number
+                .checked_mul(128)
+                .map(|n| n | usize::from(b & 0x7F))
+                .ok_or(OUT_OF_RANGE)?;
+            if b & 0x80 == 0 {
+                return Ok(number);
+            }
+        }
+    }
+}
+
+// patches declare their target's size up front, but since a hostile one
+// may declare any size at all, no more is reserved than base and patch
+// could plausibly make, and anything beyond that grows as it's written
+const MAX_EXPANSION: usize = 16;
+
+#[inline]
+fn reserved(declared: usize, base: &[u8], patch: &[u8]) -> usize {
+    declared.min(
+        base.len()
+            .saturating_add(patch.len().saturating_mul(MAX_EXPANSION)),
+    )
+}
+
+#[inline]
+fn slice(data: &[u8], start: usize, len: usize) -> Result<&[u8], PatchError> {
+    start
+        .checked_add(len)
+        .and_then(|end| data.get(start..end))
+        .ok_or(OUT_OF_RANGE)
+}
+
+// IPS records overwrite base at offsets given in the patch,
+// growing it if necessary, with an optional size to truncate to at the end
+fn apply_ips(base: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut r = Reader::new(patch);
+    let mut target = base.to_vec();
+
+    r.bytes(IPS_MAGIC.len())?;
+
+    loop {
+        let offset = r.be(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+
+        match r.be(2)? {
+            // run-length encoded records repeat a single byte
+            0 => {
+                let len = r.be(2)?;
+                let value = r.byte()?;
+                if target.len() < offset + len {
+                    target.resize(offset + len, 0);
+                }
+                target[offset..offset + len].fill(value);
+            }
+            len => {
+                let data = r.bytes(len)?;
+                if target.len() < offset + len {
+                    target.resize(offset + len, 0);
+                }
+                target[offset..offset + len].copy_from_slice(data);
+            }
+        }
+    }
+
+    if !r.is_empty() {
+        target.truncate(r.be(3)?);
+    }
+
+    Ok(target)
+}
+
+fn apply_bps(base: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    const FOOTER: usize = 12;
+
+    let (body, footer) = patch
+        .len()
+        .checked_sub(FOOTER)
+        .map(|split| patch.split_at(split))
+        .ok_or(TRUNCATED)?;
+
+    let crc = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+
+    if crc32fast::hash(&patch[..patch.len() - 4]) != crc(&footer[8..]) {
+        return Err(PatchError::new("BPS patch is damaged"));
+    }
+
+    if crc32fast::hash(base) != crc(&footer[..4]) {
+        return Err(PatchError::new("base doesn't match BPS patch's source"));
+    }
+
+    let mut r = Reader::new(body);
+    r.bytes(BPS_MAGIC.len())?;
+
+    if r.bps_number()? != base.len() {
+        return Err(PatchError::new(
+            "base doesn't match BPS patch's source size",
+        ));
+    }
+
+    let target_size = r.bps_number()?;
+    let metadata_size = r.bps_number()?;
+    r.bytes(metadata_size)?;
+
+    let mut target: Vec<u8> = Vec::with_capacity(reserved(target_size, base, patch));
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+
+    // relative offsets are stored as a magnitude with a sign bit
+    let relative = |offset: usize, data: usize| {
+        if data & 1 == 0 {
+            offset.checked_add(data >> 1)
+        } else {
+            offset.checked_sub(data >> 1)
+        }
+        .ok_or(OUT_OF_RANGE)
+    };
+
+    while !r.is_empty() {
+        let action = r.bps_number()?;
+        let len = (action >> 2) + 1;
+
+        match action & 3 {
+            // source read
+            0 => {
+                let data = slice(base, target.len(), len)?;
+                target.extend_from_slice(data);
+            }
+            // target read
+            1 => target.extend_from_slice(r.bytes(len)?),
+            // source copy
+            2 => {
+                source_offset = relative(source_offset, r.bps_number()?)?;
+                target.extend_from_slice(slice(base, source_offset, len)?);
+                source_offset += len;
+            }
+            // target copy, which may overlap what it's writing
+            _ => {
+                target_offset = relative(target_offset, r.bps_number()?)?;
+                for _ in 0..len {
+                    let b = *target.get(target_offset).ok_or(OUT_OF_RANGE)?;
+                    target.push(b);
+                    target_offset += 1;
+                }
+            }
+        }
+
+        if target.len() > target_size {
+            return Err(OUT_OF_RANGE);
+        }
+    }
+
+    if target.len() != target_size || crc32fast::hash(&target) != crc(&footer[4..8]) {
+        return Err(PatchError::new("BPS patch produced the wrong target"));
+    }
+
+    Ok(target)
+}
+
+// VCDIFF as described by RFC 3284, and written by xdelta3
+// when secondary compression is disabled (with "-S none")
+const VCD_DECOMPRESS: u8 = 0x01;
+const VCD_CODETABLE: u8 = 0x02;
+const VCD_APPHEADER: u8 = 0x04;
+
+const VCD_SOURCE: u8 = 0x01;
+const VCD_TARGET: u8 = 0x02;
+const VCD_ADLER32: u8 = 0x04;
+
+const S_NEAR: usize = 4;
+const S_SAME: usize = 3;
+
+#[derive(Copy, Clone, Default)]
+enum Op {
+    #[default]
+    Noop,
+    Add,
+    Run,
+    Copy(u8),
+}
+
+// each instruction code performs up to two operations,
+// where a size of 0 is read from the instructions that follow
+#[derive(Copy, Clone, Default)]
+struct Code {
+    ops: [(Op, usize); 2],
+}
+
+fn default_code_table() -> [Code; 256] {
+    let mut table = [Code::default(); 256];
+    let mut codes = table.iter_mut();
+    let mut push = |first: (Op, usize), second: (Op, usize)| {
+        codes.next().unwrap().ops = [first, second];
+    };
+    let noop = (Op::Noop, 0);
+
+    push((Op::Run, 0), noop);
+
+    for size in 0..=17 {
+        push((Op::Add, size), noop);
+    }
+
+    for mode in 0..9 {
+        push((Op::Copy(mode), 0), noop);
+        for size in 4..=18 {
+            push((Op::Copy(mode), size), noop);
+        }
+    }
+
+    for mode in 0..9 {
+        let copy_sizes = if mode < 6 { 4..=6 } else { 4..=4 };
+        for add_size in 1..=4 {
+            for copy_size in copy_sizes.clone() {
+                push((Op::Add, add_size), (Op::Copy(mode), copy_size));
+            }
+        }
+    }
+
+    for mode in 0..9 {
+        push((Op::Copy(mode), 4), (Op::Add, 1));
+    }
+
+    table
+}
+
+// recently used copy addresses, which later copies can refer to compactly
+struct AddressCache {
+    near: [usize; S_NEAR],
+    next_slot: usize,
+    same: [usize; S_SAME * 256],
+}
+
+impl AddressCache {
+    fn new() -> Self {
+        Self {
+            near: [0; S_NEAR],
+            next_slot: 0,
+            same: [0; S_SAME * 256],
+        }
+    }
+
+    fn decode(&mut self, addrs: &mut Reader, here: usize, mode: u8) -> Result<usize, PatchError> {
+        let mode = usize::from(mode);
+
+        let addr = match mode {
+            0 => addrs.vcdiff_number()?,
+            1 => here
+                .checked_sub(addrs.vcdiff_number()?)
+                .ok_or(OUT_OF_RANGE)?,
+            m if m < 2 + S_NEAR => self.near[m - 2]
+                .checked_add(addrs.vcdiff_number()?)
+                .ok_or(OUT_OF_RANGE)?,
+            m => self.same[(m - 2 - S_NEAR) * 256 + usize::from(addrs.byte()?)],
+        };
+
+        self.near[self.next_slot] = addr;
+        self.next_slot = (self.next_slot + 1) % S_NEAR;
+        self.same[addr % (S_SAME * 256)] = addr;
+
+        Ok(addr)
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+
+    let (a, b) = data.chunks(5552).fold((1, 0), |(mut a, mut b), chunk| {
+        for byte in chunk {
+            a += u32::from(*byte);
+            b += a;
+        }
+        (a % MOD, b % MOD)
+    });
+
+    (b << 16) | a
+}
+
+fn apply_vcdiff(base: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut r = Reader::new(patch);
+    r.bytes(VCDIFF_MAGIC.len())?;
+
+    let header = r.byte()?;
+    if header & VCD_DECOMPRESS != 0 {
+        return Err(PatchError::new(
+            "secondary compression isn't supported, create patches with \"xdelta3 -S none\"",
+        ));
+    }
+    if header & VCD_CODETABLE != 0 {
+        return Err(PatchError::new("custom code tables aren't supported"));
+    }
+    if header & VCD_APPHEADER != 0 {
+        let len = r.vcdiff_number()?;
+        r.bytes(len)?;
+    }
+
+    let table = default_code_table();
+    let mut target: Vec<u8> = Vec::new();
+
+    while !r.is_empty() {
+        let indicator = r.byte()?;
+
+        let source = if indicator & (VCD_SOURCE | VCD_TARGET) != 0 {
+            let len = r.vcdiff_number()?;
+            let pos = r.vcdiff_number()?;
+            if indicator & VCD_SOURCE != 0 {
+                slice(base, pos, len)?.to_vec()
+            } else {
+                slice(&target, pos, len)?.to_vec()
+            }
+        } else {
+            Vec::new()
+        };
+
+        r.vcdiff_number()?; // length of the rest of the window
+        let window_len = r.vcdiff_number()?;
+
+        if r.byte()? != 0 {
+            return Err(PatchError::new(
+                "secondary compression isn't supported, create patches with \"xdelta3 -S none\"",
+            ));
+        }
+
+        let data_len = r.vcdiff_number()?;
+        let inst_len = r.vcdiff_number()?;
+        let addr_len = r.vcdiff_number()?;
+        let checksum = if indicator & VCD_ADLER32 != 0 {
+            Some(r.be(4)? as u32)
+        } else {
+            None
+        };
+
+        let mut data = Reader::new(r.bytes(data_len)?);
+        let mut insts = Reader::new(r.bytes(inst_len)?);
+        let mut addrs = Reader::new(r.bytes(addr_len)?);
+
+        let mut window: Vec<u8> = Vec::with_capacity(reserved(window_len, base, patch));
+        let mut cache = AddressCache::new();
+
+        while !insts.is_empty() {
+            let code = table[usize::from(insts.byte()?)];
+
+            for (op, size) in code.ops {
+                let size = match (op, size) {
+                    (Op::Noop, _) => continue,
+                    (_, 0) => insts.vcdiff_number()?,
+                    (_, size) => size,
+                };
+
+                match op {
+                    Op::Noop => {}
+                    Op::Add => window.extend_from_slice(data.bytes(size)?),
+                    Op::Run => {
+                        let b = data.byte()?;
+                        let len = window
+                            .len()
+                            .checked_add(size)
+                            .filter(|len| *len <= window_len)
+                            .ok_or(OUT_OF_RANGE)?;
+                        window.resize(len, b);
+                    }
+                    // addresses run through the source segment
+                    // and on into the window being written, which copies may overlap
+                    Op::Copy(mode) => {
+                        let here = source.len() + window.len();
+                        let start = cache.decode(&mut addrs, here, mode)?;
+                        if start >= here {
+                            return Err(OUT_OF_RANGE);
+                        }
+                        for addr in start..start.checked_add(size).ok_or(OUT_OF_RANGE)? {
+                            let b = match addr.checked_sub(source.len()) {
+                                None => source[addr],
+                                Some(offset) => *window.get(offset).ok_or(OUT_OF_RANGE)?,
+                            };
+                            window.push(b);
+                        }
+                    }
+                }
+
+                if window.len() > window_len {
+                    return Err(OUT_OF_RANGE);
+                }
+            }
+        }
+
+        if window.len() != window_len {
+            return Err(PatchError::new("xdelta patch produced the wrong target"));
+        }
+
+        if checksum.is_some_and(|checksum| checksum != adler32(&window)) {
+            return Err(PatchError::new("xdelta patch produced the wrong target"));
+        }
+
+        target.extend(window);
+    }
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bps_number(mut number: usize, patch: &mut Vec<u8>) {
+        loop {
+            let group = (number & 0x7F) as u8;
+            number >>= 7;
+            if number == 0 {
+                patch.push(0x80 | group);
+                return;
+            }
+            patch.push(group);
+            number -= 1;
+        }
+    }
+
+    fn vcdiff_number(number: usize, patch: &mut Vec<u8>) {
+        let mut groups = vec![(number & 0x7F) as u8];
+        let mut number = number >> 7;
+        while number != 0 {
+            groups.push(0x80 | (number & 0x7F) as u8);
+            number >>= 7;
+        }
+        patch.extend(groups.iter().rev());
+    }
+
+    fn bps_footer(base: &[u8], target: &[u8], mut patch: Vec<u8>) -> Vec<u8> {
+        patch.extend(crc32fast::hash(base).to_le_bytes());
+        patch.extend(crc32fast::hash(target).to_le_bytes());
+        let crc = crc32fast::hash(&patch);
+        patch.extend(crc.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn ips() {
+        let mut patch = b"PATCH".to_vec();
+        // plain record
+        patch.extend([0x00, 0x00, 0x00, 0x00, 0x05]);
+        patch.extend(b"HELLO");
+        // run-length record past the end of base
+        patch.extend([0x00, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x03, b'!']);
+        patch.extend(b"EOF");
+
+        assert_eq!(
+            apply(b"hello world", &patch).unwrap(),
+            (Format::Ips, b"HELLO world!!!".to_vec())
+        );
+
+        // with a size to truncate to
+        patch.extend([0x00, 0x00, 0x05]);
+        assert_eq!(apply(b"hello world", &patch).unwrap().1, b"HELLO");
+    }
+
+    #[test]
+    fn ips_truncated() {
+        assert!(apply(b"hello", b"PATCH\x00\x00\x00\x00\x05HE").is_err());
+    }
+
+    #[test]
+    fn bps() {
+        let base = b"abcdefgh";
+        let target = b"abcXYZghab";
+
+        let mut patch = b"BPS1".to_vec();
+        bps_number(base.len(), &mut patch);
+        bps_number(target.len(), &mut patch);
+        bps_number(0, &mut patch);
+        // source read of "abc"
+        bps_number(2 << 2, &mut patch);
+        // target read of "XYZ"
+        bps_number((2 << 2) | 1, &mut patch);
+        patch.extend(b"XYZ");
+        // source copy of "gh"
+        bps_number((1 << 2) | 2, &mut patch);
+        bps_number(6 << 1, &mut patch);
+        // target copy of "ab"
+        bps_number((1 << 2) | 3, &mut patch);
+        bps_number(0, &mut patch);
+
+        let patch = bps_footer(base, target, patch);
+
+        assert_eq!(apply(base, &patch).unwrap(), (Format::Bps, target.to_vec()));
+
+        // a base other than the one the patch was made from
+        assert!(apply(b"abcdefgi", &patch).is_err());
+
+        // a damaged patch
+        let mut damaged = patch.clone();
+        damaged[10] ^= 0xFF;
+        assert!(apply(base, &damaged).is_err());
+    }
+
+    #[test]
+    fn bps_hostile_size() {
+        let base = b"abcdefgh";
+
+        // a target far larger than could ever be reserved
+        let mut patch = b"BPS1".to_vec();
+        bps_number(base.len(), &mut patch);
+        bps_number(usize::MAX >> 8, &mut patch);
+        bps_number(0, &mut patch);
+        bps_number(7 << 2, &mut patch);
+
+        let patch = bps_footer(base, base, patch);
+
+        assert!(apply(base, &patch).is_err());
+    }
+
+    #[test]
+    fn bps_numbers() {
+        for number in [0, 1, 127, 128, 129, 16511, 16512, 1 << 20, usize::MAX >> 8] {
+            let mut encoded = Vec::new();
+            bps_number(number, &mut encoded);
+            assert_eq!(Reader::new(&encoded).bps_number().unwrap(), number);
+        }
+    }
+
+    #[test]
+    fn vcdiff() {
+        let base = b"abcdefgh";
+        let target = b"abcdefghXYZabc!!!!";
+
+        // copy 8 from the source, add 3, copy 3 from the source, run of 4
+        let data = b"XYZ!";
+        let insts = [24, 4, 19, 3, 0, 4];
+        let addrs = [0, 0];
+
+        let mut patch = vec![0xD6, 0xC3, 0xC4, 0x00, 0x00];
+        patch.push(VCD_SOURCE | VCD_ADLER32);
+        patch.push(base.len() as u8);
+        patch.push(0);
+        let delta = [
+            &[
+                target.len() as u8,
+                0,
+                data.len() as u8,
+                insts.len() as u8,
+                addrs.len() as u8,
+            ][..],
+            &adler32(target).to_be_bytes(),
+            data,
+            &insts,
+            &addrs,
+        ]
+        .concat();
+        patch.push(delta.len() as u8);
+        patch.extend(delta);
+
+        assert_eq!(
+            apply(base, &patch).unwrap(),
+            (Format::Vcdiff, target.to_vec())
+        );
+
+        // a checksum the target doesn't match
+        let mut damaged = patch.clone();
+        damaged[14] ^= 0xFF;
+        assert!(apply(base, &damaged).is_err());
+    }
+
+    #[test]
+    fn vcdiff_hostile_size() {
+        let base = b"abcdefgh";
+
+        // a window far larger than could ever be reserved,
+        // followed by a run longer still
+        let mut insts = vec![0];
+        vcdiff_number((usize::MAX >> 8) + 1, &mut insts);
+
+        let mut delta = Vec::new();
+        vcdiff_number(usize::MAX >> 8, &mut delta);
+        delta.extend([0, 1, insts.len() as u8, 0]);
+        delta.push(b'!');
+        delta.extend(insts);
+
+        let mut patch = vec![0xD6, 0xC3, 0xC4, 0x00, 0x00];
+        patch.push(VCD_SOURCE);
+        patch.push(base.len() as u8);
+        patch.push(0);
+        vcdiff_number(delta.len(), &mut patch);
+        patch.extend(delta);
+
+        assert!(apply(base, &patch).is_err());
+    }
+
+    #[test]
+    fn vcdiff_numbers() {
+        assert_eq!(Reader::new(&[0x00]).vcdiff_number().unwrap(), 0);
+        assert_eq!(Reader::new(&[0x7F]).vcdiff_number().unwrap(), 127);
+        assert_eq!(Reader::new(&[0x81, 0x00]).vcdiff_number().unwrap(), 128);
+        assert_eq!(
+            Reader::new(&[0xBA, 0xEF, 0x9A, 0x15])
+                .vcdiff_number()
+                .unwrap(),
+            123456789
+        );
+        assert!(Reader::new(&[0x81]).vcdiff_number().is_err());
+
+        let mut encoded = Vec::new();
+        vcdiff_number(123456789, &mut encoded);
+        assert_eq!(Reader::new(&encoded).vcdiff_number().unwrap(), 123456789);
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert!(apply(b"base", b"not a patch").is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use std::path::Path;
+
+const PIN_XATTR: &str = "user.emupin";
+
+// pinned files are never modified or removed by emuman,
+// such as ROMs patched by hand or files shared with other tools
+#[inline]
+pub fn is_pinned(path: &Path) -> bool {
+    matches!(xattr::get(path, PIN_XATTR), Ok(Some(_)))
+}
+
+// whether path or any file under it is pinned,
+// which keeps a directory from being moved or removed as a whole
+pub fn contains_pinned(path: &Path) -> bool {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .any(|e| is_pinned(e.path()))
+}
+
+#[inline]
+pub fn pin(path: &Path) -> Result<(), std::io::Error> {
+    xattr::set(path, PIN_XATTR, b"1")
+}
+
+#[inline]
+pub fn unpin(path: &Path) -> Result<(), std::io::Error> {
+    if is_pinned(path) {
+        xattr::remove(path, PIN_XATTR)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,380 @@
+use crate::game::{DigestFormat, ExtractedPart, Part, VerifyFailure};
+use crate::sink::{ProgressSink, TaskId, Total};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ProgressFormat {
+    // progress bars and plain messages for people
+    #[default]
+    Bars,
+    // one JSON event per line for frontends
+    Json,
+}
+
+impl FromStr for ProgressFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "bars" => Ok(ProgressFormat::Bars),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err("invalid progress format".to_string()),
+        }
+    }
+}
+
+static FORMAT: OnceCell<ProgressFormat> = OnceCell::new();
+
+#[inline]
+pub fn set_format(format: ProgressFormat) {
+    let _ = FORMAT.set(format);
+}
+
+#[inline]
+fn is_json() -> bool {
+    matches!(FORMAT.get(), Some(ProgressFormat::Json))
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event<'e> {
+    ScanStarted {
+        root: &'e Path,
+    },
+    FileHashed {
+        path: &'e Path,
+        digest: String,
+    },
+    PartExtracted {
+        source: String,
+        target: &'e Path,
+        method: &'static str,
+    },
+    FailureFound {
+        game: &'e str,
+        kind: &'static str,
+        path: &'e Path,
+    },
+    Message {
+        text: String,
+    },
+}
+
+// events are written by a single thread as they arrive
+// so lines from parallel workers never interleave
+struct Channel {
+    sender: Mutex<Option<Sender<String>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+static CHANNEL: OnceCell<Channel> = OnceCell::new();
+
+fn send(event: Event) {
+    let channel = CHANNEL.get_or_init(|| {
+        let (sender, receiver) = channel::<String>();
+
+        let writer = std::thread::spawn(move || {
+            use std::io::Write;
+
+            let stderr = std::io::stderr();
+            for line in receiver {
+                let _ = writeln!(stderr.lock(), "{}", line);
+            }
+        });
+
+        Channel {
+            sender: Mutex::new(Some(sender)),
+            writer: Mutex::new(Some(writer)),
+        }
+    });
+
+    if let Some(sender) = channel.sender.lock().unwrap().as_ref() {
+        let _ = sender.send(serde_json::to_string(&event).expect("unable to serialize event"));
+    }
+}
+
+// sends the event built by f, if events are wanted
+#[inline]
+pub fn emit<'e, F>(f: F)
+where
+    F: FnOnce() -> Event<'e>,
+{
+    if is_json() {
+        send(f())
+    }
+}
+
+// displays text above the progress bar,
+// or sends the event built by f instead
+pub fn report<'e, T, F>(pbar: &ProgressBar, text: T, f: F)
+where
+    T: fmt::Display,
+    F: FnOnce() -> Event<'e>,
+{
+    if is_json() {
+        send(f())
+    } else {
+        pbar.println(text.to_string())
+    }
+}
+
+#[inline]
+pub fn message<T: fmt::Display>(pbar: &ProgressBar, text: T) {
+    report(pbar, &text, || Event::Message {
+        text: text.to_string(),
+    })
+}
+
+// bars are all drawn together while running,
+// so those of concurrent phases stack rather than overwrite each other
+struct Bars {
+    multi: Arc<MultiProgress>,
+    // never drawn, but keeps the bars running until finished
+    // however many others come and go in the meantime
+    anchor: ProgressBar,
+    // signaled once the bars are cleared
+    cleared: Receiver<()>,
+}
+
+static BARS: Mutex<Option<Bars>> = Mutex::new(None);
+
+// starts drawing bars made by bar and spinner together
+pub fn start() {
+    let multi = Arc::new(MultiProgress::new());
+    let anchor = multi.add(ProgressBar::hidden());
+    let (sender, cleared) = channel();
+
+    let drawer = multi.clone();
+    std::thread::spawn(move || {
+        let _ = drawer.join_and_clear();
+        let _ = sender.send(());
+    });
+
+    *BARS.lock().unwrap() = Some(Bars {
+        multi,
+        anchor,
+        cleared,
+    });
+}
+
+// bars made while watched are kept rather than drawn,
+// for an interface which draws progress its own way to follow
+static WATCHED: Mutex<Option<Vec<ProgressBar>>> = Mutex::new(None);
+
+pub fn watch() {
+    if let Some(bars) = BARS.lock().unwrap().as_ref() {
+        bars.multi
+            .set_draw_target(indicatif::ProgressDrawTarget::hidden());
+    }
+
+    *WATCHED.lock().unwrap() = Some(Vec::new());
+}
+
+// the position and length of the newest bar still running, if any
+pub fn watched() -> Option<(u64, u64)> {
+    let mut watched = WATCHED.lock().unwrap();
+    let bars = watched.as_mut()?;
+    bars.retain(|pbar| !pbar.is_finished());
+    bars.last().map(|pbar| (pbar.position(), pbar.length()))
+}
+
+// draws the bar along with any others, once started
+#[inline]
+pub fn add(pbar: ProgressBar) -> ProgressBar {
+    let pbar = match BARS.lock().unwrap().as_ref() {
+        Some(bars) => bars.multi.add(pbar),
+        None => pbar,
+    };
+
+    if let Some(bars) = WATCHED.lock().unwrap().as_mut() {
+        bars.push(pbar.clone());
+    }
+
+    pbar
+}
+
+#[inline]
+pub fn bar(len: u64) -> ProgressBar {
+    add(ProgressBar::new(len))
+}
+
+#[inline]
+pub fn spinner() -> ProgressBar {
+    add(ProgressBar::new_spinner())
+}
+
+// waits for any outstanding events to be written
+// and clears any bars still drawn
+pub fn finish() {
+    if let Some(bars) = BARS.lock().unwrap().take() {
+        bars.anchor.finish_and_clear();
+
+        // a bar left running by an error shouldn't keep us from exiting
+        let _ = bars.cleared.recv_timeout(Duration::from_secs(1));
+    }
+
+    if let Some(channel) = CHANNEL.get() {
+        channel.sender.lock().unwrap().take();
+
+        if let Some(writer) = channel.writer.lock().unwrap().take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+#[inline]
+pub fn find_files_style() -> ProgressStyle {
+    ProgressStyle::default_spinner().template("{spinner} {wide_msg} {pos}")
+}
+
+#[inline]
+pub fn verify_style() -> ProgressStyle {
+    ProgressStyle::default_bar().template("{spinner} {wide_msg} {pos} / {len}")
+}
+
+#[inline]
+pub fn hashing_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{spinner} {wide_msg} {bytes} / {total_bytes} ({bytes_per_sec}, {eta})")
+}
+
+// draws the library's progress as bars, one per task,
+// or sends events in place of text if those are wanted instead
+#[derive(Default)]
+pub struct BarSink {
+    // keyed by task, so the newest is last
+    bars: Mutex<BTreeMap<TaskId, ProgressBar>>,
+}
+
+impl BarSink {
+    // displays text above the newest bar, if any,
+    // unless an interface is drawing progress its own way
+    fn report<'e, T, F>(&self, text: T, f: F)
+    where
+        T: fmt::Display,
+        F: FnOnce() -> Event<'e>,
+    {
+        match self.bars.lock().unwrap().values().next_back() {
+            Some(pbar) => report(pbar, text, f),
+            None => report_unbarred(text, f),
+        }
+    }
+}
+
+// displays text on its own, or sends the event built by f instead,
+// unless an interface is drawing progress its own way
+fn report_unbarred<'e, T, F>(text: T, f: F)
+where
+    T: fmt::Display,
+    F: FnOnce() -> Event<'e>,
+{
+    if is_json() {
+        send(f())
+    } else if WATCHED.lock().unwrap().is_none() {
+        eprintln!("{}", text)
+    }
+}
+
+// reports what happens outside of any task, like defaults remembered
+// once a command is done, so it needs nothing kept alongside it
+#[derive(Copy, Clone, Default)]
+pub struct Notices;
+
+impl ProgressSink for Notices {
+    fn warning(&self, text: &str) {
+        report_unbarred(format_args!("* {}", text), || Event::Message {
+            text: format!("* {}", text),
+        })
+    }
+
+    fn notice(&self, text: &str) {
+        self.warning(text)
+    }
+}
+
+impl ProgressSink for BarSink {
+    fn started(&self, task: TaskId, name: &str, total: Total) {
+        let pbar = match total {
+            Total::Unknown => {
+                let pbar = spinner().with_style(find_files_style());
+                pbar.set_draw_delta(100);
+                pbar
+            }
+            Total::Items(total) => {
+                let pbar = bar(total).with_style(verify_style());
+                pbar.set_draw_delta(total / 1000);
+                pbar
+            }
+            Total::Bytes(total) => {
+                let pbar = bar(total).with_style(hashing_style());
+                pbar.set_draw_delta(total / 1000);
+                pbar
+            }
+        };
+        pbar.set_message(name.to_owned());
+
+        self.bars.lock().unwrap().insert(task, pbar);
+    }
+
+    #[inline]
+    fn advanced(&self, task: TaskId, steps: u64) {
+        if let Some(pbar) = self.bars.lock().unwrap().get(&task) {
+            pbar.inc(steps);
+        }
+    }
+
+    #[inline]
+    fn finished(&self, task: TaskId) {
+        if let Some(pbar) = self.bars.lock().unwrap().remove(&task) {
+            pbar.finish_and_clear();
+        }
+    }
+
+    #[inline]
+    fn scanning(&self, root: &Path) {
+        emit(|| Event::ScanStarted { root })
+    }
+
+    fn file_hashed(&self, path: &Path, part: &Part) {
+        emit(|| Event::FileHashed {
+            path,
+            digest: part.digest_as(DigestFormat::Prefixed).to_string(),
+        })
+    }
+
+    fn part_placed(&self, part: &ExtractedPart) {
+        self.report(part, || Event::PartExtracted {
+            source: part.source().to_string(),
+            target: part.target(),
+            method: part.method(),
+        })
+    }
+
+    fn failure_found(&self, game: &str, failure: &VerifyFailure) {
+        emit(|| Event::FailureFound {
+            game,
+            kind: failure.kind(),
+            path: failure.path(),
+        })
+    }
+
+    fn warning(&self, text: &str) {
+        self.report(format_args!("* {}", text), || Event::Message {
+            text: format!("* {}", text),
+        })
+    }
+
+    fn notice(&self, text: &str) {
+        self.warning(text)
+    }
+}
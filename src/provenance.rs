@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+const PROVENANCE_XATTR: &str = "user.emusource";
+
+// where a file added to a set came from, kept with the file itself
+// so how a collection was assembled can be audited later
+#[derive(Serialize, Deserialize)]
+pub struct Provenance {
+    pub source: String,
+    // "copied", "cloned" or "linked"
+    pub method: String,
+    pub added: String,
+    pub version: String,
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "  source : {}", self.source)?;
+        writeln!(f, "  method : {}", self.method)?;
+        writeln!(f, "  added : {}", self.added)?;
+        write!(f, "  version : emuman {}", self.version)
+    }
+}
+
+#[inline]
+pub fn get(path: &Path) -> Result<Option<Provenance>, std::io::Error> {
+    Ok(xattr::get(path, PROVENANCE_XATTR)?.and_then(|data| serde_json::from_slice(&data).ok()))
+}
+
+// links share their data with the source, whose provenance
+// is kept if it has any, so only the first time it's added counts
+pub fn record(path: &Path, source: String, method: &str) {
+    if crate::game::is_read_only() || (method == "linked" && matches!(get(path), Ok(Some(_)))) {
+        return;
+    }
+
+    let provenance = Provenance {
+        source,
+        method: method.to_owned(),
+        added: crate::html::utc_now(),
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+    };
+
+    if let Ok(data) = serde_json::to_vec(&provenance) {
+        let _ = xattr::set(path, PROVENANCE_XATTR, &data);
+    }
+}
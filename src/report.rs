@@ -0,0 +1,737 @@
+// everything the CLI displays of the library's results,
+// kept apart so that verifying and rebuilding only ever return data
+// and programs embedding them decide for themselves what to show
+
+use crate::dat::DatFile;
+use crate::game::{
+    bytes_processed, escape_name, DbDiff, DigestFormat, Game, GameDb, GameParts, GameRow, Part,
+    PartChange, PartId, Status, VerifyFailure, VerifyResultsSummary,
+};
+use crate::select::Choice;
+use crate::tags::TagDb;
+use crate::Error;
+use prettytable::Table;
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+
+// any driver shortcomings, categories, player counts, tags and notes
+// for the games are listed after them
+pub fn display_games(games: &[GameRow], curation: Option<&TagDb>) {
+    use prettytable::{format, Cell, Row};
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.get_format().column_separator('\u{2502}');
+
+    let curation =
+        curation.filter(|curation| games.iter().any(|game| curation.get(game.name).is_some()));
+    let driven = games.iter().any(|game| game.driver.is_some());
+    let categorized = games.iter().any(|game| game.category.is_some());
+    let counted = games.iter().any(|game| game.players.is_some());
+
+    for game in games {
+        let mut columns = vec![
+            game.description.to_owned(),
+            game.creator.to_owned(),
+            game.year.to_owned(),
+            game.name.to_owned(),
+            game.cloneof.unwrap_or_default().to_owned(),
+        ];
+
+        if driven {
+            columns.push(
+                game.driver
+                    .map(|driver| driver.shortcomings())
+                    .unwrap_or_default(),
+            );
+        }
+
+        if categorized {
+            columns.push(game.category.unwrap_or_default().to_owned());
+        }
+
+        if counted {
+            columns.push(game.players.unwrap_or_default().to_owned());
+        }
+
+        if let Some(curation) = curation {
+            let (tags, note) = match curation.get(game.name) {
+                Some(curated) => (
+                    curated
+                        .tags
+                        .iter()
+                        .map(|tag| tag.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    curated.note.clone().unwrap_or_default(),
+                ),
+                None => (String::new(), String::new()),
+            };
+            columns.push(tags);
+            columns.push(note);
+        }
+
+        let style = match game.status {
+            Status::Working => "",
+            Status::Partial => "FY",
+            Status::NotWorking => "FR",
+        };
+
+        table.add_row(Row::new(
+            columns
+                .iter()
+                .map(|column| Cell::new(column).style_spec(style))
+                .collect(),
+        ));
+    }
+
+    table.printstd();
+}
+
+pub fn display_parts(db: &GameDb, name: &str, format: DigestFormat) -> Result<(), Error> {
+    use prettytable::{cell, format, row};
+
+    let game = db
+        .game(name)
+        .ok_or_else(|| Error::NoSuchSoftware(name.to_string()))?;
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.get_format().column_separator('\u{2502}');
+
+    let devices: BTreeMap<&str, &Game> = game
+        .devices
+        .iter()
+        .map(|dev| db.game(dev).expect("unknown device in game"))
+        .filter(|game| !game.parts.is_empty())
+        .map(|game| (game.name.as_str(), game))
+        .collect();
+
+    match (&game.cloneof, &game.romof) {
+        (Some(parent), _) => {
+            table.add_row(row![H3cu->format!("{} (clone of {})", name, parent)]);
+        }
+        (None, Some(romof)) => {
+            table.add_row(row![H3cu->format!("{} (ROMs of {})", name, romof)]);
+        }
+        (None, None) if !devices.is_empty() => {
+            table.add_row(row![H3cu->name]);
+        }
+        (None, None) => {}
+    }
+
+    if !game.requires.is_empty() {
+        table.add_row(row![H3cu->format!("requires {}", game.requires.join(", "))]);
+    }
+
+    if devices.is_empty() {
+        add_parts(&mut table, game, format);
+    } else {
+        add_parts(&mut table, game, format);
+        for (dev_name, dev) in devices.into_iter() {
+            table.add_row(row![H3cu->dev_name]);
+            add_parts(&mut table, dev, format);
+        }
+    }
+
+    table.printstd();
+    Ok(())
+}
+
+pub fn add_parts(table: &mut Table, game: &Game, format: DigestFormat) {
+    use prettytable::{cell, row};
+
+    let parts: BTreeMap<&str, &Part> = game
+        .parts
+        .iter()
+        .map(|(name, part)| (name.as_str(), part))
+        .collect();
+
+    if !parts.is_empty() {
+        for (name, part) in parts {
+            table.add_row(row![name, part.digest_as(format)]);
+        }
+    }
+}
+
+pub fn display_why(db: &GameDb, game_name: &str, part_name: &str) -> Result<(), Error> {
+    let origin = db.why(game_name, part_name)?;
+
+    println!("{} : {}", origin.name, origin.part.digest());
+    println!("  {}", origin.source);
+
+    if origin.shared.is_empty() {
+        println!("  not shared with any other games");
+    } else {
+        println!(
+            "  shared with {} other {} :",
+            origin.shared.len(),
+            if origin.shared.len() == 1 {
+                "game"
+            } else {
+                "games"
+            }
+        );
+        for (game, name) in origin.shared.iter() {
+            if *name == origin.name {
+                println!("    {}", game);
+            } else {
+                println!("    {} (as {})", game, name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn display_diff(diff: &DbDiff) {
+    for name in diff.added.iter() {
+        println!("ADDED : {}", name);
+    }
+
+    for name in diff.removed.iter() {
+        println!("REMOVED : {}", name);
+    }
+
+    for (old, new) in diff.renamed.iter() {
+        println!("RENAMED : {} \u{2192} {}", old, new);
+    }
+
+    for change in diff.parts.iter() {
+        match change {
+            PartChange::Changed { old, new } => println!("CHANGED : {} \u{2192} {}", old, new),
+            PartChange::Removed(part) => println!("REMOVED PART : {}", part),
+            PartChange::Added(part) => println!("ADDED PART : {}", part),
+        }
+    }
+
+    eprintln!(
+        "{} added, {} removed, {} renamed, {} changed",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.renamed.len(),
+        diff.changed
+    );
+}
+
+// warns about parts which differ only by case
+// and the names they'll be stored under
+pub fn warn_case_collisions<'g, I>(games: I)
+where
+    I: IntoIterator<Item = (&'g str, &'g GameParts)>,
+{
+    for (game, parts) in games {
+        for names in parts.case_collisions() {
+            let (first, rest) = names.split_first().unwrap();
+            for (index, name) in rest.iter().enumerate() {
+                eprintln!(
+                    "* {} : \"{}\" differs from \"{}\" only by case, storing it as \"{}\"",
+                    game,
+                    name,
+                    first,
+                    escape_name(name, index + 1)
+                );
+            }
+        }
+    }
+}
+
+// games from any software list, alongside the list they're in
+pub fn display_software_results(results: &[(&str, GameRow)]) {
+    use prettytable::{cell, format, row};
+
+    let mut table = Table::new();
+
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.get_format().column_separator('\u{2502}');
+
+    for (db_name, game) in results {
+        let description = game.description;
+        let creator = game.creator;
+        let year = game.year;
+        let name = game.name;
+        let parent = game.cloneof.unwrap_or_default();
+
+        table.add_row(match game.status {
+            Status::Working => row![description, creator, year, db_name, name, parent],
+            Status::Partial => row![FY => description, creator, year, db_name, name, parent],
+            Status::NotWorking => {
+                row![FR => description, creator, year, db_name, name, parent]
+            }
+        });
+    }
+
+    table.printstd();
+}
+
+pub fn display_software_lists(db: &BTreeMap<String, GameDb>) {
+    use prettytable::{cell, format, row};
+
+    let mut table = Table::new();
+
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    for (name, game_db) in db.iter() {
+        table.add_row(row![game_db.description(), name]);
+    }
+
+    table.printstd();
+}
+
+// a change put back by undo, or why it couldn't be
+pub fn display_undone(entry: &crate::journal::Entry, result: &Result<(), std::io::Error>) {
+    use crate::journal::Entry;
+
+    match (entry, result) {
+        (_, Err(err)) => eprintln!("* {}", err),
+        (Entry::Created { path, .. }, Ok(())) => println!("removed : {}", path.display()),
+        (Entry::Removed { path, .. }, Ok(())) => println!("restored : {}", path.display()),
+        (Entry::Renamed { from, to }, Ok(())) => {
+            println!("moved back : {} \u{2192} {}", to.display(), from.display())
+        }
+    }
+}
+
+// asks before doing something which is tedious to undo,
+// assuming no if there's nobody around to answer
+pub fn confirm(prompt: &str) -> Result<bool, Error> {
+    if crate::safety::assumes_yes() {
+        Ok(true)
+    } else if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        Ok(inquire::Confirm::new(prompt).with_default(false).prompt()?)
+    } else {
+        Ok(false)
+    }
+}
+
+// asked once a run's deletions would go past safety.toml's limits,
+// where anything but a yes keeps them from happening
+pub fn confirm_deletions(files: usize, bytes: u64) -> bool {
+    eprintln!(
+        "* about to delete {} files ({}) in all",
+        files,
+        indicatif::HumanBytes(bytes)
+    );
+
+    confirm("delete them?").unwrap_or(false)
+}
+
+// the game chosen from each group of alternatives, and why
+pub fn display_choices(choices: &[Choice]) {
+    use prettytable::{cell, format, row};
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.get_format().column_separator('\u{2502}');
+
+    for choice in choices {
+        table.add_row(row![
+            choice.title,
+            choice.key,
+            r->choice.candidates,
+            choice.reason
+        ]);
+    }
+
+    table.printstd();
+}
+
+pub fn display_info(db: &crate::info::InfoDb, game: &str) -> Option<()> {
+    if !db.contains(game) {
+        return None;
+    }
+
+    for (index, (label, text)) in db.sections(game).enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        println!("\u{2500}\u{2500} {} \u{2500}\u{2500}", label);
+        println!("{}", text);
+    }
+
+    Some(())
+}
+
+// displays each set's sample results, returning how many were OK
+pub fn display_sample_results(
+    results: &BTreeMap<&str, Vec<crate::samples::SampleFailure>>,
+    only_failures: bool,
+) -> usize {
+    let mut successes = 0;
+
+    for (set, failures) in results {
+        if failures.is_empty() {
+            successes += 1;
+            if !only_failures {
+                println!("OK : {}", set);
+            }
+        } else {
+            for failure in failures {
+                println!("{} : {}", failure, set);
+            }
+        }
+    }
+
+    successes
+}
+
+pub fn display_all_results(game: &str, failures: &[VerifyFailure]) {
+    if failures.is_empty() {
+        println!("OK : {}", game);
+    } else {
+        display_bad_results(game, failures)
+    }
+}
+
+pub fn display_bad_results(game: &str, failures: &[VerifyFailure]) {
+    if !failures.is_empty() {
+        use std::io::{stdout, Write};
+
+        // ensure results are generated as a unit
+        let stdout = stdout();
+        let mut handle = stdout.lock();
+        for failure in failures {
+            writeln!(&mut handle, "{failure} : {game}").unwrap();
+        }
+    }
+}
+
+// like display_all_results or display_bad_results,
+// with a suggested remedy below each failure
+pub fn display_triaged_results(
+    triage: &crate::triage::Triage,
+    game: &str,
+    failures: &[VerifyFailure],
+    only_failures: bool,
+) {
+    use std::io::{stdout, Write};
+
+    // ensure results are generated as a unit
+    let stdout = stdout();
+    let mut handle = stdout.lock();
+
+    if failures.is_empty() {
+        if !only_failures {
+            writeln!(&mut handle, "OK : {game}").unwrap();
+        }
+        return;
+    }
+
+    for failure in failures {
+        writeln!(&mut handle, "{failure} : {game}").unwrap();
+        if let Some(suggestion) = triage.suggest(failure) {
+            writeln!(&mut handle, "  \u{21b3} {suggestion}").unwrap();
+        }
+    }
+}
+
+// acknowledged failures follow the rest in a section of their own
+pub fn display_acknowledged(acknowledged: &BTreeMap<&str, Vec<VerifyFailure>>) {
+    use std::io::{stdout, Write};
+
+    let stdout = stdout();
+    let mut handle = stdout.lock();
+
+    writeln!(&mut handle, "acknowledged :").unwrap();
+    for (game, failures) in acknowledged {
+        for failure in failures {
+            writeln!(&mut handle, "  {failure} : {game}").unwrap();
+        }
+    }
+}
+
+// plain lists of complete ("have") or incomplete ("missing") games,
+// one name per line, where parts are listed indented beneath each game;
+// all of a complete game's parts, or only an incomplete game's missing and bad ones
+pub fn display_have_missing(
+    db: &GameDb,
+    results: &BTreeMap<&str, Vec<VerifyFailure>>,
+    have: bool,
+    parts: bool,
+) {
+    use std::io::{stdout, Write};
+
+    let stdout = stdout();
+    let mut handle = stdout.lock();
+
+    for (game, failures) in results.iter() {
+        if failures.iter().all(|failure| !failure.is_incomplete()) != have {
+            continue;
+        }
+
+        writeln!(&mut handle, "{game}").unwrap();
+
+        if parts {
+            if have {
+                if let Some(game) = db.game(game) {
+                    let names: BTreeSet<&String> =
+                        game.parts.iter().map(|(name, _)| name).collect();
+                    for name in names {
+                        writeln!(&mut handle, "\t{name}").unwrap();
+                    }
+                }
+            } else {
+                for failure in failures {
+                    if let VerifyFailure::Missing { name, .. }
+                    | VerifyFailure::MissingUndumped { name, .. }
+                    | VerifyFailure::Bad { name, .. } = failure
+                    {
+                        writeln!(&mut handle, "\t{name}").unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub enum ResultsFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for ResultsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(ResultsFormat::Text),
+            "json" => Ok(ResultsFormat::Json),
+            _ => Err("invalid results format".to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ResultRecord<'r> {
+    game: &'r str,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'r Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part: Option<PartId<'r>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actual: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<&'r Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    acknowledged: bool,
+}
+
+impl<'r> ResultRecord<'r> {
+    fn new(game: &'r str, kind: &'static str) -> Self {
+        Self {
+            game,
+            kind,
+            path: None,
+            part: None,
+            expected: None,
+            actual: None,
+            parent: None,
+            error: None,
+            hint: None,
+            acknowledged: false,
+        }
+    }
+
+    fn from_failure(game: &'r str, failure: &'r VerifyFailure) -> Self {
+        let digest = |part: &Part| part.digest_as(DigestFormat::Prefixed).to_string();
+
+        let record = Self {
+            path: Some(failure.path()),
+            ..Self::new(game, failure.kind())
+        };
+
+        match failure {
+            VerifyFailure::Missing { name, part, .. } => Self {
+                part: Some(PartId::new(game, name, part)),
+                expected: Some(digest(part)),
+                ..record
+            },
+            VerifyFailure::Bad {
+                name,
+                expected,
+                actual,
+                ..
+            } => Self {
+                part: Some(PartId::new(game, name, expected)),
+                expected: Some(digest(expected)),
+                actual: Some(digest(actual)),
+                ..record
+            },
+            VerifyFailure::Extra { part, .. } => Self {
+                actual: part.as_ref().ok().map(digest),
+                error: part.as_ref().err().map(|err| err.to_string()),
+                ..record
+            },
+            VerifyFailure::Error { err, .. } => Self {
+                error: Some(err.to_string()),
+                ..record
+            },
+            VerifyFailure::MissingParent { parent, .. } => Self {
+                expected: Some(digest(parent)),
+                ..record
+            },
+            VerifyFailure::BadParent { parent_path, .. } => Self {
+                parent: Some(parent_path),
+                ..record
+            },
+            VerifyFailure::MissingUndumped { .. }
+            | VerifyFailure::Corrupt { .. }
+            | VerifyFailure::NotCanonical { .. }
+            | VerifyFailure::MissingBios { .. }
+            | VerifyFailure::MissingDevice { .. } => record,
+        }
+    }
+}
+
+// displays results as JSON lines, one per failure,
+// along with one for each game without failures
+// unless only failures are wanted
+pub fn display_json_results<H>(game: &str, failures: &[VerifyFailure], only_failures: bool, hint: H)
+where
+    H: Fn(&VerifyFailure) -> Option<String>,
+{
+    use std::io::{stdout, Write};
+
+    // ensure results are generated as a unit
+    let stdout = stdout();
+    let mut handle = stdout.lock();
+
+    if failures.is_empty() {
+        if !only_failures {
+            serde_json::to_writer(&mut handle, &ResultRecord::new(game, "ok")).unwrap();
+            writeln!(&mut handle).unwrap();
+        }
+    } else {
+        for failure in failures {
+            let record = ResultRecord {
+                hint: hint(failure),
+                ..ResultRecord::from_failure(game, failure)
+            };
+            serde_json::to_writer(&mut handle, &record).unwrap();
+            writeln!(&mut handle).unwrap();
+        }
+    }
+}
+
+// acknowledged failures are marked as such, and never come with hints
+pub fn display_json_acknowledged(game: &str, failures: &[VerifyFailure]) {
+    use std::io::{stdout, Write};
+
+    let stdout = stdout();
+    let mut handle = stdout.lock();
+
+    for failure in failures {
+        let record = ResultRecord {
+            acknowledged: true,
+            ..ResultRecord::from_failure(game, failure)
+        };
+        serde_json::to_writer(&mut handle, &record).unwrap();
+        writeln!(&mut handle).unwrap();
+    }
+}
+
+// displays totals once a run is done, where verb is
+// what was done to each game, like "tested" or "added"
+pub fn display_totals(summary: &VerifyResultsSummary, verb: &str) {
+    eprintln!(
+        "{} {}, {} OK, {} with failures",
+        summary.total,
+        verb,
+        summary.successes,
+        summary.total - summary.successes
+    );
+
+    if summary.successes < summary.total {
+        eprintln!(
+            "{} missing, {} bad, {} extra, {} errors",
+            summary.missing, summary.bad, summary.extra, summary.errors
+        );
+    }
+
+    if summary.acknowledged > 0 {
+        eprintln!("{} acknowledged failures", summary.acknowledged);
+    }
+
+    match bytes_processed() {
+        0 => {}
+        bytes => eprintln!("{} processed", indicatif::HumanBytes(bytes)),
+    }
+}
+
+pub fn display_dat_results(
+    table: &mut Table,
+    dat: &DatFile,
+    results: BTreeMap<&str, Vec<VerifyFailure>>,
+    failures_only: bool,
+) -> VerifyResultsSummary {
+    use prettytable::{cell, row};
+
+    let summary = VerifyResultsSummary::new(results.values());
+
+    if failures_only {
+        for (name, failures) in results {
+            display_bad_results(name, &failures);
+        }
+    } else {
+        for (name, failures) in results {
+            // like "mame -verifyroms", note sets which can't be any better
+            if failures.is_empty() && dat.has_bad_dumps(name) {
+                println!("OK : {} (best available)", name);
+            } else {
+                display_all_results(name, &failures);
+            }
+        }
+    }
+
+    table.add_row(row![r->summary.total, r->summary.successes, dat.name()]);
+
+    summary
+}
+
+pub fn display_dats<I, T>(iter: I)
+where
+    I: IntoIterator<Item = (T, DatFile)>,
+{
+    use prettytable::{cell, format, row};
+    let mut table = Table::new();
+
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    for (_, datfile) in iter {
+        table.add_row(row![datfile.name(), datfile.version()]);
+    }
+
+    table.printstd();
+}
+
+pub fn display_dat_games(datfile: &DatFile) {
+    use prettytable::{cell, format, row};
+
+    let mut games = datfile.games().collect::<Vec<_>>();
+    games.sort_unstable();
+
+    let mut table = Table::new();
+
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+    for game in games {
+        match datfile.parent(game) {
+            Some(parent) => table.add_row(row![game, parent]),
+            None => table.add_row(row![game]),
+        };
+    }
+
+    table.printstd();
+}
@@ -0,0 +1,136 @@
+use crate::{Error, FileError};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+const SAFETY_CONFIG_FILE: &str = "safety.toml";
+
+// deleting more files or bytes than these in one run
+// needs confirmation first, where the defaults may be changed like:
+//
+// max-files = 500
+// max-bytes = 10737418240
+#[derive(Copy, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+struct Limits {
+    max_files: usize,
+    max_bytes: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_files: 100,
+            max_bytes: 1 << 30,
+        }
+    }
+}
+
+impl Limits {
+    fn load() -> Result<Self, Error> {
+        let location = crate::data_dir().join(SAFETY_CONFIG_FILE);
+
+        match std::fs::read(&location) {
+            Ok(toml) => toml::from_slice(&toml).map_err(|error| {
+                Error::TomlRead(FileError {
+                    file: location,
+                    error,
+                })
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(Error::IO(err)),
+        }
+    }
+}
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+// confirms any deletions up front
+#[inline]
+pub fn assume_yes() {
+    ASSUME_YES.store(true, Ordering::Relaxed)
+}
+
+#[inline]
+pub fn assumes_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+}
+
+// asks once whether a run may go past the limits,
+// given how many files and bytes it would then have deleted,
+// where without one nothing past the limits is ever deleted
+static CONFIRMATION: OnceLock<fn(usize, u64) -> bool> = OnceLock::new();
+
+#[inline]
+pub fn set_confirmation(confirm: fn(usize, u64) -> bool) {
+    let _ = CONFIRMATION.set(confirm);
+}
+
+struct Deletions {
+    limits: Option<Limits>,
+    files: usize,
+    bytes: u64,
+    declined: bool,
+}
+
+static DELETIONS: Mutex<Deletions> = Mutex::new(Deletions {
+    limits: None,
+    files: 0,
+    bytes: 0,
+    declined: false,
+});
+
+#[inline]
+pub fn before_deleting(path: &Path) -> Result<(), Error> {
+    before_deleting_all(std::iter::once(path))
+}
+
+// tallies every file about to be deleted before any of them are,
+// and once they'd take a run past either limit,
+// confirms them all at once so a mistyped root can't be wiped out wholesale
+pub fn before_deleting_all<'p, I>(paths: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = &'p Path>,
+{
+    if assumes_yes() {
+        return Ok(());
+    }
+
+    // held while confirming, so a run only ever asks once
+    let mut deletions = DELETIONS.lock().unwrap();
+
+    let limits = match deletions.limits {
+        Some(limits) => limits,
+        None => *deletions.limits.insert(Limits::load()?),
+    };
+
+    let (files, bytes) = paths.into_iter().fold(
+        (deletions.files, deletions.bytes),
+        |(files, bytes), path| {
+            (
+                files + 1,
+                bytes + path.metadata().map(|m| m.len()).unwrap_or_default(),
+            )
+        },
+    );
+
+    if files > limits.max_files || bytes > limits.max_bytes {
+        let confirmed = !deletions.declined
+            && CONFIRMATION
+                .get()
+                .is_some_and(|confirm| confirm(files, bytes));
+
+        if !confirmed {
+            deletions.declined = true;
+            return Err(Error::DeletionsNotConfirmed(files));
+        }
+
+        assume_yes();
+    }
+
+    deletions.files = files;
+    deletions.bytes = bytes;
+
+    Ok(())
+}
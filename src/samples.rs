@@ -0,0 +1,434 @@
+use crate::sink::ProgressSink;
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// the WAV files in each sample set, which MAME gives
+// by name only, so samples are matched by name rather than digest,
+// and where machines naming a set with "sampleof" all share it
+#[derive(Default, Serialize, Deserialize)]
+pub struct SampleDb {
+    // set name -> sample file names, like "astrob" -> "invaderhit.wav"
+    sets: BTreeMap<String, BTreeSet<String>>,
+}
+
+#[derive(Deserialize)]
+struct Mame {
+    #[serde(default)]
+    machine: Vec<Machine>,
+}
+
+#[derive(Deserialize)]
+struct Machine {
+    name: String,
+    sampleof: Option<String>,
+    sample: Option<Vec<Sample>>,
+}
+
+#[derive(Deserialize)]
+struct Datafile {
+    #[serde(default)]
+    game: Vec<DatGame>,
+    #[serde(default)]
+    machine: Vec<DatGame>,
+}
+
+#[derive(Deserialize)]
+struct DatGame {
+    name: String,
+    rom: Option<Vec<Sample>>,
+}
+
+#[derive(Deserialize)]
+struct Sample {
+    name: String,
+}
+
+impl SampleDb {
+    // imports sets from "mame -listxml" output, a Logiqx samples DAT
+    // or "mame -listsamples" output, like:
+    //
+    // Samples required for driver "astrob".
+    // invaderhit
+    // ...
+    //
+    // returns the number of sets imported
+    pub fn import(&mut self, data: &str) -> Result<usize, Error> {
+        let before = self.sets.len();
+
+        if !data.trim_start().starts_with('<') {
+            self.import_listsamples(data);
+        } else if data.contains("<mame") {
+            let mame: Mame = quick_xml::de::from_str(data).map_err(Error::Xml)?;
+
+            for machine in mame.machine {
+                let set = machine.sampleof.unwrap_or(machine.name);
+
+                self.add_samples(
+                    set,
+                    machine
+                        .sample
+                        .into_iter()
+                        .flatten()
+                        .map(|sample| sample.name + ".wav"),
+                );
+            }
+        } else {
+            let datafile: Datafile = quick_xml::de::from_str(data).map_err(Error::Xml)?;
+
+            for game in datafile.game.into_iter().chain(datafile.machine) {
+                self.add_samples(
+                    game.name,
+                    game.rom.into_iter().flatten().map(|sample| sample.name),
+                );
+            }
+        }
+
+        Ok(self.sets.len() - before)
+    }
+
+    fn import_listsamples(&mut self, data: &str) {
+        let mut set: Option<String> = None;
+
+        for line in data.lines().map(|line| line.trim()) {
+            match line
+                .strip_prefix("Samples required for driver \"")
+                .and_then(|line| line.strip_suffix("\"."))
+            {
+                Some(driver) => set = Some(driver.to_owned()),
+                None if line.is_empty() => set = None,
+                None => {
+                    if let Some(set) = &set {
+                        self.add_samples(set.clone(), std::iter::once(format!("{}.wav", line)));
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_samples<I: Iterator<Item = String>>(&mut self, set: String, samples: I) {
+        let mut samples = samples.peekable();
+
+        // machines without samples don't need sets
+        if samples.peek().is_some() {
+            self.sets.entry(set).or_default().extend(samples);
+        }
+    }
+
+    #[inline]
+    pub fn all_sets(&self) -> BTreeSet<&str> {
+        self.sets.keys().map(|set| set.as_str()).collect()
+    }
+
+    #[inline]
+    pub fn set_len(&self, set: &str) -> usize {
+        self.sets
+            .get(set)
+            .map(|samples| samples.len())
+            .unwrap_or_default()
+    }
+
+    // sets present in root, either as Zip archives or directories
+    pub fn sets_in(&self, root: &Path) -> Result<BTreeSet<&str>, Error> {
+        let mut sets = BTreeSet::new();
+
+        for entry in root.read_dir()?.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = match path.extension() {
+                Some(ext) if ext == "zip" => path.file_stem(),
+                _ if path.is_dir() => path.file_name(),
+                _ => None,
+            };
+
+            if let Some((set, _)) = name
+                .and_then(|name| name.to_str())
+                .and_then(|name| self.sets.get_key_value(name))
+            {
+                sets.insert(set.as_str());
+            }
+        }
+
+        Ok(sets)
+    }
+
+    pub fn verify<'s>(
+        &self,
+        root: &Path,
+        sets: &BTreeSet<&'s str>,
+    ) -> BTreeMap<&'s str, Vec<SampleFailure>> {
+        sets.iter()
+            .map(|set| (*set, self.verify_set(root, set)))
+            .collect()
+    }
+
+    fn verify_set(&self, root: &Path, set: &str) -> Vec<SampleFailure> {
+        let expected = match self.sets.get(set) {
+            Some(expected) => expected,
+            None => return Vec::new(),
+        };
+
+        let (path, found) = match stored_samples(root, set) {
+            Ok(stored) => stored,
+            Err((path, err)) => return vec![SampleFailure::Error { path, err }],
+        };
+
+        expected
+            .difference(&found)
+            .map(|name| SampleFailure::Missing {
+                path: path.join(name),
+            })
+            .chain(found.difference(expected).map(|name| SampleFailure::Extra {
+                path: path.join(name),
+            }))
+            .collect()
+    }
+
+    // fills in each set's missing samples from sources
+    // holding sets of the same name, as Zip archives or directories,
+    // storing them in a "<set>.zip" archive in root
+    // and returning each set's remaining failures
+    // while calling placed with each sample's source and target
+    pub fn add<'s, P>(
+        &self,
+        root: &Path,
+        sources: &[PathBuf],
+        sets: &BTreeSet<&'s str>,
+        dry_run: bool,
+        sink: &dyn ProgressSink,
+        mut placed: P,
+    ) -> Result<BTreeMap<&'s str, Vec<SampleFailure>>, Error>
+    where
+        P: FnMut(&SampleSource, &Path),
+    {
+        let sources = SampleSources::new(sources, self);
+
+        let mut results = BTreeMap::new();
+
+        for set in sets {
+            let target = root.join(format!("{}.zip", set));
+
+            let wanted: Vec<(&str, &SampleSource)> = self
+                .verify_set(root, set)
+                .iter()
+                .filter_map(|failure| match failure {
+                    SampleFailure::Missing { path } => path.file_name()?.to_str(),
+                    _ => None,
+                })
+                .filter_map(|name| {
+                    self.sets[*set].get(name).and_then(|name| {
+                        sources.get(set, name).map(|source| (name.as_str(), source))
+                    })
+                })
+                .collect();
+
+            if !wanted.is_empty() && !crate::pin::is_pinned(&target) {
+                for (name, source) in wanted.iter() {
+                    placed(source, &target.join(name));
+                }
+
+                if !dry_run {
+                    crate::snapshot::before_modifying(root, sink)?;
+                    add_to_zip(root, set, &target, &wanted)?;
+                }
+            }
+
+            results.insert(*set, self.verify_set(root, set));
+        }
+
+        Ok(results)
+    }
+}
+
+// the samples stored for set, in its Zip archive if any,
+// or otherwise its directory
+fn stored_samples(
+    root: &Path,
+    set: &str,
+) -> Result<(PathBuf, BTreeSet<String>), (PathBuf, std::io::Error)> {
+    let zip_path = root.join(format!("{}.zip", set));
+
+    if zip_path.is_file() {
+        zip_members(&zip_path)
+            .map(|members| (zip_path.clone(), members))
+            .map_err(|err| (zip_path, err))
+    } else {
+        let dir = root.join(set);
+
+        match dir.read_dir() {
+            Ok(entries) => Ok((
+                dir,
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+                    .filter_map(|e| e.file_name().into_string().ok())
+                    .collect(),
+            )),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Ok((zip_path, BTreeSet::new()))
+            }
+            Err(err) => Err((dir, err)),
+        }
+    }
+}
+
+fn zip_members(path: &Path) -> Result<BTreeSet<String>, std::io::Error> {
+    let mut zip = std::fs::File::open(path)
+        .and_then(|f| zip::ZipArchive::new(f).map_err(std::io::Error::other))?;
+
+    (0..zip.len())
+        .map(|index| {
+            zip.by_index(index)
+                .map(|entry| entry.name().to_owned())
+                .map_err(std::io::Error::other)
+        })
+        .collect()
+}
+
+// writes a new archive holding any of set's existing samples,
+// copied from its old archive or directory, along with the wanted ones
+fn add_to_zip(
+    root: &Path,
+    set: &str,
+    target: &Path,
+    wanted: &[(&str, &SampleSource)],
+) -> Result<(), Error> {
+    use std::io::{copy, Write};
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let tmp = root.join(format!(".{}.zip.tmp", set));
+    let mut zip = ZipWriter::new(std::fs::File::create(&tmp)?);
+
+    if target.is_file() {
+        let mut old = zip::ZipArchive::new(std::fs::File::open(target)?)?;
+        for index in 0..old.len() {
+            zip.raw_copy_file(old.by_index(index)?)?;
+        }
+    } else if let Ok((_, existing)) = stored_samples(root, set) {
+        for name in existing {
+            zip.start_file(name.as_str(), options)?;
+            copy(
+                &mut std::fs::File::open(root.join(set).join(&name))?,
+                &mut zip,
+            )?;
+        }
+    }
+
+    for (name, source) in wanted {
+        zip.start_file(*name, options)?;
+        source.copy_to(&mut zip)?;
+    }
+
+    zip.finish()?.flush()?;
+
+    std::fs::rename(&tmp, target).map_err(Error::IO)
+}
+
+pub enum SampleSource {
+    File(PathBuf),
+    Zip { path: PathBuf, name: String },
+}
+
+impl SampleSource {
+    fn copy_to<W: std::io::Write>(&self, w: &mut W) -> Result<(), Error> {
+        match self {
+            SampleSource::File(path) => {
+                std::io::copy(&mut std::fs::File::open(path)?, w)?;
+            }
+            SampleSource::Zip { path, name } => {
+                let mut zip = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+                std::io::copy(&mut zip.by_name(name)?, w)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SampleSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SampleSource::File(path) => path.display().fmt(f),
+            SampleSource::Zip { path, name } => write!(f, "{}:{}", path.display(), name),
+        }
+    }
+}
+
+// samples found in sources, by set and sample name
+struct SampleSources {
+    samples: HashMap<(String, String), SampleSource>,
+}
+
+impl SampleSources {
+    fn new(sources: &[PathBuf], db: &SampleDb) -> Self {
+        let mut samples = HashMap::new();
+
+        for path in sources
+            .iter()
+            .flat_map(|source| walkdir::WalkDir::new(source).into_iter())
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+        {
+            let stem = |p: &Path| p.file_stem().and_then(|s| s.to_str()).map(|s| s.to_owned());
+
+            match path.extension() {
+                Some(ext) if ext == "zip" => {
+                    if let Some(set) = stem(&path).filter(|set| db.sets.contains_key(set)) {
+                        for name in zip_members(&path).into_iter().flatten() {
+                            samples
+                                .entry((set.clone(), name.clone()))
+                                .or_insert_with(|| SampleSource::Zip {
+                                    path: path.clone(),
+                                    name,
+                                });
+                        }
+                    }
+                }
+                _ => {
+                    let set = path
+                        .parent()
+                        .and_then(|dir| dir.file_name())
+                        .and_then(|set| set.to_str())
+                        .filter(|set| db.sets.contains_key(*set));
+
+                    let name = path.file_name().and_then(|name| name.to_str());
+
+                    if let (Some(set), Some(name)) = (set, name) {
+                        samples
+                            .entry((set.to_owned(), name.to_owned()))
+                            .or_insert_with(|| SampleSource::File(path.clone()));
+                    }
+                }
+            }
+        }
+
+        Self { samples }
+    }
+
+    #[inline]
+    fn get(&self, set: &str, name: &str) -> Option<&SampleSource> {
+        self.samples.get(&(set.to_owned(), name.to_owned()))
+    }
+}
+
+pub enum SampleFailure {
+    Missing { path: PathBuf },
+    Extra { path: PathBuf },
+    Error { path: PathBuf, err: std::io::Error },
+}
+
+impl fmt::Display for SampleFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SampleFailure::Missing { path } => write!(f, "MISSING SAMPLE : {}", path.display()),
+            SampleFailure::Extra { path } => write!(f, "EXTRA SAMPLE : {}", path.display()),
+            SampleFailure::Error { path, err } => {
+                write!(f, "ERROR : {} : {}", path.display(), err)
+            }
+        }
+    }
+}
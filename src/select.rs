@@ -0,0 +1,153 @@
+use crate::locale::Locale;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+// tags marking releases which weren't finished or weren't sold
+const PRERELEASES: &[&str] = &[
+    "Beta", "Demo", "Kiosk", "Pirate", "Preview", "Promo", "Proto", "Sample",
+];
+
+// a game which may be chosen to represent its title
+pub struct Candidate<'g> {
+    // the game as its database knows it
+    pub key: &'g str,
+    // the game's own name
+    pub name: &'g str,
+    // the parent whose title the game shares, if any
+    pub parent: Option<&'g str>,
+    pub locale: Locale,
+}
+
+// the one game chosen for a title and why
+pub struct Choice {
+    pub title: String,
+    pub key: String,
+    pub candidates: usize,
+    pub reason: &'static str,
+}
+
+// ranks of a candidate, from most to least important,
+// where lower ranks are preferred
+type Rank<'g> = (usize, bool, Reverse<Vec<u32>>, bool, &'g str);
+
+// one game, one ROM: picks a single preferred game per title,
+// where titles are either a parent and its clones or,
+// for DATs without any parent/clone data, games sharing a name
+// before any parenthesized tags, and preferred games are
+// those released in the earliest region in priority,
+// then finished games, then newer revisions, then parents
+//
+// titles without any games in priority's regions are left out
+pub fn one_game_one_rom<'g, I>(candidates: I, priority: &[String]) -> Vec<Choice>
+where
+    I: IntoIterator<Item = Candidate<'g>>,
+{
+    let candidates: Vec<Candidate<'g>> = candidates.into_iter().collect();
+    let has_families = candidates
+        .iter()
+        .any(|candidate| candidate.parent.is_some());
+
+    let mut titles: BTreeMap<&str, Vec<(Rank<'g>, &'g str)>> = BTreeMap::new();
+
+    for candidate in candidates {
+        let title = match candidate.parent {
+            Some(parent) => parent,
+            None if has_families => candidate.name,
+            None => match candidate.name.find(" (") {
+                Some(index) => &candidate.name[0..index],
+                None => candidate.name,
+            },
+        };
+
+        if let Some(region) = region_rank(&candidate.locale, priority) {
+            titles.entry(title).or_default().push((
+                (
+                    region,
+                    is_prerelease(candidate.name),
+                    Reverse(revision(candidate.name)),
+                    candidate.parent.is_some(),
+                    candidate.name,
+                ),
+                candidate.key,
+            ));
+        }
+    }
+
+    titles
+        .into_iter()
+        .map(|(title, mut ranked)| {
+            ranked.sort_unstable();
+
+            let reason = match &ranked[..] {
+                [_] => "only release",
+                [(best, _), (next, _), ..] if best.0 != next.0 => "preferred region",
+                [(best, _), (next, _), ..] if best.1 != next.1 => "not a prerelease",
+                [(best, _), (next, _), ..] if best.2 != next.2 => "newer revision",
+                [(best, _), (next, _), ..] if best.3 != next.3 => "parent set",
+                _ => "first by name",
+            };
+
+            Choice {
+                title: title.to_owned(),
+                key: ranked[0].1.to_owned(),
+                candidates: ranked.len(),
+                reason,
+            }
+        })
+        .collect()
+}
+
+// the position of the game's best region in priority,
+// where "World" releases suit any region,
+// and any region will do if priority is empty
+fn region_rank(locale: &Locale, priority: &[String]) -> Option<usize> {
+    if priority.is_empty() {
+        return Some(0);
+    }
+
+    locale
+        .regions
+        .iter()
+        .filter_map(|region| match region.as_str() {
+            "World" => Some(0),
+            region => priority
+                .iter()
+                .position(|wanted| wanted.eq_ignore_ascii_case(region)),
+        })
+        .min()
+}
+
+#[inline]
+fn tags(name: &str) -> impl Iterator<Item = &str> {
+    name.split('(')
+        .skip(1)
+        .filter_map(|group| group.split_once(')'))
+        .map(|(group, _)| group.trim())
+}
+
+fn is_prerelease(name: &str) -> bool {
+    tags(name).any(|tag| {
+        PRERELEASES
+            .iter()
+            .any(|prerelease| tag.split(' ').next() == Some(prerelease))
+    })
+}
+
+// revisions given as "(Rev 2)", "(Rev A)" or "(v1.1)",
+// where unrevised games have none
+fn revision(name: &str) -> Vec<u32> {
+    tags(name)
+        .find_map(|tag| {
+            if let Some(rev) = tag.strip_prefix("Rev ") {
+                match rev.parse() {
+                    Ok(rev) => Some(vec![rev]),
+                    Err(_) => rev.chars().next().map(|c| vec![u32::from(c)]),
+                }
+            } else {
+                tag.strip_prefix('v')
+                    .filter(|v| v.starts_with(|c: char| c.is_ascii_digit()))
+                    .map(|v| v.split('.').filter_map(|n| n.parse().ok()).collect())
+            }
+        })
+        .unwrap_or_default()
+}
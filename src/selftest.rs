@@ -0,0 +1,394 @@
+use crate::dat::{read_dats, DatFile, DirectiveOverrides};
+use crate::game::{get_rom_sources, Part, VerifyFailure};
+use crate::progress::BarSink;
+use crate::Error;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+// each synthetic game and the sizes of its ROMs,
+// where single-ROM games are stored flat
+// and the rest in their own directories
+const GAMES: &[(&str, &[usize])] = &[
+    ("single", &[1024]),
+    ("multi", &[2048, 333, 4096]),
+    ("zipped", &[777, 1500]),
+    ("empty", &[0, 64]),
+];
+
+// a ROM larger than archive members are ever buffered in memory,
+// so archives holding it must be streamed or spooled
+const LARGE_SIZE: usize = 80 * 1024 * 1024;
+
+// ROM contents are generated from a fixed seed
+// so every run builds byte-for-byte identical trees
+struct RomReader {
+    state: u64,
+    remaining: usize,
+}
+
+impl RomReader {
+    fn new(seed: u64, size: usize) -> Self {
+        Self {
+            state: seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1,
+            remaining: size,
+        }
+    }
+}
+
+impl Read for RomReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.remaining);
+
+        for byte in buf[..len].iter_mut() {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            *byte = self.state as u8;
+        }
+
+        self.remaining -= len;
+
+        Ok(len)
+    }
+}
+
+fn rom_data(seed: u64, size: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(size);
+    RomReader::new(seed, size)
+        .read_to_end(&mut data)
+        .expect("generated ROMs are always readable");
+    data
+}
+
+struct Rom {
+    game: &'static str,
+    name: String,
+    data: Vec<u8>,
+}
+
+fn roms() -> Vec<Rom> {
+    GAMES
+        .iter()
+        .enumerate()
+        .flat_map(|(g, (game, sizes))| {
+            sizes.iter().enumerate().map(move |(r, size)| Rom {
+                game,
+                name: format!("{}{}.bin", game, r),
+                data: rom_data((g * 100 + r) as u64, *size),
+            })
+        })
+        .collect()
+}
+
+fn write_dat(path: &Path, roms: &[Rom]) -> Result<(), Error> {
+    let mut dat = std::fs::File::create(path)?;
+
+    writeln!(dat, "<?xml version=\"1.0\"?>")?;
+    writeln!(dat, "<datafile>")?;
+    writeln!(
+        dat,
+        "<header><name>selftest</name><description>emuman self-test</description><version>1</version></header>"
+    )?;
+
+    for (game, _) in GAMES {
+        writeln!(
+            dat,
+            "<game name=\"{0}\"><description>{0}</description>",
+            game
+        )?;
+        for rom in roms.iter().filter(|rom| rom.game == *game) {
+            writeln!(
+                dat,
+                "<rom name=\"{}\" size=\"{}\" sha1=\"{}\"/>",
+                rom.name,
+                rom.data.len(),
+                Part::from_slice(&rom.data)?.digest()
+            )?;
+        }
+        writeln!(dat, "</game>")?;
+    }
+
+    writeln!(dat, "</datafile>").map_err(Error::IO)
+}
+
+// sources hold every ROM, with the "zipped" game's ROMs in a Zip,
+// along with a damaged copy of one ROM and a file no game uses
+fn write_sources(dir: &Path, roms: &[Rom]) -> Result<(), Error> {
+    use zip::{write::FileOptions, ZipWriter};
+
+    std::fs::create_dir_all(dir)?;
+
+    let mut zip = ZipWriter::new(std::fs::File::create(dir.join("zipped.zip"))?);
+
+    for rom in roms {
+        if rom.game == "zipped" {
+            zip.start_file(rom.name.as_str(), FileOptions::default())?;
+            zip.write_all(&rom.data)?;
+        } else {
+            std::fs::write(dir.join(&rom.name), &rom.data)?;
+        }
+    }
+
+    zip.finish()?;
+
+    std::fs::write(dir.join("damaged.bin"), damaged(&roms[1].data))?;
+    std::fs::write(dir.join("unused.bin"), rom_data(9999, 512))?;
+
+    Ok(())
+}
+
+#[inline]
+fn damaged(data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    data[0] ^= 0xff;
+    data
+}
+
+// tallies failures by kind, like "missing" or "bad"
+fn tally(results: &BTreeMap<&str, Vec<VerifyFailure<'_>>>) -> BTreeMap<&'static str, usize> {
+    let mut tally = BTreeMap::new();
+    for failure in results.values().flatten() {
+        *tally.entry(failure.kind()).or_default() += 1;
+    }
+    tally
+}
+
+struct Checks {
+    failed: usize,
+}
+
+impl Checks {
+    fn check<T: std::fmt::Debug + PartialEq>(&mut self, step: &str, expected: T, actual: T) {
+        if expected == actual {
+            println!("OK     : {}", step);
+        } else {
+            println!(
+                "FAILED : {} (expected {:?}, got {:?})",
+                step, expected, actual
+            );
+            self.failed += 1;
+        }
+    }
+}
+
+// builds a single large ROM's DAT along with sources holding it
+// in a nested Zip and a multi-volume Zip, then scans each,
+// adds it and rewrites it in canonical form
+// while checking each step's results
+fn check_large(dir: &Path, checks: &mut Checks) -> Result<(), Error> {
+    use std::fs::{create_dir_all, File};
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    const SEED: u64 = 7777;
+    const NAME: &str = "large0.bin";
+
+    let dat_file = dir.join("large.dat");
+    let nested = dir.join("large-nested");
+    let volumes = dir.join("large-volumes");
+    let output = dir.join("large-output");
+
+    for dir in [&nested, &volumes, &output] {
+        create_dir_all(dir)?;
+    }
+
+    let large = || RomReader::new(SEED, LARGE_SIZE);
+    let part = Part::from_reader(large())?;
+
+    std::fs::write(
+        &dat_file,
+        format!(
+            "<?xml version=\"1.0\"?>\n<datafile>\n\
+             <header><name>selftest-large</name><description>emuman large self-test</description><version>1</version></header>\n\
+             <game name=\"large\"><description>large</description>\
+             <rom name=\"{}\" size=\"{}\" sha1=\"{}\"/></game>\n</datafile>\n",
+            NAME,
+            LARGE_SIZE,
+            part.digest()
+        ),
+    )?;
+
+    // writes a Zip archive with the large ROM stored uncompressed
+    let write_zip = |path: &Path| -> Result<(), Error> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        zip.start_file(
+            NAME,
+            FileOptions::default().compression_method(CompressionMethod::Stored),
+        )?;
+        std::io::copy(&mut large(), &mut zip)?;
+        zip.finish()?;
+        Ok(())
+    };
+
+    // a Zip nested in another
+    let inner = dir.join("inner.zip");
+    write_zip(&inner)?;
+    let mut zip = ZipWriter::new(File::create(nested.join("large.zip"))?);
+    zip.start_file(
+        "inner.zip",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    std::io::copy(&mut File::open(&inner)?, &mut zip)?;
+    zip.finish()?;
+    std::fs::remove_file(&inner)?;
+
+    // a Zip split into two volumes, with the final one
+    // holding the central directory
+    let whole = dir.join("whole.zip");
+    write_zip(&whole)?;
+    let mut r = File::open(&whole)?;
+    std::io::copy(
+        &mut (&mut r).take(LARGE_SIZE as u64 / 2),
+        &mut File::create(volumes.join("large.z01"))?,
+    )?;
+    std::io::copy(&mut r, &mut File::create(volumes.join("large.zip"))?)?;
+    std::fs::remove_file(&whole)?;
+
+    let datfile: DatFile = read_dats(dat_file, &DirectiveOverrides::default())?
+        .pop()
+        .ok_or(Error::NoDatFiles)?;
+
+    // scan and add from each
+    for (kind, source) in [("nested Zip", nested), ("multi-volume Zip", volumes)] {
+        let source_dirs = [source];
+        let mut rom_sources = get_rom_sources(
+            &source_dirs,
+            &[],
+            datfile.required_parts(),
+            &BarSink::default(),
+        );
+        checks.check(&format!("scan large {}", kind), 1, rom_sources.len());
+
+        let (added, failed) =
+            datfile.add_and_verify(&mut rom_sources, &output, true, false, &BarSink::default())?;
+        checks.check(
+            &format!("add large from {}", kind),
+            (BTreeMap::new(), 0),
+            (tally(&added), failed.len()),
+        );
+
+        drop(rom_sources);
+        std::fs::remove_dir_all(&output)?;
+        create_dir_all(&output)?;
+    }
+
+    // canonical form
+    let torrentzip = dir.join("large-torrentzip.zip");
+    write_zip(&torrentzip)?;
+    crate::torrentzip::canonicalize(&torrentzip)?;
+    checks.check(
+        "canonicalize large",
+        (true, Some(part.clone())),
+        (
+            crate::torrentzip::is_canonical(&torrentzip)?,
+            zip::ZipArchive::new(File::open(&torrentzip)?)?
+                .by_index(0)
+                .ok()
+                .and_then(|entry| Part::from_reader(entry).ok()),
+        ),
+    );
+
+    Ok(())
+}
+
+// builds a synthetic DAT and file trees under dir,
+// then imports, scans, verifies, adds and cleans up
+// while checking each step's results,
+// along with a ROM larger than archive members are buffered if large
+pub fn run(dir: &Path, keep: bool, large: bool) -> Result<(), Error> {
+    let roms = roms();
+    let dat_file = dir.join("selftest.dat");
+    let sources = dir.join("sources");
+    let output = dir.join("output");
+
+    std::fs::create_dir_all(&output)?;
+    write_dat(&dat_file, &roms)?;
+    write_sources(&sources, &roms)?;
+
+    let mut checks = Checks { failed: 0 };
+
+    // import
+    let datfile: DatFile = read_dats(dat_file, &DirectiveOverrides::default())?
+        .pop()
+        .ok_or(Error::NoDatFiles)?;
+    checks.check("import", GAMES.len(), datfile.games().count());
+
+    // scan
+    let source_dirs = [sources.clone()];
+    let rom_sources = get_rom_sources(
+        &source_dirs,
+        &[],
+        datfile.required_parts(),
+        &BarSink::default(),
+    );
+    checks.check("scan", datfile.required_parts().len(), rom_sources.len());
+    drop(rom_sources);
+
+    // verify with nothing present
+    checks.check(
+        "verify empty",
+        BTreeMap::from([("missing", roms.len())]),
+        tally(&datfile.verify(&output, true, &BarSink::default())),
+    );
+
+    // seed the output with a damaged part and an extra file
+    let multi = output.join("multi");
+    let extra = multi.join("extra.bin");
+    std::fs::create_dir_all(&multi)?;
+    std::fs::write(multi.join(&roms[1].name), damaged(&roms[1].data))?;
+    std::fs::write(&extra, rom_data(8888, 256))?;
+    checks.check(
+        "verify damaged",
+        BTreeMap::from([("bad", 1), ("extra", 1), ("missing", roms.len() - 1)]),
+        tally(&datfile.verify(&output, true, &BarSink::default())),
+    );
+
+    // add
+    let mut rom_sources = get_rom_sources(
+        &source_dirs,
+        &[],
+        datfile.required_parts(),
+        &BarSink::default(),
+    );
+    let (added, failed) =
+        datfile.add_and_verify(&mut rom_sources, &output, true, false, &BarSink::default())?;
+    checks.check("add", BTreeMap::from([("extra", 1)]), tally(&added));
+    checks.check("add errors", 0, failed.len());
+    drop(rom_sources);
+
+    // verify what was added
+    checks.check(
+        "verify added",
+        BTreeMap::from([("extra", 1)]),
+        tally(&datfile.verify(&output, true, &BarSink::default())),
+    );
+
+    // cleanup
+    std::fs::remove_file(&extra)?;
+    checks.check(
+        "verify cleaned",
+        BTreeMap::new(),
+        tally(&datfile.verify(&output, true, &BarSink::default())),
+    );
+
+    if large {
+        check_large(dir, &mut checks)?;
+    }
+
+    if keep {
+        println!("* self-test files kept in {}", dir.display());
+    } else {
+        std::fs::remove_dir_all(dir)?;
+    }
+
+    match checks.failed {
+        0 => Ok(()),
+        n => Err(Error::FailedChecks(n)),
+    }
+}
+
+// a fresh directory for each run within base
+pub fn test_dir(base: Option<PathBuf>) -> PathBuf {
+    base.unwrap_or_else(std::env::temp_dir)
+        .join(format!("emuman-selftest-{}", std::process::id()))
+}
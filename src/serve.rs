@@ -0,0 +1,550 @@
+use crate::collection::{Collection, MaintainState, VerifyState};
+use crate::dat::escape;
+use crate::game::Filter;
+use crate::html::{percent, utc_now, utc_time, STYLE};
+use crate::Error;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+// searches stop here, since a short term can match most of MAME
+const SEARCH_LIMIT: usize = 500;
+
+// the status page lists this many failures per collection
+const RECENT_FAILURES: usize = 10;
+
+// the status page is meant for glancing at from a phone,
+// so it fits a narrow screen and refreshes itself
+const STATUS_STYLE: &str = "\
+body { font-family: sans-serif; margin: 0.5em; }
+section { border: 1px solid #ddd; border-radius: 0.4em; padding: 0.5em; margin: 0.5em 0; }
+h2 { font-size: 1.1em; margin: 0 0 0.3em 0; }
+progress { width: 100%; }
+p { margin: 0.2em 0; }
+ul { margin: 0.2em 0; padding-left: 1.2em; }
+.ok { color: #2a7d2a; }
+.failed { color: #b02020; }
+";
+
+const STATUS_REFRESH_SECS: u32 = 300;
+
+// no more than this many connections are answered at once,
+// and no more than this many more wait to be
+const WORKERS: usize = 4;
+
+// a client which stops sending or receiving is given up on after this long
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// and one which keeps sending, but too slowly to finish its request,
+// is given up on after this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// requests' lines are short, and there's no need for many headers,
+// so anything longer is refused rather than read into memory
+const MAX_LINE_LEN: u64 = 8192;
+const MAX_HEADERS: usize = 100;
+
+// a collection as the dashboard shows it,
+// with whatever's on disk and in its state files read fresh each time
+// so results from scheduled runs show up without a restart
+struct Status<'c> {
+    collection: &'c Collection,
+    total: usize,
+    present: HashSet<String>,
+    verify: Option<VerifyState>,
+    maintain: Option<MaintainState>,
+}
+
+impl<'c> Status<'c> {
+    fn new(collection: &'c Collection) -> Self {
+        Self {
+            collection,
+            total: collection.all_games().len(),
+            present: collection
+                .roots
+                .iter()
+                .filter_map(|root| collection.games_in(root).ok())
+                .flatten()
+                .collect(),
+            verify: collection.last_verify(),
+            maintain: collection.last_maintain(),
+        }
+    }
+
+    fn is_failing(&self, game: &str) -> bool {
+        self.maintain
+            .as_ref()
+            .is_some_and(|state| state.failing.contains(game))
+    }
+
+    fn game_status(&self, game: &str) -> (&'static str, &'static str) {
+        if self.is_failing(game) {
+            ("failed", "FAILING")
+        } else if self.present.contains(game) {
+            ("ok", "PRESENT")
+        } else {
+            ("failed", "MISSING")
+        }
+    }
+}
+
+// serves a read-only dashboard of the given collections until killed
+pub fn run(collections: &[Collection], listen: &str) -> Result<(), Error> {
+    let listener = TcpListener::bind(listen)?;
+
+    eprintln!("* serving on http://{}/", listener.local_addr()?);
+
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(WORKERS);
+    let receiver = Mutex::new(receiver);
+
+    std::thread::scope(|s| {
+        for _ in 0..WORKERS {
+            s.spawn(|| loop {
+                // the lock is released as soon as a stream is received
+                let stream = match receiver.lock().unwrap().recv() {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                if let Err(err) = respond(collections, stream) {
+                    eprintln!("* {}", err);
+                }
+            });
+        }
+
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if sender.send(stream).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => eprintln!("* {}", err),
+            }
+        }
+
+        drop(sender);
+    });
+
+    Ok(())
+}
+
+// a client's stream, which it has until deadline to send
+// its whole request on, however slowly that trickles in
+struct Deadlined<'s> {
+    stream: &'s TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadlined<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self
+            .deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::TimedOut, "request took too long")
+            })?;
+
+        self.stream
+            .set_read_timeout(Some(remaining.min(CLIENT_TIMEOUT)))?;
+        self.stream.read(buf)
+    }
+}
+
+// reads a single line, failing if it runs past MAX_LINE_LEN
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> std::io::Result<usize> {
+    let read = reader.by_ref().take(MAX_LINE_LEN).read_line(line)?;
+    if read as u64 == MAX_LINE_LEN && !line.ends_with('\n') {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "request line too long",
+        ))
+    } else {
+        Ok(read)
+    }
+}
+
+fn respond(collections: &[Collection], stream: TcpStream) -> std::io::Result<()> {
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut reader = BufReader::new(Deadlined {
+        stream: &stream,
+        deadline: Instant::now() + REQUEST_TIMEOUT,
+    });
+
+    let mut request = String::new();
+    read_line(&mut reader, &mut request)?;
+
+    // headers aren't needed for anything
+    let mut header = String::new();
+    let mut headers = 0;
+    while read_line(&mut reader, &mut header)? > 2 {
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return reply(
+                stream,
+                "431 Request Header Fields Too Large",
+                b"too many headers",
+            );
+        }
+        header.clear();
+    }
+
+    let (method, target) = match request.split_whitespace().collect::<Vec<_>>()[..] {
+        [method, target, _] => (method, target),
+        _ => return reply(stream, "400 Bad Request", b"bad request"),
+    };
+
+    if method != "GET" {
+        return reply(stream, "405 Method Not Allowed", b"method not allowed");
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let param = |key: &str| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_default()
+    };
+
+    let mut page = Vec::new();
+
+    match path {
+        "/" => index_page(&mut page, collections)?,
+        "/status" => status_page(&mut page, collections)?,
+        "/collection" => match collections.iter().find(|c| c.name == param("name")) {
+            Some(collection) => collection_page(&mut page, collection)?,
+            None => return reply(stream, "404 Not Found", b"no such collection"),
+        },
+        "/search" => search_page(&mut page, collections, param("q").trim())?,
+        _ => return reply(stream, "404 Not Found", b"not found"),
+    }
+
+    reply(stream, "200 OK", &page)
+}
+
+fn reply(mut stream: TcpStream, status: &str, body: &[u8]) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        if status.starts_with("200") {
+            "text/html; charset=utf-8"
+        } else {
+            "text/plain; charset=utf-8"
+        },
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+fn link(page: &str, key: &str, value: &str) -> String {
+    format!(
+        "/{}?{}={}",
+        page,
+        key,
+        url::form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>()
+    )
+}
+
+fn header(w: &mut Vec<u8>, title: &str, search: &str) -> std::io::Result<()> {
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, "<html lang=\"en\">")?;
+    writeln!(w, "<head>")?;
+    writeln!(w, "<meta charset=\"utf-8\">")?;
+    writeln!(
+        w,
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">"
+    )?;
+    writeln!(w, "<title>{}</title>", escape(title))?;
+    writeln!(w, "<style>\n{}</style>", STYLE)?;
+    writeln!(w, "</head>")?;
+    writeln!(w, "<body>")?;
+    writeln!(
+        w,
+        "<form action=\"/search\"><a href=\"/\">emuman</a> <a href=\"/status\">status</a> \
+         <input name=\"q\" value=\"{}\" placeholder=\"search games\"></form>",
+        escape(search)
+    )?;
+    writeln!(w, "<h1>{}</h1>", escape(title))
+}
+
+fn footer(w: &mut Vec<u8>) -> std::io::Result<()> {
+    writeln!(w, "</body>")?;
+    writeln!(w, "</html>")
+}
+
+fn verify_summary(state: Option<&VerifyState>) -> String {
+    match state {
+        Some(state) => format!(
+            "{} of {} OK, {}",
+            state.ok,
+            state.tested,
+            utc_time(state.timestamp)
+        ),
+        None => "never".to_owned(),
+    }
+}
+
+fn maintain_summary(state: Option<&MaintainState>) -> String {
+    match state {
+        Some(state) => format!(
+            "{} failing, {}",
+            state.failing.len(),
+            utc_time(state.timestamp)
+        ),
+        None => "never".to_owned(),
+    }
+}
+
+fn index_page(w: &mut Vec<u8>, collections: &[Collection]) -> std::io::Result<()> {
+    header(w, "Collections", "")?;
+
+    writeln!(w, "<table class=\"summary\">")?;
+    writeln!(
+        w,
+        "<tr><th>Collection</th><th>Database</th><th>Present</th><th></th>\
+         <th>Last verify</th><th>Last maintain</th></tr>"
+    )?;
+    for status in collections.iter().map(Status::new) {
+        writeln!(
+            w,
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{} of {} ({})</td>\
+             <td><progress max=\"{}\" value=\"{}\"></progress></td><td>{}</td><td>{}</td></tr>",
+            escape(&link("collection", "name", &status.collection.name)),
+            escape(&status.collection.name),
+            escape(status.collection.db.description()),
+            status.present.len(),
+            status.total,
+            percent(status.present.len(), status.total),
+            status.total.max(1),
+            status.present.len(),
+            escape(&verify_summary(status.verify.as_ref())),
+            escape(&maintain_summary(status.maintain.as_ref())),
+        )?;
+    }
+    writeln!(w, "</table>")?;
+
+    footer(w)
+}
+
+// a single self-contained page summarizing every collection's health
+fn status_page(w: &mut Vec<u8>, collections: &[Collection]) -> std::io::Result<()> {
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, "<html lang=\"en\">")?;
+    writeln!(w, "<head>")?;
+    writeln!(w, "<meta charset=\"utf-8\">")?;
+    writeln!(
+        w,
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">"
+    )?;
+    writeln!(
+        w,
+        "<meta http-equiv=\"refresh\" content=\"{}\">",
+        STATUS_REFRESH_SECS
+    )?;
+    writeln!(w, "<title>emuman status</title>")?;
+    writeln!(w, "<style>\n{}</style>", STATUS_STYLE)?;
+    writeln!(w, "</head>")?;
+    writeln!(w, "<body>")?;
+
+    for status in collections.iter().map(Status::new) {
+        let present = status.present.len();
+
+        writeln!(w, "<section>")?;
+        writeln!(w, "<h2>{}</h2>", escape(&status.collection.name))?;
+        writeln!(
+            w,
+            "<progress max=\"{}\" value=\"{}\"></progress>",
+            status.total.max(1),
+            present
+        )?;
+        writeln!(
+            w,
+            "<p>{} of {} present ({})</p>",
+            present,
+            status.total,
+            percent(present, status.total)
+        )?;
+
+        match status.verify.as_ref() {
+            Some(verify) => writeln!(
+                w,
+                "<p class=\"{}\">verified {} : {} of {} OK</p>",
+                if verify.ok == verify.tested {
+                    "ok"
+                } else {
+                    "failed"
+                },
+                utc_time(verify.timestamp),
+                verify.ok,
+                verify.tested
+            )?,
+            None => writeln!(w, "<p>never verified</p>")?,
+        }
+
+        if let Some(maintain) = status.maintain.as_ref() {
+            if !maintain.failing.is_empty() {
+                writeln!(w, "<ul class=\"failed\">")?;
+                for game in maintain.failing.iter().take(RECENT_FAILURES) {
+                    writeln!(w, "<li>{}</li>", escape(game))?;
+                }
+                if maintain.failing.len() > RECENT_FAILURES {
+                    writeln!(
+                        w,
+                        "<li>and {} more</li>",
+                        maintain.failing.len() - RECENT_FAILURES
+                    )?;
+                }
+                writeln!(w, "</ul>")?;
+            }
+        }
+
+        writeln!(w, "</section>")?;
+    }
+
+    writeln!(w, "<p><small>{}</small></p>", utc_now())?;
+
+    footer(w)
+}
+
+fn collection_page(w: &mut Vec<u8>, collection: &Collection) -> std::io::Result<()> {
+    let status = Status::new(collection);
+    let description = |game: &str| {
+        collection
+            .db
+            .game(game)
+            .map(|g| g.description.as_str())
+            .unwrap_or_default()
+    };
+
+    header(w, &collection.name, "")?;
+
+    writeln!(w, "<table class=\"summary\">")?;
+    writeln!(
+        w,
+        "<tr><td>Database</td><td>{}</td></tr>",
+        escape(collection.db.description())
+    )?;
+    for root in collection.roots.iter() {
+        writeln!(
+            w,
+            "<tr><td>Root</td><td>{}</td></tr>",
+            escape(&root.display().to_string())
+        )?;
+    }
+    writeln!(
+        w,
+        "<tr><td>Present</td><td>{} of {} ({})</td></tr>",
+        status.present.len(),
+        status.total,
+        percent(status.present.len(), status.total)
+    )?;
+    writeln!(
+        w,
+        "<tr><td>Last verify</td><td>{}</td></tr>",
+        escape(&verify_summary(status.verify.as_ref()))
+    )?;
+    writeln!(
+        w,
+        "<tr><td>Last maintain</td><td>{}</td></tr>",
+        escape(&maintain_summary(status.maintain.as_ref()))
+    )?;
+    writeln!(w, "</table>")?;
+
+    if let Some(maintain) = status.maintain.as_ref() {
+        writeln!(w, "<h2>Failing ({})</h2>", maintain.failing.len())?;
+        writeln!(w, "<ul>")?;
+        for game in maintain.failing.iter() {
+            writeln!(
+                w,
+                "<li><span class=\"failed\">FAILING</span> {} <small>{}</small></li>",
+                escape(game),
+                escape(description(game))
+            )?;
+        }
+        writeln!(w, "</ul>")?;
+    }
+
+    let mut missing: Vec<String> = collection
+        .all_games()
+        .into_iter()
+        .filter(|game| !status.present.contains(game))
+        .collect();
+    missing.sort_unstable();
+
+    writeln!(w, "<h2>Missing ({})</h2>", missing.len())?;
+    writeln!(w, "<details><summary>show all</summary>")?;
+    writeln!(w, "<ul>")?;
+    for game in missing.iter() {
+        writeln!(
+            w,
+            "<li>{} <small>{}</small></li>",
+            escape(game),
+            escape(description(game))
+        )?;
+    }
+    writeln!(w, "</ul>")?;
+    writeln!(w, "</details>")?;
+
+    footer(w)
+}
+
+fn search_page(w: &mut Vec<u8>, collections: &[Collection], search: &str) -> std::io::Result<()> {
+    header(w, &format!("Search \u{201c}{}\u{201d}", search), search)?;
+
+    if search.is_empty() {
+        return footer(w);
+    }
+
+    let mut shown = 0;
+
+    writeln!(w, "<table class=\"failures\">")?;
+    'collections: for collection in collections {
+        let status = Status::new(collection);
+
+        let mut rows = collection
+            .db
+            .list_results(Some(search), None, &Filter::default(), true);
+        rows.retain(|row| !collection.is_ignored(row.name));
+        rows.sort_unstable_by_key(|row| row.name);
+
+        for row in rows {
+            if shown == SEARCH_LIMIT {
+                break 'collections;
+            }
+
+            let (class, label) = status.game_status(row.name);
+
+            writeln!(
+                w,
+                "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td>\
+                 <td><span class=\"{}\">{}</span></td></tr>",
+                escape(&link("collection", "name", &collection.name)),
+                escape(&collection.name),
+                escape(row.name),
+                escape(row.description),
+                class,
+                label
+            )?;
+
+            shown += 1;
+        }
+    }
+    writeln!(w, "</table>")?;
+
+    if shown == SEARCH_LIMIT {
+        writeln!(
+            w,
+            "<p>only the first {} matches are shown</p>",
+            SEARCH_LIMIT
+        )?;
+    } else if shown == 0 {
+        writeln!(w, "<p>no matches</p>")?;
+    }
+
+    footer(w)
+}
@@ -0,0 +1,118 @@
+#[cfg(feature = "native")]
+use crate::game::ExtractedPart;
+use crate::game::{Part, VerifyFailure};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// identifies one task among any others running alongside it,
+// such as the scans of several roots at once
+pub type TaskId = u64;
+
+// how far a task has to go
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Total {
+    // unknown until it's done, like the number of files under a root
+    Unknown,
+    // some number of items, like games
+    Items(u64),
+    // some number of bytes, like those left to hash
+    Bytes(u64),
+}
+
+// receives what verifying, adding and gathering sources are doing
+// as they go, so a program embedding them can show progress its own way,
+// log it, or ignore it altogether, since every method does nothing by default
+//
+// methods may be called from several threads at once,
+// with tasks started by one finished by another
+pub trait ProgressSink: Send + Sync {
+    // a task has begun
+    fn started(&self, _task: TaskId, _name: &str, _total: Total) {}
+
+    // a task has gone some steps further
+    fn advanced(&self, _task: TaskId, _steps: u64) {}
+
+    // a task is done
+    fn finished(&self, _task: TaskId) {}
+
+    // a root's files are about to be listed
+    fn scanning(&self, _root: &Path) {}
+
+    // a file's digest was calculated rather than read from its cache
+    fn file_hashed(&self, _path: &Path, _part: &Part) {}
+
+    // a part was placed where it belongs, or would be if not a dry run
+    #[cfg(feature = "native")]
+    fn part_placed(&self, _part: &ExtractedPart) {}
+
+    // a game was found missing a part, or with a bad or extra one
+    fn failure_found(&self, _game: &str, _failure: &VerifyFailure) {}
+
+    // something went wrong which doesn't stop the task, like a corrupt source
+    fn warning(&self, _text: &str) {}
+
+    // something worth mentioning which isn't a problem,
+    // like a snapshot taken or a new default directory
+    fn notice(&self, _text: &str) {}
+}
+
+// ignores everything
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Quiet;
+
+impl ProgressSink for Quiet {}
+
+static NEXT_TASK: AtomicU64 = AtomicU64::new(0);
+
+// a task reported to a sink, which is finished once dropped
+pub struct Task<'s> {
+    sink: &'s dyn ProgressSink,
+    id: TaskId,
+}
+
+impl<'s> Task<'s> {
+    pub fn start(sink: &'s dyn ProgressSink, name: &str, total: Total) -> Self {
+        let id = NEXT_TASK.fetch_add(1, Ordering::Relaxed);
+        sink.started(id, name, total);
+        Task { sink, id }
+    }
+
+    #[inline]
+    pub fn advance(&self, steps: u64) {
+        self.sink.advanced(self.id, steps)
+    }
+
+    // advances the task by each byte read from reader
+    #[inline]
+    pub fn wrap_read<R: Read>(&self, reader: R) -> TaskReader<'_, 's, R> {
+        TaskReader { task: self, reader }
+    }
+}
+
+impl Drop for Task<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.sink.finished(self.id)
+    }
+}
+
+pub struct TaskReader<'t, 's, R> {
+    task: &'t Task<'s>,
+    reader: R,
+}
+
+impl<R: Read> Read for TaskReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let bytes = self.reader.read(buf)?;
+        self.task.advance(bytes as u64);
+        Ok(bytes)
+    }
+}
+
+impl<R: Seek> Seek for TaskReader<'_, '_, R> {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.reader.seek(pos)
+    }
+}
@@ -0,0 +1,171 @@
+use crate::sink::ProgressSink;
+use crate::{Error, FileError};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+const SNAPSHOTS_CONFIG_FILE: &str = "snapshots.toml";
+const JOURNAL_FILE: &str = "journal.log";
+
+// roots on copy-on-write filesystems may be snapshotted
+// before anything in them is modified, like:
+//
+// "/roms/mame" = "btrfs"
+// "/tank/roms" = "zfs"
+// "/mnt/roms" = "snapper -c roms create --description emuman"
+//
+// where "btrfs" roots must be subvolumes, with read-only snapshots
+// placed alongside them as "<root>@<snapshot>",
+// and anything else is a command which receives the root
+// and snapshot name as JSON on stdin, like hooks do
+fn configs() -> Result<BTreeMap<PathBuf, String>, Error> {
+    let location = crate::data_dir().join(SNAPSHOTS_CONFIG_FILE);
+
+    match std::fs::read(&location) {
+        Ok(toml) => toml::from_slice(&toml).map_err(|error| {
+            Error::TomlRead(FileError {
+                file: location,
+                error,
+            })
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::default()),
+        Err(err) => Err(Error::IO(err)),
+    }
+}
+
+// snapshots the configured root containing path, if any,
+// at most once per run, noting each one taken to sink
+pub fn before_modifying(path: &Path, sink: &dyn ProgressSink) -> Result<(), Error> {
+    static TAKEN: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    for (root, method) in configs()? {
+        let root = root.canonicalize().unwrap_or(root);
+
+        if path.starts_with(&root) && TAKEN.lock().unwrap().insert(root.clone()) {
+            let snapshot = take(&root, &method)?;
+            sink.notice(&format!("snapshot of {} : {}", root.display(), snapshot));
+            record(&root, &snapshot)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn take(root: &Path, method: &str) -> Result<String, Error> {
+    let name = format!(
+        "emuman-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    );
+
+    match method {
+        "btrfs" => {
+            let mut snapshot = root.as_os_str().to_owned();
+            snapshot.push("@");
+            snapshot.push(&name);
+
+            run(Command::new("btrfs")
+                .args(["subvolume", "snapshot", "-r"])
+                .arg(root)
+                .arg(&snapshot))?;
+
+            Ok(PathBuf::from(snapshot).display().to_string())
+        }
+
+        "zfs" => {
+            let output = Command::new("zfs")
+                .args(["list", "-H", "-o", "name"])
+                .arg(root)
+                .stderr(Stdio::inherit())
+                .output()?;
+
+            if !output.status.success() {
+                return Err(failed("zfs list", output.status));
+            }
+
+            let snapshot = format!(
+                "{}@{}",
+                String::from_utf8_lossy(&output.stdout).trim(),
+                name
+            );
+
+            run(Command::new("zfs").arg("snapshot").arg(&snapshot))?;
+
+            Ok(snapshot)
+        }
+
+        command => {
+            #[derive(Serialize)]
+            struct Snapshot<'s> {
+                root: &'s Path,
+                snapshot: &'s str,
+            }
+
+            let json = serde_json::to_vec(&Snapshot {
+                root,
+                snapshot: &name,
+            })
+            .expect("unable to serialize snapshot");
+
+            crate::hooks::run_hook(command, &json).map_err(|err| {
+                Error::IO(std::io::Error::other(format!(
+                    "snapshot command \"{}\" : {}",
+                    command, err
+                )))
+            })?;
+
+            Ok(name)
+        }
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), Error> {
+    let status = command.stdout(Stdio::null()).status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(failed(&format!("{:?}", command.get_program()), status))
+    }
+}
+
+#[inline]
+fn failed(command: &str, status: std::process::ExitStatus) -> Error {
+    Error::IO(std::io::Error::other(format!(
+        "snapshot command {} exited with {}",
+        command, status
+    )))
+}
+
+// the journal lists each snapshot taken, one per line, like:
+//
+// 1700000000	/roms/mame	/roms/mame@emuman-1700000000
+fn record(root: &Path, snapshot: &str) -> Result<(), Error> {
+    use std::io::Write;
+
+    let dir = crate::data_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let mut journal = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(JOURNAL_FILE))?;
+
+    writeln!(
+        journal,
+        "{}\t{}\t{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        root.display(),
+        snapshot
+    )
+    .map_err(Error::IO)
+}
@@ -2,9 +2,9 @@ use crate::dat::Datafile;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct SplitDb {
     games: HashMap<u64, Vec<SplitGame>>,
 }
@@ -41,10 +41,7 @@ impl SplitDb {
         for game in datafile.games() {
             let (total_size, split) = game_to_split(game);
             if split.tracks.len() > 1 {
-                self.games
-                    .entry(total_size)
-                    .or_insert_with(Vec::new)
-                    .push(split);
+                self.games.entry(total_size).or_default().push(split);
             }
         }
     }
@@ -95,7 +92,9 @@ impl SplitGame {
         self.tracks.par_iter().all(|t| t.matches(data))
     }
 
-    pub fn extract(&self, root: &Path, data: &[u8]) -> Result<(), io::Error> {
+    // extracts each track into a directory under root,
+    // returning the paths of the tracks written
+    pub fn extract(&self, root: &Path, data: &[u8]) -> Result<Vec<PathBuf>, io::Error> {
         use rayon::prelude::*;
 
         let game_root = root.join(&self.name);
@@ -106,7 +105,8 @@ impl SplitGame {
         }
         self.tracks
             .par_iter()
-            .try_for_each(|t| t.extract(&game_root, data))
+            .map(|t| t.extract(&game_root, data))
+            .collect()
     }
 }
 
@@ -136,17 +136,12 @@ impl SplitPart {
         Sha1::from(&data[self.start..self.end]).digest().bytes() == self.sha1
     }
 
-    fn extract(&self, root: &Path, data: &[u8]) -> Result<(), io::Error> {
+    fn extract(&self, root: &Path, data: &[u8]) -> Result<PathBuf, io::Error> {
         use std::fs::File;
         use std::io::Write;
 
         let path = root.join(&self.name);
-        match File::create(&path).and_then(|mut f| f.write_all(&data[self.start..self.end])) {
-            Ok(()) => {
-                println!("* {}", path.display());
-                Ok(())
-            }
-            Err(err) => Err(err),
-        }
+        File::create(&path).and_then(|mut f| f.write_all(&data[self.start..self.end]))?;
+        Ok(path)
     }
 }
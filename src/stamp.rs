@@ -0,0 +1,56 @@
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STAMPING: AtomicBool = AtomicBool::new(false);
+
+// once set, game Zip archives are stamped whenever they're built
+#[inline]
+pub fn set_stamping() {
+    STAMPING.store(true, Ordering::Relaxed)
+}
+
+// which DAT a game's Zip archive was built against, kept in the
+// archive's comment so it travels with the archive wherever it's copied
+//
+// since canonical archives claim the comment for their own stamp,
+// stamped archives are never in canonical form
+#[derive(Serialize, Deserialize)]
+pub struct Stamp {
+    pub dat: String,
+    pub built: String,
+    pub version: String,
+}
+
+impl Stamp {
+    // the stamp for archives built against the given DAT, if stamping
+    pub fn new(dat: &str) -> Option<Self> {
+        STAMPING.load(Ordering::Relaxed).then(|| Self {
+            dat: dat.to_owned(),
+            built: crate::html::utc_now(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+        })
+    }
+
+    #[inline]
+    pub fn to_comment(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "  dat : {}", self.dat)?;
+        writeln!(f, "  built : {}", self.built)?;
+        write!(f, "  version : emuman {}", self.version)
+    }
+}
+
+// archives with any other comment, or none, have no stamp
+#[inline]
+pub fn get(path: &Path) -> Result<Option<Stamp>, Error> {
+    let zip = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    Ok(serde_json::from_slice(zip.comment()).ok())
+}
@@ -0,0 +1,118 @@
+use crate::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// the first entry of every state archive, holding the version
+// of emuman which wrote it, so other Zips aren't unpacked by mistake
+const MARKER: &str = "EMUMAN-STATE";
+
+// packs every file in our data directory into a Zip archive,
+// except for quarantined files, returning how many files were packed
+pub fn export(output: &Path) -> Result<usize, Error> {
+    use walkdir::WalkDir;
+    use zip::{write::FileOptions, ZipWriter};
+
+    let dir = crate::data_dir();
+    let staging = output.with_extension("tmp");
+    let mut zip = ZipWriter::new(File::create(&staging)?);
+
+    // in case the archive is being written into the data directory itself
+    let skip = [output, staging.as_path()].map(|path| path.canonicalize().ok());
+
+    let quarantined = crate::collection::quarantine_dirs()?
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .collect::<Vec<PathBuf>>();
+
+    zip.start_file(MARKER, FileOptions::default())?;
+    writeln!(zip, "{}", env!("CARGO_PKG_VERSION"))?;
+
+    let mut exported = 0;
+
+    if dir.is_dir() {
+        for entry in WalkDir::new(&dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                !entry.file_type().is_dir()
+                    || !entry
+                        .path()
+                        .canonicalize()
+                        .is_ok_and(|dir| quarantined.contains(&dir))
+            })
+        {
+            let entry = entry.map_err(|err| Error::IO(err.into()))?;
+            if !entry.file_type().is_file() || skip.contains(&entry.path().canonicalize().ok()) {
+                continue;
+            }
+
+            let name = entry
+                .path()
+                .strip_prefix(&dir)
+                .ok()
+                .and_then(|name| name.to_str())
+                .ok_or(Error::InvalidPath)?
+                .replace(std::path::MAIN_SEPARATOR, "/");
+
+            zip.start_file(name, FileOptions::default().large_file(true))?;
+            std::io::copy(&mut File::open(entry.path())?, &mut zip)?;
+            exported += 1;
+        }
+    }
+
+    zip.finish()?;
+    drop(zip);
+    std::fs::rename(&staging, output)?;
+
+    Ok(exported)
+}
+
+// unpacks a state archive into our data directory,
+// returning how many files were unpacked,
+// where files already present are only replaced if overwrite is set
+// and files absent from the archive are left as-is
+pub fn import(archive: &Path, overwrite: bool) -> Result<usize, Error> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+
+    if zip.by_name(MARKER).is_err() {
+        return Err(Error::InvalidStateArchive(archive.to_path_buf()));
+    }
+
+    let dir = crate::data_dir();
+    let mut files = Vec::with_capacity(zip.len());
+
+    for index in 0..zip.len() {
+        let entry = zip.by_index(index)?;
+        if entry.is_dir() || entry.name() == MARKER {
+            continue;
+        }
+        match entry.enclosed_name() {
+            Some(name) => files.push((index, dir.join(name))),
+            None => {
+                return Err(Error::CorruptZip(
+                    archive.to_path_buf(),
+                    entry.name().to_owned(),
+                ))
+            }
+        }
+    }
+
+    let existing = files.iter().filter(|(_, path)| path.exists()).count();
+    if existing > 0 && !overwrite {
+        return Err(Error::StateExists(existing));
+    }
+
+    for (index, path) in files.iter() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // each file is replaced whole or not at all
+        let staging = path.with_extension("tmp");
+        std::io::copy(&mut zip.by_index(*index)?, &mut File::create(&staging)?)?;
+        std::fs::rename(&staging, path)?;
+    }
+
+    Ok(files.len())
+}
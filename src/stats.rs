@@ -0,0 +1,180 @@
+use crate::collection::Collection;
+use crate::game::{Game, Status, VerifyFailure};
+use crate::progress::BarSink;
+use crate::Error;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+// how much of some group of games is present
+#[derive(Copy, Clone, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Completion {
+    pub games: usize,
+    pub complete: usize,
+    pub partial: usize,
+    pub missing: usize,
+    pub bytes_required: u64,
+    pub bytes_present: u64,
+}
+
+impl std::ops::AddAssign for Completion {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        self.games += rhs.games;
+        self.complete += rhs.complete;
+        self.partial += rhs.partial;
+        self.missing += rhs.missing;
+        self.bytes_required += rhs.bytes_required;
+        self.bytes_present += rhs.bytes_present;
+    }
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Stats {
+    pub name: String,
+    pub working: Completion,
+    pub partial: Completion,
+    pub not_working: Completion,
+    pub total: Completion,
+}
+
+impl Stats {
+    // verifies each of the collection's roots to tally
+    // which of its games are complete, partial or missing
+    pub fn new(collection: &Collection) -> Result<Self, Error> {
+        // the parts which are missing or bad in the best copy of each game found
+        let mut found: BTreeMap<String, (BTreeSet<&str>, bool)> = BTreeMap::new();
+
+        for root in collection.roots.iter() {
+            let games = collection.games_in(root)?;
+
+            for (game, failures) in collection.db.verify(
+                root,
+                &games,
+                false,
+                collection.is_zip(),
+                &BarSink::default(),
+            ) {
+                let mut unusable = BTreeSet::new();
+                let mut incomplete = false;
+
+                for failure in failures.iter().filter(|f| f.is_incomplete()) {
+                    incomplete = true;
+                    if let VerifyFailure::Missing { name, .. }
+                    | VerifyFailure::MissingUndumped { name, .. }
+                    | VerifyFailure::Bad { name, .. } = failure
+                    {
+                        unusable.insert(*name);
+                    }
+                }
+
+                match found.get(game) {
+                    Some((best, best_incomplete))
+                        if (*best_incomplete, best.len()) <= (incomplete, unusable.len()) => {}
+                    _ => {
+                        found.insert(game.to_owned(), (unusable, incomplete));
+                    }
+                }
+            }
+        }
+
+        let mut stats = Stats {
+            name: collection.name.clone(),
+            ..Stats::default()
+        };
+
+        for game in collection
+            .db
+            .games_iter()
+            .filter(|game| !game.is_device && !collection.is_ignored(&game.name))
+        {
+            let completion = Completion::new(game, found.get(&game.name));
+
+            match game.status {
+                Status::Working => stats.working += completion,
+                Status::Partial => stats.partial += completion,
+                Status::NotWorking => stats.not_working += completion,
+            }
+            stats.total += completion;
+        }
+
+        Ok(stats)
+    }
+}
+
+impl Completion {
+    // a single game's completion, given its unusable parts if it was found
+    fn new(game: &Game, found: Option<&(BTreeSet<&str>, bool)>) -> Self {
+        let size = |name: &str| game.sizes.get(name).copied().unwrap_or_default();
+
+        let bytes_required = game.parts.iter().map(|(name, _)| size(name)).sum();
+
+        let mut completion = Completion {
+            games: 1,
+            bytes_required,
+            ..Completion::default()
+        };
+
+        match found {
+            None => completion.missing = 1,
+            Some((_, false)) => {
+                completion.complete = 1;
+                completion.bytes_present = bytes_required;
+            }
+            Some((unusable, true)) if unusable.len() >= game.parts.len() => completion.missing = 1,
+            Some((unusable, true)) => {
+                completion.partial = 1;
+                completion.bytes_present = game
+                    .parts
+                    .iter()
+                    .filter(|(name, _)| !unusable.contains(name.as_str()))
+                    .map(|(name, _)| size(name))
+                    .sum();
+            }
+        }
+
+        completion
+    }
+}
+
+pub fn display_stats(stats: &[Stats]) {
+    use indicatif::HumanBytes;
+    use prettytable::{cell, format, row, Table};
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.get_format().column_separator('\u{2502}');
+    table.set_titles(row![
+        "",
+        "",
+        r->"Games",
+        r->"Complete",
+        r->"Partial",
+        r->"Missing",
+        r->"Required",
+        r->"Present"
+    ]);
+
+    for stats in stats {
+        for (status, c) in [
+            ("Working", &stats.working),
+            ("Partial", &stats.partial),
+            ("Not Working", &stats.not_working),
+            ("Total", &stats.total),
+        ] {
+            table.add_row(row![
+                stats.name,
+                status,
+                r->c.games,
+                r->c.complete,
+                r->c.partial,
+                r->c.missing,
+                r->HumanBytes(c.bytes_required),
+                r->HumanBytes(c.bytes_present)
+            ]);
+        }
+    }
+
+    table.printstd();
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// user tags and notes for games by name,
+// for curating personal subsets of them
+#[derive(Default, Serialize, Deserialize)]
+pub struct TagDb {
+    games: BTreeMap<String, Curation>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Curation {
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl Curation {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.note.is_none()
+    }
+}
+
+impl TagDb {
+    #[inline]
+    pub fn get(&self, game: &str) -> Option<&Curation> {
+        self.games.get(game)
+    }
+
+    #[inline]
+    pub fn has_any(&self, game: &str, tags: &[String]) -> bool {
+        self.get(game)
+            .map(|curation| tags.iter().any(|tag| curation.tags.contains(tag)))
+            .unwrap_or(false)
+    }
+
+    pub fn tag<I: IntoIterator<Item = String>>(&mut self, game: &str, tags: I) {
+        self.games
+            .entry(game.to_owned())
+            .or_default()
+            .tags
+            .extend(tags);
+    }
+
+    pub fn untag(&mut self, game: &str, tags: &[String]) {
+        self.update(game, |curation| {
+            curation.tags.retain(|tag| !tags.contains(tag))
+        });
+    }
+
+    pub fn set_note(&mut self, game: &str, note: Option<String>) {
+        self.update(game, |curation| curation.note = note);
+    }
+
+    // games left with neither tags nor a note are forgotten
+    fn update<F: FnOnce(&mut Curation)>(&mut self, game: &str, f: F) {
+        let curation = self.games.entry(game.to_owned()).or_default();
+        f(curation);
+        if curation.is_empty() {
+            self.games.remove(game);
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use crate::Error;
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// every TorrentZip member is stamped 1996-12-24 23:32:00
+const DOS_TIME: u16 = 0xbc00;
+const DOS_DATE: u16 = 0x2198;
+
+// version needed to extract 2.0, for deflate
+const VERSION: u16 = 20;
+
+// general purpose flag for maximum compression
+const FLAGS: u16 = 2;
+
+const DEFLATED: u16 = 8;
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR: u32 = 0x06054b50;
+
+const CENTRAL_HEADER_LEN: usize = 46;
+const END_OF_CENTRAL_DIR_LEN: usize = 22;
+
+// archives written by TorrentZip tools are stamped with this prefix
+// followed by the central directory's CRC32 in hex
+const TORRENTZIP_PREFIX: &[u8] = b"TORRENTZIPPED-";
+
+// archives rewritten here share TorrentZip's layout, but flate2's deflate
+// doesn't reproduce zlib's output byte for byte, so they're stamped apart
+// to keep TorrentZip tools from taking them for their own
+const CANONICAL_PREFIX: &[u8] = b"EMUMANZIPPED-";
+
+// the comment's CRC32 in hex
+const CRC_LEN: usize = 8;
+
+// whether the Zip archive at path is already in canonical form,
+// either written by a TorrentZip tool or rewritten here
+//
+// like other TorrentZip checkers, this trusts the archive comment's
+// CRC32 to vouch for the central directory, and the central directory
+// to vouch for the rest of the file
+pub fn is_canonical(path: &Path) -> Result<bool, Error> {
+    let mut file = std::fs::File::open(path)?;
+
+    for prefix in [TORRENTZIP_PREFIX, CANONICAL_PREFIX] {
+        if has_canonical_layout(&mut file, prefix)? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn has_canonical_layout(file: &mut std::fs::File, prefix: &[u8]) -> Result<bool, Error> {
+    let comment_len = prefix.len() + CRC_LEN;
+    let len = file.seek(SeekFrom::End(0))?;
+
+    if len < (END_OF_CENTRAL_DIR_LEN + comment_len) as u64 {
+        return Ok(false);
+    }
+
+    let mut end = vec![0; END_OF_CENTRAL_DIR_LEN + comment_len];
+    file.seek(SeekFrom::End(-(end.len() as i64)))?;
+    file.read_exact(&mut end)?;
+
+    let (record, comment) = end.split_at(END_OF_CENTRAL_DIR_LEN);
+
+    if u32_at(record, 0) != END_OF_CENTRAL_DIR
+        || u16_at(record, 4) != 0
+        || u16_at(record, 6) != 0
+        || u16_at(record, 8) != u16_at(record, 10)
+        || usize::from(u16_at(record, 20)) != comment_len
+        || !comment.starts_with(prefix)
+    {
+        return Ok(false);
+    }
+
+    let entries = usize::from(u16_at(record, 8));
+    let central_size = u32_at(record, 12) as u64;
+    let central_offset = u32_at(record, 16) as u64;
+
+    // nothing may sit between the central directory and its end record
+    if central_offset + central_size != len - end.len() as u64 {
+        return Ok(false);
+    }
+
+    let mut central = vec![0; central_size as usize];
+    file.seek(SeekFrom::Start(central_offset))?;
+    file.read_exact(&mut central)?;
+
+    if comment[prefix.len()..] != comment_crc(&central)[..] {
+        return Ok(false);
+    }
+
+    let mut offset = 0;
+    let mut previous: Option<String> = None;
+
+    for _ in 0..entries {
+        let header = match central.get(offset..offset + CENTRAL_HEADER_LEN) {
+            Some(header) => header,
+            None => return Ok(false),
+        };
+
+        let name_len = usize::from(u16_at(header, 28));
+
+        if u32_at(header, 0) != CENTRAL_HEADER
+            || u16_at(header, 4) != 0
+            || u16_at(header, 6) != VERSION
+            || u16_at(header, 8) != FLAGS
+            || u16_at(header, 10) != DEFLATED
+            || u16_at(header, 12) != DOS_TIME
+            || u16_at(header, 14) != DOS_DATE
+            || u16_at(header, 30) != 0
+            || u16_at(header, 32) != 0
+            || u16_at(header, 34) != 0
+            || u16_at(header, 36) != 0
+            || u32_at(header, 38) != 0
+        {
+            return Ok(false);
+        }
+
+        offset += CENTRAL_HEADER_LEN;
+
+        let name = match central.get(offset..offset + name_len) {
+            Some(name) => sort_key(name),
+            None => return Ok(false),
+        };
+
+        if previous.map(|previous| previous > name).unwrap_or(false) {
+            return Ok(false);
+        }
+
+        previous = Some(name);
+        offset += name_len;
+    }
+
+    Ok(offset == central.len())
+}
+
+// rewrites the Zip archive at path in TorrentZip's layout:
+// members sorted by name, with fixed timestamps,
+// maximum deflate compression and no extra fields,
+// but stamped as rewritten here rather than as TorrentZip
+pub fn canonicalize(path: &Path) -> Result<(), Error> {
+    use flate2::{write::DeflateEncoder, Compression};
+    use std::fs::{rename, File};
+    use std::io::BufWriter;
+
+    let mut zip = zip::ZipArchive::new(File::open(path)?)?;
+
+    let mut names = zip.file_names().map(|s| s.to_owned()).collect::<Vec<_>>();
+    names.sort_by_cached_key(|name| sort_key(name.as_bytes()));
+
+    let staging = path.with_extension("zip.tmp");
+    let mut writer = BufWriter::new(File::create(&staging)?);
+    let mut central = Vec::new();
+    let mut offset: u64 = 0;
+
+    for name in names.iter() {
+        let mut entry = zip.by_name(name)?;
+        let crc = entry.crc32();
+
+        let name = name.as_bytes();
+
+        let (size, name_len, offset32) = match (
+            u32::try_from(entry.size()),
+            u16::try_from(name.len()),
+            u32::try_from(offset),
+        ) {
+            (Ok(size), Ok(name_len), Ok(offset)) => (size, name_len, offset),
+            _ => return Err(too_large()),
+        };
+
+        // the compressed size isn't known until the entry
+        // has been compressed, so it's filled in afterwards
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        local.extend_from_slice(&VERSION.to_le_bytes());
+        local.extend_from_slice(&FLAGS.to_le_bytes());
+        local.extend_from_slice(&DEFLATED.to_le_bytes());
+        local.extend_from_slice(&DOS_TIME.to_le_bytes());
+        local.extend_from_slice(&DOS_DATE.to_le_bytes());
+        local.extend_from_slice(&crc.to_le_bytes());
+        local.extend_from_slice(&0u32.to_le_bytes());
+        local.extend_from_slice(&size.to_le_bytes());
+        local.extend_from_slice(&name_len.to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());
+        local.extend_from_slice(name);
+
+        writer.write_all(&local)?;
+
+        // entries are compressed as they're read rather than
+        // read whole, since they may be larger than memory,
+        // and reading them through to the end verifies their CRC32
+        let data_start = offset + local.len() as u64;
+        let mut encoder = DeflateEncoder::new(&mut writer, Compression::best());
+        std::io::copy(&mut entry, &mut encoder)?;
+        let data_end = encoder.finish()?.stream_position()?;
+
+        let compressed_size = u32::try_from(data_end - data_start).map_err(|_| too_large())?;
+
+        writer.seek(SeekFrom::Start(offset + 18))?;
+        writer.write_all(&compressed_size.to_le_bytes())?;
+        writer.seek(SeekFrom::Start(data_end))?;
+
+        central.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&VERSION.to_le_bytes());
+        central.extend_from_slice(&FLAGS.to_le_bytes());
+        central.extend_from_slice(&DEFLATED.to_le_bytes());
+        central.extend_from_slice(&DOS_TIME.to_le_bytes());
+        central.extend_from_slice(&DOS_DATE.to_le_bytes());
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&compressed_size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&name_len.to_le_bytes());
+        // extra field length, comment length,
+        // starting disk and internal attributes
+        central.extend_from_slice(&[0; 8]);
+        // external attributes
+        central.extend_from_slice(&0u32.to_le_bytes());
+        central.extend_from_slice(&offset32.to_le_bytes());
+        central.extend_from_slice(name);
+
+        offset = data_end;
+    }
+
+    let (entries, central_size, central_offset) = match (
+        u16::try_from(names.len()),
+        u32::try_from(central.len()),
+        u32::try_from(offset),
+    ) {
+        (Ok(entries), Ok(central_size), Ok(central_offset)) => {
+            (entries, central_size, central_offset)
+        }
+        _ => return Err(too_large()),
+    };
+
+    writer.write_all(&central)?;
+    writer.write_all(&END_OF_CENTRAL_DIR.to_le_bytes())?;
+    // this disk and the central directory's starting disk
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&entries.to_le_bytes())?;
+    writer.write_all(&entries.to_le_bytes())?;
+    writer.write_all(&central_size.to_le_bytes())?;
+    writer.write_all(&central_offset.to_le_bytes())?;
+    writer.write_all(&((CANONICAL_PREFIX.len() + CRC_LEN) as u16).to_le_bytes())?;
+    writer.write_all(CANONICAL_PREFIX)?;
+    writer.write_all(&comment_crc(&central))?;
+    writer.flush()?;
+    drop(writer);
+
+    rename(&staging, path)?;
+
+    Ok(())
+}
+
+#[inline]
+fn too_large() -> Error {
+    Error::Zip(zip::result::ZipError::UnsupportedArchive(
+        "archive too large for canonical form",
+    ))
+}
+
+// TorrentZip orders members by their lowercase names
+#[inline]
+fn sort_key(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).to_lowercase()
+}
+
+#[inline]
+fn comment_crc(central: &[u8]) -> [u8; CRC_LEN] {
+    let mut crc = [0; CRC_LEN];
+    crc.copy_from_slice(format!("{:08X}", crc32fast::hash(central)).as_bytes());
+    crc
+}
+
+#[inline]
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+#[inline]
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn canonicalize_streamed() {
+        // larger than the buffers members are streamed through
+        let large = (0..3 * 1024 * 1024)
+            .map(|i: u32| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect::<Vec<_>>();
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in [("b.bin", &large[..]), ("A.bin", b"small")] {
+            zip.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(data).unwrap();
+        }
+        let zip = zip.finish().unwrap().into_inner();
+
+        let path =
+            std::env::temp_dir().join(format!("emuman-canonicalize-{}.zip", std::process::id()));
+        std::fs::write(&path, zip).unwrap();
+        assert!(!is_canonical(&path).unwrap());
+
+        canonicalize(&path).unwrap();
+        assert!(is_canonical(&path).unwrap());
+        assert!(!path.with_extension("zip.tmp").exists());
+
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(zip.len(), 2);
+        assert_eq!(zip.by_index(0).unwrap().name(), "A.bin");
+
+        let mut data = Vec::new();
+        zip.by_name("b.bin")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert!(data == large);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+// where files are moved rather than deleted,
+// so anything removed because of a mistaken DAT can be recovered
+#[derive(Clone, Debug)]
+pub enum Trash {
+    // a directory of our own, where each run's files
+    // are kept under their original absolute paths
+    Dir(PathBuf),
+    // the desktop's own trash can
+    System,
+}
+
+impl FromStr for Trash {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "system" => Trash::System,
+            dir => Trash::Dir(PathBuf::from(dir)),
+        })
+    }
+}
+
+// the trash in use, along with the directory for this run's files
+static TRASH: Mutex<Option<(Trash, Option<PathBuf>)>> = Mutex::new(None);
+
+#[inline]
+pub fn set_trash(trash: Trash) {
+    *TRASH.lock().unwrap() = Some((trash, None));
+}
+
+// removes file, or moves it to the trash if one is set,
+// where journaled runs set files aside in the journal otherwise
+pub fn remove_file(path: &Path) -> Result<(), std::io::Error> {
+    let mut trash = TRASH.lock().unwrap();
+
+    let saved = match trash.as_mut() {
+        None => match crate::journal::saved_path(path) {
+            Some(saved) => saved,
+            None => return std::fs::remove_file(path),
+        },
+        Some((Trash::Dir(dir), run)) => {
+            // runs starting within the same second each get their own directory
+            let run = run.get_or_insert_with(|| {
+                dir.join(format!(
+                    "{}-{}",
+                    crate::collection::unix_now(),
+                    std::process::id()
+                ))
+            });
+            run.join(relative(&absolute(path)))
+        }
+        Some((Trash::System, _)) => system_trash_path(path)?,
+    };
+
+    move_to(path, &saved)?;
+
+    crate::journal::record(crate::journal::Entry::Removed {
+        path: absolute(path),
+        saved,
+    })
+}
+
+// removes directory and everything in it,
+// or moves its files to the trash if one is set
+pub fn remove_dir_all(path: &Path) -> Result<(), std::io::Error> {
+    if TRASH.lock().unwrap().is_some() || crate::journal::is_journaling() {
+        for file in walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| !e.file_type().is_dir())
+        {
+            remove_file(file.path())?;
+        }
+    }
+
+    std::fs::remove_dir_all(path)
+}
+
+#[inline]
+pub fn absolute(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+// an absolute path made relative, to be joined to a trash directory
+#[inline]
+pub fn relative(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+// the trash may well be on another filesystem
+pub fn move_to(path: &Path, target: &Path) -> Result<(), std::io::Error> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    match std::fs::rename(path, target) {
+        Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+            std::fs::copy(path, target)?;
+            std::fs::remove_file(path)
+        }
+        result => result,
+    }
+}
+
+// picks a name not yet used in dir, starting with path's own
+fn unused_name(dir: &Path, path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+
+    (0..)
+        .map(|n| match n {
+            0 => dir.join(name),
+            n => {
+                let mut numbered = name.to_owned();
+                numbered.push(format!(".{}", n));
+                dir.join(numbered)
+            }
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+// where the freedesktop.org trash keeps path, with an info file
+// recording where each trashed file came from
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_trash_path(path: &Path) -> Result<PathBuf, std::io::Error> {
+    let trash = directories::BaseDirs::new()
+        .expect("no valid home directory")
+        .data_dir()
+        .join("Trash");
+    let files = trash.join("files");
+    let info = trash.join("info");
+
+    std::fs::create_dir_all(&files)?;
+    std::fs::create_dir_all(&info)?;
+
+    let target = unused_name(&files, path);
+    let mut info_name = target.file_name().unwrap_or_default().to_owned();
+    info_name.push(".trashinfo");
+
+    let original = absolute(path);
+    let deleted = crate::html::utc_time(crate::collection::unix_now());
+
+    std::fs::write(
+        info.join(info_name),
+        format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            url::Url::from_file_path(&original)
+                .map(|url| url.path().to_owned())
+                .unwrap_or_else(|()| original.display().to_string()),
+            deleted.trim_end_matches(" UTC").replace(' ', "T"),
+        ),
+    )?;
+
+    Ok(target)
+}
+
+#[cfg(target_os = "macos")]
+fn system_trash_path(path: &Path) -> Result<PathBuf, std::io::Error> {
+    let trash = directories::BaseDirs::new()
+        .expect("no valid home directory")
+        .home_dir()
+        .join(".Trash");
+
+    std::fs::create_dir_all(&trash)?;
+    Ok(unused_name(&trash, path))
+}
+
+// elsewhere, files go to a trash directory of our own
+#[cfg(not(unix))]
+fn system_trash_path(path: &Path) -> Result<PathBuf, std::io::Error> {
+    let trash = crate::data_dir().join("trash");
+
+    std::fs::create_dir_all(&trash)?;
+    Ok(unused_name(&trash, path))
+}
@@ -0,0 +1,120 @@
+use crate::dat::DatFile;
+use crate::game::{GameDb, Part, VerifyFailure};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+// suggests what to do about verification failures
+pub struct Triage {
+    // where each known part belongs, like "mame pacman/pacman.6e"
+    known: HashMap<Part, String>,
+    // roots outside the one being verified
+    other_roots: Vec<PathBuf>,
+}
+
+impl Triage {
+    pub fn new(db: &GameDb, root: &Path) -> Self {
+        let mut known = HashMap::default();
+
+        let mut add_game_db = |system: &str, db: &GameDb| {
+            for game in db.games_iter() {
+                for (rom, part) in game.parts.iter() {
+                    known
+                        .entry(part.clone())
+                        .or_insert_with(|| format!("{} {}/{}", system, game.name, rom));
+                }
+            }
+        };
+
+        // the database being verified takes precedence
+        add_game_db(db.description(), db);
+
+        if let Ok(mame) = crate::read_game_db::<GameDb>(crate::MAME, crate::DB_MAME) {
+            add_game_db("mame", &mame);
+        }
+
+        for (name, db) in crate::read_collected_dbs::<BTreeMap<_, _>, GameDb>(crate::DIR_SL) {
+            add_game_db(&name, &db);
+        }
+
+        for dir in [crate::DIR_EXTRA, crate::DIR_NOINTRO, crate::DIR_REDUMP] {
+            for (name, datfile) in crate::read_collected_dbs::<BTreeMap<_, _>, DatFile>(dir) {
+                for (game, parts) in datfile.game_parts() {
+                    for (rom, part) in parts.iter() {
+                        known.entry(part.clone()).or_insert_with(|| match game {
+                            "" => format!("{} {}", name, rom),
+                            game => format!("{} {}/{}", name, game, rom),
+                        });
+                    }
+                }
+            }
+        }
+
+        let root = root.canonicalize().ok();
+
+        let mut other_roots: Vec<PathBuf> = crate::dirs::configured_dirs()
+            .into_iter()
+            .chain(
+                crate::collection::configs()
+                    .unwrap_or_default()
+                    .into_values()
+                    .flat_map(|config| config.roots),
+            )
+            .filter(|dir| dir.is_dir() && dir.canonicalize().ok() != root)
+            .collect();
+        other_roots.sort_unstable();
+        other_roots.dedup();
+
+        Self { known, other_roots }
+    }
+
+    pub fn suggest(&self, failure: &VerifyFailure) -> Option<String> {
+        match failure {
+            VerifyFailure::Missing { .. } if !self.other_roots.is_empty() => Some(format!(
+                "not scanned for sources : {}",
+                self.other_roots
+                    .iter()
+                    .map(|root| root.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            VerifyFailure::Extra { part: Ok(part), .. }
+            | VerifyFailure::Bad { actual: part, .. }
+                if matches!(crate::blocklist::is_blocked(part), Ok(true)) =>
+            {
+                Some("data is blocklisted, remove it".to_string())
+            }
+            VerifyFailure::Bad { actual, .. } => Some(match self.known.get(actual) {
+                Some(owner) => format!("data matches {}", owner),
+                None => "data matches no known part, possibly a bad dump".to_string(),
+            }),
+            VerifyFailure::Extra { part: Ok(part), .. } => self
+                .known
+                .get(part)
+                .map(|owner| format!("belongs to {}", owner)),
+            VerifyFailure::MissingBios { bios, .. } => {
+                Some(format!("the game needs the \"{}\" BIOS set, add it", bios))
+            }
+            VerifyFailure::MissingDevice { device, .. } => Some(format!(
+                "the game needs the \"{}\" device set, add it",
+                device
+            )),
+            VerifyFailure::NotCanonical { .. } => {
+                Some("contents are OK, run \"emuman canonicalize\" to repack it".to_string())
+            }
+            VerifyFailure::Error { path, .. } if path.is_symlink() && !path.exists() => {
+                Some("links to a file which is gone, add the part again".to_string())
+            }
+            VerifyFailure::Error { err, .. } => Some(
+                match err.kind() {
+                    std::io::ErrorKind::PermissionDenied => {
+                        "permission denied, check the file's owner and mode"
+                    }
+                    std::io::ErrorKind::NotFound => "removed while verifying, try again",
+                    _ => "I/O error, check the drive and filesystem",
+                }
+                .to_string(),
+            ),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use crate::game::{Game, GameDb, RomSources, VerifyFailure};
+use crate::info::InfoDb;
+use crate::progress::BarSink;
+use crate::Error;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+// games are verified a chunk at a time,
+// so results and progress show up while the rest are still going
+const VERIFY_CHUNK: usize = 64;
+
+const HELP: &str = "/ search  v verify  V verify shown  a add  q quit";
+
+// where a browsed database's games are stored and added from,
+// along with anything else known about them
+pub struct Storage {
+    pub roots: Vec<PathBuf>,
+    pub zip: bool,
+    pub sources: Vec<PathBuf>,
+    pub urls: Vec<String>,
+    // history and mameinfo text, shown alongside a game's details
+    pub info: InfoDb,
+}
+
+impl Storage {
+    // the root a game is stored in, or where it would be added
+    fn game_root(&self, game: &str) -> &Path {
+        let entry = if self.zip {
+            format!("{}.zip", game)
+        } else {
+            game.to_owned()
+        };
+
+        self.roots
+            .iter()
+            .find(|root| root.join(&entry).exists())
+            .or_else(|| self.roots.first())
+            .map(|root| root.as_path())
+            .unwrap_or_else(|| Path::new("."))
+    }
+}
+
+// how a game last fared when verified
+struct Verdict {
+    failures: Vec<String>,
+    // part names which are missing or bad
+    parts: HashMap<String, &'static str>,
+}
+
+impl Verdict {
+    fn new(failures: &[VerifyFailure]) -> Self {
+        Self {
+            failures: failures.iter().map(|failure| failure.to_string()).collect(),
+            parts: failures
+                .iter()
+                .filter_map(|failure| match failure {
+                    VerifyFailure::Missing { name, .. }
+                    | VerifyFailure::MissingUndumped { name, .. } => {
+                        Some((name.to_string(), "MISSING"))
+                    }
+                    VerifyFailure::Bad { name, .. } => Some((name.to_string(), "BAD")),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    #[inline]
+    fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// what background tasks share with the browser drawing them
+#[derive(Default)]
+struct Shared {
+    verdicts: Mutex<HashMap<String, Verdict>>,
+    busy: AtomicBool,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    message: Mutex<String>,
+}
+
+impl Shared {
+    fn verify<'g, I>(&self, db: &GameDb, storage: &Storage, games: I)
+    where
+        I: IntoIterator<Item = &'g str>,
+    {
+        let mut by_root: HashMap<&Path, HashSet<String>> = HashMap::new();
+
+        for game in games {
+            by_root
+                .entry(storage.game_root(game))
+                .or_default()
+                .insert(game.to_owned());
+        }
+
+        for (root, games) in by_root {
+            let results = db.verify(root, &games, false, storage.zip, &BarSink::default());
+            let mut verdicts = self.verdicts.lock().unwrap();
+
+            for (game, failures) in results.iter() {
+                verdicts.insert(game.to_string(), Verdict::new(failures));
+            }
+        }
+    }
+
+    #[inline]
+    fn set_message<S: Into<String>>(&self, message: S) {
+        *self.message.lock().unwrap() = message.into();
+    }
+}
+
+struct Browser<'d> {
+    db: &'d GameDb,
+    storage: &'d Storage,
+    // every game but devices, by description
+    games: Vec<&'d Game>,
+    // indexes of the games matching the search
+    shown: Vec<usize>,
+    search: String,
+    searching: bool,
+    list: ListState,
+    shared: &'d Shared,
+}
+
+impl<'d> Browser<'d> {
+    fn new(db: &'d GameDb, storage: &'d Storage, shared: &'d Shared) -> Self {
+        let mut games = db
+            .games_iter()
+            .filter(|game| !game.is_device)
+            .collect::<Vec<_>>();
+        games.sort_unstable_by(|a, b| {
+            a.description
+                .cmp(&b.description)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let mut browser = Self {
+            db,
+            storage,
+            games,
+            shown: Vec::new(),
+            search: String::new(),
+            searching: false,
+            list: ListState::default(),
+            shared,
+        };
+        browser.refilter();
+        browser
+    }
+
+    // searches match names from their start or descriptions anywhere,
+    // ignoring case either way
+    fn refilter(&mut self) {
+        let search = self.search.to_lowercase();
+
+        self.shown = self
+            .games
+            .iter()
+            .enumerate()
+            .filter(|(_, game)| {
+                search.is_empty()
+                    || game.name.to_lowercase().starts_with(&search)
+                    || game.description.to_lowercase().contains(&search)
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        self.list.select((!self.shown.is_empty()).then_some(0));
+    }
+
+    fn selected(&self) -> Option<&'d Game> {
+        self.list
+            .selected()
+            .and_then(|index| self.shown.get(index))
+            .map(|index| self.games[*index])
+    }
+
+    // runs task in the background unless another is already running
+    fn spawn<'s, F>(&self, scope: &'s std::thread::Scope<'s, 'd>, total: usize, task: F)
+    where
+        F: FnOnce() + Send + 's,
+    {
+        if self.shared.busy.swap(true, Ordering::SeqCst) {
+            self.shared.set_message("still busy with the last task");
+            return;
+        }
+
+        crate::progress::watch();
+        self.shared.done.store(0, Ordering::Relaxed);
+        self.shared.total.store(total, Ordering::Relaxed);
+
+        let busy = &self.shared.busy;
+        scope.spawn(move || {
+            task();
+            busy.store(false, Ordering::SeqCst);
+        });
+    }
+
+    fn verify_shown<'s>(&self, scope: &'s std::thread::Scope<'s, 'd>) {
+        let (db, storage, shared) = (self.db, self.storage, self.shared);
+        let games = self
+            .shown
+            .iter()
+            .map(|index| self.games[*index].name.as_str())
+            .collect::<Vec<_>>();
+
+        self.spawn(scope, games.len(), move || {
+            for chunk in games.chunks(VERIFY_CHUNK) {
+                shared.verify(db, storage, chunk.iter().copied());
+                shared.done.fetch_add(chunk.len(), Ordering::Relaxed);
+            }
+
+            let failing = games
+                .iter()
+                .filter(|game| {
+                    shared
+                        .verdicts
+                        .lock()
+                        .unwrap()
+                        .get(**game)
+                        .is_some_and(|verdict| !verdict.is_ok())
+                })
+                .count();
+
+            shared.set_message(format!(
+                "{} verified, {} with failures",
+                games.len(),
+                failing
+            ));
+        })
+    }
+
+    fn add_selected<'s>(&self, scope: &'s std::thread::Scope<'s, 'd>) {
+        let (db, storage, shared) = (self.db, self.storage, self.shared);
+
+        let game = match self.selected() {
+            Some(game) => game,
+            None => return,
+        };
+
+        if storage.sources.is_empty() && storage.urls.is_empty() {
+            shared.set_message("no sources to add from");
+            return;
+        }
+
+        self.spawn(scope, 0, move || {
+            let required = db.required_parts([&game.name]).unwrap_or_default();
+            let roms: RomSources = crate::game::get_rom_sources(
+                &storage.sources,
+                &storage.urls,
+                required,
+                &BarSink::default(),
+            );
+            let root = storage.game_root(&game.name);
+
+            let added = if storage.zip {
+                game.add_and_verify_zip(&roms, root, root, None, false, &BarSink::default())
+            } else {
+                game.add_and_verify(&roms, root, root, false, &BarSink::default())
+            };
+
+            match added {
+                Ok(_) => {
+                    shared.verify(db, storage, std::iter::once(game.name.as_str()));
+                    shared.set_message(format!("{} added", game.name));
+                }
+                Err(err) => shared.set_message(format!("{} : {}", game.name, err)),
+            }
+        })
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        if let Some(last) = self.shown.len().checked_sub(1) {
+            let selected = self.list.selected().unwrap_or_default() as isize;
+            self.list
+                .select(Some((selected + offset).clamp(0, last as isize) as usize));
+        }
+    }
+
+    // whether to keep browsing after the given key
+    fn handle<'s>(&mut self, key: KeyCode, scope: &'s std::thread::Scope<'s, 'd>) -> bool {
+        if self.searching {
+            match key {
+                KeyCode::Char(c) => {
+                    self.search.push(c);
+                    self.refilter();
+                }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                    self.refilter();
+                }
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.searching = false;
+                    self.refilter();
+                }
+                KeyCode::Enter => self.searching = false,
+                _ => {}
+            }
+            return true;
+        }
+
+        match key {
+            KeyCode::Char('q') => return false,
+            KeyCode::Esc if self.search.is_empty() => return false,
+            KeyCode::Esc => {
+                self.search.clear();
+                self.refilter();
+            }
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-20),
+            KeyCode::PageDown => self.move_by(20),
+            KeyCode::Home => self.move_by(isize::MIN / 2),
+            KeyCode::End => self.move_by(isize::MAX / 2),
+            KeyCode::Char('v') => {
+                if let Some(game) = self.selected() {
+                    self.shared
+                        .verify(self.db, self.storage, std::iter::once(game.name.as_str()));
+                }
+            }
+            KeyCode::Char('V') => self.verify_shown(scope),
+            KeyCode::Char('a') => self.add_selected(scope),
+            _ => {}
+        }
+
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, middle, bottom] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(middle);
+
+        let search_style = if self.searching {
+            Style::new().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::new()
+        };
+
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::raw(format!("{} : ", self.db.description())),
+                Span::styled(format!("/{}", self.search), search_style),
+                Span::raw(format!("  ({} games)", self.shown.len())),
+            ])),
+            top,
+        );
+
+        let verdicts = self.shared.verdicts.lock().unwrap();
+
+        let items = self
+            .shown
+            .iter()
+            .map(|index| {
+                let game = self.games[*index];
+                let style = match verdicts.get(&game.name) {
+                    Some(verdict) if verdict.is_ok() => Style::new().fg(Color::Green),
+                    Some(_) => Style::new().fg(Color::Red),
+                    None => Style::new(),
+                };
+                ListItem::new(format!("{} ({})", game.description, game.name)).style(style)
+            })
+            .collect::<Vec<_>>();
+
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title("games"))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            left,
+            &mut self.list,
+        );
+
+        let details = match self.selected() {
+            Some(game) => game_details(game, verdicts.get(&game.name), &self.storage.info),
+            None => Vec::new(),
+        };
+
+        frame.render_widget(
+            Paragraph::new(details)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title("details")),
+            right,
+        );
+
+        drop(verdicts);
+
+        let total = self.shared.total.load(Ordering::Relaxed);
+
+        if self.shared.busy.load(Ordering::Relaxed) {
+            // tasks of unknown length follow whatever they're busy with
+            let (done, total) = match total {
+                0 => crate::progress::watched().unwrap_or_default(),
+                total => (
+                    self.shared.done.load(Ordering::Relaxed) as u64,
+                    total as u64,
+                ),
+            };
+
+            frame.render_widget(
+                Gauge::default()
+                    .ratio(if total == 0 {
+                        0.0
+                    } else {
+                        (done as f64 / total as f64).min(1.0)
+                    })
+                    .label(format!("{} / {}", done, total)),
+                bottom,
+            );
+        } else {
+            let message = self.shared.message.lock().unwrap();
+
+            frame.render_widget(
+                Paragraph::new(if message.is_empty() {
+                    HELP
+                } else {
+                    message.as_str()
+                }),
+                bottom,
+            );
+        }
+    }
+}
+
+fn game_details<'g>(game: &'g Game, verdict: Option<&Verdict>, info: &'g InfoDb) -> Vec<Line<'g>> {
+    let mut lines = vec![
+        Line::styled(
+            game.description.as_str(),
+            Style::new().add_modifier(Modifier::BOLD),
+        ),
+        Line::raw(format!("{} : {} {}", game.name, game.creator, game.year)),
+    ];
+
+    if let Some(parent) = &game.cloneof {
+        lines.push(Line::raw(format!("clone of {}", parent)));
+    }
+
+    lines.push(Line::raw(format!("status : {:?}", game.status)));
+
+    if let Some(category) = &game.category {
+        lines.push(Line::raw(format!("category : {}", category)));
+    }
+
+    lines.push(Line::raw(""));
+
+    let mut parts = game.parts.iter().collect::<Vec<_>>();
+    parts.sort_unstable_by_key(|(name, _)| *name);
+
+    for (name, part) in parts {
+        let (status, style) = match verdict {
+            Some(verdict) => match verdict.parts.get(name.as_str()) {
+                Some(status) => (*status, Style::new().fg(Color::Red)),
+                None => ("OK", Style::new().fg(Color::Green)),
+            },
+            None => ("", Style::new()),
+        };
+
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:8}", status), style),
+            Span::raw(format!("{} {}", name, part.digest())),
+        ]));
+    }
+
+    if let Some(verdict) = verdict {
+        lines.push(Line::raw(""));
+
+        if verdict.is_ok() {
+            lines.push(Line::styled("OK", Style::new().fg(Color::Green)));
+        } else {
+            lines.extend(
+                verdict
+                    .failures
+                    .iter()
+                    .map(|failure| Line::styled(failure.clone(), Style::new().fg(Color::Red))),
+            );
+        }
+    }
+
+    for (label, text) in info.sections(&game.name) {
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(
+            format!("\u{2500}\u{2500} {} \u{2500}\u{2500}", label),
+            Style::new().add_modifier(Modifier::BOLD),
+        ));
+        lines.extend(text.lines().map(Line::raw));
+    }
+
+    lines
+}
+
+// browses db's games until quit, verifying and adding them on request
+pub fn run(db: &GameDb, storage: &Storage) -> Result<(), Error> {
+    let shared = Shared::default();
+
+    crate::progress::watch();
+
+    let mut terminal = ratatui::init();
+    let result = std::thread::scope(|scope| browse(&mut terminal, db, storage, &shared, scope));
+    ratatui::restore();
+
+    result
+}
+
+fn browse<'s, 'd>(
+    terminal: &mut DefaultTerminal,
+    db: &'d GameDb,
+    storage: &'d Storage,
+    shared: &'d Shared,
+    scope: &'s std::thread::Scope<'s, 'd>,
+) -> Result<(), Error> {
+    let mut browser = Browser::new(db, storage, shared);
+
+    loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+
+        // redrawn regularly either way, to follow background tasks
+        if event::poll(Duration::from_millis(100))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !browser.handle(key.code, scope) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use crate::collection::Collection;
+use crate::game::{Part, VerifyFailure};
+use crate::progress::BarSink;
+use crate::Error;
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+// changes are gathered until things have been quiet this long,
+// so files still being copied in are only looked at once they're done
+const SETTLE: Duration = Duration::from_secs(2);
+
+// a game which doesn't verify, along with the parts it still needs
+struct Incomplete {
+    root: PathBuf,
+    needed: Vec<Part>,
+}
+
+struct Watch<'c> {
+    collection: &'c Collection,
+    deep: bool,
+    incomplete: HashMap<String, Incomplete>,
+    // hashed storage names files by digest rather than game,
+    // so each root's digest files map back to the games using them
+    hashed: HashMap<PathBuf, Vec<String>>,
+}
+
+impl<'c> Watch<'c> {
+    fn new(collection: &'c Collection, deep: bool) -> Self {
+        let hashed = if collection.is_hashed() {
+            let mut hashed: HashMap<PathBuf, Vec<String>> = HashMap::new();
+            for game in collection.all_games() {
+                if let Some(entry) = collection.db.game(&game) {
+                    for root in collection.roots.iter() {
+                        for part in entry.parts.values() {
+                            hashed
+                                .entry(part.hashed_path(root))
+                                .or_default()
+                                .push(game.clone());
+                        }
+                    }
+                }
+            }
+            hashed
+        } else {
+            HashMap::new()
+        };
+
+        Self {
+            collection,
+            deep,
+            incomplete: HashMap::new(),
+            hashed,
+        }
+    }
+
+    // the root and games a changed path belongs to, if any
+    fn games_of(&self, path: &Path) -> Option<(&'c Path, Vec<String>)> {
+        let collection = self.collection;
+        let root = collection
+            .roots
+            .iter()
+            .find(|root| path.starts_with(root))?;
+
+        let games = if collection.is_hashed() {
+            self.hashed.get(path).cloned().unwrap_or_default()
+        } else {
+            let entry = path
+                .strip_prefix(root)
+                .ok()?
+                .components()
+                .next()?
+                .as_os_str()
+                .to_str()?;
+
+            let game = if collection.is_zip() {
+                entry.strip_suffix(".zip")?
+            } else {
+                entry
+            };
+
+            if collection.db.is_game(game) && !collection.is_ignored(game) {
+                vec![game.to_owned()]
+            } else {
+                Vec::new()
+            }
+        };
+
+        Some((root.as_path(), games))
+    }
+
+    // whether any of the game remains in root,
+    // since games removed on purpose aren't filled back in
+    fn is_present(&self, root: &Path, game: &str) -> bool {
+        if self.collection.is_hashed() {
+            self.collection.db.game(game).is_some_and(|game| {
+                game.parts
+                    .values()
+                    .any(|part| part.hashed_path(root).is_file())
+            })
+        } else if self.collection.is_zip() {
+            root.join(format!("{}.zip", game)).is_file()
+        } else {
+            root.join(game).is_dir()
+        }
+    }
+
+    // verifies games in root, displaying and remembering how they fared
+    fn verify(&mut self, root: &Path, games: HashSet<String>) {
+        let (games, gone): (HashSet<String>, HashSet<String>) = games
+            .into_iter()
+            .partition(|game| self.is_present(root, game));
+
+        for game in gone {
+            if self.incomplete.remove(&game).is_some() {
+                println!("REMOVED : {}", game);
+            }
+        }
+
+        let results = self.collection.db.verify(
+            root,
+            &games,
+            self.deep,
+            self.collection.is_zip(),
+            &BarSink::default(),
+        );
+
+        for (game, failures) in results.iter() {
+            crate::report::display_all_results(game, failures);
+
+            let needed = failures
+                .iter()
+                .filter_map(|failure| match failure {
+                    VerifyFailure::Missing { part, .. }
+                    | VerifyFailure::Bad { expected: part, .. } => Some((*part).clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            if failures.iter().any(|failure| failure.is_incomplete()) {
+                self.incomplete.insert(
+                    game.to_string(),
+                    Incomplete {
+                        root: root.to_path_buf(),
+                        needed,
+                    },
+                );
+            } else {
+                self.incomplete.remove(*game);
+            }
+        }
+    }
+
+    // pulls whatever incomplete games need out of newly appeared source files
+    fn fill(&mut self, files: &[PathBuf]) -> Result<(), Error> {
+        let required = self
+            .incomplete
+            .values()
+            .flat_map(|incomplete| incomplete.needed.iter().cloned())
+            .collect();
+
+        let mut roms = crate::game::get_rom_sources(files, &[], required, &BarSink::default());
+
+        if roms.is_empty() {
+            return Ok(());
+        }
+
+        let mut by_root: HashMap<PathBuf, HashSet<String>> = HashMap::new();
+
+        for (game, incomplete) in self.incomplete.iter() {
+            if incomplete.needed.iter().any(|part| roms.contains_key(part)) {
+                by_root
+                    .entry(incomplete.root.clone())
+                    .or_default()
+                    .insert(game.clone());
+            }
+        }
+
+        for (root, games) in by_root {
+            for (game, err) in crate::add_and_verify(
+                &self.collection.name,
+                self.collection.db.description(),
+                &mut roms,
+                &root,
+                None,
+                games
+                    .iter()
+                    .filter_map(|game| self.collection.db.game(game)),
+                false,
+                self.collection.is_zip(),
+            )? {
+                eprintln!("* {} : {}", game, err);
+            }
+
+            self.verify(&root, games);
+        }
+
+        Ok(())
+    }
+}
+
+// verifies the collection's games once, then keeps watching its roots
+// and local sources, re-verifying only games whose files change
+// and filling incomplete games from files appearing in sources
+pub fn run(collection: &Collection, sources: &[PathBuf], deep: bool) -> Result<(), Error> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+
+    for dir in collection.roots.iter().chain(sources) {
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+    }
+
+    let mut watch = Watch::new(collection, deep);
+
+    for root in collection.roots.iter() {
+        let games = collection.games_in(root)?;
+        watch.verify(root, games);
+    }
+
+    eprintln!(
+        "watching {} roots and {} sources, {} games incomplete",
+        collection.roots.len(),
+        sources.len(),
+        watch.incomplete.len()
+    );
+
+    loop {
+        let mut changed = HashSet::new();
+        let mut event = rx.recv().map_err(|_| Error::WatchStopped)?;
+
+        loop {
+            match event? {
+                // reading files or tagging them with cached digests
+                // doesn't change what's in them
+                notify::Event {
+                    kind: EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(_)),
+                    ..
+                } => {}
+                notify::Event { paths, .. } => changed.extend(paths),
+            }
+
+            event = match rx.recv_timeout(SETTLE) {
+                Ok(event) => event,
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Err(Error::WatchStopped),
+            };
+        }
+
+        let mut games: HashMap<&Path, HashSet<String>> = HashMap::new();
+        let mut appeared = Vec::new();
+
+        for path in changed {
+            match watch.games_of(&path) {
+                Some((root, changed)) => games.entry(root).or_default().extend(changed),
+                None if path.is_file() => appeared.push(path),
+                None => {}
+            }
+        }
+
+        for (root, games) in games {
+            watch.verify(root, games);
+        }
+
+        if !appeared.is_empty() && !watch.incomplete.is_empty() {
+            appeared.sort_unstable();
+            watch.fill(&appeared)?;
+        }
+    }
+}